@@ -0,0 +1,504 @@
+//! # Derive macros for cbor_event
+//!
+//! `#[derive(Serialize, Deserialize)]` for the traits of the `cbor_event`
+//! crate, re-exported by it with the `derive` feature.
+//!
+//! By default a struct is encoded as an array of its fields, in
+//! declaration order, and an enum as an array of the id of the variant
+//! (its position, starting at 0) followed by the fields of the variant.
+//!
+//! ```
+//! # extern crate cbor_event;
+//! use cbor_event::{de::Deserializer, se::Serializer, Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Point(u64, u64);
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! enum Shape {
+//!     Empty,
+//!     Line(Point, Point),
+//!     #[cbor(id = 7)]
+//!     Circle { center: Point, radius: u64 },
+//! }
+//!
+//! # fn main() {
+//! let shape = Shape::Circle { center: Point(1, 2), radius: 3 };
+//! let mut se = Serializer::new_vec();
+//! se.serialize(&shape).unwrap();
+//! let bytes = se.finalize();
+//! // [7, [1, 2], 3]
+//! assert_eq!(bytes, [0x83, 0x07, 0x82, 0x01, 0x02, 0x03]);
+//!
+//! let decoded: Shape = Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//! assert_eq!(decoded, shape);
+//! # }
+//! ```
+//!
+//! ## Attributes
+//!
+//! - `#[cbor(map)]` on a struct with named fields: encode it as a map
+//!   keyed by the names of the fields, or by integers if every field has
+//!   a `#[cbor(key = N)]` attribute. Decoding skips the unknown keys and
+//!   fails with `Error::MissingField` if a field is missing;
+//! - `#[cbor(indefinite)]` on a struct or an enum: encode the arrays and
+//!   maps with an indefinite length. Decoding accepts both;
+//! - `#[cbor(id = N)]` on a variant: the id of the variant.
+//!
+//! ```
+//! # extern crate cbor_event;
+//! use cbor_event::{de::Deserializer, se::Serializer, Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! #[cbor(map, indefinite)]
+//! struct Header {
+//!     #[cbor(key = 1)]
+//!     version: u64,
+//!     #[cbor(key = 4)]
+//!     name: String,
+//! }
+//!
+//! # fn main() {
+//! let header = Header { version: 2, name: "a".to_owned() };
+//! let mut se = Serializer::new_vec();
+//! se.serialize(&header).unwrap();
+//! let bytes = se.finalize();
+//! // {_ 1: 2, 4: "a"}
+//! assert_eq!(bytes, [0xbf, 0x01, 0x02, 0x04, 0x61, 0x61, 0xff]);
+//!
+//! // {4: "a", 1: 2, 9: null}
+//! let bytes = [0xa3, 0x04, 0x61, 0x61, 0x01, 0x02, 0x09, 0xf6];
+//! let decoded: Header = Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//! assert_eq!(decoded, header);
+//! # }
+//! ```
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::BTreeSet;
+use syn::{
+    ext::IdentExt, parse_quote, Attribute, Data, DeriveInput, Fields, Generics, Ident, LitInt,
+    Member,
+};
+
+#[proc_macro_derive(Serialize, attributes(cbor))]
+pub fn derive_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    serialize(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Deserialize, attributes(cbor))]
+pub fn derive_deserialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    deserialize(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Container {
+    map: bool,
+    indefinite: bool,
+}
+
+enum Key {
+    Text(String),
+    Integer(u64),
+}
+
+struct Field {
+    member: Member,
+    name: String,
+    // local variable holding the value of the field
+    binding: Ident,
+    key: Key,
+}
+
+// a struct, or a variant of an enum with its id
+struct Variant {
+    path: TokenStream,
+    location: String,
+    id: Option<u64>,
+    fields: Vec<Field>,
+}
+
+fn cbor_attrs<F>(attrs: &[Attribute], mut f: F) -> syn::Result<()>
+where
+    F: FnMut(syn::meta::ParseNestedMeta) -> syn::Result<()>,
+{
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("cbor")) {
+        attr.parse_nested_meta(&mut f)?;
+    }
+    Ok(())
+}
+
+fn container(input: &DeriveInput) -> syn::Result<Container> {
+    let mut container = Container::default();
+    cbor_attrs(&input.attrs, |meta| {
+        if meta.path.is_ident("map") {
+            container.map = true;
+        } else if meta.path.is_ident("indefinite") {
+            container.indefinite = true;
+        } else {
+            return Err(meta.error("expected `map` or `indefinite`"));
+        }
+        Ok(())
+    })?;
+    if container.map {
+        match input.data {
+            Data::Struct(ref data) => {
+                if let Fields::Named(_) = data.fields {
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        &input.ident,
+                        "`map` needs a struct with named fields",
+                    ));
+                }
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "`map` needs a struct with named fields",
+                ))
+            }
+        }
+    }
+    Ok(container)
+}
+
+fn int_attr(attrs: &[Attribute], name: &str) -> syn::Result<Option<u64>> {
+    let mut value = None;
+    cbor_attrs(attrs, |meta| {
+        if meta.path.is_ident(name) {
+            let lit: LitInt = meta.value()?.parse()?;
+            value = Some(lit.base10_parse()?);
+            Ok(())
+        } else {
+            Err(meta.error(format!("expected `{}`", name)))
+        }
+    })?;
+    Ok(value)
+}
+
+fn fields(fields: &Fields, map: bool) -> syn::Result<Vec<Field>> {
+    let mut result = Vec::new();
+    let mut keys = BTreeSet::new();
+    for (index, field) in fields.iter().enumerate() {
+        let integer = int_attr(&field.attrs, "key")?;
+        let (member, name) = match field.ident {
+            Some(ref ident) => (Member::Named(ident.clone()), ident.unraw().to_string()),
+            None => (Member::Unnamed(index.into()), index.to_string()),
+        };
+        let key = match integer {
+            Some(_) if !map => {
+                return Err(syn::Error::new_spanned(field, "`key` needs #[cbor(map)]"))
+            }
+            Some(key) => {
+                if !keys.insert(key) {
+                    return Err(syn::Error::new_spanned(field, "duplicate key"));
+                }
+                Key::Integer(key)
+            }
+            None => Key::Text(name.clone()),
+        };
+        result.push(Field {
+            member,
+            binding: format_ident!("__field_{}", name),
+            name,
+            key,
+        });
+    }
+    if !keys.is_empty() && keys.len() != result.len() {
+        return Err(syn::Error::new_spanned(
+            fields,
+            "either every field or none has a `key`",
+        ));
+    }
+    Ok(result)
+}
+
+fn variants(input: &DeriveInput, container: &Container) -> syn::Result<Vec<Variant>> {
+    let name = &input.ident;
+    match input.data {
+        Data::Struct(ref data) => Ok(vec![Variant {
+            path: quote!(Self),
+            location: name.to_string(),
+            id: None,
+            fields: fields(&data.fields, container.map)?,
+        }]),
+        Data::Enum(ref data) => {
+            let mut ids = BTreeSet::new();
+            let mut result = Vec::new();
+            for (index, variant) in data.variants.iter().enumerate() {
+                let id = int_attr(&variant.attrs, "id")?.unwrap_or(index as u64);
+                if !ids.insert(id) {
+                    return Err(syn::Error::new_spanned(variant, "duplicate id"));
+                }
+                let ident = &variant.ident;
+                result.push(Variant {
+                    path: quote!(Self::#ident),
+                    location: format!("{}::{}", name, ident),
+                    id: Some(id),
+                    fields: fields(&variant.fields, false)?,
+                });
+            }
+            Ok(result)
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "unions are not supported",
+        )),
+    }
+}
+
+// `path { member: binding, .. }`, as a pattern or as an expression. The
+// braces are accepted for the tuple and unit structs and variants too.
+fn pattern(path: &TokenStream, fields: &[Field]) -> TokenStream {
+    let members = fields.iter().map(|field| &field.member);
+    let bindings = fields.iter().map(|field| &field.binding);
+    quote!(#path { #(#members: #bindings),* })
+}
+
+fn with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn serialize(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = container(input)?;
+    let variants = variants(input, &container)?;
+    let arms = variants.iter().map(|variant| {
+        let pattern = pattern(&variant.path, &variant.fields);
+        let fields = &variant.fields;
+        let len = fields.len() as u64 + variant.id.map_or(0, |_| 1);
+        let len = if container.indefinite {
+            quote!(::cbor_event::Len::Indefinite)
+        } else {
+            quote!(::cbor_event::Len::Len(#len))
+        };
+        let header = if container.map {
+            quote!(serializer.write_map(#len)?;)
+        } else {
+            quote!(serializer.write_array(#len)?;)
+        };
+        let id = variant
+            .id
+            .map(|id| quote!(serializer.write_unsigned_integer(#id)?;));
+        let items = fields.iter().map(|field| {
+            let binding = &field.binding;
+            let key = match field.key {
+                _ if !container.map => None,
+                Key::Text(ref key) => Some(quote!(serializer.write_text(#key)?;)),
+                Key::Integer(key) => Some(quote!(serializer.write_unsigned_integer(#key)?;)),
+            };
+            quote!(#key serializer.serialize(#binding)?;)
+        });
+        let end = if container.indefinite {
+            Some(quote!(serializer.write_special(::cbor_event::Special::Break)?;))
+        } else {
+            None
+        };
+        quote!(#pattern => { #header #id #(#items)* #end })
+    });
+
+    // an enum without variants cannot be matched through a reference
+    let body = if variants.is_empty() {
+        quote!(match *self {})
+    } else {
+        quote! {
+            match self {
+                #(#arms)*
+            }
+            Ok(serializer)
+        }
+    };
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::cbor_event::se::Serialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::cbor_event::se::Serialize for #name #ty_generics #where_clause {
+            fn serialize<'se, W: ::std::io::Write + Sized>(
+                &self,
+                serializer: &'se mut ::cbor_event::se::Serializer<W>,
+            ) -> ::cbor_event::Result<&'se mut ::cbor_event::se::Serializer<W>> {
+                #body
+            }
+        }
+    })
+}
+
+// decode the items of an array of `len` elements, the id of the variant
+// already read, then build the variant
+fn deserialize_array(variant: &Variant) -> TokenStream {
+    let fields = &variant.fields;
+    let len = fields.len() as u64 + variant.id.map_or(0, |_| 1);
+    let location = &variant.location;
+    let bindings = fields.iter().map(|field| &field.binding);
+    let value = pattern(&variant.path, &variant.fields);
+    quote! {
+        match __len {
+            ::cbor_event::Len::Len(#len) | ::cbor_event::Len::Indefinite => {}
+            _ => return Err(::cbor_event::Error::WrongLen(#len, __len, #location)),
+        }
+        #(let #bindings = raw.deserialize()?;)*
+        if __len == ::cbor_event::Len::Indefinite && !raw.special_break()? {
+            return Err(::cbor_event::Error::WrongLen(#len, __len, #location));
+        }
+        Ok(#value)
+    }
+}
+
+fn deserialize_map(variant: &Variant) -> TokenStream {
+    let fields = &variant.fields;
+    let location = &variant.location;
+    let bindings = fields.iter().map(|field| &field.binding);
+    let (key_type, key) = match fields.first().map(|field| &field.key) {
+        Some(&Key::Integer(_)) => (quote!(u64), quote!(__key)),
+        _ => (quote!(String), quote!(__key.as_str())),
+    };
+    let arms = fields.iter().map(|field| {
+        let binding = &field.binding;
+        let key = match field.key {
+            Key::Text(ref key) => quote!(#key),
+            Key::Integer(key) => quote!(#key),
+        };
+        quote! {
+            #key => {
+                if #binding.is_some() {
+                    return Err(::cbor_event::Error::DuplicateKey);
+                }
+                #binding = Some(raw.deserialize()?);
+            }
+        }
+    });
+    let members = fields.iter().map(|field| &field.member);
+    let values = fields.iter().map(|field| {
+        let (binding, name) = (&field.binding, &field.name);
+        quote!(#binding.ok_or(::cbor_event::Error::MissingField(#name, #location))?)
+    });
+    let path = &variant.path;
+    quote! {
+        #(let mut #bindings = None;)*
+        raw.map_entries_with(|raw, __key: #key_type| {
+            match #key {
+                #(#arms)*
+                _ => raw.skip_value()?,
+            }
+            Ok(())
+        })?;
+        Ok(#path { #(#members: #values),* })
+    }
+}
+
+fn deserialize(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = container(input)?;
+    let variants = variants(input, &container)?;
+    let body = match input.data {
+        Data::Struct(_) if container.map => deserialize_map(&variants[0]),
+        Data::Struct(_) => {
+            let items = deserialize_array(&variants[0]);
+            quote! {
+                let __len = raw.array()?;
+                #items
+            }
+        }
+        _ => {
+            let location = input.ident.to_string();
+            let arms = variants.iter().map(|variant| {
+                let id = variant.id;
+                let items = deserialize_array(variant);
+                quote!(#id => { #items })
+            });
+            quote! {
+                let __len = raw.array()?;
+                if __len == ::cbor_event::Len::Len(0) {
+                    return Err(::cbor_event::Error::WrongLen(1, __len, #location));
+                }
+                match raw.unsigned_integer()? {
+                    #(#arms)*
+                    __id => Err(::cbor_event::Error::UnknownEnumValue(#location, __id)),
+                }
+            }
+        }
+    };
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, quote!(::cbor_event::de::Deserialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::cbor_event::de::Deserialize for #name #ty_generics #where_clause {
+            fn deserialize<R: ::std::io::BufRead>(
+                raw: &mut ::cbor_event::de::Deserializer<R>,
+            ) -> ::cbor_event::Result<Self> {
+                #body
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        match serialize(&input) {
+            Err(error) => {
+                assert!(deserialize(&input).is_err());
+                error.to_string()
+            }
+            Ok(tokens) => panic!("expected an error, got {}", tokens),
+        }
+    }
+
+    #[test]
+    fn invalid_attributes() {
+        let map_of_tuple = parse_quote! {
+            #[cbor(map)]
+            struct A(u8);
+        };
+        assert_eq!(
+            error(map_of_tuple),
+            "`map` needs a struct with named fields"
+        );
+
+        let key_of_array = parse_quote! {
+            struct A { #[cbor(key = 1)] a: u8 }
+        };
+        assert_eq!(error(key_of_array), "`key` needs #[cbor(map)]");
+
+        let missing_key = parse_quote! {
+            #[cbor(map)]
+            struct A { #[cbor(key = 1)] a: u8, b: u8 }
+        };
+        assert_eq!(error(missing_key), "either every field or none has a `key`");
+
+        let duplicate_key = parse_quote! {
+            #[cbor(map)]
+            struct A { #[cbor(key = 1)] a: u8, #[cbor(key = 1)] b: u8 }
+        };
+        assert_eq!(error(duplicate_key), "duplicate key");
+
+        let duplicate_id = parse_quote! {
+            enum A { B, #[cbor(id = 0)] C }
+        };
+        assert_eq!(error(duplicate_id), "duplicate id");
+
+        let unknown = parse_quote! {
+            #[cbor(compact)]
+            struct A;
+        };
+        assert_eq!(error(unknown), "expected `map` or `indefinite`");
+    }
+}
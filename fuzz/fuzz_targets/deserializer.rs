@@ -0,0 +1,32 @@
+#![no_main]
+
+use cbor_event::de::{Deserializer, DEFAULT_MAX_DEPTH};
+use cbor_event::se::Serializer;
+use cbor_event::{BorrowedValue, RawCbor, Value};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // a `Value` is encoded and dropped recursively
+    let mut raw = Deserializer::from(data);
+    raw.set_max_depth(DEFAULT_MAX_DEPTH);
+    if let Ok(value) = raw.deserialize_complete::<Value>() {
+        // anything decoded and encoded again must decode to the same value
+        let mut se = Serializer::new_vec();
+        if se.serialize(&value).is_ok() {
+            let bytes = se.finalize();
+            let decoded: Value = Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+            assert_eq!(decoded, value);
+        }
+    }
+
+    let mut raw = Deserializer::from(data);
+    let _ = raw.borrowed_value().map(BorrowedValue::into_owned);
+
+    let mut raw = Deserializer::from(data);
+    raw.set_lenient(true);
+    let _ = raw.deserialize::<RawCbor>();
+    let _ = raw.skip_value();
+
+    let mut raw = Deserializer::from(data);
+    let _ = raw.tuple_elems::<(u64, String, Vec<u8>, bool, f64)>("fuzz");
+});
@@ -0,0 +1,259 @@
+#![no_main]
+
+//! decode the same input with cbor_event and ciborium-ll, the low level
+//! decoder of ciborium, and check they agree on whether it is well-formed
+//! and on the decoded data item.
+//!
+//! Both sides build an `Item` of the CBOR data model: the encoding details
+//! (definite or indefinite lengths, size of the arguments, string chunks)
+//! are not compared. ciborium-ll is lax on two rules of RFC 8949 which are
+//! checked here instead: indefinite length strings only contain definite
+//! length chunks (section 3.2.3), and the simple values below 32 are not
+//! encoded on two bytes (section 3.3).
+
+use cbor_event::de::Deserializer;
+use cbor_event::{Len, Special, Type};
+use ciborium_ll::{Decoder, Header};
+use libfuzzer_sys::fuzz_target;
+
+// deeper items are not compared, to not overflow the stack
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Positive(u64),
+    // `-1 - n`
+    Negative(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Item>),
+    Map(Vec<(Item, Item)>),
+    Tag(u64, Box<Item>),
+    Simple(u8),
+    // bits of the value widened to a f64, all NaNs are the same
+    Float(u64),
+}
+
+fn float(value: f64) -> Item {
+    Item::Float(if value.is_nan() { f64::NAN } else { value }.to_bits())
+}
+
+#[derive(Debug)]
+enum Stop {
+    // only read by the report of a disagreement
+    #[allow(dead_code)]
+    Invalid(String),
+    TooDeep,
+}
+
+impl From<cbor_event::Error> for Stop {
+    fn from(error: cbor_event::Error) -> Self {
+        Stop::Invalid(error.to_string())
+    }
+}
+
+impl<E: std::fmt::Debug> From<ciborium_ll::Error<E>> for Stop {
+    fn from(error: ciborium_ll::Error<E>) -> Self {
+        Stop::Invalid(format!("{:?}", error))
+    }
+}
+
+fn ours(raw: &mut Deserializer<&[u8]>, depth: usize) -> Result<Item, Stop> {
+    if depth > MAX_DEPTH {
+        return Err(Stop::TooDeep);
+    }
+    Ok(match raw.cbor_type()? {
+        Type::UnsignedInteger => Item::Positive(raw.unsigned_integer()?),
+        Type::NegativeInteger => Item::Negative((-1 - raw.negative_integer_sz()?.0) as u64),
+        Type::Bytes => Item::Bytes(raw.bytes()?),
+        Type::Text => Item::Text(raw.text()?),
+        Type::Array => {
+            let mut items = Vec::new();
+            match raw.array()? {
+                Len::Len(n) => {
+                    for _ in 0..n {
+                        items.push(ours(raw, depth + 1)?);
+                    }
+                }
+                Len::Indefinite => {
+                    while !raw.special_break()? {
+                        items.push(ours(raw, depth + 1)?);
+                    }
+                }
+            }
+            Item::Array(items)
+        }
+        Type::Map => {
+            let mut entries = Vec::new();
+            match raw.map()? {
+                Len::Len(n) => {
+                    for _ in 0..n {
+                        entries.push((ours(raw, depth + 1)?, ours(raw, depth + 1)?));
+                    }
+                }
+                Len::Indefinite => {
+                    while !raw.special_break()? {
+                        entries.push((ours(raw, depth + 1)?, ours(raw, depth + 1)?));
+                    }
+                }
+            }
+            Item::Map(entries)
+        }
+        Type::Tag => {
+            let tag = raw.tag()?;
+            Item::Tag(tag, Box::new(ours(raw, depth + 1)?))
+        }
+        Type::Special => match raw.special()? {
+            Special::Bool(false) => Item::Simple(20),
+            Special::Bool(true) => Item::Simple(21),
+            Special::Null => Item::Simple(22),
+            Special::Undefined => Item::Simple(23),
+            Special::Unassigned(value) => Item::Simple(value),
+            Special::Float(value) => float(value),
+            Special::Break => return Err(Stop::Invalid("unexpected break".to_owned())),
+        },
+    })
+}
+
+// content of a text or byte string, gathering the chunks of an indefinite
+// length string
+fn theirs_string(
+    decoder: &mut Decoder<&[u8]>,
+    len: Option<usize>,
+    text: bool,
+) -> Result<Vec<u8>, Stop> {
+    let mut content = Vec::new();
+    match len {
+        Some(len) => theirs_chunk(decoder, len, text, &mut content)?,
+        None => loop {
+            match (decoder.pull()?, text) {
+                (Header::Break, _) => break,
+                (Header::Bytes(Some(len)), false) | (Header::Text(Some(len)), true) => {
+                    theirs_chunk(decoder, len, text, &mut content)?
+                }
+                (header, _) => return Err(Stop::Invalid(format!("invalid chunk {:?}", header))),
+            }
+        },
+    }
+    Ok(content)
+}
+
+fn theirs_chunk(
+    decoder: &mut Decoder<&[u8]>,
+    len: usize,
+    text: bool,
+    content: &mut Vec<u8>,
+) -> Result<(), Stop> {
+    let mut buffer = [0; 4096];
+    if text {
+        let mut segments = decoder.text(Some(len));
+        while let Some(mut segment) = segments.pull()? {
+            while let Some(text) = segment.pull(&mut buffer)? {
+                content.extend_from_slice(text.as_bytes());
+            }
+        }
+    } else {
+        let mut segments = decoder.bytes(Some(len));
+        while let Some(mut segment) = segments.pull()? {
+            while let Some(bytes) = segment.pull(&mut buffer)? {
+                content.extend_from_slice(bytes);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn theirs(decoder: &mut Decoder<&[u8]>, depth: usize) -> Result<Item, Stop> {
+    if depth > MAX_DEPTH {
+        return Err(Stop::TooDeep);
+    }
+    let offset = decoder.offset();
+    Ok(match decoder.pull()? {
+        Header::Positive(value) => Item::Positive(value),
+        Header::Negative(value) => Item::Negative(value),
+        Header::Bytes(len) => Item::Bytes(theirs_string(decoder, len, false)?),
+        Header::Text(len) => {
+            // each chunk has been checked on its own
+            let content = theirs_string(decoder, len, true)?;
+            Item::Text(String::from_utf8(content).unwrap())
+        }
+        Header::Array(len) => {
+            let mut items = Vec::new();
+            match len {
+                Some(len) => {
+                    for _ in 0..len {
+                        items.push(theirs(decoder, depth + 1)?);
+                    }
+                }
+                None => loop {
+                    match decoder.pull()? {
+                        Header::Break => break,
+                        header => decoder.push(header),
+                    }
+                    items.push(theirs(decoder, depth + 1)?);
+                },
+            }
+            Item::Array(items)
+        }
+        Header::Map(len) => {
+            let mut entries = Vec::new();
+            match len {
+                Some(len) => {
+                    for _ in 0..len {
+                        entries.push((theirs(decoder, depth + 1)?, theirs(decoder, depth + 1)?));
+                    }
+                }
+                None => loop {
+                    match decoder.pull()? {
+                        Header::Break => break,
+                        header => decoder.push(header),
+                    }
+                    entries.push((theirs(decoder, depth + 1)?, theirs(decoder, depth + 1)?));
+                },
+            }
+            Item::Map(entries)
+        }
+        Header::Tag(tag) => Item::Tag(tag, Box::new(theirs(decoder, depth + 1)?)),
+        Header::Simple(value) => {
+            if value < 32 && decoder.offset() - offset == 2 {
+                return Err(Stop::Invalid(format!(
+                    "simple value {} on two bytes",
+                    value
+                )));
+            }
+            Item::Simple(value)
+        }
+        Header::Float(value) => float(value),
+        Header::Break => return Err(Stop::Invalid("unexpected break".to_owned())),
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut raw = Deserializer::from(data);
+    let ours = ours(&mut raw, 0).and_then(|item| {
+        if raw.as_ref().is_empty() {
+            Ok(item)
+        } else {
+            Err(Stop::Invalid("trailing data".to_owned()))
+        }
+    });
+
+    let mut decoder = Decoder::from(data);
+    let theirs = theirs(&mut decoder, 0).and_then(|item| {
+        if decoder.offset() == data.len() {
+            Ok(item)
+        } else {
+            Err(Stop::Invalid("trailing data".to_owned()))
+        }
+    });
+
+    match (ours, theirs) {
+        (Ok(ours), Ok(theirs)) => assert_eq!(ours, theirs),
+        (Err(Stop::TooDeep), _) | (_, Err(Stop::TooDeep)) => {}
+        (Err(_), Err(_)) => {}
+        (ours, theirs) => panic!(
+            "disagreement on well-formedness, cbor_event: {:?}, ciborium-ll: {:?}",
+            ours, theirs
+        ),
+    }
+});
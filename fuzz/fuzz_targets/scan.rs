@@ -0,0 +1,15 @@
+#![no_main]
+
+use cbor_event::{canonical, diag, sequence::SequenceIndex, stats, validate, validate_reader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // the slice and reader validations must agree
+    assert_eq!(validate(data).is_ok(), validate_reader(data).is_ok());
+
+    let _ = SequenceIndex::new(data);
+    let _ = diag::to_diag(data);
+    let _ = stats::stats(data);
+    let _ = canonical::canonicalize(data);
+    let _ = canonical::to_definite(data);
+});
@@ -0,0 +1,245 @@
+//! Decoding into a `bumpalo` arena
+//!
+//! Batch processing decodes many short lived trees: allocating every
+//! string and container separately, then freeing them one by one, costs
+//! more than the decoding itself. [`ArenaValue`] is the equivalent of
+//! [`Value`] whose strings and containers are allocated in a
+//! [`Bump`] arena, so a whole tree is freed at once by resetting the arena.
+//!
+//! [`ArenaValue`]: ./enum.ArenaValue.html
+//! [`Value`]: ../enum.Value.html
+//! [`Bump`]: https://docs.rs/bumpalo/3/bumpalo/struct.Bump.html
+//!
+//! ```
+//! # extern crate bumpalo;
+//! # extern crate cbor_event;
+//! use bumpalo::Bump;
+//! use cbor_event::{arena::ArenaValue, de::Deserializer};
+//!
+//! let mut bump = Bump::new();
+//! for message in [&[0x82, 0x61, 0x61, 0x41, 0x00][..], &[0x81, 0x62, 0x62, 0x63]].iter() {
+//!     let mut raw = Deserializer::from(*message);
+//!     let value = raw.value_in(&bump).unwrap();
+//!     if let ArenaValue::Array(items) = value {
+//!         assert!(matches!(items[0], ArenaValue::Text(_)));
+//!     }
+//!     // free everything decoded from the message at once
+//!     bump.reset();
+//! }
+//! ```
+
+use bumpalo::{collections::Vec as BumpVec, Bump};
+use de::{invalid_utf8, Deserializer};
+use error::Error;
+use len::Len;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::io::{BufRead, Write};
+use std::str;
+use types::{Special, Type};
+
+/// CBOR value allocated in a [`Bump`] arena, see the
+/// [module documentation](./index.html).
+///
+/// Unlike [`Value`](../enum.Value.html) the entries of the maps are kept in
+/// the order of the input and their keys can be of any type.
+///
+/// [`Bump`]: https://docs.rs/bumpalo/3/bumpalo/struct.Bump.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaValue<'bump> {
+    U64(u64),
+    I64(i64),
+    Bytes(&'bump [u8]),
+    Text(&'bump str),
+    Array(&'bump [ArenaValue<'bump>]),
+    IArray(&'bump [ArenaValue<'bump>]),
+    Object(&'bump [(ArenaValue<'bump>, ArenaValue<'bump>)]),
+    IObject(&'bump [(ArenaValue<'bump>, ArenaValue<'bump>)]),
+    Tag(u64, &'bump ArenaValue<'bump>),
+    Special(Special),
+}
+
+impl<'bump> Serialize for ArenaValue<'bump> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        match self {
+            ArenaValue::U64(v) => serializer.write_unsigned_integer(*v),
+            ArenaValue::I64(v) => serializer.write_integer(*v),
+            ArenaValue::Bytes(v) => serializer.write_bytes(v),
+            ArenaValue::Text(v) => serializer.write_text(v),
+            ArenaValue::Array(v) | ArenaValue::IArray(v) => {
+                let indefinite = matches!(self, ArenaValue::IArray(_));
+                serializer.write_array(array_len(v.len(), indefinite))?;
+                for element in v.iter() {
+                    serializer.serialize(element)?;
+                }
+                write_break(serializer, indefinite)
+            }
+            ArenaValue::Object(v) | ArenaValue::IObject(v) => {
+                let indefinite = matches!(self, ArenaValue::IObject(_));
+                serializer.write_map(array_len(v.len(), indefinite))?;
+                for (key, value) in v.iter() {
+                    serializer.serialize(key)?.serialize(value)?;
+                }
+                write_break(serializer, indefinite)
+            }
+            ArenaValue::Tag(tag, v) => serializer.write_tag(*tag)?.serialize(*v),
+            ArenaValue::Special(v) => serializer.write_special(*v),
+        }
+    }
+}
+
+fn array_len(len: usize, indefinite: bool) -> Len {
+    if indefinite {
+        Len::Indefinite
+    } else {
+        Len::Len(len as u64)
+    }
+}
+
+fn write_break<W: Write>(
+    serializer: &mut Serializer<W>,
+    indefinite: bool,
+) -> Result<&mut Serializer<W>> {
+    if indefinite {
+        serializer.write_special(Special::Break)
+    } else {
+        Ok(serializer)
+    }
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// Read a Bytes into `bump`, chunked bytes are concatenated.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Bytes`.
+    pub fn bytes_in<'bump>(&mut self, bump: &'bump Bump) -> Result<&'bump [u8]> {
+        let mut bytes = BumpVec::new_in(bump);
+        self.bytes_to_writer(&mut bytes)?;
+        Ok(bytes.into_bump_slice())
+    }
+
+    /// Read a Text into `bump`, chunked texts are concatenated.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Text` or if the content is not valid UTF-8.
+    pub fn text_in<'bump>(&mut self, bump: &'bump Bump) -> Result<&'bump str> {
+        let mut bytes = BumpVec::new_in(bump);
+        self.text_to_writer(&mut bytes)?;
+        let bytes = bytes.into_bump_slice();
+        str::from_utf8(bytes).map_err(|_| invalid_utf8(bytes))
+    }
+
+    /// Read the next value into `bump`, see
+    /// [`ArenaValue`](../arena/enum.ArenaValue.html).
+    pub fn value_in<'bump>(&mut self, bump: &'bump Bump) -> Result<ArenaValue<'bump>> {
+        self.scoped(|raw| raw.value_in_entered(bump))
+    }
+
+    // read the next value into `bump`, the containers it enters are left
+    // by `value_in`
+    fn value_in_entered<'bump>(&mut self, bump: &'bump Bump) -> Result<ArenaValue<'bump>> {
+        match self.cbor_type()? {
+            Type::UnsignedInteger => Ok(ArenaValue::U64(self.unsigned_integer()?)),
+            Type::NegativeInteger => Ok(ArenaValue::I64(self.negative_integer()?)),
+            Type::Bytes => Ok(ArenaValue::Bytes(self.bytes_in(bump)?)),
+            Type::Text => Ok(ArenaValue::Text(self.text_in(bump)?)),
+            Type::Array => {
+                let len = self.array()?;
+                let mut items = BumpVec::new_in(bump);
+                while self.has_next(len, items.len())? {
+                    items.push(self.value_in(bump)?);
+                }
+                match len {
+                    Len::Len(_) => Ok(ArenaValue::Array(items.into_bump_slice())),
+                    Len::Indefinite => Ok(ArenaValue::IArray(items.into_bump_slice())),
+                }
+            }
+            Type::Map => {
+                let len = self.map()?;
+                let mut entries = BumpVec::new_in(bump);
+                while self.has_next(len, entries.len())? {
+                    let key = self.value_in(bump)?;
+                    entries.push((key, self.value_in(bump)?));
+                }
+                match len {
+                    Len::Len(_) => Ok(ArenaValue::Object(entries.into_bump_slice())),
+                    Len::Indefinite => Ok(ArenaValue::IObject(entries.into_bump_slice())),
+                }
+            }
+            Type::Tag => {
+                let tag = self.tag()?;
+                Ok(ArenaValue::Tag(tag, bump.alloc(self.value_in(bump)?)))
+            }
+            Type::Special => match self.special()? {
+                Special::Break => Err(Error::UnexpectedBreak),
+                special => Ok(ArenaValue::Special(special)),
+            },
+        }
+    }
+
+    // whether there is another element to read in a container of length
+    // `len` of which `read` elements have been read
+    fn has_next(&mut self, len: Len, read: usize) -> Result<bool> {
+        match len {
+            Len::Len(len) => Ok((read as u64) < len),
+            Len::Indefinite => Ok(!self.special_break()?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // {_ "a": [1, -2, (_ h'01', h'02')], 3: 24("é")}
+        let bytes = [
+            0xbf, 0x61, 0x61, 0x83, 0x01, 0x21, 0x5f, 0x41, 0x01, 0x41, 0x02, 0xff, 0x03, 0xd8,
+            0x18, 0x62, 0xc3, 0xa9, 0xff,
+        ];
+        let bump = Bump::new();
+        let value = Deserializer::from(&bytes[..]).value_in(&bump).unwrap();
+
+        let entries = match value {
+            ArenaValue::IObject(entries) => entries,
+            _ => panic!("expected an indefinite map"),
+        };
+        assert_eq!(entries[0].0, ArenaValue::Text("a"));
+        assert_eq!(
+            entries[0].1,
+            ArenaValue::Array(&[
+                ArenaValue::U64(1),
+                ArenaValue::I64(-2),
+                ArenaValue::Bytes(&[1, 2])
+            ])
+        );
+        assert_eq!(entries[1].1, ArenaValue::Tag(24, &ArenaValue::Text("é")));
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&value).unwrap();
+        // the chunks of the byte string are concatenated
+        assert_eq!(
+            se.finalize(),
+            vec![
+                0xbf, 0x61, 0x61, 0x83, 0x01, 0x21, 0x42, 0x01, 0x02, 0x03, 0xd8, 0x18, 0x62, 0xc3,
+                0xa9, 0xff
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let bump = Bump::new();
+        assert!(Deserializer::from(&[0x81, 0xff][..])
+            .value_in(&bump)
+            .is_err());
+        assert!(Deserializer::from(&[0x62, 0xc3, 0x28][..])
+            .text_in(&bump)
+            .is_err());
+        assert!(Deserializer::from(&[0x01][..]).bytes_in(&bump).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! Decode from asynchronous readers
+//!
+//! An [`AsyncDeserializer`] decodes from a tokio `AsyncBufRead`, a socket
+//! for example, without buffering whole messages first. It mirrors the
+//! main functions of the [`Deserializer`], returning futures.
+//!
+//! The bytes of the next object are copied into an internal buffer as
+//! they arrive and decoded with a [`Deserializer`] once complete: the
+//! headers for [`array`], [`map`], [`tag`] and the integers, the whole
+//! object for [`bytes`], [`text`] and [`deserialize`]. The buffered bytes
+//! are kept when a future is dropped before completion, so the functions
+//! can be used in `select!` loops.
+//!
+//! [`AsyncDeserializer`]: ./struct.AsyncDeserializer.html
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//! [`array`]: ./struct.AsyncDeserializer.html#method.array
+//! [`map`]: ./struct.AsyncDeserializer.html#method.map
+//! [`tag`]: ./struct.AsyncDeserializer.html#method.tag
+//! [`bytes`]: ./struct.AsyncDeserializer.html#method.bytes
+//! [`text`]: ./struct.AsyncDeserializer.html#method.text
+//! [`deserialize`]: ./struct.AsyncDeserializer.html#method.deserialize
+//!
+//! ```
+//! use cbor_event::{async_de::AsyncDeserializer, Len};
+//! # use std::{future::Future, pin::pin, ptr, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+//! # fn block_on<F: Future>(future: F) -> F::Output {
+//! #     fn clone(_: *const ()) -> RawWaker {
+//! #         RawWaker::new(ptr::null(), &VTABLE)
+//! #     }
+//! #     fn noop(_: *const ()) {}
+//! #     static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+//! #     let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+//! #     let mut future = pin!(future);
+//! #     loop {
+//! #         if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+//! #             return output;
+//! #         }
+//! #     }
+//! # }
+//!
+//! // [1000, "text", [1, 2]]
+//! let bytes = vec![0x83, 0x19, 0x03, 0xe8, 0x64, 0x74, 0x65, 0x78, 0x74, 0x82, 0x01, 0x02];
+//! let mut raw = AsyncDeserializer::from(&bytes[..]);
+//!
+//! assert_eq!(block_on(raw.array()).unwrap(), Len::Len(3));
+//! assert_eq!(block_on(raw.unsigned_integer()).unwrap(), 1000);
+//! assert_eq!(block_on(raw.text()).unwrap(), "text");
+//! assert_eq!(block_on(raw.deserialize::<Vec<u64>>()).unwrap(), vec![1, 2]);
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+use len::Len;
+use result::Result;
+use scan::{self, Walker};
+use std::{
+    convert::TryFrom,
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::io::AsyncBufRead;
+use types::{Special, Type};
+
+/// asynchronous counterpart of the
+/// [`Deserializer`](../de/struct.Deserializer.html), see the
+/// [module documentation](./index.html).
+pub struct AsyncDeserializer<R> {
+    reader: R,
+    // the bytes of the next object received so far
+    buffer: Vec<u8>,
+}
+
+impl<R> From<R> for AsyncDeserializer<R> {
+    fn from(reader: R) -> Self {
+        AsyncDeserializer {
+            reader,
+            buffer: Vec::new(),
+        }
+    }
+}
+impl<R> AsyncDeserializer<R> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &R {
+        &self.reader
+    }
+    pub fn as_mut_ref(&mut self) -> &mut R {
+        &mut self.reader
+    }
+    /// returns the reader, the bytes of a partially received object are
+    /// lost.
+    pub fn inner(self) -> R {
+        self.reader
+    }
+}
+impl<R: AsyncBufRead + Unpin> AsyncDeserializer<R> {
+    /// the [`Type`](../enum.Type.html) of the next object, without
+    /// consuming it.
+    pub fn cbor_type(&mut self) -> impl Future<Output = Result<Type>> + '_ {
+        poll_fn(move |cx| {
+            ready!(self.poll_fill(cx, 1))?;
+            Poll::Ready(Ok(Type::from(self.buffer[0])))
+        })
+    }
+
+    pub fn unsigned_integer(&mut self) -> impl Future<Output = Result<u64>> + '_ {
+        self.header_with(|raw| raw.unsigned_integer())
+    }
+
+    pub fn negative_integer(&mut self) -> impl Future<Output = Result<i64>> + '_ {
+        self.header_with(|raw| raw.negative_integer())
+    }
+
+    pub fn array(&mut self) -> impl Future<Output = Result<Len>> + '_ {
+        self.header_with(|raw| raw.array())
+    }
+
+    pub fn map(&mut self) -> impl Future<Output = Result<Len>> + '_ {
+        self.header_with(|raw| raw.map())
+    }
+
+    pub fn tag(&mut self) -> impl Future<Output = Result<u64>> + '_ {
+        self.header_with(|raw| raw.tag())
+    }
+
+    pub fn special(&mut self) -> impl Future<Output = Result<Special>> + '_ {
+        self.header_with(|raw| raw.special())
+    }
+
+    pub fn bool(&mut self) -> impl Future<Output = Result<bool>> + '_ {
+        self.header_with(|raw| raw.bool())
+    }
+
+    /// consume the next object if it is a `Special::Break`, see
+    /// [`Deserializer::special_break`](../de/struct.Deserializer.html#method.special_break).
+    pub fn special_break(&mut self) -> impl Future<Output = Result<bool>> + '_ {
+        self.header_with(|raw| raw.special_break())
+    }
+
+    /// byte string, definite or indefinite length.
+    pub fn bytes(&mut self) -> impl Future<Output = Result<Vec<u8>>> + '_ {
+        self.item_with(|raw| raw.bytes())
+    }
+
+    /// text, definite or indefinite length.
+    pub fn text(&mut self) -> impl Future<Output = Result<String>> + '_ {
+        self.item_with(|raw| raw.text())
+    }
+
+    /// consume the next object, nested objects included, checking it is
+    /// well-formed.
+    pub fn skip_value(&mut self) -> impl Future<Output = Result<()>> + '_ {
+        self.item_with(|raw| raw.validate_item())
+    }
+
+    /// consume the next object, returning its encoding exactly as read.
+    pub fn read_raw_value(&mut self) -> impl Future<Output = Result<Vec<u8>>> + '_ {
+        self.item_with(|raw| raw.read_raw_value())
+    }
+
+    /// receive the next object entirely then decode it as a `T`.
+    pub fn deserialize<'a, T: Deserialize + 'a>(
+        &'a mut self,
+    ) -> impl Future<Output = Result<T>> + 'a {
+        self.item_with(|raw| raw.deserialize())
+    }
+
+    /// same as [`deserialize`](#method.deserialize), then check there is
+    /// nothing left to read.
+    pub fn deserialize_complete<'a, T: Deserialize + 'a>(
+        &'a mut self,
+    ) -> impl Future<Output = Result<T>> + 'a {
+        let mut item = Walker::default();
+        let mut end = 0;
+        let mut value = None;
+        poll_fn(move |cx| {
+            if value.is_none() {
+                ready!(self.poll_item(cx, &mut item, &mut end))?;
+                value = Some(self.decode(|raw| raw.deserialize())?);
+            }
+            if !self.buffer.is_empty() {
+                return Poll::Ready(Err(Error::TrailingData));
+            }
+            if !ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?.is_empty() {
+                return Poll::Ready(Err(Error::TrailingData));
+            }
+            Poll::Ready(Ok(value.take().expect("polled after completion")))
+        })
+    }
+
+    // decode the next header with `f` once it is buffered
+    fn header_with<'a, T, F>(&'a mut self, f: F) -> impl Future<Output = Result<T>> + 'a
+    where
+        T: 'a,
+        F: FnOnce(&mut Deserializer<&[u8]>) -> Result<T> + 'a,
+    {
+        let mut f = Some(f);
+        poll_fn(move |cx| {
+            ready!(self.poll_header(cx))?;
+            Poll::Ready(self.decode(f.take().expect("polled after completion")))
+        })
+    }
+
+    // decode the next object with `f` once it is entirely buffered
+    fn item_with<'a, T, F>(&'a mut self, f: F) -> impl Future<Output = Result<T>> + 'a
+    where
+        T: 'a,
+        F: FnOnce(&mut Deserializer<&[u8]>) -> Result<T> + 'a,
+    {
+        let mut f = Some(f);
+        let mut walker = Walker::default();
+        let mut end = 0;
+        poll_fn(move |cx| {
+            ready!(self.poll_item(cx, &mut walker, &mut end))?;
+            Poll::Ready(self.decode(f.take().expect("polled after completion")))
+        })
+    }
+
+    // decode from the buffered bytes, dropping the ones `f` consumed
+    fn decode<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Deserializer<&[u8]>) -> Result<T>,
+    {
+        let mut raw = Deserializer::from(&self.buffer[..]);
+        let value = f(&mut raw)?;
+        let consumed = raw.position() as usize;
+        self.buffer.drain(..consumed);
+        Ok(value)
+    }
+
+    // read until the buffer holds at least `n` bytes
+    fn poll_fill(&mut self, cx: &mut Context<'_>, n: usize) -> Poll<Result<()>> {
+        while self.buffer.len() < n {
+            let available = ready!(Pin::new(&mut self.reader).poll_fill_buf(cx))?;
+            if available.is_empty() {
+                return Poll::Ready(Err(Error::NotEnough(self.buffer.len(), n)));
+            }
+            let len = available.len().min(n - self.buffer.len());
+            self.buffer.extend_from_slice(&available[..len]);
+            Pin::new(&mut self.reader).consume(len);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    // read until the header of the next object is buffered
+    fn poll_header(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            match scan::parse_header(&self.buffer) {
+                Err(Error::NotEnough(_, needed)) => ready!(self.poll_fill(cx, needed))?,
+                header => return Poll::Ready(header.map(|_| ())),
+            }
+        }
+    }
+
+    // read until the next object is buffered entirely. `walker` and `end`,
+    // the size of the headers and payloads walked so far, keep the
+    // progress between two polls.
+    fn poll_item(
+        &mut self,
+        cx: &mut Context<'_>,
+        walker: &mut Walker,
+        end: &mut usize,
+    ) -> Poll<Result<()>> {
+        loop {
+            ready!(self.poll_fill(cx, *end))?;
+            if walker.is_finished() {
+                return Poll::Ready(Ok(()));
+            }
+            let (cbor_type, len, sz) = match scan::parse_header(&self.buffer[*end..]) {
+                Err(Error::NotEnough(_, needed)) => {
+                    ready!(self.poll_fill(cx, *end + needed))?;
+                    continue;
+                }
+                header => header?,
+            };
+            let payload = walker.header(cbor_type, len, sz)?;
+            *end = usize::try_from(payload)
+                .ok()
+                .and_then(|payload| end.checked_add(sz + payload))
+                .ok_or(Error::NotEnough(self.buffer.len(), usize::MAX))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io,
+        pin::pin,
+        ptr,
+        task::{Poll, RawWaker, RawWakerVTable, Waker},
+    };
+    use value::Value;
+
+    // waker doing nothing, the futures are polled in a loop
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        // the functions of the vtable ignore the data pointer
+        unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    // reader returning one byte at a time, after a `Pending` every time
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        ready: bool,
+    }
+    impl<'a> tokio::io::AsyncRead for Trickle<'a> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            unimplemented!()
+        }
+    }
+    impl<'a> AsyncBufRead for Trickle<'a> {
+        fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+            let this = self.get_mut();
+            if !this.ready {
+                this.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(Ok(&this.bytes[..this.bytes.len().min(1)]))
+        }
+        fn consume(self: Pin<&mut Self>, amt: usize) {
+            let this = self.get_mut();
+            this.bytes = &this.bytes[amt..];
+            this.ready = false;
+        }
+    }
+
+    #[test]
+    fn trickle() {
+        // [_ h'0102', {"a": -500}, 1(2)], then "end"
+        let bytes = [
+            0x9f, 0x42, 0x01, 0x02, 0xa1, 0x61, 0x61, 0x39, 0x01, 0xf3, 0xc1, 0x02, 0xff, 0x63,
+            0x65, 0x6e, 0x64,
+        ];
+        let mut raw = AsyncDeserializer::from(Trickle {
+            bytes: &bytes,
+            ready: false,
+        });
+        assert_eq!(block_on(raw.cbor_type()).unwrap(), Type::Array);
+        assert_eq!(block_on(raw.array()).unwrap(), Len::Indefinite);
+        assert!(!block_on(raw.special_break()).unwrap());
+        assert_eq!(block_on(raw.bytes()).unwrap(), vec![1, 2]);
+        let value: Value = block_on(raw.deserialize()).unwrap();
+        assert_eq!(value.as_map().unwrap().len(), 1);
+        assert_eq!(block_on(raw.tag()).unwrap(), 1);
+        assert_eq!(block_on(raw.unsigned_integer()).unwrap(), 2);
+        assert!(block_on(raw.special_break()).unwrap());
+        assert_eq!(block_on(raw.read_raw_value()).unwrap(), &bytes[13..]);
+        assert!(matches!(
+            block_on(raw.cbor_type()),
+            Err(Error::NotEnough(0, 1))
+        ));
+    }
+
+    #[test]
+    fn cancelled() {
+        // "hello", 3
+        let bytes = [0x65, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x03];
+        let mut raw = AsyncDeserializer::from(Trickle {
+            bytes: &bytes,
+            ready: false,
+        });
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..5 {
+            let future = pin!(raw.text());
+            assert!(future.poll(&mut cx).is_pending());
+        }
+        assert_eq!(block_on(raw.text()).unwrap(), "hello");
+        assert_eq!(block_on(raw.deserialize_complete::<u64>()).unwrap(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        let bytes = [0x61, 0x61, 0x01];
+        let mut raw = AsyncDeserializer::from(&bytes[..]);
+        assert!(matches!(
+            block_on(raw.unsigned_integer()),
+            Err(Error::Expected(Type::UnsignedInteger, Type::Text))
+        ));
+        assert!(matches!(
+            block_on(raw.deserialize_complete::<String>()),
+            Err(Error::TrailingData)
+        ));
+
+        // a byte string announcing more bytes than the input holds
+        let mut raw = AsyncDeserializer::from(&[0x5b, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0][..]);
+        assert!(matches!(block_on(raw.bytes()), Err(Error::NotEnough(..))));
+        let mut raw = AsyncDeserializer::from(&[0x81, 0xff][..]);
+        assert!(matches!(
+            block_on(raw.skip_value()),
+            Err(Error::UnexpectedBreak)
+        ));
+    }
+}
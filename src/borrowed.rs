@@ -0,0 +1,466 @@
+use de::{invalid_utf8, Deserializer, DEFAULT_MAX_DEPTH};
+use error::Error;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::{borrow::Cow, collections::BTreeMap, io::Write, str};
+use types::{Special, Type};
+use value::{ObjectKey, Value};
+
+/// key of a [`BorrowedValue`](./enum.BorrowedValue.html) map, the
+/// borrowed equivalent of [`ObjectKey`](./enum.ObjectKey.html).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BorrowedKey<'a> {
+    Integer(u64),
+    Bytes(Cow<'a, [u8]>),
+    Text(Cow<'a, str>),
+}
+impl<'a> BorrowedKey<'a> {
+    /// copy the strings borrowed from the input into an `ObjectKey`
+    pub fn into_owned(self) -> ObjectKey {
+        match self {
+            BorrowedKey::Integer(v) => ObjectKey::Integer(v),
+            BorrowedKey::Bytes(v) => ObjectKey::Bytes(v.into_owned()),
+            BorrowedKey::Text(v) => ObjectKey::Text(v.into_owned()),
+        }
+    }
+}
+
+/// equivalent of [`Value`](./enum.Value.html) whose byte and text strings
+/// borrow from the decoded slice.
+///
+/// Only the containers are allocated, so inspecting a tree and discarding
+/// it costs much less than building a `Value`. The strings encoded in
+/// chunks are the only ones copied, to join their chunks. Unlike `Value`
+/// the entries of the maps are kept in the order of the input.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, BorrowedValue, Value};
+/// use std::borrow::Cow;
+///
+/// // ["text", h'0102']
+/// let bytes = [0x82, 0x64, 0x74, 0x65, 0x78, 0x74, 0x42, 0x01, 0x02];
+///
+/// let value = Deserializer::from(&bytes[..]).borrowed_value().unwrap();
+/// match value {
+///     BorrowedValue::Array(ref items) => match items[0] {
+///         BorrowedValue::Text(Cow::Borrowed(text)) => assert_eq!(text, "text"),
+///         _ => panic!("expected a borrowed text"),
+///     },
+///     _ => panic!("expected an array"),
+/// }
+/// assert_eq!(
+///     value.into_owned(),
+///     Value::Array(vec![Value::Text("text".to_owned()), Value::Bytes(vec![1, 2])])
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    U64(u64),
+    I64(i64),
+    Bytes(Cow<'a, [u8]>),
+    Text(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    IArray(Vec<BorrowedValue<'a>>),
+    Object(Vec<(BorrowedKey<'a>, BorrowedValue<'a>)>),
+    IObject(Vec<(BorrowedKey<'a>, BorrowedValue<'a>)>),
+    Tag(u64, Box<BorrowedValue<'a>>),
+    Special(Special),
+}
+impl<'a> BorrowedValue<'a> {
+    /// copy the strings borrowed from the input into a `Value`. For keys
+    /// present more than once in a map, the last value is kept.
+    pub fn into_owned(self) -> Value {
+        fn entries(entries: Vec<(BorrowedKey, BorrowedValue)>) -> BTreeMap<ObjectKey, Value> {
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                .collect()
+        }
+        match self {
+            BorrowedValue::U64(v) => Value::U64(v),
+            BorrowedValue::I64(v) => Value::I64(v),
+            BorrowedValue::Bytes(v) => Value::Bytes(v.into_owned()),
+            BorrowedValue::Text(v) => Value::Text(v.into_owned()),
+            BorrowedValue::Array(v) => {
+                Value::Array(v.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+            BorrowedValue::IArray(v) => {
+                Value::IArray(v.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+            BorrowedValue::Object(v) => Value::Object(entries(v)),
+            BorrowedValue::IObject(v) => Value::IObject(entries(v)),
+            BorrowedValue::Tag(tag, v) => Value::Tag(tag, Box::new(v.into_owned())),
+            BorrowedValue::Special(v) => Value::Special(v),
+        }
+    }
+}
+impl<'a> From<BorrowedValue<'a>> for Value {
+    fn from(value: BorrowedValue<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+impl<'a> Serialize for BorrowedKey<'a> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        match self {
+            BorrowedKey::Integer(v) => serializer.write_unsigned_integer(*v),
+            BorrowedKey::Bytes(v) => serializer.write_bytes(v),
+            BorrowedKey::Text(v) => serializer.write_text(v),
+        }
+    }
+}
+impl<'a> Serialize for BorrowedValue<'a> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        match self {
+            BorrowedValue::U64(v) => serializer.write_unsigned_integer(*v),
+            BorrowedValue::I64(v) => serializer.write_integer(*v),
+            BorrowedValue::Bytes(v) => serializer.write_bytes(v),
+            BorrowedValue::Text(v) => serializer.write_text(v),
+            BorrowedValue::Array(v) => {
+                serializer.write_array(Len::Len(v.len() as u64))?;
+                for element in v {
+                    serializer.serialize(element)?;
+                }
+                Ok(serializer)
+            }
+            BorrowedValue::IArray(v) => {
+                serializer.write_array(Len::Indefinite)?;
+                for element in v {
+                    serializer.serialize(element)?;
+                }
+                serializer.write_special(Special::Break)
+            }
+            BorrowedValue::Object(v) => {
+                serializer.write_map(Len::Len(v.len() as u64))?;
+                for (key, value) in v {
+                    serializer.serialize(key)?.serialize(value)?;
+                }
+                Ok(serializer)
+            }
+            BorrowedValue::IObject(v) => {
+                serializer.write_map(Len::Indefinite)?;
+                for (key, value) in v {
+                    serializer.serialize(key)?.serialize(value)?;
+                }
+                serializer.write_special(Special::Break)
+            }
+            BorrowedValue::Tag(tag, v) => serializer.write_tag(*tag)?.serialize(v.as_ref()),
+            BorrowedValue::Special(v) => serializer.write_special(*v),
+        }
+    }
+}
+
+impl<'a> Deserializer<&'a [u8]> {
+    /// Read a Bytes borrowing from the input, only the bytes encoded in
+    /// chunks are copied.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Bytes`.
+    pub fn borrowed_bytes(&mut self) -> Result<Cow<'a, [u8]>> {
+        self.cbor_expect_type(Type::Bytes)?;
+        match self.borrow_payload()? {
+            Some(bytes) => Ok(Cow::Borrowed(bytes)),
+            None => self.bytes().map(Cow::Owned),
+        }
+    }
+
+    /// Read a Text borrowing from the input, only the texts encoded in
+    /// chunks are copied.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Text` or if the content is not valid UTF-8.
+    pub fn borrowed_text(&mut self) -> Result<Cow<'a, str>> {
+        self.cbor_expect_type(Type::Text)?;
+        match self.borrow_payload()? {
+            Some(bytes) => str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|_| invalid_utf8(bytes)),
+            None => self.text().map(Cow::Owned),
+        }
+    }
+
+    /// Read a definite length Bytes, borrowed from the input without any
+    /// copy.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Bytes` or if the Bytes is encoded in chunks, use
+    /// [`borrowed_bytes`](#method.borrowed_bytes) to accept them too.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // [h'0102', "text", (_ h'03')]
+    /// let vec = vec![0x83, 0x42, 0x01, 0x02, 0x64, 0x74, 0x65, 0x78, 0x74, 0x5f, 0x41, 0x03, 0xff];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// assert!(std::ptr::eq(raw.bytes_borrowed().unwrap(), &vec[2..4]));
+    /// assert_eq!(raw.text_borrowed().unwrap(), "text");
+    /// assert!(raw.bytes_borrowed().is_err());
+    /// ```
+    pub fn bytes_borrowed(&mut self) -> Result<&'a [u8]> {
+        self.cbor_expect_type(Type::Bytes)?;
+        self.borrow_payload()?
+            .ok_or(Error::IndefiniteLenNotSupported(Type::Bytes))
+    }
+
+    /// Read a definite length Text, borrowed from the input without any
+    /// copy.
+    ///
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Text`, if the content is not valid UTF-8 or if the Text is
+    /// encoded in chunks, use [`borrowed_text`](#method.borrowed_text) to
+    /// accept them too.
+    pub fn text_borrowed(&mut self) -> Result<&'a str> {
+        self.cbor_expect_type(Type::Text)?;
+        let bytes = self
+            .borrow_payload()?
+            .ok_or(Error::IndefiniteLenNotSupported(Type::Text))?;
+        str::from_utf8(bytes).map_err(|_| invalid_utf8(bytes))
+    }
+
+    /// Read the next value, borrowing its strings from the input, see
+    /// [`BorrowedValue`](../enum.BorrowedValue.html).
+    ///
+    /// The value is decoded recursively: without a limit set with
+    /// [`set_max_depth`](#method.set_max_depth), it nests at most
+    /// [`DEFAULT_MAX_DEPTH`](../de/constant.DEFAULT_MAX_DEPTH.html) arrays,
+    /// maps and tags.
+    pub fn borrowed_value(&mut self) -> Result<BorrowedValue<'a>> {
+        let max_depth = self.max_depth();
+        if max_depth == usize::MAX {
+            let limit = self.depth().saturating_add(DEFAULT_MAX_DEPTH);
+            self.set_max_depth(limit);
+        }
+        let value = self.scoped(|raw| raw.borrowed_value_entered());
+        self.set_max_depth(max_depth);
+        value
+    }
+
+    // read the next value, the containers it enters are left by
+    // `borrowed_value`
+    fn borrowed_value_entered(&mut self) -> Result<BorrowedValue<'a>> {
+        match self.cbor_type()? {
+            Type::UnsignedInteger => Ok(BorrowedValue::U64(self.unsigned_integer()?)),
+            Type::NegativeInteger => Ok(BorrowedValue::I64(self.negative_integer()?)),
+            Type::Bytes => Ok(BorrowedValue::Bytes(self.borrowed_bytes()?)),
+            Type::Text => Ok(BorrowedValue::Text(self.borrowed_text()?)),
+            Type::Array => {
+                let len = self.array()?;
+                let mut items = Vec::new();
+                self.borrowed_items(len, |raw| {
+                    items.push(raw.borrowed_value()?);
+                    Ok(())
+                })?;
+                match len {
+                    Len::Len(_) => Ok(BorrowedValue::Array(items)),
+                    Len::Indefinite => Ok(BorrowedValue::IArray(items)),
+                }
+            }
+            Type::Map => {
+                let len = self.map()?;
+                let mut entries = Vec::new();
+                self.borrowed_items(len, |raw| {
+                    let key = raw.borrowed_key()?;
+                    entries.push((key, raw.borrowed_value()?));
+                    Ok(())
+                })?;
+                match len {
+                    Len::Len(_) => Ok(BorrowedValue::Object(entries)),
+                    Len::Indefinite => Ok(BorrowedValue::IObject(entries)),
+                }
+            }
+            Type::Tag => {
+                let tag = self.tag()?;
+                Ok(BorrowedValue::Tag(tag, Box::new(self.borrowed_value()?)))
+            }
+            Type::Special => match self.special()? {
+                Special::Break => Err(Error::UnexpectedBreak),
+                special => Ok(BorrowedValue::Special(special)),
+            },
+        }
+    }
+
+    fn borrowed_key(&mut self) -> Result<BorrowedKey<'a>> {
+        match self.cbor_type()? {
+            Type::UnsignedInteger => Ok(BorrowedKey::Integer(self.unsigned_integer()?)),
+            Type::Bytes => Ok(BorrowedKey::Bytes(self.borrowed_bytes()?)),
+            Type::Text => Ok(BorrowedKey::Text(self.borrowed_text()?)),
+            t => Err(Error::CustomError(format!(
+                "Type `{:?}' is not a support type for CBOR Map's key",
+                t
+            ))),
+        }
+    }
+
+    // call `f` for each item of a container of length `len`
+    fn borrowed_items<F>(&mut self, len: Len, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
+    {
+        match len {
+            Len::Len(len) => {
+                for _ in 0..len {
+                    f(self)?;
+                }
+            }
+            Len::Indefinite => {
+                while !self.special_break()? {
+                    f(self)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // consume the header and the payload of the string of definite length
+    // at the head of the input and return the payload, `None` if the
+    // string is encoded in chunks
+    fn borrow_payload(&mut self) -> Result<Option<&'a [u8]>> {
+        let (len, sz) = self.cbor_len()?;
+        let len = match len {
+            Len::Indefinite => return Ok(None),
+            Len::Len(len) => len,
+        };
+        let input: &'a [u8] = self.as_ref();
+        let start = 1 + sz;
+        let available = (input.len() - start) as u64;
+        if len > available {
+            return Err(Error::NotEnough(available as usize, len as usize));
+        }
+        let end = start + len as usize;
+        self.advance(end)?;
+        Ok(Some(&input[start..end]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn borrowed_and_chunked() {
+        // {_ "a": (_ "b", "c"), h'01': [1, -2, 1(null)]}
+        let bytes = [
+            0xbf, 0x61, 0x61, 0x7f, 0x61, 0x62, 0x61, 0x63, 0xff, 0x41, 0x01, 0x83, 0x01, 0x21,
+            0xc1, 0xf6, 0xff,
+        ];
+        let mut raw = Deserializer::from(&bytes[..]);
+        let value = raw.borrowed_value().unwrap();
+        assert!(raw.as_ref().is_empty());
+
+        let entries = match value {
+            BorrowedValue::IObject(ref entries) => entries,
+            _ => panic!("expected an indefinite map"),
+        };
+        assert!(matches!(
+            entries[0].0,
+            BorrowedKey::Text(Cow::Borrowed("a"))
+        ));
+        match entries[0].1 {
+            BorrowedValue::Text(Cow::Owned(ref text)) => assert_eq!(text, "bc"),
+            _ => panic!("expected a joined text"),
+        }
+        assert!(matches!(
+            entries[1].0,
+            BorrowedKey::Bytes(Cow::Borrowed(&[1]))
+        ));
+
+        let owned: Value = Deserializer::from(&bytes[..]).deserialize().unwrap();
+        assert_eq!(value.clone().into_owned(), owned);
+
+        // the entries stay in order, the chunks are joined
+        let mut se = Serializer::new_vec();
+        se.serialize(&value).unwrap();
+        assert_eq!(
+            se.finalize(),
+            vec![
+                0xbf, 0x61, 0x61, 0x62, 0x62, 0x63, 0x41, 0x01, 0x83, 0x01, 0x21, 0xc1, 0xf6, 0xff
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        // truncated payload
+        assert!(Deserializer::from(&[0x43, 0x01][..])
+            .borrowed_bytes()
+            .is_err());
+        // invalid UTF-8
+        assert!(Deserializer::from(&[0x62, 0xc3, 0x28][..])
+            .borrowed_text()
+            .is_err());
+        // array key
+        assert!(Deserializer::from(&[0xa1, 0x80, 0x00][..])
+            .borrowed_value()
+            .is_err());
+        assert!(Deserializer::from(&[0xff][..]).borrowed_value().is_err());
+        // nested deeper than the default limit
+        let mut bytes = vec![0x81; DEFAULT_MAX_DEPTH + 1];
+        bytes.push(0x00);
+        assert!(matches!(
+            Deserializer::from(&bytes[..]).borrowed_value(),
+            Err(Error::MaxDepthExceeded)
+        ));
+        let mut raw = Deserializer::from(&bytes[1..]);
+        assert!(raw.borrowed_value().is_ok());
+        assert_eq!(raw.max_depth(), usize::MAX);
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(DEFAULT_MAX_DEPTH + 1);
+        assert!(raw.borrowed_value().is_ok());
+    }
+
+    #[test]
+    fn definite_strings_borrowed() {
+        // [h'', h'0102', "", "é"]
+        let bytes = [0x84, 0x40, 0x42, 0x01, 0x02, 0x60, 0x62, 0xc3, 0xa9];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.array().unwrap();
+        assert_eq!(raw.bytes_borrowed().unwrap(), &[] as &[u8]);
+        let payload = raw.bytes_borrowed().unwrap();
+        assert!(std::ptr::eq(payload, &bytes[3..5]));
+        assert_eq!(raw.text_borrowed().unwrap(), "");
+        let text = raw.text_borrowed().unwrap();
+        assert_eq!(text, "é");
+        assert!(std::ptr::eq(text.as_bytes(), &bytes[7..]));
+        assert!(raw.as_ref().is_empty());
+
+        // truncated payload
+        assert!(matches!(
+            Deserializer::from(&[0x43, 0x01][..]).bytes_borrowed(),
+            Err(Error::NotEnough(..))
+        ));
+        assert!(Deserializer::from(&[][..]).text_borrowed().is_err());
+        // invalid UTF-8
+        assert!(Deserializer::from(&[0x62, 0xc3, 0x28][..])
+            .text_borrowed()
+            .is_err());
+        // chunks
+        match Deserializer::from(&[0x5f, 0x41, 0x01, 0xff][..]).bytes_borrowed() {
+            Err(Error::IndefiniteLenNotSupported(Type::Bytes)) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+        match Deserializer::from(&[0x7f, 0x61, 0x61, 0xff][..]).text_borrowed() {
+            Err(Error::IndefiniteLenNotSupported(Type::Text)) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+        // wrong type
+        assert!(matches!(
+            Deserializer::from(&[0x61, 0x61][..]).bytes_borrowed(),
+            Err(Error::Expected(Type::Bytes, Type::Text))
+        ));
+        assert!(matches!(
+            Deserializer::from(&[0x41, 0x61][..]).text_borrowed(),
+            Err(Error::Expected(Type::Text, Type::Bytes))
+        ));
+    }
+}
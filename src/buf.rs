@@ -0,0 +1,188 @@
+//! Decode from non contiguous [`bytes::Buf`] sources
+//!
+//! Network stacks often hand over data as a chain of buffers. Wrapping
+//! such a [`Buf`] in a [`SegmentedReader`] lets a [`Deserializer`] decode
+//! it directly, without first copying everything into one contiguous
+//! slice. Only the headers straddling two segments are copied, into a
+//! small internal buffer.
+//!
+//! Only available with the `bytes` feature.
+//!
+//! [`bytes::Buf`]: https://docs.rs/bytes/1/bytes/trait.Buf.html
+//! [`Buf`]: https://docs.rs/bytes/1/bytes/trait.Buf.html
+//! [`SegmentedReader`]: ./struct.SegmentedReader.html
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//!
+//! ```
+//! # extern crate bytes;
+//! # extern crate cbor_event;
+//! use bytes::Buf;
+//! use cbor_event::{buf::SegmentedReader, de::Deserializer};
+//!
+//! # fn main() {
+//! // [1000, "text"] received in two segments, splitting the integer
+//! let segments = (&[0x82, 0x19, 0x03][..]).chain(&[0xe8, 0x64, 0x74, 0x65, 0x78, 0x74][..]);
+//! let mut raw = Deserializer::from(SegmentedReader::new(segments));
+//!
+//! raw.array().unwrap();
+//! assert_eq!(raw.unsigned_integer().unwrap(), 1000);
+//! assert_eq!(raw.text().unwrap(), "text");
+//! # }
+//! ```
+
+use bytes::Buf;
+use std::io::{self, BufRead, Read};
+use stitch::{Stitched, MAX_HEADER_SIZE};
+
+/// reader over a [`Buf`](https://docs.rs/bytes/1/bytes/trait.Buf.html),
+/// making sure a whole CBOR header is always available to the
+/// [`Deserializer`](../de/struct.Deserializer.html).
+pub struct SegmentedReader<B> {
+    buf: B,
+    // bytes copied out of `buf` when a header straddles two segments
+    stitched: Stitched,
+}
+impl<B: Buf> SegmentedReader<B> {
+    pub fn new(buf: B) -> Self {
+        SegmentedReader {
+            buf,
+            stitched: Stitched::new(),
+        }
+    }
+
+    /// returns the underlying `Buf`. Bytes returned by the last
+    /// `fill_buf` may already have been taken out of it.
+    pub fn get_ref(&self) -> &B {
+        &self.buf
+    }
+
+    /// number of bytes left to read
+    pub fn remaining(&self) -> usize {
+        self.stitched.len() + self.buf.remaining()
+    }
+
+    pub fn into_inner(self) -> B {
+        self.buf
+    }
+}
+impl<B: Buf> Read for SegmentedReader<B> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+impl<B: Buf> BufRead for SegmentedReader<B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.stitched.check()?;
+        if self.stitched.is_empty() {
+            let chunk_len = self.buf.chunk().len();
+            if chunk_len >= MAX_HEADER_SIZE || chunk_len == self.buf.remaining() {
+                return Ok(self.buf.chunk());
+            }
+        }
+        // the next header may continue in the following segments
+        self.stitched.compact();
+        while !self.stitched.is_full() && self.buf.has_remaining() {
+            let n = self.stitched.append(self.buf.chunk());
+            self.buf.advance(n);
+        }
+        Ok(self.stitched.pending())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if !self.stitched.is_empty() {
+            self.stitched.consume(amt);
+        } else if amt > self.buf.chunk().len() {
+            self.stitched.overrun();
+        } else {
+            self.buf.advance(amt)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use se::Serializer;
+    use std::collections::VecDeque;
+    use value::Value;
+
+    // `Buf` made of segments of the given sizes
+    struct Segments(VecDeque<Vec<u8>>);
+    impl Segments {
+        fn new(bytes: &[u8], size: usize) -> Self {
+            Segments(bytes.chunks(size).map(|chunk| chunk.to_vec()).collect())
+        }
+    }
+    impl Buf for Segments {
+        fn remaining(&self) -> usize {
+            self.0.iter().map(|segment| segment.len()).sum()
+        }
+        fn chunk(&self) -> &[u8] {
+            self.0.front().map_or(&[], |segment| &segment[..])
+        }
+        fn advance(&mut self, mut cnt: usize) {
+            while cnt > 0 {
+                let front = self.0.front_mut().unwrap();
+                let n = cnt.min(front.len());
+                front.drain(..n);
+                if front.is_empty() {
+                    self.0.pop_front();
+                }
+                cnt -= n;
+            }
+        }
+    }
+
+    #[test]
+    fn every_segment_size() {
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(4))
+            .unwrap()
+            .write_unsigned_integer(0x1234_5678_9abc)
+            .unwrap()
+            .write_bytes(vec![0xaa; 300])
+            .unwrap()
+            .write_tag(1_000_000)
+            .unwrap()
+            .write_text("segmented")
+            .unwrap()
+            .write_negative_integer(-70_000)
+            .unwrap();
+        let bytes = se.finalize();
+        let expected: Value = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+
+        for size in 1..bytes.len() {
+            let reader = SegmentedReader::new(Segments::new(&bytes, size));
+            let mut raw = Deserializer::from(reader);
+            let value: Value = raw.deserialize_complete().unwrap();
+            assert_eq!(value, expected, "segments of {} bytes", size);
+            assert_eq!(raw.as_ref().remaining(), 0);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = [0x82, 0x19, 0x03];
+        let reader = SegmentedReader::new(Segments::new(&bytes, 1));
+        let mut raw = Deserializer::from(reader);
+        raw.array().unwrap();
+        assert!(raw.unsigned_integer().is_err());
+    }
+
+    #[test]
+    fn consume_overrun() {
+        let bytes = [0x82, 0x19, 0x03, 0xe8];
+        let mut reader = SegmentedReader::new(Segments::new(&bytes, 2));
+        assert_eq!(reader.fill_buf().unwrap(), bytes);
+        reader.consume(5);
+        let error = reader.fill_buf().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
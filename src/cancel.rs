@@ -0,0 +1,130 @@
+//! Cooperative cancellation of decodings
+//!
+//! A server decoding untrusted payloads may need to give up on a
+//! pathological input once the deadline of the request has passed. The
+//! [`CancellableReader`] calls a function every time the decoder asks for
+//! more input, which happens at least once per object, string chunk or
+//! refill of the buffer; when the function returns `true` the decoding
+//! fails with [`Error::Cancelled`].
+//!
+//! [`CancellableReader`]: ./struct.CancellableReader.html
+//! [`Error::Cancelled`]: ../enum.Error.html#variant.Cancelled
+//!
+//! ```
+//! use cbor_event::{cancel::CancellableReader, de::Deserializer, Error};
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//!
+//! let cancelled = AtomicBool::new(false);
+//! let bytes = [0x83, 0x01, 0x02, 0x03];
+//! let reader = CancellableReader::new(&bytes[..], || cancelled.load(Ordering::Relaxed));
+//! let mut raw = Deserializer::from(reader);
+//!
+//! raw.array().unwrap();
+//! raw.unsigned_integer().unwrap();
+//! cancelled.store(true, Ordering::Relaxed);
+//! match raw.unsigned_integer() {
+//!     Err(Error::Cancelled) => {}
+//!     result => panic!("unexpected {:?}", result),
+//! }
+//! ```
+
+use std::{
+    error, fmt,
+    io::{self, BufRead, Read},
+};
+
+// payload of the `io::Error` returned by a cancelled reader, converted to
+// `Error::Cancelled`
+#[derive(Debug)]
+pub(crate) struct Cancelled;
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cancelled")
+    }
+}
+impl error::Error for Cancelled {}
+
+/// reader adapter failing once `should_cancel` returns `true`, see the
+/// [module documentation](./index.html).
+pub struct CancellableReader<R, F> {
+    reader: R,
+    should_cancel: F,
+}
+impl<R, F: FnMut() -> bool> CancellableReader<R, F> {
+    pub fn new(reader: R, should_cancel: F) -> Self {
+        CancellableReader {
+            reader,
+            should_cancel,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn check(&mut self) -> io::Result<()> {
+        if (self.should_cancel)() {
+            Err(io::Error::new(io::ErrorKind::Other, Cancelled))
+        } else {
+            Ok(())
+        }
+    }
+}
+impl<R: Read, F: FnMut() -> bool> Read for CancellableReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        self.reader.read(buf)
+    }
+}
+impl<R: BufRead, F: FnMut() -> bool> BufRead for CancellableReader<R, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.check()?;
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use error::Error;
+    use value::Value;
+
+    #[test]
+    fn cancel_after_budget() {
+        // [_ [_ [_ ... ]]] nested 10000 times
+        let mut bytes = vec![0x9f; 10_000];
+        bytes.extend(vec![0xff; 10_000]);
+
+        let mut budget = 100;
+        let reader = CancellableReader::new(&bytes[..], || {
+            budget -= 1;
+            budget == 0
+        });
+        let mut raw = Deserializer::from(reader);
+        match raw.validate_item() {
+            Err(Error::Cancelled) => {}
+            result => panic!("unexpected {:?}", result),
+        }
+
+        let reader = CancellableReader::new(&bytes[..], || false);
+        let mut raw = Deserializer::from(reader);
+        raw.validate_item().unwrap();
+    }
+
+    #[test]
+    fn cancel_string() {
+        let bytes = [0x5f, 0x41, 0x00, 0x41, 0x00, 0xff];
+        let reader = CancellableReader::new(&bytes[..], || true);
+        let mut raw = Deserializer::from(reader);
+        assert!(matches!(raw.deserialize::<Value>(), Err(Error::Cancelled)));
+    }
+}
@@ -0,0 +1,456 @@
+//! Canonical re-encoding of encoded CBOR
+//!
+//! [`canonicalize`] re-encodes any well-formed CBOR object into the core
+//! deterministic encoding of RFC 8949 (section 4.2.1): integers, lengths
+//! and tags on the smallest header, definite lengths only, map keys sorted
+//! in the bytewise lexicographic order of their encodings and floating
+//! point values in their shortest exact form. Two encodings of the same
+//! data canonicalize to the same bytes, which is what should be hashed or
+//! signed.
+//!
+//! [`canonicalize_with`] sorts the keys in the length-first order of the
+//! canonical CBOR of RFC 7049 instead, still used by older protocols.
+//!
+//! [`to_definite`] only converts the indefinite lengths, for consumers not
+//! supporting them.
+//!
+//! [`equivalent`] and [`cmp_canonical`] compare two encodings by their
+//! content, whatever the encoding choices of their producers.
+//!
+//! [`canonicalize`]: ./fn.canonicalize.html
+//! [`canonicalize_with`]: ./fn.canonicalize_with.html
+//! [`to_definite`]: ./fn.to_definite.html
+//! [`equivalent`]: ./fn.equivalent.html
+//! [`cmp_canonical`]: ./fn.cmp_canonical.html
+//!
+//! ```
+//! use cbor_event::canonical::canonicalize;
+//!
+//! // {_ "b": 1 (on 3 bytes), "a": [_ 1.5 (as a double)]}
+//! let bytes = [
+//!     0xbf, 0x61, 0x62, 0x19, 0x00, 0x01, 0x61, 0x61, 0x9f, 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0,
+//!     0xff, 0xff,
+//! ];
+//!
+//! // {"a": [1.5], "b": 1}
+//! assert_eq!(
+//!     canonicalize(&bytes).unwrap(),
+//!     vec![0xa2, 0x61, 0x61, 0x81, 0xf9, 0x3e, 0x00, 0x61, 0x62, 0x01]
+//! );
+//! ```
+
+use de::DEFAULT_MAX_DEPTH;
+use error::Error;
+use float;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use se::encode_header;
+use std::cmp::Ordering;
+use types::Type;
+
+/// order of the keys of the maps in a canonical encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// bytewise lexicographic order of the encoded keys, as in RFC 8949
+    /// (section 4.2.1)
+    #[default]
+    Lexicographic,
+    /// shorter encoded keys first, then bytewise lexicographic order for
+    /// keys of the same length, as in the canonical CBOR of RFC 7049
+    /// (section 3.9)
+    LengthFirst,
+}
+impl KeyOrder {
+    /// compare two encoded keys
+    ///
+    /// ```
+    /// use cbor_event::canonical::KeyOrder;
+    /// use std::cmp::Ordering;
+    ///
+    /// // 1000 and "a"
+    /// let (a, b) = ([0x19, 0x03, 0xe8], [0x61, 0x61]);
+    /// assert_eq!(KeyOrder::Lexicographic.compare(&a, &b), Ordering::Less);
+    /// assert_eq!(KeyOrder::LengthFirst.compare(&a, &b), Ordering::Greater);
+    /// ```
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            KeyOrder::Lexicographic => a.cmp(b),
+            KeyOrder::LengthFirst => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+        }
+    }
+}
+
+/// re-encode the CBOR object in `bytes` in the core deterministic encoding
+/// of RFC 8949, see the [module documentation](./index.html).
+///
+/// Fails if `bytes` is not exactly one well-formed CBOR object or if a map
+/// has the same key twice (once canonicalized, e.g. `1` and `1` encoded on
+/// 2 bytes) as there would be no deterministic way to keep one of them.
+/// Objects nesting more than
+/// [`DEFAULT_MAX_DEPTH`](../de/constant.DEFAULT_MAX_DEPTH.html) arrays,
+/// maps and tags fail with `Error::MaxDepthExceeded`.
+pub fn canonicalize(bytes: &[u8]) -> Result<Vec<u8>> {
+    canonicalize_with(bytes, KeyOrder::Lexicographic)
+}
+
+/// same as [`canonicalize`](./fn.canonicalize.html) but sorting the map
+/// keys in the given order.
+///
+/// ```
+/// use cbor_event::canonical::{canonicalize_with, KeyOrder};
+///
+/// // {"a": 0, 1000: 0}
+/// let bytes = [0xa2, 0x61, 0x61, 0x00, 0x19, 0x03, 0xe8, 0x00];
+///
+/// assert_eq!(
+///     canonicalize_with(&bytes, KeyOrder::Lexicographic).unwrap(),
+///     vec![0xa2, 0x19, 0x03, 0xe8, 0x00, 0x61, 0x61, 0x00]
+/// );
+/// assert_eq!(canonicalize_with(&bytes, KeyOrder::LengthFirst).unwrap(), bytes.to_vec());
+/// ```
+pub fn canonicalize_with(bytes: &[u8], order: KeyOrder) -> Result<Vec<u8>> {
+    let layout = layout(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len());
+    canonical_item(bytes, &layout, 0, order, &mut out)?;
+    Ok(out)
+}
+
+/// whether the CBOR objects in `a` and `b` hold the same data, ignoring
+/// the differences of encoding: size of the headers, indefinite lengths,
+/// order of the map keys and width of the floating point values.
+///
+/// Fails like [`canonicalize`](./fn.canonicalize.html), in particular if a
+/// map has the same key twice or on deeply nested objects.
+///
+/// ```
+/// use cbor_event::canonical::equivalent;
+///
+/// // [_ 1 (on 2 bytes), {"b": 0, "a": 0}] and [1, {"a": 0, "b": 0}]
+/// let a = [0x9f, 0x18, 0x01, 0xa2, 0x61, 0x62, 0x00, 0x61, 0x61, 0x00, 0xff];
+/// let b = [0x82, 0x01, 0xa2, 0x61, 0x61, 0x00, 0x61, 0x62, 0x00];
+/// assert!(equivalent(&a, &b).unwrap());
+/// assert!(!equivalent(&a, &[0x82, 0x01, 0xa0]).unwrap());
+/// ```
+pub fn equivalent(a: &[u8], b: &[u8]) -> Result<bool> {
+    Ok(canonicalize(a)? == canonicalize(b)?)
+}
+
+/// order the CBOR objects in `a` and `b` by the bytewise lexicographic
+/// order of their canonical encodings, the order of the map keys in RFC
+/// 8949 (section 4.2.1). Equivalent objects are `Ordering::Equal`.
+///
+/// Fails like [`canonicalize`](./fn.canonicalize.html).
+///
+/// ```
+/// use cbor_event::canonical::cmp_canonical;
+/// use std::cmp::Ordering;
+///
+/// // 10 (on 2 bytes) and 1000
+/// assert_eq!(cmp_canonical(&[0x18, 0x0a], &[0x19, 0x03, 0xe8]).unwrap(), Ordering::Less);
+/// ```
+pub fn cmp_canonical(a: &[u8], b: &[u8]) -> Result<Ordering> {
+    Ok(canonicalize(a)?.cmp(&canonicalize(b)?))
+}
+
+// the layout of the well-formed object in `bytes`, failing if it nests
+// more than `DEFAULT_MAX_DEPTH` arrays, maps and tags as the re-encoding
+// recurses once per level
+fn layout(bytes: &[u8]) -> Result<scan::Layout> {
+    let layout = scan::Layout::new(bytes)?;
+    let too_deep = layout.items().iter().any(|item| {
+        item.depth >= DEFAULT_MAX_DEPTH
+            && matches!(item.cbor_type, Type::Array | Type::Map | Type::Tag)
+    });
+    if too_deep {
+        Err(Error::MaxDepthExceeded)
+    } else {
+        Ok(layout)
+    }
+}
+
+// re-encode the object at `index` into `out`
+fn canonical_item(
+    bytes: &[u8],
+    layout: &scan::Layout,
+    index: usize,
+    order: KeyOrder,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let item = &layout.items()[index];
+    let (cbor_type, len, sz) = (item.cbor_type, item.len, item.sz);
+    match (cbor_type, len) {
+        (Type::Special, Len::Len(value)) if sz > 2 => {
+            write_float(float::decode(value, sz - 1), out);
+        }
+        (Type::Special, _) => out.extend_from_slice(&bytes[item.pos..item.end]),
+        (Type::UnsignedInteger, Len::Len(value))
+        | (Type::NegativeInteger, Len::Len(value))
+        | (Type::Tag, Len::Len(value)) => {
+            write_header(cbor_type, value, out);
+            if cbor_type == Type::Tag {
+                canonical_item(bytes, layout, index + 1, order, out)?;
+            }
+        }
+        (Type::Bytes, _) | (Type::Text, _) => join_chunks(bytes, layout, index, out),
+        (Type::Array, _) => {
+            write_header(Type::Array, layout.children(index).count() as u64, out);
+            for child in layout.children(index) {
+                canonical_item(bytes, layout, child, order, out)?;
+            }
+        }
+        (Type::Map, _) => {
+            let mut entries = Vec::new();
+            let mut children = layout.children(index);
+            while let (Some(key_index), Some(value_index)) = (children.next(), children.next()) {
+                let mut key = Vec::new();
+                let mut value = Vec::new();
+                canonical_item(bytes, layout, key_index, order, &mut key)?;
+                canonical_item(bytes, layout, value_index, order, &mut value)?;
+                entries.push((key, value));
+            }
+            entries.sort_by(|a, b| order.compare(&a.0, &b.0));
+            if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                return Err(Error::DuplicateKey);
+            }
+            write_header(Type::Map, entries.len() as u64, out);
+            for (key, value) in entries {
+                out.extend_from_slice(&key);
+                out.extend_from_slice(&value);
+            }
+        }
+        (_, Len::Indefinite) => return Err(Error::IndefiniteLenNotSupported(cbor_type)),
+    }
+    Ok(())
+}
+
+/// convert the strings, arrays and maps of indefinite length of the CBOR
+/// object in `bytes` to their definite length form, leaving everything else
+/// (size of the headers, order of the map keys, floating point encodings)
+/// untouched.
+///
+/// This is for consumers rejecting indefinite lengths, [`canonicalize`]
+/// should be used for the full deterministic encoding. It fails on the
+/// same deeply nested objects.
+///
+/// [`canonicalize`]: ./fn.canonicalize.html
+///
+/// ```
+/// use cbor_event::canonical::to_definite;
+///
+/// // {_ "b": (_ h'01', h'02'), "a": 1 (on 2 bytes)}
+/// let bytes = [
+///     0xbf, 0x61, 0x62, 0x5f, 0x41, 0x01, 0x41, 0x02, 0xff, 0x61, 0x61, 0x18, 0x01, 0xff,
+/// ];
+///
+/// // {"b": h'0102', "a": 1 (on 2 bytes)}
+/// assert_eq!(
+///     to_definite(&bytes).unwrap(),
+///     vec![0xa2, 0x61, 0x62, 0x42, 0x01, 0x02, 0x61, 0x61, 0x18, 0x01]
+/// );
+/// ```
+pub fn to_definite(bytes: &[u8]) -> Result<Vec<u8>> {
+    let layout = layout(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len());
+    definite_item(bytes, &layout, 0, &mut out);
+    Ok(out)
+}
+
+// copy the object at `index` into `out` with definite lengths only
+fn definite_item(bytes: &[u8], layout: &scan::Layout, index: usize, out: &mut Vec<u8>) {
+    let item = &layout.items()[index];
+    let (cbor_type, len) = (item.cbor_type, item.len);
+    let start = item.pos + item.sz;
+    match (cbor_type, len) {
+        (Type::Bytes, Len::Indefinite) | (Type::Text, Len::Indefinite) => {
+            join_chunks(bytes, layout, index, out)
+        }
+        (Type::Array, _) | (Type::Map, _) => {
+            match len {
+                Len::Len(_) => out.extend_from_slice(&bytes[item.pos..start]),
+                Len::Indefinite => {
+                    let children = layout.children(index).count() as u64;
+                    let count = if cbor_type == Type::Map {
+                        children / 2
+                    } else {
+                        children
+                    };
+                    write_header(cbor_type, count, out);
+                }
+            }
+            for child in layout.children(index) {
+                definite_item(bytes, layout, child, out);
+            }
+        }
+        (Type::Tag, _) => {
+            out.extend_from_slice(&bytes[item.pos..start]);
+            definite_item(bytes, layout, index + 1, out);
+        }
+        _ => out.extend_from_slice(&bytes[item.pos..item.end]),
+    }
+}
+
+// write the string at `index` as one definite length string, joining the
+// chunks of indefinite length strings
+fn join_chunks(bytes: &[u8], layout: &scan::Layout, index: usize, out: &mut Vec<u8>) {
+    let item = &layout.items()[index];
+    write_header(item.cbor_type, layout.string_len(index), out);
+    match item.len {
+        Len::Len(_) => out.extend_from_slice(&bytes[item.pos + item.sz..item.end]),
+        Len::Indefinite => {
+            for chunk in layout.children(index) {
+                let chunk = &layout.items()[chunk];
+                out.extend_from_slice(&bytes[chunk.pos + chunk.sz..chunk.end]);
+            }
+        }
+    }
+}
+
+pub(crate) fn write_header(cbor_type: Type, value: u64, out: &mut Vec<u8>) {
+    let (header, sz) = encode_header(cbor_type, value);
+    out.extend_from_slice(&header[..sz]);
+}
+
+// write `value` in the shortest floating point encoding representing it
+// exactly
+pub(crate) fn write_float(value: f64, out: &mut Vec<u8>) {
+    if let Some(half) = float::f64_to_f16_exact(value) {
+        out.push(0xf9);
+        out.extend_from_slice(&half.to_be_bytes());
+    } else if let Some(single) = float::f64_to_f32_exact(value) {
+        out.push(0xfa);
+        out.extend_from_slice(&single.to_bits().to_be_bytes());
+    } else {
+        out.push(0xfb);
+        out.extend_from_slice(&value.to_bits().to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lint::{lint_canonical, lint_canonical_with};
+
+    #[test]
+    fn already_canonical() {
+        let bytes = [
+            0xa3, 0x01, 0x18, 0x18, 0x20, 0xf9, 0x3e, 0x00, 0x61, 0x61, 0xc1, 0x1a, 0x00, 0x01,
+            0x00, 0x00,
+        ];
+        assert_eq!(canonicalize(&bytes).unwrap(), bytes.to_vec());
+    }
+
+    #[test]
+    fn headers_and_strings() {
+        // [0 on 2 bytes, 1(h'' with length on 3 bytes), (_ "ab", "c"), -1 on 9 bytes]
+        let bytes = [
+            0x84, 0x18, 0x00, 0xd8, 0x01, 0x59, 0x00, 0x00, 0x7f, 0x62, 0x61, 0x62, 0x61, 0x63,
+            0xff, 0x3b, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            canonicalize(&bytes).unwrap(),
+            vec![0x84, 0x00, 0xc1, 0x40, 0x63, 0x61, 0x62, 0x63, 0x20]
+        );
+    }
+
+    #[test]
+    fn nested_maps_and_floats() {
+        // {_ [_ ]: 0, 10: {3: 100000.0 (as a double), 1: NaN (as a float)}, -1: 1.1}
+        let bytes = [
+            0xbf, 0x9f, 0xff, 0x00, 0x0a, 0xa2, 0x03, 0xfb, 0x40, 0xf8, 0x6a, 0, 0, 0, 0, 0, 0x01,
+            0xfa, 0x7f, 0xc0, 0x00, 0x00, 0x20, 0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99,
+            0x9a, 0xff,
+        ];
+        let canonical = canonicalize(&bytes).unwrap();
+        assert!(lint_canonical(&canonical).unwrap().is_empty());
+        assert_eq!(
+            canonical,
+            vec![
+                0xa3, 0x0a, 0xa2, 0x01, 0xf9, 0x7e, 0x00, 0x03, 0xfa, 0x47, 0xc3, 0x50, 0x00, 0x20,
+                0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a, 0x80, 0x00
+            ]
+        );
+    }
+
+    #[test]
+    fn definite() {
+        // [_ 1 (on 2 bytes), [_ ], {_ 2: (_ "a", "b"), 1: 1.5 (as a double)}, 24(h'00')]
+        let bytes = [
+            0x9f, 0x18, 0x01, 0x9f, 0xff, 0xbf, 0x02, 0x7f, 0x61, 0x61, 0x61, 0x62, 0xff, 0x01,
+            0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xff, 0xd8, 0x18, 0x41, 0x00, 0xff,
+        ];
+        assert_eq!(
+            to_definite(&bytes).unwrap(),
+            vec![
+                0x84, 0x18, 0x01, 0x80, 0xa2, 0x02, 0x62, 0x61, 0x62, 0x01, 0xfb, 0x3f, 0xf8, 0, 0,
+                0, 0, 0, 0, 0xd8, 0x18, 0x41, 0x00
+            ]
+        );
+        let definite = [0xa1, 0x19, 0x00, 0x01, 0x5a, 0, 0, 0, 1, 0xff];
+        assert_eq!(to_definite(&definite).unwrap(), definite.to_vec());
+    }
+
+    #[test]
+    fn length_first() {
+        // {"b": 0, 1000: 0, "a": 0, 24: 0}
+        let bytes = [
+            0xa4, 0x61, 0x62, 0x00, 0x19, 0x03, 0xe8, 0x00, 0x61, 0x61, 0x00, 0x18, 0x18, 0x00,
+        ];
+        let canonical = canonicalize_with(&bytes, KeyOrder::LengthFirst).unwrap();
+        assert_eq!(
+            canonical,
+            vec![
+                0xa4, 0x18, 0x18, 0x00, 0x61, 0x61, 0x00, 0x61, 0x62, 0x00, 0x19, 0x03, 0xe8, 0x00
+            ]
+        );
+        assert!(lint_canonical_with(&canonical, KeyOrder::LengthFirst)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        // {1: 0, 1 (on 2 bytes): 0}
+        assert!(matches!(
+            canonicalize(&[0xa2, 0x01, 0x00, 0x18, 0x01, 0x00]),
+            Err(Error::DuplicateKey)
+        ));
+        assert!(canonicalize(&[0x82, 0x00]).is_err());
+        assert!(matches!(
+            canonicalize(&[0x00, 0x00]),
+            Err(Error::TrailingData)
+        ));
+    }
+
+    #[test]
+    fn comparisons() {
+        // 1.5 as a double and as a half
+        let (double, half) = ([0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], [0xf9, 0x3e, 0x00]);
+        assert!(equivalent(&double, &half).unwrap());
+        assert_eq!(cmp_canonical(&double, &half).unwrap(), Ordering::Equal);
+        // (_ "a", "b") and "ab"
+        assert!(equivalent(&[0x7f, 0x61, 0x61, 0x61, 0x62, 0xff], &[0x62, 0x61, 0x62]).unwrap());
+        // 1 and 1.0 are different types
+        assert!(!equivalent(&[0x01], &[0xf9, 0x3c, 0x00]).unwrap());
+        // 23 is encoded before 24 in 2 bytes
+        assert_eq!(
+            cmp_canonical(&[0x18, 0x17], &[0x18, 0x18]).unwrap(),
+            Ordering::Less
+        );
+        assert!(equivalent(&[0x01], &[0x82]).is_err());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let mut bytes = vec![0x81; DEFAULT_MAX_DEPTH];
+        bytes.push(0x00);
+        assert_eq!(canonicalize(&bytes).unwrap(), bytes);
+
+        let mut bytes = vec![0x9f; 500_000];
+        bytes.push(0x00);
+        bytes.extend(vec![0xff; 500_000]);
+        assert!(matches!(canonicalize(&bytes), Err(Error::MaxDepthExceeded)));
+    }
+}
@@ -0,0 +1,914 @@
+//! Rust code generation from CDDL schemas
+//!
+//! [`generate`] reads a CDDL schema (RFC 8610) and returns the Rust source
+//! of one type per rule, with its `Serialize` and `Deserialize`
+//! implementations. Called from a build script, it keeps the CDDL as the
+//! only definition of a protocol. The types derive `Debug`, `Clone` and
+//! `PartialEq`; the implementations refer to `::std::io`, so the crate
+//! including them needs `std`.
+//!
+//! [`generate`]: ./fn.generate.html
+//!
+//! ```
+//! use cbor_event::cddl::generate;
+//!
+//! let schema = r#"
+//!     point = [x: uint, y: uint]
+//!     ; a named polygon
+//!     shape = {
+//!         name: tstr,
+//!         points: [* point],
+//!     }
+//! "#;
+//! let code = generate(schema).unwrap();
+//! assert!(code.contains("pub struct Point {\n    pub x: u64,\n    pub y: u64,\n}"));
+//! assert!(code.contains("impl ::cbor_event::se::Serialize for Shape {"));
+//! assert!(code.contains("    pub points: Vec<Point>,\n"));
+//! ```
+//!
+//! The generated code is then included by the crate:
+//!
+//! ```no_run
+//! // build.rs
+//! use std::{env, fs, path::Path};
+//!
+//! let schema = fs::read_to_string("protocol.cddl").unwrap();
+//! let code = cbor_event::cddl::generate(&schema).unwrap();
+//! let out = Path::new(&env::var("OUT_DIR").unwrap()).join("protocol.rs");
+//! fs::write(out, code).unwrap();
+//! println!("cargo:rerun-if-changed=protocol.cddl");
+//!
+//! // src/lib.rs
+//! // include!(concat!(env!("OUT_DIR"), "/protocol.rs"));
+//! ```
+//!
+//! # Supported subset
+//!
+//! - `name = {key: type, ...}`: a struct encoded as a map. Either all the
+//!   keys are names (`key: type` or `"key": type`), encoded as texts and
+//!   used as field names, or all are integers (`1: type` or `1 => type`)
+//!   and the fields are named `key_1`, `key_2`... Decoding skips the
+//!   unknown keys and fails with `Error::MissingField` if one is missing;
+//! - `name = [field: type, ...]`: a struct encoded as an array of its
+//!   fields, `name = [type, ...]` a tuple struct;
+//! - `name = type`: a type alias, without implementations of its own.
+//!
+//! The types are `uint` (`u64`), `int` and `nint` (`i64`), `bool`,
+//! `float`, `float16`, `float32` and `float64` (`f64`), `tstr` and `text`
+//! (`String`), `bstr` and `bytes` (`ByteString<Vec<u8>>`), `any`
+//! (`Value`), the arrays `[* type]` and `[+ type]` (`Vec`), the maps
+//! `{* key => value}` (`OrderedMap`) and the names of the other rules. Any
+//! other construct (choices, optional entries, occurrences, controls...)
+//! fails with `Error::InvalidCddl`.
+
+use error::Error;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+
+/// generate the Rust types of the rules of `schema`, see the
+/// [module documentation](./index.html).
+///
+/// ```
+/// use cbor_event::{cddl::generate, Error};
+///
+/// let code = generate("header = {1: uint, 2: bstr}").unwrap();
+/// assert!(code.contains(
+///     "pub struct Header {\n    \
+///          pub key_1: u64,\n    \
+///          pub key_2: ::cbor_event::ByteString<Vec<u8>>,\n\
+///      }\n"
+/// ));
+///
+/// // choices are not supported
+/// assert!(matches!(
+///     generate("id = uint / tstr"),
+///     Err(Error::InvalidCddl(ref token, 1)) if token == "/"
+/// ));
+/// ```
+pub fn generate(schema: &str) -> Result<String> {
+    let mut parser = Parser {
+        tokens: tokenize(schema)?,
+        pos: 0,
+        references: Vec::new(),
+    };
+    let mut rules = Vec::new();
+    while parser.pos < parser.tokens.len() {
+        rules.push(parser.rule()?);
+    }
+    for (name, line) in &parser.references {
+        if !rules.iter().any(|rule| rule.name == *name) {
+            return Err(Error::InvalidCddl(name.clone(), *line));
+        }
+    }
+
+    let mut out = String::from("// generated from CDDL by cbor_event::cddl, do not edit\n");
+    let mut generator = Generator {
+        rules: &rules,
+        out: &mut out,
+        aliases: Vec::new(),
+    };
+    for rule in &rules {
+        generator.check_alias(rule)?;
+    }
+    for rule in &rules {
+        generator.out.push('\n');
+        generator.rule(rule)?;
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Integer(u64),
+    Text(String),
+    Punct(&'static str),
+}
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Name(name) => name.clone(),
+            Token::Integer(value) => value.to_string(),
+            Token::Text(text) => format!("\"{}\"", text),
+            Token::Punct(punct) => (*punct).to_owned(),
+        }
+    }
+}
+
+const PUNCTS: [&str; 15] = [
+    "=>", "=", "{", "}", "[", "]", "(", ")", ",", ":", "*", "+", "?", "/", "&",
+];
+
+// split `schema` into tokens with their line, dropping the comments
+fn tokenize(schema: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    for (index, line) in schema.lines().enumerate() {
+        let line_number = index + 1;
+        let mut rest = line;
+        loop {
+            rest = rest.trim_start();
+            let c = match rest.chars().next() {
+                None | Some(';') => break,
+                Some(c) => c,
+            };
+            let len = if c.is_ascii_alphabetic() || c == '_' || c == '$' || c == '@' {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "_$@-.".contains(c)))
+                    .unwrap_or(rest.len());
+                // names do not end with `-` or `.`
+                let len = rest[..len].trim_end_matches(['-', '.']).len();
+                tokens.push((Token::Name(rest[..len].to_owned()), line_number));
+                len
+            } else if c.is_ascii_digit() {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let value = rest[..len]
+                    .parse()
+                    .map_err(|_| Error::InvalidCddl(rest[..len].to_owned(), line_number))?;
+                tokens.push((Token::Integer(value), line_number));
+                len
+            } else if c == '"' {
+                let end = rest[1..]
+                    .find('"')
+                    .ok_or_else(|| Error::InvalidCddl(rest.to_owned(), line_number))?;
+                tokens.push((Token::Text(rest[1..end + 1].to_owned()), line_number));
+                end + 2
+            } else {
+                let punct = PUNCTS
+                    .iter()
+                    .find(|punct| rest.starts_with(**punct))
+                    .ok_or_else(|| Error::InvalidCddl(c.to_string(), line_number))?;
+                tokens.push((Token::Punct(punct), line_number));
+                punct.len()
+            };
+            rest = &rest[len..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Rule {
+    name: String,
+    line: usize,
+    body: Body,
+}
+
+enum Body {
+    Alias(Ty),
+    Map(Vec<Field>),
+    // a tuple struct if the fields have no names
+    Array(Vec<Field>),
+}
+
+struct Field {
+    // the integer key of a map entry, `None` if keyed by its name
+    key: Option<u64>,
+    name: String,
+    ty: Ty,
+}
+
+enum Ty {
+    // a type of the crate or of the standard library
+    Plain(&'static str),
+    // the type of another rule
+    Rule(String),
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+}
+impl Ty {
+    fn rust_type(&self) -> String {
+        match self {
+            Ty::Plain(name) => (*name).to_owned(),
+            Ty::Rule(name) => type_name(name),
+            Ty::Array(element) => format!("Vec<{}>", element.rust_type()),
+            Ty::Map(key, value) => format!(
+                "::cbor_event::OrderedMap<{}, {}>",
+                key.rust_type(),
+                value.rust_type()
+            ),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    // the names of the rules used as types, with their line
+    references: Vec<(String, usize)>,
+}
+impl Parser {
+    fn peek(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset).map(|(token, _)| token)
+    }
+
+    fn is_punct(&self, offset: usize, punct: &'static str) -> bool {
+        self.peek(offset) == Some(&Token::Punct(punct))
+    }
+
+    // the error on the next token, or on the last line at the end
+    fn unexpected(&self) -> Error {
+        match self.tokens.get(self.pos) {
+            Some((token, line)) => Error::InvalidCddl(token.describe(), *line),
+            None => Error::InvalidCddl(
+                "end of schema".to_owned(),
+                self.tokens.last().map_or(1, |(_, line)| *line),
+            ),
+        }
+    }
+
+    // the next token and its line
+    fn current(&self) -> Result<(Token, usize)> {
+        self.tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| self.unexpected())
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<()> {
+        if self.is_punct(0, punct) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn rule(&mut self) -> Result<Rule> {
+        let (name, line) = match self.current()? {
+            (Token::Name(name), line) => (name, line),
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        self.expect("=")?;
+        let body = if self.is_punct(0, "{") && !self.is_punct(1, "*") {
+            self.pos += 1;
+            Body::Map(self.map_fields()?)
+        } else if self.is_punct(0, "[") && !self.is_punct(1, "*") && !self.is_punct(1, "+") {
+            self.pos += 1;
+            Body::Array(self.array_fields()?)
+        } else {
+            Body::Alias(self.ty()?)
+        };
+        // a rule ends where the next one starts
+        let next_rule = match self.peek(0) {
+            None => true,
+            Some(Token::Name(_)) => self.is_punct(1, "="),
+            Some(_) => false,
+        };
+        if !next_rule {
+            return Err(self.unexpected());
+        }
+        Ok(Rule { name, line, body })
+    }
+
+    // the entries of a map up to its closing brace
+    fn map_fields(&mut self) -> Result<Vec<Field>> {
+        let mut fields: Vec<Field> = Vec::new();
+        while !self.is_punct(0, "}") {
+            let (token, line) = self.current()?;
+            let arrow = self.is_punct(1, "=>");
+            let colon = self.is_punct(1, ":");
+            let field = match token {
+                Token::Name(ref name) if colon => field_name(name).map(|name| (None, name)),
+                Token::Text(ref name) if colon || arrow => {
+                    field_name(name).map(|name| (None, name))
+                }
+                Token::Integer(key) if colon || arrow => Some((Some(key), format!("key_{}", key))),
+                _ => return Err(self.unexpected()),
+            };
+            // the names are the keys, they must be usable as they are, and
+            // the keys are all names or all integers as in the derived code
+            let invalid = || Error::InvalidCddl(token.describe(), line);
+            let (key, name) = field.ok_or_else(invalid)?;
+            let mixed = fields
+                .first()
+                .is_some_and(|first| first.key.is_some() != key.is_some());
+            if mixed || fields.iter().any(|field| field.name == name) {
+                return Err(invalid());
+            }
+            self.pos += 2;
+            let ty = self.ty()?;
+            fields.push(Field { key, name, ty });
+            if !self.is_punct(0, "}") {
+                self.expect(",")?;
+            }
+        }
+        self.pos += 1;
+        Ok(fields)
+    }
+
+    // the elements of an array up to its closing bracket, named or not
+    fn array_fields(&mut self) -> Result<Vec<Field>> {
+        let mut fields: Vec<Field> = Vec::new();
+        while !self.is_punct(0, "]") {
+            let (token, line) = self.current()?;
+            let name = match token {
+                Token::Name(ref name) if self.is_punct(1, ":") => {
+                    self.pos += 2;
+                    field_name(&name.replace(['-', '.'], "_").to_lowercase())
+                        .ok_or_else(|| Error::InvalidCddl(token.describe(), line))?
+                }
+                _ => String::new(),
+            };
+            let mixed = fields
+                .first()
+                .is_some_and(|first| first.name.is_empty() != name.is_empty());
+            if mixed || (!name.is_empty() && fields.iter().any(|field| field.name == name)) {
+                return Err(Error::InvalidCddl(token.describe(), line));
+            }
+            let ty = self.ty()?;
+            fields.push(Field {
+                key: None,
+                name,
+                ty,
+            });
+            if !self.is_punct(0, "]") {
+                self.expect(",")?;
+            }
+        }
+        self.pos += 1;
+        Ok(fields)
+    }
+
+    // the next type of the schema
+    fn ty(&mut self) -> Result<Ty> {
+        let (token, line) = self.current()?;
+        self.pos += 1;
+        match token {
+            Token::Name(name) => Ok(match name.as_str() {
+                "uint" => Ty::Plain("u64"),
+                "int" | "nint" => Ty::Plain("i64"),
+                "bool" => Ty::Plain("bool"),
+                "float" | "float16" | "float32" | "float64" => Ty::Plain("f64"),
+                "tstr" | "text" => Ty::Plain("String"),
+                "bstr" | "bytes" => Ty::Plain("::cbor_event::ByteString<Vec<u8>>"),
+                "any" => Ty::Plain("::cbor_event::Value"),
+                _ => {
+                    self.references.push((name.clone(), line));
+                    Ty::Rule(name)
+                }
+            }),
+            Token::Punct("[") if self.is_punct(0, "*") || self.is_punct(0, "+") => {
+                self.pos += 1;
+                let element = self.ty()?;
+                self.expect("]")?;
+                Ok(Ty::Array(Box::new(element)))
+            }
+            Token::Punct("{") if self.is_punct(0, "*") => {
+                self.pos += 1;
+                let key = self.ty()?;
+                self.expect("=>")?;
+                let value = self.ty()?;
+                self.expect("}")?;
+                Ok(Ty::Map(Box::new(key), Box::new(value)))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+}
+
+struct Generator<'a> {
+    rules: &'a [Rule],
+    out: &'a mut String,
+    // the aliases being checked, to detect the cycles
+    aliases: Vec<&'a str>,
+}
+impl<'a> Generator<'a> {
+    fn line(&mut self, indent: usize, line: &str) {
+        for _ in 0..indent {
+            self.out.push_str("    ");
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn find(&self, name: &str) -> &'a Rule {
+        let rules = self.rules;
+        rules.iter().find(|rule| rule.name == name).unwrap()
+    }
+
+    // fail if the alias `rule` refers to itself, directly or through other
+    // aliases
+    fn check_alias(&mut self, rule: &'a Rule) -> Result<()> {
+        if let Body::Alias(ref ty) = rule.body {
+            if self.aliases.contains(&rule.name.as_str()) {
+                return Err(Error::InvalidCddl(rule.name.clone(), rule.line));
+            }
+            self.aliases.push(&rule.name);
+            self.check_type(ty)?;
+            self.aliases.pop();
+        }
+        Ok(())
+    }
+
+    fn check_type(&mut self, ty: &'a Ty) -> Result<()> {
+        match ty {
+            Ty::Plain(_) => Ok(()),
+            Ty::Rule(name) => self.check_alias(self.find(name)),
+            Ty::Array(element) => self.check_type(element),
+            Ty::Map(key, value) => {
+                self.check_type(key)?;
+                self.check_type(value)
+            }
+        }
+    }
+
+    fn rule(&mut self, rule: &'a Rule) -> Result<()> {
+        let name = type_name(&rule.name);
+        let fields = match rule.body {
+            Body::Alias(ref ty) => {
+                let line = format!("pub type {} = {};", name, ty.rust_type());
+                self.line(0, &line);
+                return Ok(());
+            }
+            Body::Map(ref fields) | Body::Array(ref fields) => fields,
+        };
+        let tuple = !fields.is_empty() && fields.iter().all(|field| field.name.is_empty());
+        let map = matches!(rule.body, Body::Map(_));
+
+        self.line(0, "#[derive(Debug, Clone, PartialEq)]");
+        if tuple {
+            let types: Vec<_> = fields
+                .iter()
+                .map(|field| format!("pub {}", field.ty.rust_type()))
+                .collect();
+            self.line(0, &format!("pub struct {}({});", name, types.join(", ")));
+        } else {
+            self.line(0, &format!("pub struct {} {{", name));
+            for field in fields {
+                let line = format!("pub {}: {},", field.name, field.ty.rust_type());
+                self.line(1, &line);
+            }
+            self.line(0, "}");
+        }
+        // the expressions of the fields, and the local variables holding
+        // them while decoding
+        let members: Vec<String> = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| match tuple {
+                true => index.to_string(),
+                false => field.name.clone(),
+            })
+            .collect();
+        let bindings: Vec<String> = members
+            .iter()
+            .map(|member| format!("__field_{}", member.trim_start_matches("r#")))
+            .collect();
+
+        self.line(
+            0,
+            &format!("impl ::cbor_event::se::Serialize for {} {{", name),
+        );
+        self.line(1, "fn serialize<'se, W: ::std::io::Write + Sized>(");
+        self.line(2, "&self,");
+        self.line(2, "serializer: &'se mut ::cbor_event::se::Serializer<W>,");
+        self.line(
+            1,
+            ") -> ::cbor_event::Result<&'se mut ::cbor_event::se::Serializer<W>> {",
+        );
+        let header = format!(
+            "serializer.write_{}(::cbor_event::Len::Len({}))?;",
+            if map { "map" } else { "array" },
+            fields.len()
+        );
+        self.line(2, &header);
+        for (field, member) in fields.iter().zip(&members) {
+            match field.key {
+                _ if !map => {}
+                Some(key) => self.line(2, &format!("serializer.write_unsigned_integer({})?;", key)),
+                None => {
+                    let key = field.name.trim_start_matches("r#");
+                    self.line(2, &format!("serializer.write_text(\"{}\")?;", key));
+                }
+            }
+            self.serialize(&field.ty, &format!("self.{}", member), 2)?;
+        }
+        self.line(2, "Ok(serializer)");
+        self.line(1, "}");
+        self.line(0, "}");
+
+        self.line(
+            0,
+            &format!("impl ::cbor_event::de::Deserialize for {} {{", name),
+        );
+        self.line(1, "fn deserialize<R: ::std::io::BufRead>(");
+        self.line(2, "raw: &mut ::cbor_event::de::Deserializer<R>,");
+        self.line(1, ") -> ::cbor_event::Result<Self> {");
+        if map {
+            self.deserialize_map(fields, &bindings);
+        } else {
+            self.deserialize_array(&name, fields.len(), &bindings);
+        }
+        let values: Vec<String> = match map {
+            true => fields
+                .iter()
+                .zip(&bindings)
+                .map(|(field, binding)| {
+                    format!(
+                        "{}.ok_or(::cbor_event::Error::MissingField(\"{}\", \"{}\"))?",
+                        binding,
+                        field.name.trim_start_matches("r#"),
+                        name
+                    )
+                })
+                .collect(),
+            false => bindings.clone(),
+        };
+        if tuple {
+            self.line(2, &format!("Ok({}({}))", name, values.join(", ")));
+        } else if values.is_empty() {
+            self.line(2, &format!("Ok({} {{}})", name));
+        } else {
+            self.line(2, &format!("Ok({} {{", name));
+            for (member, value) in members.iter().zip(&values) {
+                self.line(3, &format!("{}: {},", member, value));
+            }
+            self.line(2, "})");
+        }
+        self.line(1, "}");
+        self.line(0, "}");
+        Ok(())
+    }
+
+    // write the statements serializing `expr` of type `ty`
+    fn serialize(&mut self, ty: &'a Ty, expr: &str, indent: usize) -> Result<()> {
+        match ty {
+            // the aliased type may not implement `Serialize`
+            Ty::Rule(name) => match self.find(name).body {
+                Body::Alias(ref ty) => self.serialize(ty, expr, indent)?,
+                _ => self.line(indent, &format!("serializer.serialize(&{})?;", expr)),
+            },
+            Ty::Plain(_) => self.line(indent, &format!("serializer.serialize(&{})?;", expr)),
+            Ty::Array(element) => {
+                let item = format!("__item{}", indent);
+                let header = format!(
+                    "serializer.write_array(::cbor_event::Len::Len({}.len() as u64))?;",
+                    expr
+                );
+                self.line(indent, &header);
+                self.line(indent, &format!("for {} in {}.iter() {{", item, expr));
+                self.serialize(element, &item, indent + 1)?;
+                self.line(indent, "}");
+            }
+            Ty::Map(key, value) => {
+                let (k, v) = (format!("__key{}", indent), format!("__value{}", indent));
+                let header = format!(
+                    "serializer.write_map(::cbor_event::Len::Len({}.len() as u64))?;",
+                    expr
+                );
+                self.line(indent, &header);
+                self.line(indent, &format!("for ({}, {}) in {}.iter() {{", k, v, expr));
+                self.serialize(key, &k, indent + 1)?;
+                self.serialize(value, &v, indent + 1)?;
+                self.line(indent, "}");
+            }
+        }
+        Ok(())
+    }
+
+    fn deserialize_map(&mut self, fields: &[Field], bindings: &[String]) {
+        for binding in bindings {
+            self.line(2, &format!("let mut {} = None;", binding));
+        }
+        let key_type = match fields.first() {
+            Some(Field { key: Some(_), .. }) => "u64",
+            _ => "String",
+        };
+        self.line(
+            2,
+            &format!("raw.map_entries_with(|raw, __key: {}| {{", key_type),
+        );
+        if fields.is_empty() {
+            self.line(3, "raw.skip_value()?;");
+            self.line(3, "Ok(())");
+            self.line(2, "})?;");
+            return;
+        }
+        let key = if key_type == "u64" {
+            "__key"
+        } else {
+            "__key.as_str()"
+        };
+        self.line(3, &format!("match {} {{", key));
+        for (field, binding) in fields.iter().zip(bindings) {
+            let key = match field.key {
+                Some(key) => key.to_string(),
+                None => format!("\"{}\"", field.name.trim_start_matches("r#")),
+            };
+            self.line(4, &format!("{} => {{", key));
+            self.line(5, &format!("if {}.is_some() {{", binding));
+            self.line(6, "return Err(::cbor_event::Error::DuplicateKey);");
+            self.line(5, "}");
+            self.line(5, &format!("{} = Some(raw.deserialize()?);", binding));
+            self.line(4, "}");
+        }
+        self.line(4, "_ => raw.skip_value()?,");
+        self.line(3, "}");
+        self.line(3, "Ok(())");
+        self.line(2, "})?;");
+    }
+
+    fn deserialize_array(&mut self, name: &str, len: usize, bindings: &[String]) {
+        let wrong_len = format!(
+            "return Err(::cbor_event::Error::WrongLen({}, __len, \"{}\"))",
+            len, name
+        );
+        self.line(2, "let __len = raw.array()?;");
+        self.line(2, "match __len {");
+        let sizes = format!(
+            "::cbor_event::Len::Len({}) | ::cbor_event::Len::Indefinite => {{}}",
+            len
+        );
+        self.line(3, &sizes);
+        self.line(3, &format!("_ => {},", wrong_len));
+        self.line(2, "}");
+        for binding in bindings {
+            self.line(2, &format!("let {} = raw.deserialize()?;", binding));
+        }
+        self.line(
+            2,
+            "if __len == ::cbor_event::Len::Indefinite && !raw.special_break()? {",
+        );
+        self.line(3, &format!("{};", wrong_len));
+        self.line(2, "}");
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+// `name` as a field name, raw if it is a keyword, `None` if it is not a
+// valid identifier
+fn field_name(name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && name != "_";
+    if !valid || name == "self" || name == "super" || name == "crate" {
+        None
+    } else if KEYWORDS.contains(&name) {
+        Some(format!("r#{}", name))
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+// `name` in upper camel case, e.g. `block-header` as `BlockHeader`
+fn type_name(name: &str) -> String {
+    name.split(['-', '_', '.', '$', '@'])
+        .flat_map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HEADER: &str = "// generated from CDDL by cbor_event::cddl, do not edit\n";
+
+    #[test]
+    fn types() {
+        let schema = "
+            ; comments are ignored
+            block-header = [body-hash: bstr, slot: uint, type: tstr]
+            pair = [int, float]  ; trailing comment
+            entries = {* tstr => [+ any]}
+            hashes = [* block-header]
+            record = {\"type\": bool, \"fn\" => entries, sizes: {* uint => float16},}
+        ";
+        let code = generate(schema).unwrap();
+        let declarations = [
+            "pub struct BlockHeader {\n    \
+                 pub body_hash: ::cbor_event::ByteString<Vec<u8>>,\n    \
+                 pub slot: u64,\n    \
+                 pub r#type: String,\n\
+             }\n",
+            "pub struct Pair(pub i64, pub f64);\n",
+            "pub type Entries = ::cbor_event::OrderedMap<String, Vec<::cbor_event::Value>>;\n",
+            "pub type Hashes = Vec<BlockHeader>;\n",
+            "pub struct Record {\n    \
+                 pub r#type: bool,\n    \
+                 pub r#fn: Entries,\n    \
+                 pub sizes: ::cbor_event::OrderedMap<u64, f64>,\n\
+             }\n",
+        ];
+        for declaration in declarations.iter() {
+            assert!(code.contains(declaration), "{}", declaration);
+        }
+        // the aliases are expanded as `Vec` and `OrderedMap` don't
+        // implement `Serialize`
+        assert!(code.contains(
+            "        serializer.write_text(\"fn\")?;\n        \
+                 serializer.write_map(::cbor_event::Len::Len(self.r#fn.len() as u64))?;\n        \
+                 for (__key2, __value2) in self.r#fn.iter() {\n            \
+                     serializer.serialize(&__key2)?;\n            \
+                     serializer.write_array(::cbor_event::Len::Len(__value2.len() as u64))?;\n            \
+                     for __item3 in __value2.iter() {\n                \
+                         serializer.serialize(&__item3)?;\n            \
+                     }\n        \
+                 }\n"
+        ));
+        assert!(code.contains(
+            "        Ok(Record {\n            \
+                 r#type: __field_type.ok_or(::cbor_event::Error::MissingField(\"type\", \"Record\"))?,\n            \
+                 r#fn: __field_fn.ok_or(::cbor_event::Error::MissingField(\"fn\", \"Record\"))?,\n            \
+                 sizes: __field_sizes.ok_or(::cbor_event::Error::MissingField(\"sizes\", \"Record\"))?,\n        \
+             })\n"
+        ));
+        assert!(!code.contains("impl ::cbor_event::se::Serialize for Hashes"));
+    }
+
+    #[test]
+    fn integer_keys() {
+        assert_eq!(
+            generate("header = {1: uint, 2 => [* tstr]}").unwrap(),
+            format!(
+                "{}\n{}",
+                HEADER,
+                r#"#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub key_1: u64,
+    pub key_2: Vec<String>,
+}
+impl ::cbor_event::se::Serialize for Header {
+    fn serialize<'se, W: ::std::io::Write + Sized>(
+        &self,
+        serializer: &'se mut ::cbor_event::se::Serializer<W>,
+    ) -> ::cbor_event::Result<&'se mut ::cbor_event::se::Serializer<W>> {
+        serializer.write_map(::cbor_event::Len::Len(2))?;
+        serializer.write_unsigned_integer(1)?;
+        serializer.serialize(&self.key_1)?;
+        serializer.write_unsigned_integer(2)?;
+        serializer.write_array(::cbor_event::Len::Len(self.key_2.len() as u64))?;
+        for __item2 in self.key_2.iter() {
+            serializer.serialize(&__item2)?;
+        }
+        Ok(serializer)
+    }
+}
+impl ::cbor_event::de::Deserialize for Header {
+    fn deserialize<R: ::std::io::BufRead>(
+        raw: &mut ::cbor_event::de::Deserializer<R>,
+    ) -> ::cbor_event::Result<Self> {
+        let mut __field_key_1 = None;
+        let mut __field_key_2 = None;
+        raw.map_entries_with(|raw, __key: u64| {
+            match __key {
+                1 => {
+                    if __field_key_1.is_some() {
+                        return Err(::cbor_event::Error::DuplicateKey);
+                    }
+                    __field_key_1 = Some(raw.deserialize()?);
+                }
+                2 => {
+                    if __field_key_2.is_some() {
+                        return Err(::cbor_event::Error::DuplicateKey);
+                    }
+                    __field_key_2 = Some(raw.deserialize()?);
+                }
+                _ => raw.skip_value()?,
+            }
+            Ok(())
+        })?;
+        Ok(Header {
+            key_1: __field_key_1.ok_or(::cbor_event::Error::MissingField("key_1", "Header"))?,
+            key_2: __field_key_2.ok_or(::cbor_event::Error::MissingField("key_2", "Header"))?,
+        })
+    }
+}
+"#
+            )
+        );
+    }
+
+    #[test]
+    fn array() {
+        let code = generate("pair = [int, float]").unwrap();
+        assert!(code.contains(
+            "        let __len = raw.array()?;\n        \
+                 match __len {\n            \
+                     ::cbor_event::Len::Len(2) | ::cbor_event::Len::Indefinite => {}\n            \
+                     _ => return Err(::cbor_event::Error::WrongLen(2, __len, \"Pair\")),\n        \
+                 }\n        \
+                 let __field_0 = raw.deserialize()?;\n        \
+                 let __field_1 = raw.deserialize()?;\n        \
+                 if __len == ::cbor_event::Len::Indefinite && !raw.special_break()? {\n            \
+                     return Err(::cbor_event::Error::WrongLen(2, __len, \"Pair\"));\n        \
+                 }\n        \
+                 Ok(Pair(__field_0, __field_1))\n"
+        ));
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(generate("; nothing\n").unwrap(), HEADER);
+        let code = generate("unit = []\nempty = {}").unwrap();
+        assert!(code.contains("pub struct Unit {\n}\n"));
+        assert!(code.contains("Ok(Unit {})"));
+        assert!(code.contains("pub struct Empty {\n}\n"));
+        assert!(code.contains(
+            "        raw.map_entries_with(|raw, __key: String| {\n            \
+                 raw.skip_value()?;\n            \
+                 Ok(())\n        \
+             })?;\n"
+        ));
+    }
+
+    fn error(schema: &str) -> (String, usize) {
+        match generate(schema) {
+            Err(Error::InvalidCddl(token, line)) => (token, line),
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        // unknown rule
+        assert_eq!(error("a = [b]\nb = [* c]"), ("c".to_owned(), 2));
+        // aliases of themselves
+        assert_eq!(error("a = [x: b]\nb = c\nc = [* b]"), ("b".to_owned(), 2));
+        assert_eq!(error("a = a"), ("a".to_owned(), 1));
+        // optional entries, choices and groups
+        assert_eq!(error("a = {? 1: uint}"), ("?".to_owned(), 1));
+        assert_eq!(error("a = uint / tstr"), ("/".to_owned(), 1));
+        assert_eq!(error("a = (uint)"), ("(".to_owned(), 1));
+        // literals
+        assert_eq!(error("a = 1"), ("1".to_owned(), 1));
+        // keys mixing names and integers, or duplicated
+        assert_eq!(error("a = {x: uint,\n1: uint}"), ("1".to_owned(), 2));
+        assert_eq!(error("a = {x: uint, \"x\": uint}"), ("\"x\"".to_owned(), 1));
+        // keys not usable as field names
+        assert_eq!(error("a = {Name: uint}"), ("Name".to_owned(), 1));
+        assert_eq!(error("a = {\"self\": uint}"), ("\"self\"".to_owned(), 1));
+        // named and unnamed elements of an array
+        assert_eq!(error("a = [x: uint, tstr]"), ("tstr".to_owned(), 1));
+        // malformed
+        assert_eq!(error("a = [uint"), ("end of schema".to_owned(), 1));
+        assert_eq!(error("a = [uint uint]"), ("uint".to_owned(), 1));
+        assert_eq!(error("a = uint uint"), ("uint".to_owned(), 1));
+        assert_eq!(error("= uint"), ("=".to_owned(), 1));
+        assert_eq!(error("a = \"text"), ("\"text".to_owned(), 1));
+        assert_eq!(error("a = uint .size 8"), (".".to_owned(), 1));
+        assert_eq!(error("a = %"), ("%".to_owned(), 1));
+    }
+}
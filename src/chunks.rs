@@ -0,0 +1,190 @@
+//! Decode from a sequence of byte chunks
+//!
+//! Message queue consumers and framed transports often deliver a payload
+//! as several fragments. A [`ChunkReader`] lets a [`Deserializer`] decode
+//! them in place, from any iterator of byte slices or buffers, without
+//! first gluing them into one buffer. Only the headers straddling two
+//! chunks are copied, into a small internal buffer.
+//!
+//! [`ChunkReader`]: ./struct.ChunkReader.html
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//!
+//! ```
+//! use cbor_event::de::Deserializer;
+//!
+//! // [1000, "text"] received in two fragments, splitting the integer
+//! let fragments = vec![vec![0x82, 0x19, 0x03], vec![0xe8, 0x64, 0x74, 0x65, 0x78, 0x74]];
+//! let mut raw = Deserializer::from_chunks(fragments);
+//!
+//! raw.array().unwrap();
+//! assert_eq!(raw.unsigned_integer().unwrap(), 1000);
+//! assert_eq!(raw.text().unwrap(), "text");
+//! ```
+
+use de::Deserializer;
+use std::io::{self, BufRead, Read};
+use stitch::{Stitched, MAX_HEADER_SIZE};
+
+/// reader over an iterator of byte chunks, making sure a whole CBOR
+/// header is always available to the
+/// [`Deserializer`](../de/struct.Deserializer.html).
+pub struct ChunkReader<I: Iterator> {
+    chunks: I,
+    chunk: Option<I::Item>,
+    chunk_pos: usize,
+    // bytes copied out of the chunks when a header straddles two of them
+    stitched: Stitched,
+}
+impl<I> ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    pub fn new<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        ChunkReader {
+            chunks: chunks.into_iter(),
+            chunk: None,
+            chunk_pos: 0,
+            stitched: Stitched::new(),
+        }
+    }
+
+    /// returns the iterator of the chunks not read yet
+    pub fn into_inner(self) -> I {
+        self.chunks
+    }
+
+    // move to the next non empty chunk if the current one is exhausted,
+    // returning the bytes left in the current chunk
+    #[allow(clippy::unnecessary_map_or)] // `is_none_or` needs Rust 1.82
+    fn current(&mut self) -> &[u8] {
+        while self
+            .chunk
+            .as_ref()
+            .map_or(true, |chunk| self.chunk_pos == chunk.as_ref().len())
+        {
+            self.chunk = self.chunks.next();
+            self.chunk_pos = 0;
+            if self.chunk.is_none() {
+                break;
+            }
+        }
+        match self.chunk {
+            None => &[],
+            Some(ref chunk) => &chunk.as_ref()[self.chunk_pos..],
+        }
+    }
+}
+impl<I> Read for ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+impl<I> BufRead for ChunkReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.stitched.check()?;
+        if self.stitched.is_empty() && self.current().len() >= MAX_HEADER_SIZE {
+            return Ok(self.current());
+        }
+        // the next header may continue in the following chunks
+        self.stitched.compact();
+        while !self.stitched.is_full() && !self.current().is_empty() {
+            let chunk = self.chunk.as_ref().unwrap().as_ref();
+            self.chunk_pos += self.stitched.append(&chunk[self.chunk_pos..]);
+        }
+        Ok(self.stitched.pending())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if !self.stitched.is_empty() {
+            self.stitched.consume(amt);
+        } else if amt > self.current().len() {
+            self.stitched.overrun();
+        } else {
+            self.chunk_pos += amt;
+        }
+    }
+}
+
+impl<I> Deserializer<ChunkReader<I>>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// decode from the given chunks, see [`ChunkReader`](../chunks/struct.ChunkReader.html).
+    pub fn from_chunks<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        Deserializer::from(ChunkReader::new(chunks))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use value::Value;
+
+    #[test]
+    fn every_chunk_size() {
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(4))
+            .unwrap()
+            .write_unsigned_integer(0x1234_5678_9abc)
+            .unwrap()
+            .write_bytes(vec![0xaa; 300])
+            .unwrap()
+            .write_tag(1_000_000)
+            .unwrap()
+            .write_text("chunked")
+            .unwrap()
+            .write_negative_integer(-70_000)
+            .unwrap();
+        let bytes = se.finalize();
+        let expected: Value = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+
+        for size in 1..bytes.len() {
+            // empty chunks in between must be skipped
+            let chunks = bytes
+                .chunks(size)
+                .flat_map(|chunk| vec![chunk, &[]])
+                .collect::<Vec<_>>();
+            let mut raw = Deserializer::from_chunks(chunks);
+            let value: Value = raw.deserialize_complete().unwrap();
+            assert_eq!(value, expected, "chunks of {} bytes", size);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let mut raw = Deserializer::from_chunks(vec![&[0x82][..], &[0x19], &[0x03]]);
+        raw.array().unwrap();
+        assert!(raw.unsigned_integer().is_err());
+    }
+
+    #[test]
+    fn consume_overrun() {
+        let mut reader = ChunkReader::new(vec![&[0x82, 0x19][..], &[0x03, 0xe8]]);
+        assert_eq!(reader.fill_buf().unwrap(), [0x82, 0x19, 0x03, 0xe8]);
+        reader.consume(5);
+        let error = reader.fill_buf().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let mut reader = ChunkReader::new(vec![vec![0; 16]]);
+        assert_eq!(reader.fill_buf().unwrap().len(), 16);
+        reader.consume(17);
+        assert!(reader.read(&mut [0; 4]).is_err());
+    }
+}
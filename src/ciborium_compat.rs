@@ -0,0 +1,154 @@
+//! Interoperability with ciborium
+//!
+//! To migrate a code base one type at a time: [`Ciborium`] wraps a type
+//! implementing serde's `Serialize` and `Deserialize` so it can be written
+//! and read by this crate with ciborium, [`CborEvent`] wraps a type
+//! implementing this crate's `Serialize` and `Deserialize` so it can be
+//! used with serde, ciborium included. Both go through a temporary buffer
+//! holding the encoded value.
+//!
+//! [`Ciborium`]: ./struct.Ciborium.html
+//! [`CborEvent`]: ./struct.CborEvent.html
+//!
+//! ```
+//! # extern crate cbor_event;
+//! # extern crate ciborium;
+//! use cbor_event::{ciborium_compat::{CborEvent, Ciborium}, de::Deserializer, se::Serializer, Value};
+//! use std::collections::BTreeMap;
+//!
+//! # fn main() {
+//! // a serde type in a cbor_event pipeline
+//! let mut map = BTreeMap::new();
+//! map.insert("a".to_owned(), 1u32);
+//! let mut se = Serializer::new_vec();
+//! se.serialize(&Ciborium(&map)).unwrap();
+//! let bytes = se.finalize();
+//! let Ciborium(decoded): Ciborium<BTreeMap<String, u32>> =
+//!     Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//! assert_eq!(decoded, map);
+//!
+//! // and the other way around
+//! let mut bytes = Vec::new();
+//! ciborium::ser::into_writer(&CborEvent(Value::U64(42)), &mut bytes).unwrap();
+//! let CborEvent(value): CborEvent<Value> = ciborium::de::from_reader(&bytes[..]).unwrap();
+//! assert_eq!(value, Value::U64(42));
+//! # }
+//! ```
+
+use ciborium;
+use de::{Deserialize, Deserializer};
+use error::Error;
+use result::Result;
+use se::{Serialize, Serializer};
+use serde_crate as serde;
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+/// a type implementing serde's `Serialize` and `Deserialize`, serialised
+/// and deserialised by this crate with ciborium.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Ciborium<T>(pub T);
+
+fn ciborium_error<E: fmt::Debug>(error: E) -> Error {
+    Error::CustomError(format!("ciborium: {:?}", error))
+}
+
+impl<T: serde::Serialize> Serialize for Ciborium<T> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&self.0, &mut bytes).map_err(ciborium_error)?;
+        serializer.write_raw_bytes(&bytes)
+    }
+}
+impl<T: serde::de::DeserializeOwned> Deserialize for Ciborium<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        raw.raw_item_into(&mut bytes)?;
+        let mut reader = &bytes[..];
+        let value = ciborium::de::from_reader(&mut reader).map_err(ciborium_error)?;
+        if !reader.is_empty() {
+            return Err(Error::TrailingData);
+        }
+        Ok(Ciborium(value))
+    }
+}
+
+/// a type implementing `Serialize` and `Deserialize`, serialised and
+/// deserialised by serde.
+///
+/// The value goes through a `ciborium::Value`: serialised by ciborium, it
+/// is encoded again by ciborium's rules. The tags are kept, but the bignums
+/// fitting in 128 bits become integers, `undefined` becomes `null` and the
+/// unassigned simple values are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CborEvent<T>(pub T);
+
+impl<T: Serialize> serde::Serialize for CborEvent<T> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> ::std::result::Result<S::Ok, S::Error> {
+        use serde_crate::ser::Error;
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&self.0).map_err(S::Error::custom)?;
+        let value: ciborium::Value = ciborium::de::from_reader(&se.finalize()[..])
+            .map_err(|error| S::Error::custom(format!("ciborium: {:?}", error)))?;
+        value.serialize(serializer)
+    }
+}
+impl<'de, T: Deserialize> serde::Deserialize<'de> for CborEvent<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> ::std::result::Result<Self, D::Error> {
+        use serde_crate::de::Error;
+
+        let value = ciborium::Value::deserialize(deserializer)?;
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&value, &mut bytes)
+            .map_err(|error| D::Error::custom(format!("ciborium: {:?}", error)))?;
+        Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .map(CborEvent)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use value::Value;
+
+    #[test]
+    fn nested() {
+        // a cbor_event value inside a serde type, inside a cbor_event array
+        let inner = (Some(3u8), CborEvent(Value::Tag(1, Box::new(Value::U64(2)))));
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(1))
+            .unwrap()
+            .serialize(&Ciborium(&inner))
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, [0x81, 0x82, 0x03, 0xc1, 0x02]);
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.array().unwrap();
+        let Ciborium((n, value)): Ciborium<(Option<u8>, CborEvent<Value>)> =
+            raw.deserialize_complete().unwrap();
+        assert_eq!(n, Some(3));
+        assert_eq!(value.0, Value::Tag(1, Box::new(Value::U64(2))));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Deserializer::from(&[0x01][..])
+            .deserialize::<Ciborium<String>>()
+            .is_err());
+        assert!(ciborium::de::from_reader::<CborEvent<String>, _>(&[0x01][..]).is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Dates and times, tags 0 and 1 (RFC 8949 section 3.4.1 and 3.4.2)
+//!
+//! A date/time is encoded in one of two formats:
+//!
+//! * a standard date/time string (tag 0): RFC 3339 text,
+//!   `0("2013-03-21T20:04:00Z")`;
+//! * an epoch-based date/time (tag 1): the number of seconds since
+//!   `1970-01-01T00:00Z`, an integer or a float, `1(1363896240)`.
+//!
+//! [`DateTime`] represents either, the `chrono` and `time` features
+//! implement `Serialize` and `Deserialize` for `chrono::DateTime<Utc>` and
+//! `time::OffsetDateTime`: encoded as text, decoded from both formats.
+//!
+//! [`DateTime`]: ./enum.DateTime.html
+//!
+//! ```
+//! use cbor_event::{datetime::DateTime, de::Deserializer, se::Serializer};
+//!
+//! let mut se = Serializer::new_vec();
+//! se.write_datetime_text("2013-03-21T20:04:00Z").unwrap();
+//! se.write_epoch(1363896240).unwrap();
+//! let bytes = se.finalize();
+//!
+//! let mut raw = Deserializer::from(&bytes[..]);
+//! assert_eq!(
+//!     raw.datetime().unwrap(),
+//!     DateTime::Text("2013-03-21T20:04:00Z".to_owned())
+//! );
+//! assert_eq!(raw.datetime().unwrap(), DateTime::Epoch(1363896240));
+//! ```
+
+#[cfg(feature = "chrono")]
+use chrono;
+use de::{Deserialize, Deserializer};
+use error::Error;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::io::{BufRead, Write};
+#[cfg(feature = "time")]
+use time;
+use types::{Special, Type};
+
+/// tag of the standard date/time strings
+pub const TAG_DATETIME: u64 = 0;
+/// tag of the epoch-based date/times
+pub const TAG_EPOCH: u64 = 1;
+
+/// date/time in any of the formats of RFC 8949, see the
+/// [module documentation](./index.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateTime {
+    /// RFC 3339 text, with a time zone offset
+    Text(String),
+    /// integer number of seconds since the epoch
+    Epoch(i64),
+    /// number of seconds since the epoch with a fractional part, finite
+    EpochFloat(f64),
+}
+
+// whether `text` is an RFC 3339 date-time, `2013-03-21T20:04:00.5+01:00`.
+// Only the syntax and the range of each field are checked.
+fn is_rfc3339(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    // the value of `bytes[start..end]`, if these are all digits
+    let number = |start: usize, end: usize| -> Option<u32> {
+        bytes.get(start..end)?.iter().try_fold(0, |n, b| {
+            if b.is_ascii_digit() {
+                Some(n * 10 + (b - b'0') as u32)
+            } else {
+                None
+            }
+        })
+    };
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    if separators.iter().any(|&(i, c)| bytes.get(i) != Some(&c))
+        || !matches!(bytes.get(10), Some(b'T') | Some(b't'))
+    {
+        return false;
+    }
+    match (
+        number(0, 4),
+        number(5, 7),
+        number(8, 10),
+        number(11, 13),
+        number(14, 16),
+        number(17, 19),
+    ) {
+        (Some(_), Some(1..=12), Some(1..=31), Some(0..=23), Some(0..=59), Some(0..=60)) => (),
+        _ => return false,
+    }
+    let mut offset = 19;
+    if bytes.get(offset) == Some(&b'.') {
+        let digits = bytes[offset + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return false;
+        }
+        offset += 1 + digits;
+    }
+    match &bytes[offset..] {
+        [b'Z'] | [b'z'] => true,
+        [b'+', .., b':', _, _] | [b'-', .., b':', _, _] => {
+            bytes.len() == offset + 6
+                && matches!(number(offset + 1, offset + 3), Some(0..=23))
+                && matches!(number(offset + 4, offset + 6), Some(0..=59))
+        }
+        _ => false,
+    }
+}
+
+// the whole seconds and the nanoseconds of an epoch with a fractional part
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn split_epoch(seconds: f64) -> (i64, u32) {
+    let whole = seconds.floor();
+    let nanos = ((seconds - whole) * 1e9).round() as u32;
+    if nanos >= 1_000_000_000 {
+        ((whole as i64).saturating_add(1), 0)
+    } else {
+        (whole as i64, nanos)
+    }
+}
+
+impl<W: Write + Sized> Serializer<W> {
+    /// write a standard date/time string, `0("2013-03-21T20:04:00Z")`.
+    ///
+    /// Fails if `text` is not an RFC 3339 date-time.
+    pub fn write_datetime_text<S: AsRef<str>>(&mut self, text: S) -> Result<&mut Self> {
+        if !is_rfc3339(text.as_ref()) {
+            return Err(Error::InvalidDateTime("expected RFC 3339 text"));
+        }
+        self.write_tag(TAG_DATETIME)?.write_text(text)
+    }
+
+    /// write an epoch-based date/time in whole seconds, `1(1363896240)`.
+    pub fn write_epoch(&mut self, seconds: i64) -> Result<&mut Self> {
+        self.write_tag(TAG_EPOCH)?.write_integer(seconds)
+    }
+
+    /// write an epoch-based date/time with a fractional part,
+    /// `1(1363896240.5)`.
+    ///
+    /// Fails if `seconds` is not finite.
+    pub fn write_epoch_float(&mut self, seconds: f64) -> Result<&mut Self> {
+        if !seconds.is_finite() {
+            return Err(Error::InvalidDateTime("epoch is not finite"));
+        }
+        self.write_tag(TAG_EPOCH)?
+            .write_special(Special::Float(seconds))
+    }
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// read a date/time in any of the formats of RFC 8949.
+    ///
+    /// The text of a standard date/time string must be an RFC 3339
+    /// date-time, the integer of an epoch-based date/time must fit in an
+    /// `i64` and its float must be finite.
+    pub fn datetime(&mut self) -> Result<DateTime> {
+        match self.tag()? {
+            TAG_DATETIME => {
+                let text = self.text()?;
+                if is_rfc3339(&text) {
+                    Ok(DateTime::Text(text))
+                } else {
+                    Err(Error::InvalidDateTime("expected RFC 3339 text"))
+                }
+            }
+            TAG_EPOCH => match self.cbor_type()? {
+                Type::UnsignedInteger | Type::NegativeInteger => {
+                    let seconds = self.integer()?;
+                    if seconds < i64::MIN as i128 || seconds > i64::MAX as i128 {
+                        Err(Error::InvalidDateTime("epoch out of range"))
+                    } else {
+                        Ok(DateTime::Epoch(seconds as i64))
+                    }
+                }
+                Type::Special => {
+                    let seconds = self.float()?;
+                    if seconds.is_finite() {
+                        Ok(DateTime::EpochFloat(seconds))
+                    } else {
+                        Err(Error::InvalidDateTime("epoch is not finite"))
+                    }
+                }
+                t => Err(Error::Expected(Type::UnsignedInteger, t)),
+            },
+            _ => Err(Error::InvalidDateTime("expected tag 0 or 1")),
+        }
+    }
+}
+
+impl Serialize for DateTime {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        match self {
+            DateTime::Text(text) => serializer.write_datetime_text(text),
+            DateTime::Epoch(seconds) => serializer.write_epoch(*seconds),
+            DateTime::EpochFloat(seconds) => serializer.write_epoch_float(*seconds),
+        }
+    }
+}
+impl Deserialize for DateTime {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.datetime()
+    }
+}
+
+/// encoded as a standard date/time string
+#[cfg(feature = "chrono")]
+impl Serialize for chrono::DateTime<chrono::Utc> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_datetime_text(self.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+    }
+}
+/// decoded from a standard or an epoch-based date/time
+#[cfg(feature = "chrono")]
+impl Deserialize for chrono::DateTime<chrono::Utc> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let datetime = match raw.datetime()? {
+            DateTime::Text(text) => {
+                return chrono::DateTime::parse_from_rfc3339(&text)
+                    .map(|datetime| datetime.with_timezone(&chrono::Utc))
+                    .map_err(|_| Error::InvalidDateTime("expected RFC 3339 text"));
+            }
+            DateTime::Epoch(seconds) => chrono::DateTime::from_timestamp(seconds, 0),
+            DateTime::EpochFloat(seconds) => {
+                let (seconds, nanos) = split_epoch(seconds);
+                chrono::DateTime::from_timestamp(seconds, nanos)
+            }
+        };
+        datetime.ok_or(Error::InvalidDateTime("epoch out of range"))
+    }
+}
+
+/// encoded as a standard date/time string
+#[cfg(feature = "time")]
+impl Serialize for time::OffsetDateTime {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        let text = self
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|_| Error::InvalidDateTime("not representable as RFC 3339 text"))?;
+        serializer.write_datetime_text(text)
+    }
+}
+/// decoded from a standard or an epoch-based date/time
+#[cfg(feature = "time")]
+impl Deserialize for time::OffsetDateTime {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.datetime()? {
+            DateTime::Text(text) => {
+                time::OffsetDateTime::parse(&text, &time::format_description::well_known::Rfc3339)
+                    .map_err(|_| Error::InvalidDateTime("expected RFC 3339 text"))
+            }
+            DateTime::Epoch(seconds) => time::OffsetDateTime::from_unix_timestamp(seconds)
+                .map_err(|_| Error::InvalidDateTime("epoch out of range")),
+            DateTime::EpochFloat(seconds) => {
+                let (seconds, nanos) = split_epoch(seconds);
+                time::OffsetDateTime::from_unix_timestamp_nanos(
+                    seconds as i128 * 1_000_000_000 + nanos as i128,
+                )
+                .map_err(|_| Error::InvalidDateTime("epoch out of range"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Result<DateTime> {
+        Deserializer::from(bytes).deserialize_complete()
+    }
+
+    #[test]
+    fn rfc_examples() {
+        let examples = [
+            (
+                DateTime::Text("2013-03-21T20:04:00Z".to_owned()),
+                vec![
+                    0xc0, 0x74, 0x32, 0x30, 0x31, 0x33, 0x2d, 0x30, 0x33, 0x2d, 0x32, 0x31, 0x54,
+                    0x32, 0x30, 0x3a, 0x30, 0x34, 0x3a, 0x30, 0x30, 0x5a,
+                ],
+            ),
+            (
+                DateTime::Epoch(1363896240),
+                vec![0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0],
+            ),
+            (
+                DateTime::EpochFloat(1363896240.5),
+                vec![0xc1, 0xfb, 0x41, 0xd4, 0x52, 0xd9, 0xec, 0x20, 0x00, 0x00],
+            ),
+        ];
+        for (datetime, bytes) in examples.iter() {
+            assert_eq!(::to_vec(datetime).unwrap(), *bytes);
+            assert_eq!(decode(bytes).unwrap(), *datetime);
+        }
+        assert_eq!(decode(&[0xc1, 0x20]).unwrap(), DateTime::Epoch(-1));
+    }
+
+    #[test]
+    fn rfc3339() {
+        for text in [
+            "2013-03-21T20:04:00Z",
+            "2013-03-21t20:04:00.123456789z",
+            "1985-04-12T23:20:50.52+01:00",
+            "1990-12-31T23:59:60-08:00",
+        ] {
+            assert!(is_rfc3339(text), "{}", text);
+        }
+        for text in [
+            "",
+            "2013-03-21",
+            "2013-03-21 20:04:00Z",
+            "2013-13-21T20:04:00Z",
+            "2013-03-21T24:04:00Z",
+            "2013-03-21T20:04:00",
+            "2013-03-21T20:04:00.Z",
+            "2013-03-21T20:04:00+0100",
+            "2013-03-21T20:04:00+01:60",
+            "2013-03-21T20:04:00Z ",
+        ] {
+            assert!(!is_rfc3339(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut se = Serializer::new_vec();
+        assert!(se.write_datetime_text("yesterday").is_err());
+        assert!(se.write_epoch_float(f64::NAN).is_err());
+        assert!(se.finalize().is_empty());
+
+        // 0("yesterday"), a tag 2, 1(-2^64), 1(Infinity) and 1("1")
+        assert!(
+            decode(&[0xc0, 0x69, 0x79, 0x65, 0x73, 0x74, 0x65, 0x72, 0x64, 0x61, 0x79]).is_err()
+        );
+        assert!(decode(&[0xc2, 0x41, 0x01]).is_err());
+        assert!(decode(&[0xc1, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(decode(&[0xc1, 0xf9, 0x7c, 0x00]).is_err());
+        assert!(decode(&[0xc1, 0x61, 0x31]).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono() {
+        use chrono::{TimeZone, Utc};
+
+        let datetime = Utc.with_ymd_and_hms(2013, 3, 21, 20, 4, 0).unwrap();
+        ::test_encode_decode(&datetime).unwrap();
+        let bytes = ::to_vec(&DateTime::EpochFloat(1363896240.5)).unwrap();
+        let decoded: chrono::DateTime<Utc> = ::from_slice(&bytes).unwrap();
+        assert_eq!(decoded, datetime + chrono::Duration::milliseconds(500));
+        let bytes = ::to_vec(&DateTime::Text("2013-03-21T21:04:00+01:00".to_owned())).unwrap();
+        assert_eq!(
+            ::from_slice::<chrono::DateTime<Utc>>(&bytes).unwrap(),
+            datetime
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        let datetime = time::OffsetDateTime::from_unix_timestamp(1363896240).unwrap();
+        ::test_encode_decode(&datetime).unwrap();
+        let bytes = ::to_vec(&DateTime::EpochFloat(-0.25)).unwrap();
+        let decoded: time::OffsetDateTime = ::from_slice(&bytes).unwrap();
+        assert_eq!(decoded.unix_timestamp_nanos(), -250_000_000);
+    }
+}
@@ -1,10 +1,51 @@
 //! CBOR deserialisation tooling
 
 use error::Error;
-use len::Len;
+use float;
+#[cfg(feature = "indexmap")]
+use indexmap::{IndexMap, IndexSet};
+use len::{Len, LenSz, StringLenSz, Sz};
+use lint::{self, ViolationKind};
+#[cfg(feature = "num-bigint")]
+use num_bigint::{BigInt, BigUint};
+#[cfg(feature = "std")]
+use pool::{BufferPool, PooledBuffer};
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use raw::RawCbor;
 use result::Result;
-use std::{self, collections::BTreeMap, io::BufRead};
-use types::{Special, Type};
+use scan;
+use sequence::SequenceReader;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(any(feature = "std", feature = "indexmap"))]
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::io::{Seek, SeekFrom};
+use std::{
+    self,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufRead, Write},
+    mem,
+    ops::{Deref, DerefMut, Range, RangeInclusive},
+};
+use types::{ByteString, Number, OverflowPolicy, Special, Type};
+use value::{ObjectKey, Value};
+
+// emit a `tracing` event with the `tracing` feature, nothing otherwise
+macro_rules! trace_event {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::trace!(target: "cbor_event", $($arg)+);
+    };
+}
+
+/// nesting of arrays, maps and tags accepted by the functions re-encoding
+/// or building values out of whole objects, which recurse once per level:
+/// deeper objects fail with `Error::MaxDepthExceeded` instead of
+/// overflowing the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
 
 pub trait Deserialize: Sized {
     /// method to implement to deserialise an object from the given
@@ -12,7 +53,104 @@ pub trait Deserialize: Sized {
     fn deserialize<R: BufRead>(reader: &mut Deserializer<R>) -> Result<Self>;
 }
 
+/// decode into an existing object, reusing its allocations.
+///
+/// The strings and containers are cleared and refilled, so a decoding loop
+/// keeping the same object from one message to the next stops allocating
+/// once it reached the size of the messages. The default implementation
+/// replaces the object with a newly decoded one.
+///
+/// On error, the object is left in a valid but unspecified state.
+///
+/// ```
+/// use cbor_event::de::*;
+///
+/// let messages: [&[u8]; 2] = [&[0x82, 0x61, 0x61, 0x61, 0x62], &[0x81, 0x61, 0x63]];
+///
+/// let mut names: Vec<String> = Vec::new();
+/// for message in messages.iter() {
+///     Deserializer::from(*message).deserialize_in_place(&mut names).unwrap();
+/// }
+/// assert_eq!(names, vec!["c".to_owned()]);
+/// ```
+pub trait DeserializeInPlace: Deserialize {
+    fn deserialize_in_place<R: BufRead>(&mut self, reader: &mut Deserializer<R>) -> Result<()> {
+        *self = reader.deserialize()?;
+        Ok(())
+    }
+}
+impl DeserializeInPlace for u8 {}
+impl DeserializeInPlace for u16 {}
+impl DeserializeInPlace for u32 {}
+impl DeserializeInPlace for u64 {}
+impl DeserializeInPlace for bool {}
+impl DeserializeInPlace for f64 {}
+impl DeserializeInPlace for Value {}
+impl DeserializeInPlace for String {
+    fn deserialize_in_place<R: BufRead>(&mut self, raw: &mut Deserializer<R>) -> Result<()> {
+        let mut bytes = mem::take(self).into_bytes();
+        bytes.clear();
+        raw.string_into(Type::Text, &mut bytes, true)?;
+        *self = String::from_utf8(bytes)?;
+        Ok(())
+    }
+}
+impl DeserializeInPlace for ByteString<Vec<u8>> {
+    fn deserialize_in_place<R: BufRead>(&mut self, raw: &mut Deserializer<R>) -> Result<()> {
+        self.0.clear();
+        raw.bytes_into(&mut self.0)
+    }
+}
+/// the elements already in the vector are decoded in place
+impl<T: DeserializeInPlace> DeserializeInPlace for Vec<T> {
+    fn deserialize_in_place<R: BufRead>(&mut self, raw: &mut Deserializer<R>) -> Result<()> {
+        let mut len = 0;
+        let result = raw.array_with(|raw| {
+            match self.get_mut(len) {
+                Some(elem) => elem.deserialize_in_place(raw)?,
+                None => self.push(raw.deserialize()?),
+            }
+            len += 1;
+            Ok(())
+        });
+        self.truncate(len);
+        result
+    }
+}
+impl<K: Deserialize + Ord, V: Deserialize> DeserializeInPlace for BTreeMap<K, V> {
+    fn deserialize_in_place<R: BufRead>(&mut self, raw: &mut Deserializer<R>) -> Result<()> {
+        self.clear();
+        raw.map_with(|raw| {
+            let k = raw.deserialize()?;
+            let v = raw.deserialize()?;
+            self.insert(k, v);
+            Ok(())
+        })
+    }
+}
+
+/// decode any [`Deserialize`](./trait.Deserialize.html) type from a buffer
+/// holding exactly one encoded object.
+///
+/// ```
+/// use cbor_event::FromCborBytes;
+///
+/// assert_eq!(u64::from_cbor_bytes(&[0x18, 0x2a]).unwrap(), 42);
+/// assert!(u64::from_cbor_bytes(&[0x18, 0x2a, 0x00]).is_err());
+/// ```
+pub trait FromCborBytes: Sized {
+    /// decode the object in `bytes`, failing with `Error::TrailingData` if
+    /// it does not use all the bytes.
+    fn from_cbor_bytes(bytes: &[u8]) -> Result<Self>;
+}
+impl<T: Deserialize> FromCborBytes for T {
+    fn from_cbor_bytes(bytes: &[u8]) -> Result<Self> {
+        from_slice_complete(bytes)
+    }
+}
+
 impl Deserialize for u8 {
+    #[allow(clippy::legacy_numeric_constants)]
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         let n = raw.unsigned_integer()?;
         if n > std::u8::MAX as u64 {
@@ -24,6 +162,7 @@ impl Deserialize for u8 {
 }
 
 impl Deserialize for u16 {
+    #[allow(clippy::legacy_numeric_constants)]
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         let n = raw.unsigned_integer()?;
         if n > std::u16::MAX as u64 {
@@ -35,6 +174,7 @@ impl Deserialize for u16 {
 }
 
 impl Deserialize for u32 {
+    #[allow(clippy::legacy_numeric_constants)]
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         let n = raw.unsigned_integer()?;
         if n > std::u32::MAX as u64 {
@@ -51,12 +191,197 @@ impl Deserialize for u64 {
     }
 }
 
+/// decoded from an unsigned or negative integer
+impl Deserialize for i8 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let n = raw.integer()?;
+        if n < i8::MIN as i128 || n > i8::MAX as i128 {
+            Err(Error::ExpectedI8)
+        } else {
+            Ok(n as Self)
+        }
+    }
+}
+
+/// decoded from an unsigned or negative integer
+impl Deserialize for i16 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let n = raw.integer()?;
+        if n < i16::MIN as i128 || n > i16::MAX as i128 {
+            Err(Error::ExpectedI16)
+        } else {
+            Ok(n as Self)
+        }
+    }
+}
+
+/// decoded from an unsigned or negative integer
+impl Deserialize for i32 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let n = raw.integer()?;
+        if n < i32::MIN as i128 || n > i32::MAX as i128 {
+            Err(Error::ExpectedI32)
+        } else {
+            Ok(n as Self)
+        }
+    }
+}
+
+/// decoded from an unsigned or negative integer
+impl Deserialize for i64 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let n = raw.integer()?;
+        if n < i64::MIN as i128 || n > i64::MAX as i128 {
+            Err(Error::ExpectedI64)
+        } else {
+            Ok(n as Self)
+        }
+    }
+}
+
+// the value of the big endian bytes of a bignum, if it fits in 128 bits
+fn bignum_u128(bytes: &[u8]) -> Option<u128> {
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len());
+    if bytes.len() - start > 16 {
+        return None;
+    }
+    Some(
+        bytes[start..]
+            .iter()
+            .fold(0, |n, byte| n << 8 | *byte as u128),
+    )
+}
+
+/// decoded from an unsigned integer or a bignum (tag 2)
+impl Deserialize for u128 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.number()? {
+            Number::U64(n) => Ok(n as u128),
+            Number::BigInt(false, bytes) => bignum_u128(&bytes).ok_or(Error::ExpectedU128),
+            Number::NegU64(_) => Err(Error::Expected(
+                Type::UnsignedInteger,
+                Type::NegativeInteger,
+            )),
+            Number::BigInt(true, _) => Err(Error::ExpectedU128),
+            Number::F64(_) => Err(Error::Expected(Type::UnsignedInteger, Type::Special)),
+        }
+    }
+}
+/// decoded from an unsigned or negative integer or a bignum (tags 2 and 3)
+impl Deserialize for i128 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let (negative, n) = match raw.number()? {
+            Number::U64(n) => (false, n as u128),
+            Number::NegU64(n) => (true, n as u128),
+            Number::BigInt(negative, bytes) => {
+                (negative, bignum_u128(&bytes).ok_or(Error::ExpectedI128)?)
+            }
+            Number::F64(_) => return Err(Error::Expected(Type::UnsignedInteger, Type::Special)),
+        };
+        if n > i128::MAX as u128 {
+            Err(Error::ExpectedI128)
+        } else if negative {
+            Ok(-1 - n as i128)
+        } else {
+            Ok(n as i128)
+        }
+    }
+}
+/// decoded from an unsigned integer or a bignum (tag 2)
+#[cfg(feature = "num-bigint")]
+impl Deserialize for BigUint {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.number()? {
+            Number::U64(n) => Ok(BigUint::from(n)),
+            Number::BigInt(false, bytes) => Ok(BigUint::from_bytes_be(&bytes)),
+            Number::NegU64(_) | Number::BigInt(true, _) => Err(Error::Expected(
+                Type::UnsignedInteger,
+                Type::NegativeInteger,
+            )),
+            Number::F64(_) => Err(Error::Expected(Type::UnsignedInteger, Type::Special)),
+        }
+    }
+}
+/// decoded from an unsigned or negative integer or a bignum (tags 2 and 3)
+#[cfg(feature = "num-bigint")]
+impl Deserialize for BigInt {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.number()? {
+            Number::U64(n) => Ok(BigInt::from(n)),
+            Number::NegU64(n) => Ok(-1 - BigInt::from(n)),
+            Number::BigInt(false, bytes) => Ok(BigInt::from(BigUint::from_bytes_be(&bytes))),
+            Number::BigInt(true, bytes) => Ok(-1 - BigInt::from(BigUint::from_bytes_be(&bytes))),
+            Number::F64(_) => Err(Error::Expected(Type::UnsignedInteger, Type::Special)),
+        }
+    }
+}
+
+/// unsigned integer types an `UnsignedInteger` can be narrowed to with
+/// [`Deserializer::unsigned_as`](./struct.Deserializer.html#method.unsigned_as).
+pub trait NarrowUnsigned: Sized {
+    /// largest value of the type
+    const MAX: u64;
+
+    /// error returned when the value does not fit and the
+    /// [`OverflowPolicy`](../enum.OverflowPolicy.html) is `Error`
+    fn overflow_error() -> Error;
+
+    /// the low order bits of `n`
+    fn truncate(n: u64) -> Self;
+}
+macro_rules! narrow_unsigned {
+    ($t:ty, $error:expr) => {
+        impl NarrowUnsigned for $t {
+            const MAX: u64 = <$t>::MAX as u64;
+
+            fn overflow_error() -> Error {
+                $error
+            }
+
+            fn truncate(n: u64) -> Self {
+                n as $t
+            }
+        }
+    };
+}
+narrow_unsigned!(u8, Error::ExpectedU8);
+narrow_unsigned!(u16, Error::ExpectedU16);
+narrow_unsigned!(u32, Error::ExpectedU32);
+narrow_unsigned!(u64, Error::ExpectedU64);
+
+impl Deserialize for ByteString<Vec<u8>> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.bytes().map(ByteString)
+    }
+}
+
 impl Deserialize for bool {
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         raw.bool()
     }
 }
 
+/// decoded from a floating point number, or an integer if
+/// [`set_lenient`](./struct.Deserializer.html#method.set_lenient) is enabled
+impl Deserialize for f64 {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.cbor_type()? {
+            t @ Type::UnsignedInteger | t @ Type::NegativeInteger if !raw.lenient => {
+                Err(Error::Expected(Type::Special, t))
+            }
+            _ => match raw.number()? {
+                Number::U64(n) => Ok(n as f64),
+                Number::NegU64(n) => Ok(-1.0 - n as f64),
+                Number::F64(f) => Ok(f),
+                Number::BigInt(..) => Err(Error::Expected(Type::Special, Type::Tag)),
+            },
+        }
+    }
+}
+
 impl Deserialize for String {
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         raw.text()
@@ -73,11 +398,23 @@ impl<T: Deserialize> Deserialize for Vec<T> {
         Ok(vec)
     }
 }
+/// decoded from a CBOR map, keeping the entries in order, duplicates
+/// included. See also [`OrderedMap`](../struct.OrderedMap.html).
+impl<K: Deserialize, V: Deserialize> Deserialize for Vec<(K, V)> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut vec = Vec::new();
+        raw.map_entries_with(|raw, k| {
+            let v = Deserialize::deserialize(raw)?;
+            vec.push((k, v));
+            Ok(())
+        })?;
+        Ok(vec)
+    }
+}
 impl<K: Deserialize + Ord, V: Deserialize> Deserialize for BTreeMap<K, V> {
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
         let mut vec = BTreeMap::new();
-        raw.map_with(|raw| {
-            let k = Deserialize::deserialize(raw)?;
+        raw.map_entries_with(|raw, k| {
             let v = Deserialize::deserialize(raw)?;
             vec.insert(k, v);
             Ok(())
@@ -85,6 +422,88 @@ impl<K: Deserialize + Ord, V: Deserialize> Deserialize for BTreeMap<K, V> {
         Ok(vec)
     }
 }
+/// decoded from an array, optionally tagged with the set tag (258).
+/// Duplicated elements are kept once.
+impl<T: Deserialize + Ord> Deserialize for BTreeSet<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut set = BTreeSet::new();
+        raw.set_with(|raw| {
+            set.insert(Deserialize::deserialize(raw)?);
+            Ok(())
+        })?;
+        Ok(set)
+    }
+}
+/// decoded from an array, optionally tagged with the set tag (258).
+/// Duplicated elements are kept once.
+#[cfg(feature = "std")]
+impl<T, S> Deserialize for HashSet<T, S>
+where
+    T: Deserialize + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut set = HashSet::default();
+        raw.set_with(|raw| {
+            set.insert(Deserialize::deserialize(raw)?);
+            Ok(())
+        })?;
+        Ok(set)
+    }
+}
+/// decoded from a CBOR map, keeping the entries in the order of the input
+#[cfg(feature = "indexmap")]
+impl<K, V, S> Deserialize for IndexMap<K, V, S>
+where
+    K: Deserialize + Hash + Eq,
+    V: Deserialize,
+    S: BuildHasher + Default,
+{
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut map = IndexMap::default();
+        raw.map_entries_with(|raw, k| {
+            let v = Deserialize::deserialize(raw)?;
+            map.insert(k, v);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+/// decoded from a CBOR array, keeping the elements in the order of the input
+#[cfg(feature = "indexmap")]
+impl<T, S> Deserialize for IndexSet<T, S>
+where
+    T: Deserialize + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut set = IndexSet::default();
+        raw.array_with(|raw| {
+            set.insert(Deserialize::deserialize(raw)?);
+            Ok(())
+        })?;
+        Ok(set)
+    }
+}
+
+/// decoded from a 2 elements array `[start, end]`
+impl<T: Deserialize> Deserialize for Range<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.tuple(2, "Range<T>")?;
+        let start = raw.deserialize()?;
+        let end = raw.deserialize()?;
+        Ok(start..end)
+    }
+}
+/// decoded from a 2 elements array `[start, end]`
+impl<T: Deserialize> Deserialize for RangeInclusive<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.tuple(2, "RangeInclusive<T>")?;
+        let start = raw.deserialize()?;
+        let end = raw.deserialize()?;
+        Ok(start..=end)
+    }
+}
 
 impl<T: Deserialize> Deserialize for Option<T> {
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
@@ -99,6 +518,28 @@ impl<T: Deserialize> Deserialize for Option<T> {
     }
 }
 
+// key of a map entry, `None` if the key is not of the type expected by the
+// map helpers
+struct EntryKey<K>(Option<K>);
+impl Deserialize for EntryKey<ObjectKey> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.cbor_type()? {
+            Type::UnsignedInteger | Type::Bytes | Type::Text => {
+                Ok(EntryKey(Some(raw.deserialize()?)))
+            }
+            _ => scan::walk_item(raw).map(|()| EntryKey(None)),
+        }
+    }
+}
+impl Deserialize for EntryKey<u64> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.cbor_type()? {
+            Type::UnsignedInteger => Ok(EntryKey(Some(raw.unsigned_integer()?))),
+            _ => scan::walk_item(raw).map(|()| EntryKey(None)),
+        }
+    }
+}
+
 /// [`Deserialize`]: ./trait.Deserialize.html
 /// [`Error`]: ../enum.Error.html
 /// [`Type`]: ../enum.Type.html
@@ -156,88 +597,641 @@ impl<T: Deserialize> Deserialize for Option<T> {
 ///
 /// # Panic
 ///
-/// There is no explicit `panic!` in this code, except a few `unreachable!`.
+/// No input makes the decoding functions panic, the malformed inputs are
+/// reported as errors.
 ///
-pub struct Deserializer<R>(R);
-impl<R> From<R> for Deserializer<R> {
-    fn from(r: R) -> Self {
-        Deserializer(r)
+pub struct Deserializer<R> {
+    reader: R,
+    // accept integers for floats and 0 or 1 for booleans
+    lenient: bool,
+    // reject the indefinite length arrays, maps and strings
+    definite_only: bool,
+    // reject the encodings that are not canonical
+    canonical: bool,
+    // number of bytes consumed so far
+    position: u64,
+    // wrap the errors of `deserialize` in `Error::At`
+    error_offsets: bool,
+    // number of arrays, maps and tags entered, and its limit
+    depth: usize,
+    max_depth: usize,
+    // whether the current scope entered a container, see `scoped`
+    entered: bool,
+    // the items skipped so far, if they are preserved
+    unknown: Option<Unknown>,
+}
+
+/// the items skipped by a [`Deserializer`](./struct.Deserializer.html)
+/// preserving them, see
+/// [`set_preserve_unknown`](./struct.Deserializer.html#method.set_preserve_unknown).
+///
+/// They are kept as encoded, so writing them back reproduces them byte for
+/// byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unknown {
+    /// the map entries whose key was not known, key and value
+    pub entries: Vec<(RawCbor, RawCbor)>,
+    /// the objects skipped with [`skip_value`](./struct.Deserializer.html#method.skip_value),
+    /// like extra array elements
+    pub elements: Vec<RawCbor>,
+}
+impl Unknown {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.elements.is_empty()
     }
 }
-impl<R> Deserializer<R> {
-    pub fn as_ref(&self) -> &R {
-        &self.0
+
+/// a [`Deserializer`](./struct.Deserializer.html) limited to the next bytes
+/// of another one, see [`take`](./struct.Deserializer.html#method.take).
+///
+/// The items it skipped while preserving the unknown ones are added to the
+/// ones of the other `Deserializer` on drop.
+pub struct Frame<'a, R: 'a> {
+    raw: Deserializer<io::Take<&'a mut R>>,
+    unknown: &'a mut Option<Unknown>,
+}
+impl<'a, R> Deref for Frame<'a, R> {
+    type Target = Deserializer<io::Take<&'a mut R>>;
+    fn deref(&self) -> &Self::Target {
+        &self.raw
     }
-    pub fn as_mut_ref(&mut self) -> &mut R {
-        &mut self.0
+}
+impl<'a, R> DerefMut for Frame<'a, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.raw
     }
-    pub fn inner(self) -> R {
-        self.0
+}
+impl<'a, R> Drop for Frame<'a, R> {
+    fn drop(&mut self) {
+        if let (Some(unknown), Some(skipped)) = (self.unknown.as_mut(), self.raw.unknown.take()) {
+            unknown.entries.extend(skipped.entries);
+            unknown.elements.extend(skipped.elements);
+        }
     }
 }
-impl<R: BufRead> Deserializer<R> {
-    #[inline]
-    fn get(&mut self, index: usize) -> Result<u8> {
-        let buf = self.0.fill_buf()?;
-        match buf.get(index) {
-            None => Err(Error::NotEnough(buf.len(), index)),
-            Some(b) => Ok(*b),
+
+/// reader over the content of a Bytes, see
+/// [`bytes_reader`](./struct.Deserializer.html#method.bytes_reader).
+pub struct BytesReader<'a, R: 'a> {
+    raw: &'a mut Deserializer<R>,
+    // bytes left in the current chunk
+    left: u64,
+    // whether more chunks follow, until the break
+    chunked: bool,
+}
+impl<'a, R: BufRead> BytesReader<'a, R> {
+    // move to the next chunk if the current one has been read, returns
+    // false at the end of the content
+    fn next_chunk(&mut self) -> Result<bool> {
+        while self.left == 0 {
+            if !self.chunked || self.raw.special_break()? {
+                self.chunked = false;
+                return Ok(false);
+            }
+            self.raw.cbor_expect_type(Type::Bytes)?;
+            match self.raw.cbor_len()? {
+                (Len::Indefinite, _) => return Err(Error::InvalidIndefiniteString),
+                (Len::Len(len), len_sz) => {
+                    self.raw.advance(1 + len_sz)?;
+                    self.left = len;
+                }
+            }
         }
+        Ok(true)
     }
-    #[inline]
-    fn u8(&mut self, index: usize) -> Result<u64> {
-        let b = self.get(index)?;
-        Ok(b as u64)
+}
+impl<'a, R: BufRead> io::Read for BytesReader<'a, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let buf = self.fill_buf()?;
+        let n = buf.len().min(out.len());
+        out[..n].copy_from_slice(&buf[..n]);
+        self.consume(n);
+        Ok(n)
     }
-    #[inline]
-    fn u16(&mut self, index: usize) -> Result<u64> {
-        let b1 = self.u8(index)?;
-        let b2 = self.u8(index + 1)?;
-        Ok(b1 << 8 | b2)
+}
+impl<'a, R: BufRead> BufRead for BytesReader<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.next_chunk().map_err(into_io_error)? {
+            return Ok(&[]);
+        }
+        let left = self.left;
+        let buf = self.raw.reader.fill_buf()?;
+        if buf.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(&buf[..(buf.len() as u64).min(left) as usize])
     }
-    #[inline]
-    fn u32(&mut self, index: usize) -> Result<u64> {
-        let b1 = self.u8(index)?;
-        let b2 = self.u8(index + 1)?;
-        let b3 = self.u8(index + 2)?;
-        let b4 = self.u8(index + 3)?;
-        Ok(b1 << 24 | b2 << 16 | b3 << 8 | b4)
+
+    fn consume(&mut self, amt: usize) {
+        self.raw.reader.consume(amt);
+        self.raw.position += amt as u64;
+        self.left -= amt as u64;
     }
-    #[inline]
-    fn u64(&mut self, index: usize) -> Result<u64> {
-        let b1 = self.u8(index)?;
-        let b2 = self.u8(index + 1)?;
-        let b3 = self.u8(index + 2)?;
-        let b4 = self.u8(index + 3)?;
-        let b5 = self.u8(index + 4)?;
-        let b6 = self.u8(index + 5)?;
-        let b7 = self.u8(index + 6)?;
-        let b8 = self.u8(index + 7)?;
-        Ok(b1 << 56 | b2 << 48 | b3 << 40 | b4 << 32 | b5 << 24 | b6 << 16 | b7 << 8 | b8)
+}
+
+fn into_io_error(error: Error) -> io::Error {
+    match error {
+        Error::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
     }
+}
 
-    /// function to extract the type of the given `Deserializer`.
+/// function decoding the value of a map entry, see
+/// [`int_keyed_map_with`](./struct.Deserializer.html#method.int_keyed_map_with).
+pub type ValueHandler<'a, R> = &'a mut dyn FnMut(&mut Deserializer<R>) -> Result<()>;
+
+/// [`Deserializer`] over a type erased reader.
+///
+/// [`Deserializer`]: ./struct.Deserializer.html
+/// [`with_dyn`]: ./struct.Deserializer.html#method.with_dyn
+///
+/// Every [`Deserialize`] implementation is generic over the reader, so is
+/// compiled once for every reader it is used with. Functions targeting
+/// `DynDeserializer` are compiled only once and the generic
+/// [`Deserialize`] implementation is reduced to a small shim using
+/// [`with_dyn`]:
+///
+/// ```
+/// use cbor_event::{self, de::*};
+/// use std::io::{BufRead, Cursor};
+///
+/// struct Point { x: u64, y: u64 }
+///
+/// fn deserialize_point(raw: &mut DynDeserializer) -> cbor_event::Result<Point> {
+///     raw.tuple(2, "Point")?;
+///     let x = raw.unsigned_integer()?;
+///     let y = raw.unsigned_integer()?;
+///     Ok(Point { x, y })
+/// }
+///
+/// impl Deserialize for Point {
+///     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> cbor_event::Result<Self> {
+///         raw.with_dyn(deserialize_point)
+///     }
+/// }
+///
+/// let mut raw = Deserializer::from(Cursor::new(vec![0x82, 0x01, 0x02]));
+/// let point: Point = raw.deserialize().unwrap();
+/// # assert_eq!((point.x, point.y), (1, 2));
+/// ```
+pub type DynDeserializer<'a> = Deserializer<&'a mut dyn BufRead>;
+
+impl<R> From<R> for Deserializer<R> {
+    fn from(r: R) -> Self {
+        Deserializer {
+            reader: r,
+            lenient: false,
+            definite_only: false,
+            canonical: false,
+            position: 0,
+            error_offsets: false,
+            depth: 0,
+            max_depth: usize::MAX,
+            entered: false,
+            unknown: None,
+        }
+    }
+}
+impl<'a> Deserializer<&'a [u8]> {
+    /// decode from `bytes`, without wrapping them in a `Cursor`.
     ///
-    /// This function does not consume the underlying buffer.
+    /// ```
+    /// use cbor_event::de::Deserializer;
     ///
-    /// # Examples
+    /// let mut raw = Deserializer::from_slice(&[0x18, 0x2a]);
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 42);
+    /// ```
+    pub fn from_slice(bytes: &'a [u8]) -> Self {
+        Deserializer::from(bytes)
+    }
+}
+impl<R> Deserializer<R> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn as_ref(&self) -> &R {
+        &self.reader
+    }
+    pub fn as_mut_ref(&mut self) -> &mut R {
+        &mut self.reader
+    }
+    pub fn inner(self) -> R {
+        self.reader
+    }
+
+    /// accept the values loosely typed encoders produce instead of the
+    /// expected type: integers when decoding an `f64` and the integers 0
+    /// and 1 when decoding a [`bool`](#method.bool). Disabled by default.
     ///
     /// ```
-    /// use cbor_event::{de::*, Type};
-    /// use std::io::Cursor;
+    /// use cbor_event::de::*;
     ///
-    /// let vec = vec![0x18, 0x40];
-    /// let mut raw = Deserializer::from(Cursor::new(vec));
-    /// let cbor_type = raw.cbor_type().unwrap();
+    /// let vec = vec![0x01, 0x18, 0x2a];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// assert!(raw.bool().is_err());
     ///
-    /// assert!(cbor_type == Type::UnsignedInteger);
+    /// raw.set_lenient(true);
+    /// assert_eq!(raw.bool().unwrap(), true);
+    /// assert_eq!(raw.deserialize::<f64>().unwrap(), 42.0);
     /// ```
-    #[inline]
-    pub fn cbor_type(&mut self) -> Result<Type> {
-        Ok(Type::from(self.get(0)?))
+    pub fn set_lenient(&mut self, lenient: bool) -> &mut Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// whether [`set_lenient`](#method.set_lenient) is enabled
+    pub fn lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// reject the indefinite length arrays, maps, byte strings and texts,
+    /// as required by deterministic profiles: reading one fails with
+    /// `Error::IndefiniteLenForbidden`. Disabled by default.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Error, Type};
+    ///
+    /// let vec = vec![0x9f, 0xff];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.set_definite_only(true);
+    ///
+    /// assert!(matches!(raw.array(), Err(Error::IndefiniteLenForbidden(Type::Array))));
+    /// ```
+    pub fn set_definite_only(&mut self, definite_only: bool) -> &mut Self {
+        self.definite_only = definite_only;
+        self
+    }
+
+    /// whether [`set_definite_only`](#method.set_definite_only) is enabled
+    pub fn definite_only(&self) -> bool {
+        self.definite_only
+    }
+
+    /// accept only the canonical encodings, as written by a
+    /// [`Serializer`](../se/struct.Serializer.html) in canonical mode:
+    /// the lengths, integers and floats in their shortest form, no
+    /// indefinite lengths and the keys of the maps sorted, except with
+    /// [`map_with`](#method.map_with) which leaves the keys to its
+    /// function. Reading anything else fails with `Error::NonCanonical`,
+    /// or `Error::DuplicateKey`. Disabled by default.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, lint::ViolationKind, Error};
+    ///
+    /// // 1 in 2 bytes
+    /// let vec = vec![0x18, 0x01];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.set_canonical(true);
+    ///
+    /// assert!(matches!(
+    ///     raw.unsigned_integer(),
+    ///     Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(2, 1)))
+    /// ));
+    /// ```
+    pub fn set_canonical(&mut self, canonical: bool) -> &mut Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// whether [`set_canonical`](#method.set_canonical) is enabled
+    pub fn canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /// number of bytes consumed so far.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x82, 0x19, 0x03, 0xe8, 0x61, 0x61];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// raw.unsigned_integer().unwrap();
+    /// assert_eq!(raw.position(), 4);
+    /// ```
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// wrap the errors returned by [`deserialize`](#method.deserialize)
+    /// and the functions built on it in `Error::At` with the
+    /// [`position`](#method.position) where decoding failed, the offset
+    /// of the object that could not be decoded in most cases. Disabled by
+    /// default.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Error, Type};
+    ///
+    /// // [1, "2"]
+    /// let vec = vec![0x82, 0x01, 0x61, 0x32];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.set_error_offsets(true);
+    ///
+    /// match raw.deserialize::<Vec<u64>>() {
+    ///     Err(Error::At(2, error)) => {
+    ///         assert!(matches!(*error, Error::Expected(Type::UnsignedInteger, Type::Text)))
+    ///     }
+    ///     result => panic!("{:?}", result),
+    /// }
+    /// ```
+    pub fn set_error_offsets(&mut self, error_offsets: bool) -> &mut Self {
+        self.error_offsets = error_offsets;
+        self
+    }
+
+    /// whether [`set_error_offsets`](#method.set_error_offsets) is enabled
+    pub fn error_offsets(&self) -> bool {
+        self.error_offsets
+    }
+
+    /// limit the nesting of arrays, maps and tags: entering one more than
+    /// `max_depth` of them with [`array`](#method.array),
+    /// [`map`](#method.map) or [`tag`](#method.tag) fails with
+    /// `Error::MaxDepthExceeded`. Unlimited by default.
+    ///
+    /// A container counts until the [`deserialize`](#method.deserialize)
+    /// call, or the item of [`array_with`](#method.array_with) or
+    /// [`map_with`](#method.map_with), it was read in returns. Decoders
+    /// built on them, like the ones of the crate, are then bounded on
+    /// untrusted input nesting thousands of containers. The containers
+    /// read one after the other in the same call count once: the nesting
+    /// goes through these calls, and a long sequence of siblings does not
+    /// add up.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Error};
+    ///
+    /// // [[[1]]]
+    /// let vec = vec![0x81, 0x81, 0x81, 0x01];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.set_max_depth(2);
+    ///
+    /// assert!(matches!(
+    ///     raw.deserialize::<Vec<Vec<Vec<u64>>>>(),
+    ///     Err(Error::MaxDepthExceeded)
+    /// ));
+    /// ```
+    pub fn set_max_depth(&mut self, max_depth: usize) -> &mut Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// the limit set with [`set_max_depth`](#method.set_max_depth)
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// number of arrays, maps and tags currently entered, see
+    /// [`set_max_depth`](#method.set_max_depth).
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    // enter an array, a map or a tag, counted once per scope
+    fn enter(&mut self) -> Result<()> {
+        if self.entered {
+            return Ok(());
+        }
+        if self.depth >= self.max_depth {
+            return Err(Error::MaxDepthExceeded);
+        }
+        self.depth += 1;
+        self.entered = true;
+        Ok(())
+    }
+
+    // reset the depth to `depth` before reading the next item of an
+    // explicit stack of containers, see `set_max_depth`
+    pub(crate) fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.entered = false;
+    }
+
+    // run `f` in a new scope, leaving the containers it entered once it
+    // returns
+    pub(crate) fn scoped<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let (depth, entered) = (self.depth, self.entered);
+        self.entered = false;
+        let result = f(self);
+        self.depth = depth;
+        self.entered = entered;
+        result
+    }
+
+    /// capture the items the decoders skip instead of dropping them, so a
+    /// proxy can write back the fields it does not understand. Disabled by
+    /// default.
+    ///
+    /// The entries [`map_project`](#method.map_project) and
+    /// [`int_keyed_map_with`](#method.int_keyed_map_with) skip are kept
+    /// in [`Unknown::entries`](./struct.Unknown.html#structfield.entries),
+    /// the objects skipped with [`skip_value`](#method.skip_value) in
+    /// [`Unknown::elements`](./struct.Unknown.html#structfield.elements).
+    /// Collect them with [`take_unknown`](#method.take_unknown) once the
+    /// structure they belong to is decoded.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, se::Serializer, Len};
+    ///
+    /// // {0: 42, 5: "new", 1: "name"}
+    /// let vec = vec![0xa3, 0x00, 0x18, 0x2a, 0x05, 0x63, 0x6e, 0x65, 0x77, 0x01, 0x64, 0x6e, 0x61, 0x6d, 0x65];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.set_preserve_unknown(true);
+    ///
+    /// let (mut id, mut name) = (0, String::new());
+    /// raw.int_keyed_map_with(&mut [
+    ///     (0, &mut |raw| {
+    ///         id = raw.unsigned_integer()?;
+    ///         Ok(())
+    ///     }),
+    ///     (1, &mut |raw| {
+    ///         name = raw.text()?;
+    ///         Ok(())
+    ///     }),
+    /// ])
+    /// .unwrap();
+    /// let unknown = raw.take_unknown();
+    /// assert_eq!(unknown.entries.len(), 1);
+    ///
+    /// let mut se = Serializer::new_vec();
+    /// se.write_map(Len::Len(2 + unknown.entries.len() as u64)).unwrap()
+    ///     .write_unsigned_integer(0).unwrap()
+    ///     .write_unsigned_integer(id).unwrap();
+    /// for (key, value) in unknown.entries.iter() {
+    ///     se.serialize(key).unwrap().serialize(value).unwrap();
+    /// }
+    /// se.write_unsigned_integer(1).unwrap().write_text(&name).unwrap();
+    /// assert_eq!(se.finalize(), vec);
+    /// ```
+    pub fn set_preserve_unknown(&mut self, preserve: bool) -> &mut Self {
+        if !preserve {
+            self.unknown = None;
+        } else if self.unknown.is_none() {
+            self.unknown = Some(Unknown::default());
+        }
+        self
+    }
+
+    /// whether [`set_preserve_unknown`](#method.set_preserve_unknown) is enabled
+    pub fn preserve_unknown(&self) -> bool {
+        self.unknown.is_some()
+    }
+
+    /// returns the items skipped since the last call, see
+    /// [`set_preserve_unknown`](#method.set_preserve_unknown).
+    pub fn take_unknown(&mut self) -> Unknown {
+        self.unknown.as_mut().map(mem::take).unwrap_or_default()
+    }
+}
+impl<R: BufRead> Deserializer<R> {
+    /// run the given function on a [`DynDeserializer`] borrowing this
+    /// `Deserializer`'s reader.
+    ///
+    /// [`DynDeserializer`]: ./type.DynDeserializer.html
+    pub fn with_dyn<T, F>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut DynDeserializer<'_>) -> Result<T>,
+    {
+        let mut raw: DynDeserializer<'_> = Deserializer {
+            reader: &mut self.reader,
+            lenient: self.lenient,
+            definite_only: self.definite_only,
+            canonical: self.canonical,
+            position: self.position,
+            error_offsets: self.error_offsets,
+            depth: self.depth,
+            max_depth: self.max_depth,
+            entered: self.entered,
+            unknown: self.unknown.take(),
+        };
+        let result = f(&mut raw);
+        self.position = raw.position;
+        self.depth = raw.depth;
+        self.entered = raw.entered;
+        self.unknown = raw.unknown;
+        result
+    }
+
+    /// returns a `Deserializer` limited to the next `n` bytes, to decode a
+    /// frame or an embedded payload whose size comes from an outer
+    /// envelope. Reading an object crossing the limit fails as if the input
+    /// ended there.
+    ///
+    /// The options and the [`position`](#method.position) of this
+    /// `Deserializer` are kept, but the bytes read from the returned one are
+    /// not added to the position of this one. The bytes left unread by
+    /// the returned `Deserializer` are read next from this one, check
+    /// `as_ref().limit()` or use
+    /// [`deserialize_complete`](#method.deserialize_complete) to make sure
+    /// the frame is consumed entirely.
+    ///
+    /// If [`set_preserve_unknown`](#method.set_preserve_unknown) is enabled,
+    /// the items skipped in the frame are added to the ones of this
+    /// `Deserializer` when the returned [`Frame`](./struct.Frame.html) is
+    /// dropped.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // frame of 2 bytes holding 1 and 2, then 3
+    /// let vec = vec![0x02, 0x01, 0x02, 0x03];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let len = raw.unsigned_integer().unwrap();
+    /// let mut frame = raw.take(len);
+    /// assert_eq!(frame.unsigned_integer().unwrap(), 1);
+    /// assert_eq!(frame.unsigned_integer().unwrap(), 2);
+    /// assert!(frame.unsigned_integer().is_err());
+    /// drop(frame);
+    ///
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 3);
+    /// ```
+    pub fn take(&mut self, n: u64) -> Frame<'_, R> {
+        use std::io::Read;
+
+        let raw = Deserializer {
+            reader: (&mut self.reader).take(n),
+            lenient: self.lenient,
+            definite_only: self.definite_only,
+            canonical: self.canonical,
+            position: self.position,
+            error_offsets: self.error_offsets,
+            depth: self.depth,
+            max_depth: self.max_depth,
+            entered: self.entered,
+            unknown: self.unknown.as_ref().map(|_| Unknown::default()),
+        };
+        Frame {
+            raw,
+            unknown: &mut self.unknown,
+        }
+    }
+}
+impl<R: BufRead> Deserializer<R> {
+    #[inline]
+    fn get(&mut self, index: usize) -> Result<u8> {
+        let buf = self.reader.fill_buf()?;
+        match buf.get(index) {
+            None => Err(Error::NotEnough(buf.len(), index)),
+            Some(b) => Ok(*b),
+        }
+    }
+    #[inline]
+    fn u8(&mut self, index: usize) -> Result<u64> {
+        let b = self.get(index)?;
+        Ok(b as u64)
+    }
+    #[inline]
+    fn u16(&mut self, index: usize) -> Result<u64> {
+        let b1 = self.u8(index)?;
+        let b2 = self.u8(index + 1)?;
+        Ok(b1 << 8 | b2)
+    }
+    #[inline]
+    fn u32(&mut self, index: usize) -> Result<u64> {
+        let b1 = self.u8(index)?;
+        let b2 = self.u8(index + 1)?;
+        let b3 = self.u8(index + 2)?;
+        let b4 = self.u8(index + 3)?;
+        Ok(b1 << 24 | b2 << 16 | b3 << 8 | b4)
+    }
+    #[inline]
+    fn u64(&mut self, index: usize) -> Result<u64> {
+        let b1 = self.u8(index)?;
+        let b2 = self.u8(index + 1)?;
+        let b3 = self.u8(index + 2)?;
+        let b4 = self.u8(index + 3)?;
+        let b5 = self.u8(index + 4)?;
+        let b6 = self.u8(index + 5)?;
+        let b7 = self.u8(index + 6)?;
+        let b8 = self.u8(index + 7)?;
+        Ok(b1 << 56 | b2 << 48 | b3 << 40 | b4 << 32 | b5 << 24 | b6 << 16 | b7 << 8 | b8)
+    }
+
+    /// function to extract the type of the given `Deserializer`.
+    ///
+    /// This function does not consume the underlying buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Type};
+    /// use std::io::Cursor;
+    ///
+    /// let vec = vec![0x18, 0x40];
+    /// let mut raw = Deserializer::from(Cursor::new(vec));
+    /// let cbor_type = raw.cbor_type().unwrap();
+    ///
+    /// assert!(cbor_type == Type::UnsignedInteger);
+    /// ```
+    #[inline]
+    pub fn cbor_type(&mut self) -> Result<Type> {
+        Ok(Type::from(self.get(0)?))
     }
     #[inline]
-    fn cbor_expect_type(&mut self, t: Type) -> Result<()> {
+    pub(crate) fn cbor_expect_type(&mut self, t: Type) -> Result<()> {
         let t_ = self.cbor_type()?;
         if t_ != t {
             Err(Error::Expected(t, t_))
@@ -281,26 +1275,79 @@ impl<R: BufRead> Deserializer<R> {
     #[inline]
     pub fn cbor_len(&mut self) -> Result<(Len, usize)> {
         let b: u8 = self.get(0)? & 0b0001_1111;
-        match b {
-            0x00..=0x17 => Ok((Len::Len(b as u64), 0)),
-            0x18 => self.u8(1).map(|v| (Len::Len(v), 1)),
-            0x19 => self.u16(1).map(|v| (Len::Len(v), 2)),
-            0x1a => self.u32(1).map(|v| (Len::Len(v), 4)),
-            0x1b => self.u64(1).map(|v| (Len::Len(v), 8)),
-            0x1c..=0x1e => Err(Error::UnknownLenType(b)),
-            0x1f => Ok((Len::Indefinite, 0)),
+        let (len, sz) = match b {
+            0x00..=0x17 => (Len::Len(b as u64), 0),
+            0x18 => (Len::Len(self.u8(1)?), 1),
+            0x19 => (Len::Len(self.u16(1)?), 2),
+            0x1a => (Len::Len(self.u32(1)?), 4),
+            0x1b => (Len::Len(self.u64(1)?), 8),
+            0x1c..=0x1e => return Err(Error::UnknownLenType(b)),
+            // `b` has been masked to its 5 lowest bits: this is 0x1f
+            _ => (Len::Indefinite, 0),
+        };
+        if self.canonical {
+            self.check_canonical(len, sz)?;
+        }
+        Ok((len, sz))
+    }
 
-            // since the value `b` has been masked to only consider the first 5 lowest bits
-            // all value above 0x1f are unreachable.
-            _ => unreachable!(),
+    // fail if the header of `len` encoded on `sz` bytes is not canonical
+    fn check_canonical(&mut self, len: Len, sz: usize) -> Result<()> {
+        let cbor_type = self.cbor_type()?;
+        let (actual, min) = match len {
+            // the break closing an indefinite length is checked with it
+            Len::Indefinite if cbor_type == Type::Special => return Ok(()),
+            Len::Indefinite => return Err(Error::NonCanonical(ViolationKind::IndefiniteLength)),
+            Len::Len(value) if cbor_type == Type::Special && sz >= 2 => {
+                let min = lint::float_size(float::decode(value, sz));
+                if 1 + sz > min {
+                    return Err(Error::NonCanonical(ViolationKind::OversizedFloat(
+                        1 + sz,
+                        min,
+                    )));
+                }
+                return Ok(());
+            }
+            Len::Len(value) => (1 + sz, lint::header_size(value)),
+        };
+        if actual > min {
+            Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(
+                actual, min,
+            )))
+        } else {
+            Ok(())
         }
     }
 
+    /// returns the next `n` bytes without consuming them, fewer if the
+    /// underlying reader has less than `n` bytes buffered.
+    ///
+    /// Useful to show where the input stopped making sense in error
+    /// messages and logs.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x82, 0x01, 0xfe];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// raw.unsigned_integer().unwrap();
+    /// assert!(raw.unsigned_integer().is_err());
+    /// assert_eq!(raw.peek_remaining(16).unwrap(), &[0xfe]);
+    /// ```
+    pub fn peek_remaining(&mut self, n: usize) -> Result<&[u8]> {
+        let buf = self.reader.fill_buf()?;
+        Ok(&buf[..n.min(buf.len())])
+    }
+
     /// consume the given `len` from the underlying buffer. Skipped bytes are
     /// then lost, they cannot be retrieved for future references.
     #[inline]
     pub fn advance(&mut self, len: usize) -> Result<()> {
-        Ok(self.0.consume(len))
+        self.reader.consume(len);
+        self.position += len as u64;
+        Ok(())
     }
 
     /// Read an `UnsignedInteger` from the `Deserializer`
@@ -332,13 +1379,58 @@ impl<R: BufRead> Deserializer<R> {
     /// let integer = raw.unsigned_integer().unwrap();
     /// ```
     pub fn unsigned_integer(&mut self) -> Result<u64> {
-        self.cbor_expect_type(Type::UnsignedInteger)?;
+        self.unsigned_integer_sz().map(|(v, _)| v)
+    }
+
+    /// Read a `UnsignedInteger` and the size of its encoding, see
+    /// [`unsigned_integer`](#method.unsigned_integer).
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Sz};
+    ///
+    /// let vec = vec![0x19, 0x00, 0x01];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.unsigned_integer_sz().unwrap(), (1, Sz::Two));
+    /// ```
+    pub fn unsigned_integer_sz(&mut self) -> Result<(u64, Sz)> {
+        self.value_sz(Type::UnsignedInteger)
+    }
+
+    /// Read a `UnsignedInteger` into a smaller integer type, handling the
+    /// values too large for it according to `policy`.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, OverflowPolicy};
+    ///
+    /// let vec = vec![0x19, 0x01, 0x02, 0x19, 0x01, 0x02, 0x19, 0x01, 0x02];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert!(raw.unsigned_as::<u8>(OverflowPolicy::Error).is_err());
+    /// assert_eq!(raw.unsigned_as::<u8>(OverflowPolicy::Saturate).unwrap(), 0xff);
+    /// assert_eq!(raw.unsigned_as::<u8>(OverflowPolicy::Truncate).unwrap(), 0x02);
+    /// ```
+    pub fn unsigned_as<T: NarrowUnsigned>(&mut self, policy: OverflowPolicy) -> Result<T> {
+        let n = self.unsigned_integer()?;
+        if n <= T::MAX {
+            return Ok(T::truncate(n));
+        }
+        match policy {
+            OverflowPolicy::Error => Err(T::overflow_error()),
+            OverflowPolicy::Saturate => Ok(T::truncate(T::MAX)),
+            OverflowPolicy::Truncate => Ok(T::truncate(n)),
+        }
+    }
+
+    // read the value of an integer or tag of the given type
+    fn value_sz(&mut self, cbor_type: Type) -> Result<(u64, Sz)> {
+        self.cbor_expect_type(cbor_type)?;
         let (len, len_sz) = self.cbor_len()?;
         match len {
-            Len::Indefinite => Err(Error::IndefiniteLenNotSupported(Type::UnsignedInteger)),
+            Len::Indefinite => Err(Error::IndefiniteLenNotSupported(cbor_type)),
             Len::Len(v) => {
                 self.advance(1 + len_sz)?;
-                Ok(v)
+                Ok((v, Sz::from_bytes_following(len_sz)))
             }
         }
     }
@@ -360,15 +1452,43 @@ impl<R: BufRead> Deserializer<R> {
     ///
     /// assert_eq!(integer, -42);
     /// ```
+    ///
+    /// Values below `i64::MIN` fail with `Error::ExpectedI64`, use
+    /// [`integer`](#method.integer) to read the full range.
     pub fn negative_integer(&mut self) -> Result<i64> {
-        self.cbor_expect_type(Type::NegativeInteger)?;
-        let (len, len_sz) = self.cbor_len()?;
-        match len {
-            Len::Indefinite => Err(Error::IndefiniteLenNotSupported(Type::NegativeInteger)),
-            Len::Len(v) => {
-                self.advance(1 + len_sz)?;
-                Ok(-(v as i64) - 1)
-            }
+        let (v, _) = self.value_sz(Type::NegativeInteger)?;
+        if v > i64::MAX as u64 {
+            return Err(Error::ExpectedI64);
+        }
+        Ok(-1 - v as i64)
+    }
+
+    /// Read a `NegativeInteger` and the size of its encoding. Unlike
+    /// [`negative_integer`](#method.negative_integer) the full range of
+    /// the major type (down to `-2^64`) is supported.
+    pub fn negative_integer_sz(&mut self) -> Result<(i128, Sz)> {
+        let (v, sz) = self.value_sz(Type::NegativeInteger)?;
+        Ok((-1 - v as i128, sz))
+    }
+
+    /// Read an `UnsignedInteger` or a `NegativeInteger`, covering the full
+    /// range of both major types (from `-2^64` to `2^64 - 1`).
+    ///
+    /// ```
+    /// use cbor_event::de::{*};
+    /// use std::io::Cursor;
+    ///
+    /// let vec = vec![0x18, 0x2a, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    /// let mut raw = Deserializer::from(Cursor::new(vec));
+    ///
+    /// assert_eq!(raw.integer().unwrap(), 42);
+    /// assert_eq!(raw.integer().unwrap(), -1 - u64::MAX as i128);
+    /// ```
+    pub fn integer(&mut self) -> Result<i128> {
+        match self.cbor_type()? {
+            Type::UnsignedInteger => self.unsigned_integer().map(|v| v as i128),
+            Type::NegativeInteger => self.negative_integer_sz().map(|(v, _)| v),
+            t => Err(Error::Expected(Type::UnsignedInteger, t)),
         }
     }
 
@@ -387,103 +1507,313 @@ impl<R: BufRead> Deserializer<R> {
     ///
     /// let bytes = raw.bytes().unwrap();
     /// ```
-    pub fn bytes<'a>(&'a mut self) -> Result<Vec<u8>> {
-        use std::io::Read;
+    pub fn bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.bytes_into(&mut bytes)?;
+        Ok(bytes)
+    }
 
-        self.cbor_expect_type(Type::Bytes)?;
+    /// Read a Bytes from the Deserializer into a buffer borrowed from the
+    /// given [`BufferPool`](../pool/struct.BufferPool.html).
+    ///
+    /// The function fails if the type of the given Deserializer is not `Type::Bytes`.
+    #[cfg(feature = "std")]
+    pub fn bytes_pooled<'p>(&mut self, pool: &'p BufferPool) -> Result<PooledBuffer<'p>> {
+        let mut bytes = pool.get();
+        self.bytes_into(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Read a Bytes and how its length was encoded, see
+    /// [`bytes`](#method.bytes).
+    ///
+    /// ```
+    /// use cbor_event::{de::*, StringLenSz, Sz};
+    ///
+    /// let vec = vec![0x5f, 0x41, 0x01, 0x58, 0x01, 0x02, 0xff];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let (bytes, len_sz) = raw.bytes_sz().unwrap();
+    /// assert_eq!(bytes, vec![1, 2]);
+    /// assert_eq!(len_sz, StringLenSz::Indefinite(vec![(1, Sz::Inline), (1, Sz::One)]));
+    /// ```
+    pub fn bytes_sz(&mut self) -> Result<(Vec<u8>, StringLenSz)> {
+        let mut bytes = Vec::new();
+        let len_sz = self.string_into(Type::Bytes, &mut bytes, false)?;
+        Ok((bytes, len_sz))
+    }
+
+    // read the content of a Bytes, appending it to the given buffer
+    fn bytes_into(&mut self, bytes: &mut Vec<u8>) -> Result<()> {
+        self.string_into(Type::Bytes, bytes, false).map(|_| ())
+    }
+
+    // read the content of a Bytes or a Text, appending it to the given
+    // buffer, and return how its length was encoded. The chunks of an
+    // indefinite length Text are checked to be valid UTF-8 on their own.
+    fn string_into(
+        &mut self,
+        cbor_type: Type,
+        bytes: &mut Vec<u8>,
+        check_utf8: bool,
+    ) -> Result<StringLenSz> {
+        self.cbor_expect_type(cbor_type)?;
         let (len, len_sz) = self.cbor_len()?;
+        self.check_definite(cbor_type, len)?;
         self.advance(1 + len_sz)?;
         match len {
             Len::Indefinite => {
-                let mut bytes = vec![];
-                while self.cbor_type()? != Type::Special || !self.special_break()? {
-                    self.cbor_expect_type(Type::Bytes)?;
+                let mut chunks = Vec::new();
+                while !self.special_break()? {
+                    self.cbor_expect_type(cbor_type)?;
                     let (chunk_len, chunk_len_sz) = self.cbor_len()?;
                     match chunk_len {
                         Len::Indefinite => return Err(Error::InvalidIndefiniteString),
                         Len::Len(len) => {
                             self.advance(1 + chunk_len_sz)?;
-                            self.0.by_ref().take(len).read_to_end(&mut bytes)?;
+                            let start = bytes.len();
+                            self.read_payload(len, bytes)?;
+                            // rfc7049 forbids splitting UTF-8 characters across chunks
+                            if check_utf8 && ::std::str::from_utf8(&bytes[start..]).is_err() {
+                                String::from_utf8(bytes[start..].to_vec())?;
+                            }
+                            chunks.push((len, Sz::from_bytes_following(chunk_len_sz)));
                         }
                     }
                 }
-                Ok(bytes)
+                Ok(StringLenSz::Indefinite(chunks))
             }
             Len::Len(len) => {
-                let mut bytes = vec![0; len as usize];
-                self.0.read_exact(&mut bytes)?;
-                Ok(bytes)
+                self.read_payload(len, bytes)?;
+                Ok(StringLenSz::Len(Sz::from_bytes_following(len_sz)))
             }
         }
     }
 
-    /// Read a Text from the Deserializer
+    /// Stream the content of a Bytes to `writer` instead of holding it in
+    /// memory, returning the number of bytes written.
     ///
-    /// The function fails if the type of the given Deserializer is not `Type::Text`.
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Bytes`. On failure, part of the content may already have
+    /// been written.
+    pub fn bytes_to_writer<W: Write>(&mut self, writer: &mut W) -> Result<u64> {
+        self.string_to_writer(Type::Bytes, writer)
+    }
+
+    /// Read the content of a Bytes, definite or indefinite length, through
+    /// a streaming reader, e.g. to hand it to a decompressor without
+    /// holding it in memory.
     ///
-    /// # Example
+    /// The function fails if the type of the given Deserializer is not
+    /// `Type::Bytes`. The content must be read to the end before decoding
+    /// what follows.
     ///
     /// ```
-    /// use cbor_event::de::{*};
-    /// use std::io::Cursor;
+    /// use cbor_event::de::*;
+    /// use std::io::Read;
     ///
-    /// let vec = vec![0x64, 0x74, 0x65, 0x78, 0x74];
-    /// let mut raw = Deserializer::from(Cursor::new(vec));
+    /// // [(_ h'0102', h'03'), 4]
+    /// let vec = vec![0x82, 0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff, 0x04];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    /// raw.array().unwrap();
     ///
-    /// let text = raw.text().unwrap();
+    /// let mut content = Vec::new();
+    /// raw.bytes_reader().unwrap().read_to_end(&mut content).unwrap();
+    /// assert_eq!(content, [1, 2, 3]);
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 4);
+    /// ```
+    pub fn bytes_reader(&mut self) -> Result<BytesReader<'_, R>> {
+        self.cbor_expect_type(Type::Bytes)?;
+        let (len, len_sz) = self.cbor_len()?;
+        self.check_definite(Type::Bytes, len)?;
+        self.advance(1 + len_sz)?;
+        Ok(match len {
+            Len::Indefinite => BytesReader {
+                raw: self,
+                left: 0,
+                chunked: true,
+            },
+            Len::Len(len) => BytesReader {
+                raw: self,
+                left: len,
+                chunked: false,
+            },
+        })
+    }
+
+    /// Stream the content of a Text to `writer` instead of holding it in
+    /// memory, returning the number of bytes written.
+    ///
+    /// The content is checked to be valid UTF-8 as it is streamed, chunk
+    /// per chunk for indefinite length texts. On failure, part of the
+    /// content may already have been written.
     ///
-    /// assert!(&*text == "text");
     /// ```
-    pub fn text(&mut self) -> Result<String> {
-        self.cbor_expect_type(Type::Text)?;
+    /// use cbor_event::de::*;
+    ///
+    /// // (_ "{\"a\":", "1}")
+    /// let vec = vec![0x7f, 0x65, 0x7b, 0x22, 0x61, 0x22, 0x3a, 0x62, 0x31, 0x7d, 0xff];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let mut json = Vec::new();
+    /// assert_eq!(raw.text_to_writer(&mut json).unwrap(), 7);
+    /// assert_eq!(json, br#"{"a":1}"#);
+    /// ```
+    pub fn text_to_writer<W: Write>(&mut self, writer: &mut W) -> Result<u64> {
+        self.string_to_writer(Type::Text, writer)
+    }
+
+    fn string_to_writer<W: Write>(&mut self, cbor_type: Type, writer: &mut W) -> Result<u64> {
+        self.cbor_expect_type(cbor_type)?;
         let (len, len_sz) = self.cbor_len()?;
+        self.check_definite(cbor_type, len)?;
         self.advance(1 + len_sz)?;
+        let is_text = cbor_type == Type::Text;
         match len {
             Len::Indefinite => {
-                let mut text = String::new();
-                while self.cbor_type()? != Type::Special || !self.special_break()? {
-                    self.cbor_expect_type(Type::Text)?;
+                let mut total = 0;
+                while !self.special_break()? {
+                    self.cbor_expect_type(cbor_type)?;
                     let (chunk_len, chunk_len_sz) = self.cbor_len()?;
                     match chunk_len {
                         Len::Indefinite => return Err(Error::InvalidIndefiniteString),
                         Len::Len(len) => {
-                            // rfc7049 forbids splitting UTF-8 characters across chunks so we must
-                            // read each chunk separately as a definite encoded UTF-8 string
                             self.advance(1 + chunk_len_sz)?;
-                            let mut bytes = vec![0; len as usize];
-                            self.0.read_exact(&mut bytes)?;
-                            let chunk_text = String::from_utf8(bytes)?;
-                            text.push_str(&chunk_text);
+                            self.copy_payload(len, is_text, writer)?;
+                            total += len;
                         }
                     }
                 }
-                Ok(text)
+                Ok(total)
             }
             Len::Len(len) => {
-                let mut bytes = vec![0; len as usize];
-                self.0.read_exact(&mut bytes)?;
-                let text = String::from_utf8(bytes)?;
-                Ok(text)
+                self.copy_payload(len, is_text, writer)?;
+                Ok(len)
             }
         }
     }
 
-    // Internal helper to decode a series of `len` items using a function. If
-    // `len` is indefinite, decode until a `Special::Break`. If `len` is
-    // definite, decode that many items.
-    fn internal_items_with<F>(&mut self, len: Len, mut f: F) -> Result<()>
-    where
-        F: FnMut(&mut Self) -> Result<()>,
+    // copy the next `len` bytes to `writer`, checking they are valid UTF-8
+    // if `is_text`
+    fn copy_payload<W: Write>(&mut self, len: u64, is_text: bool, writer: &mut W) -> Result<()> {
+        let mut utf8 = Utf8Validator::default();
+        let mut left = len;
+        while left > 0 {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Err(Error::NotEnough((len - left) as usize, len as usize));
+            }
+            let n = (buf.len() as u64).min(left) as usize;
+            if is_text {
+                utf8.push(&buf[..n])?;
+            }
+            writer.write_all(&buf[..n])?;
+            self.advance(n)?;
+            left -= n as u64;
+        }
+        utf8.finish()
+    }
+
+    // append the next `len` bytes to the given buffer
+    fn read_payload(&mut self, len: u64, bytes: &mut Vec<u8>) -> Result<()> {
+        use std::io::Read;
+
+        let read = self.reader.by_ref().take(len).read_to_end(bytes)?;
+        self.position += read as u64;
+        if (read as u64) < len {
+            return Err(Error::NotEnough(read, len as usize));
+        }
+        Ok(())
+    }
+
+    /// Read a Text from the Deserializer
+    ///
+    /// The function fails if the type of the given Deserializer is not `Type::Text`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cbor_event::de::{*};
+    /// use std::io::Cursor;
+    ///
+    /// let vec = vec![0x64, 0x74, 0x65, 0x78, 0x74];
+    /// let mut raw = Deserializer::from(Cursor::new(vec));
+    ///
+    /// let text = raw.text().unwrap();
+    ///
+    /// assert!(&*text == "text");
+    /// ```
+    pub fn text(&mut self) -> Result<String> {
+        self.text_sz().map(|(text, _)| text)
+    }
+
+    /// Read the content of a Text as bytes, without checking it is valid
+    /// UTF-8.
+    ///
+    /// For callers passing the text through untouched (hashing,
+    /// re-encoding), this saves the validation and never rejects a text
+    /// produced by a faulty encoder.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x62, 0x61, 0xff];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.text_bytes().unwrap(), vec![0x61, 0xff]);
+    /// ```
+    pub fn text_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.string_into(Type::Text, &mut bytes, false)?;
+        Ok(bytes)
+    }
+
+    /// Read a Text, replacing the invalid UTF-8 sequences with
+    /// `U+FFFD REPLACEMENT CHARACTER` instead of failing.
+    ///
+    /// Meant for tools that must ingest data from faulty encoders. The
+    /// chunks of an indefinite length text are joined before the
+    /// conversion, so characters split across chunks are kept.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x63, 0x61, 0xff, 0x62];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.text_lossy().unwrap(), "a\u{fffd}b");
+    /// ```
+    pub fn text_lossy(&mut self) -> Result<String> {
+        match String::from_utf8(self.text_bytes()?) {
+            Ok(text) => Ok(text),
+            Err(e) => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        }
+    }
+
+    /// Read a Text and how its length was encoded, see
+    /// [`text`](#method.text) and [`bytes_sz`](#method.bytes_sz).
+    pub fn text_sz(&mut self) -> Result<(String, StringLenSz)> {
+        let mut bytes = Vec::new();
+        let len_sz = self.string_into(Type::Text, &mut bytes, true)?;
+        Ok((String::from_utf8(bytes)?, len_sz))
+    }
+
+    // Internal helper to decode a series of `len` items using a function. If
+    // `len` is indefinite, decode until a `Special::Break`. If `len` is
+    // definite, decode that many items.
+    fn internal_items_with<F>(&mut self, len: Len, mut f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
     {
         match len {
             Len::Indefinite => {
                 while !self.special_break()? {
-                    f(self)?;
+                    self.scoped(&mut f)?;
                 }
             }
             Len::Len(len) => {
                 for _ in 0..len {
-                    f(self)?;
+                    self.scoped(&mut f)?;
                 }
             }
         }
@@ -509,10 +1839,36 @@ impl<R: BufRead> Deserializer<R> {
     /// ```
     ///
     pub fn array(&mut self) -> Result<Len> {
-        self.cbor_expect_type(Type::Array)?;
+        self.array_sz().map(Len::from)
+    }
+
+    /// cbor array of cbor objects, returning the size of the encoding of
+    /// its length. See [`array`](#method.array).
+    pub fn array_sz(&mut self) -> Result<LenSz> {
+        self.container_sz(Type::Array)
+    }
+
+    // fail if `len` is indefinite and `set_definite_only` is enabled
+    fn check_definite(&self, cbor_type: Type, len: Len) -> Result<()> {
+        if self.definite_only && len == Len::Indefinite {
+            Err(Error::IndefiniteLenForbidden(cbor_type))
+        } else {
+            Ok(())
+        }
+    }
+
+    // read the header of an array or a map
+    fn container_sz(&mut self, cbor_type: Type) -> Result<LenSz> {
+        self.cbor_expect_type(cbor_type)?;
         let (len, sz) = self.cbor_len()?;
+        self.check_definite(cbor_type, len)?;
+        self.enter()?;
         self.advance(1 + sz)?;
-        Ok(len)
+        trace_event!(cbor_type = ?cbor_type, len = ?len, "container begin");
+        match len {
+            Len::Indefinite => Ok(LenSz::Indefinite),
+            Len::Len(len) => Ok(LenSz::Len(len, Sz::from_bytes_following(sz))),
+        }
     }
 
     /// Helper to decode a cbor array using a specified function.
@@ -528,6 +1884,33 @@ impl<R: BufRead> Deserializer<R> {
         self.internal_items_with(len, f)
     }
 
+    /// Helper to decode a finite set using a specified function, see
+    /// [`array_with`](#method.array_with).
+    ///
+    /// The set is an array, optionally tagged with the set tag (258). Any
+    /// other tag fails with `Error::ExpectedSetTag`.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0xd9, 0x01, 0x02, 0x82, 0x01, 0x02, 0x81, 0x03];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let mut items = Vec::new();
+    /// raw.set_with(|raw| Ok(items.push(raw.unsigned_integer()?))).unwrap();
+    /// raw.set_with(|raw| Ok(items.push(raw.unsigned_integer()?))).unwrap();
+    /// assert_eq!(items, vec![1, 2, 3]);
+    /// ```
+    pub fn set_with<F>(&mut self, f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
+    {
+        if self.cbor_type()? == Type::Tag {
+            self.set_tag()?;
+        }
+        self.array_with(f)
+    }
+
     /// Expect an array of a specified length. Must be a definite-length array.
     pub fn tuple(&mut self, expected_len: u64, error_location: &'static str) -> Result<()> {
         let actual_len = self.array()?;
@@ -537,6 +1920,54 @@ impl<R: BufRead> Deserializer<R> {
         }
     }
 
+    /// Read an array of exactly `n` elements of type `T`. Must be a
+    /// definite-length array, as with [`tuple`](#method.tuple) the
+    /// `error_location` is given in the `WrongLen` error otherwise.
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Error};
+    ///
+    /// let vec = vec![0x82, 0x01, 0x02, 0x82, 0x01, 0x02];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.array_elems::<u32>(2, "Point").unwrap(), vec![1, 2]);
+    /// assert!(matches!(
+    ///     raw.array_elems::<u32>(3, "Point3"),
+    ///     Err(Error::WrongLen(3, _, "Point3"))
+    /// ));
+    /// ```
+    pub fn array_elems<T: Deserialize>(
+        &mut self,
+        n: u64,
+        error_location: &'static str,
+    ) -> Result<Vec<T>> {
+        self.tuple(n, error_location)?;
+        let mut elems = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            elems.push(self.deserialize()?);
+        }
+        Ok(elems)
+    }
+
+    /// Read an array with one element per field of the tuple `T`, of
+    /// different types. Must be a definite-length array of the length of
+    /// the tuple, the `error_location` is given in the `WrongLen` error
+    /// otherwise.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x83, 0x01, 0x61, 0x61, 0xf5];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let (id, name, flag): (u64, String, bool) = raw.tuple_elems("Entry").unwrap();
+    /// assert_eq!((id, name.as_str(), flag), (1, "a", true));
+    /// ```
+    pub fn tuple_elems<T: DeserializeTuple>(&mut self, error_location: &'static str) -> Result<T> {
+        self.tuple(T::LEN, error_location)?;
+        T::deserialize_elems(self)
+    }
+
     /// cbor map
     ///
     /// The function fails if the type of the given Deserializer is not `Type::Map`.
@@ -556,10 +1987,13 @@ impl<R: BufRead> Deserializer<R> {
     /// ```
     ///
     pub fn map(&mut self) -> Result<Len> {
-        self.cbor_expect_type(Type::Map)?;
-        let (len, sz) = self.cbor_len()?;
-        self.advance(1 + sz)?;
-        Ok(len)
+        self.map_sz().map(Len::from)
+    }
+
+    /// cbor map, returning the size of the encoding of its length. See
+    /// [`map`](#method.map).
+    pub fn map_sz(&mut self) -> Result<LenSz> {
+        self.container_sz(Type::Map)
     }
 
     /// Helper to decode a cbor map using a specified function
@@ -575,6 +2009,200 @@ impl<R: BufRead> Deserializer<R> {
         self.internal_items_with(len, f)
     }
 
+    /// Helper to decode a cbor map, decoding the key of each entry as a
+    /// `K` before calling `f` with it.
+    ///
+    /// Like [`map_with`](#method.map_with) this works with either definite
+    /// or indefinite maps, but the function only has to decode the value.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // {"a": 1, "b": 2}
+    /// let vec = vec![0xa2, 0x61, 0x61, 0x01, 0x61, 0x62, 0x02];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let mut entries = Vec::new();
+    /// raw.map_entries_with(|raw, key: String| {
+    ///     entries.push((key, raw.unsigned_integer()?));
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(entries, vec![("a".to_owned(), 1), ("b".to_owned(), 2)]);
+    /// ```
+    pub fn map_entries_with<K, F>(&mut self, mut f: F) -> Result<()>
+    where
+        K: Deserialize,
+        F: FnMut(&mut Self, K) -> Result<()>,
+    {
+        let mut previous = None;
+        self.map_with(|raw| {
+            let key = raw.map_key(&mut previous)?;
+            f(raw, key)
+        })
+    }
+
+    // decode the key of a map entry. In canonical mode, the encoded keys
+    // must be strictly increasing: `previous` holds the last one.
+    pub(crate) fn map_key<K: Deserialize>(&mut self, previous: &mut Option<Vec<u8>>) -> Result<K> {
+        if !self.canonical {
+            return self.deserialize();
+        }
+        let start = self.position;
+        let key = self.read_raw_value()?;
+        let key = check_key_order(previous, key)?;
+        self.nested(key, start).deserialize_complete()
+    }
+
+    // a `Deserializer` over `bytes`, read from `position`, with the same
+    // options
+    fn nested<'b>(&self, bytes: &'b [u8], position: u64) -> Deserializer<&'b [u8]> {
+        let mut raw = Deserializer::from(bytes);
+        raw.position = position;
+        raw.lenient = self.lenient;
+        raw.definite_only = self.definite_only;
+        raw.canonical = self.canonical;
+        raw.error_offsets = self.error_offsets;
+        raw.depth = self.depth;
+        raw.max_depth = self.max_depth;
+        raw.entered = self.entered;
+        raw
+    }
+
+    /// decode the entries of a cbor map whose key is one of `keys`,
+    /// skipping the others.
+    ///
+    /// For every entry with a matching key, `f` is called with the index of
+    /// the key in `keys` and must decode the value. The other entries,
+    /// including the ones with keys that are not integers, bytes or texts,
+    /// are skipped with [`skip_value`](#method.skip_value).
+    ///
+    /// ```
+    /// use cbor_event::{de::*, ObjectKey};
+    ///
+    /// // {"id": 7, "blob": h'010203', "name": "x"}
+    /// let vec = vec![
+    ///     0xa3, 0x62, 0x69, 0x64, 0x07, 0x64, 0x62, 0x6c, 0x6f, 0x62, 0x43, 0x01, 0x02, 0x03,
+    ///     0x64, 0x6e, 0x61, 0x6d, 0x65, 0x61, 0x78,
+    /// ];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let keys = [ObjectKey::Text("name".to_owned()), ObjectKey::Text("id".to_owned())];
+    /// let (mut id, mut name) = (None, None);
+    /// raw.map_project(&keys, |index, raw| {
+    ///     match index {
+    ///         0 => name = Some(raw.text()?),
+    ///         _ => id = Some(raw.unsigned_integer()?),
+    ///     }
+    ///     Ok(())
+    /// })
+    /// .unwrap();
+    /// assert_eq!(id, Some(7));
+    /// assert_eq!(name.as_deref(), Some("x"));
+    /// ```
+    pub fn map_project<F>(&mut self, keys: &[ObjectKey], mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, &mut Self) -> Result<()>,
+    {
+        let mut previous = None;
+        self.map_with(|raw| {
+            raw.entry_with(&mut previous, |raw, key: EntryKey<ObjectKey>| {
+                match key.0.and_then(|key| keys.iter().position(|k| *k == key)) {
+                    Some(index) => f(index, raw).map(|()| true),
+                    None => Ok(false),
+                }
+            })
+        })
+    }
+
+    /// decode a cbor map with small integer keys, the compact encoding of
+    /// structures used by many protocols, dispatching each value to the
+    /// function of its key in `table`.
+    ///
+    /// Entries whose key is not in the table, or not an unsigned integer,
+    /// are skipped with [`skip_value`](#method.skip_value).
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // {0: 42, 1: "name", 7: null}
+    /// let vec = vec![0xa3, 0x00, 0x18, 0x2a, 0x01, 0x64, 0x6e, 0x61, 0x6d, 0x65, 0x07, 0xf6];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let (mut id, mut name) = (None, None);
+    /// raw.int_keyed_map_with(&mut [
+    ///     (0, &mut |raw| {
+    ///         id = Some(raw.unsigned_integer()?);
+    ///         Ok(())
+    ///     }),
+    ///     (1, &mut |raw| {
+    ///         name = Some(raw.text()?);
+    ///         Ok(())
+    ///     }),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(id, Some(42));
+    /// assert_eq!(name.as_deref(), Some("name"));
+    /// ```
+    pub fn int_keyed_map_with(&mut self, table: &mut [(u64, ValueHandler<'_, R>)]) -> Result<()> {
+        let mut previous = None;
+        self.map_with(|raw| {
+            raw.entry_with(&mut previous, |raw, key: EntryKey<u64>| {
+                match key
+                    .0
+                    .and_then(|key| table.iter_mut().find(|(k, _)| *k == key))
+                {
+                    Some((_, f)) => f(raw).map(|()| true),
+                    None => Ok(false),
+                }
+            })
+        })
+    }
+
+    // decode the key of a map entry and call `f` with it, which returns
+    // whether it decoded the value. If it did not, the value is skipped and
+    // the entry preserved if `set_preserve_unknown` is enabled. See
+    // `map_key` for `previous`.
+    fn entry_with<K, F>(&mut self, previous: &mut Option<Vec<u8>>, f: F) -> Result<()>
+    where
+        K: Deserialize,
+        F: FnOnce(&mut Self, K) -> Result<bool>,
+    {
+        if self.unknown.is_none() {
+            let key = self.map_key(previous)?;
+            if !f(self, key)? {
+                scan::walk_item(self)?;
+            }
+            return Ok(());
+        }
+        let raw_key: RawCbor = self.deserialize()?;
+        if self.canonical {
+            check_key_order(previous, raw_key.to_vec())?;
+        }
+        if !f(self, raw_key.decode()?)? {
+            let value = self.deserialize()?;
+            if let Some(unknown) = self.unknown.as_mut() {
+                unknown.entries.push((raw_key, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// decode the values of the entries of a cbor map whose key is one of
+    /// `keys`, skipping the others. See [`map_project`](#method.map_project).
+    ///
+    /// The returned vector has one element per key, `None` for the keys
+    /// missing from the map. If a key is present more than once, its last
+    /// value is returned.
+    pub fn project(&mut self, keys: &[ObjectKey]) -> Result<Vec<Option<Value>>> {
+        let mut values = vec![None; keys.len()];
+        self.map_project(keys, |index, raw| {
+            values[index] = Some(raw.deserialize()?);
+            Ok(())
+        })?;
+        Ok(values)
+    }
+
     /// Cbor Tag
     ///
     /// The function fails if the type of the given Deserializer is not `Type::Tag`.
@@ -595,16 +2223,22 @@ impl<R: BufRead> Deserializer<R> {
     /// ```
     ///
     pub fn tag(&mut self) -> Result<u64> {
+        self.tag_sz().map(|(tag, _)| tag)
+    }
+
+    /// Read a tag and the size of its encoding, see [`tag`](#method.tag).
+    pub fn tag_sz(&mut self) -> Result<(u64, Sz)> {
         self.cbor_expect_type(Type::Tag)?;
-        match self.cbor_len()? {
-            (Len::Indefinite, _) => Err(Error::IndefiniteLenNotSupported(Type::Tag)),
-            (Len::Len(len), sz) => {
-                self.advance(1 + sz)?;
-                Ok(len)
-            }
-        }
+        self.enter()?;
+        let (tag, sz) = self.value_sz(Type::Tag)?;
+        trace_event!(tag, "tag");
+        Ok((tag, sz))
     }
 
+    /// Read the set tag (258), see
+    /// [`Serializer::write_set_tag`](../se/struct.Serializer.html#method.write_set_tag).
+    ///
+    /// The function fails with `Error::ExpectedSetTag` for any other tag.
     pub fn set_tag(&mut self) -> Result<()> {
         let tag = self.tag()?;
         if tag != 258 {
@@ -619,10 +2253,9 @@ impl<R: BufRead> Deserializer<R> {
     /// Useful when decoding a variable-length array or map where the items may themselves use
     /// `Special`, such as bool values.
     pub fn special_break(&mut self) -> Result<bool> {
-        self.cbor_expect_type(Type::Special)?;
-        let b = self.get(0)? & 0b0001_1111;
-        if b == 0x1f {
+        if self.get(0)? == 0xff {
             self.advance(1)?;
+            trace_event!("container end");
             Ok(true)
         } else {
             Ok(false)
@@ -631,6 +2264,10 @@ impl<R: BufRead> Deserializer<R> {
 
     pub fn special(&mut self) -> Result<Special> {
         self.cbor_expect_type(Type::Special)?;
+        if self.canonical {
+            // checks the size of the floats
+            self.cbor_len()?;
+        }
         let b = self.get(0)? & 0b0001_1111;
         match b {
             0x00..=0x13 => {
@@ -661,99 +2298,781 @@ impl<R: BufRead> Deserializer<R> {
             0x19 => {
                 let f = self.u16(1)?;
                 self.advance(3)?;
-                Ok(Special::Float(f as f64))
+                Ok(Special::Float(float::decode(f, 2)))
             }
             0x1a => {
                 let f = self.u32(1)?;
                 self.advance(5)?;
-                Ok(Special::Float(f as f64))
+                Ok(Special::Float(float::decode(f, 4)))
             }
             0x1b => {
                 let f = self.u64(1)?;
                 self.advance(9)?;
-                Ok(Special::Float(f as f64))
+                Ok(Special::Float(float::decode(f, 8)))
             }
             0x1c..=0x1e => {
                 self.advance(1)?;
                 Ok(Special::Unassigned(b))
             }
-            0x1f => {
+            // `b` has been masked to its 5 lowest bits: this is 0x1f
+            _ => {
                 self.advance(1)?;
+                trace_event!("container end");
                 Ok(Special::Break)
             }
-            _ => unreachable!(),
         }
     }
 
-    pub fn bool(&mut self) -> Result<bool> {
-        self.special()?.unwrap_bool()
+    /// Read a half, single or double precision float.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // 1.5 as a half, 100000.0 as a single and 1.1 as a double
+    /// let vec = vec![
+    ///     0xf9, 0x3e, 0x00,
+    ///     0xfa, 0x47, 0xc3, 0x50, 0x00,
+    ///     0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a,
+    /// ];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.float().unwrap(), 1.5);
+    /// assert_eq!(raw.float().unwrap(), 100000.0);
+    /// assert_eq!(raw.float().unwrap(), 1.1);
+    /// ```
+    pub fn float(&mut self) -> Result<f64> {
+        self.cbor_expect_type(Type::Special)?;
+        match self.cbor_len()? {
+            (Len::Len(v), sz) if sz >= 2 => {
+                self.advance(1 + sz)?;
+                Ok(float::decode(v, sz))
+            }
+            _ => Err(Error::ExpectedFloat),
+        }
     }
 
-    pub fn deserialize<T>(&mut self) -> Result<T>
-    where
-        T: Deserialize,
-    {
-        Deserialize::deserialize(self)
+    /// Read a half or single precision float, or a double precision float
+    /// if it can be represented exactly as a single.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // 1.5 as a double, then 1.1 as a double
+    /// let vec = vec![
+    ///     0xfb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ///     0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a,
+    /// ];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.float32().unwrap(), 1.5);
+    /// assert!(raw.float32().is_err());
+    /// ```
+    pub fn float32(&mut self) -> Result<f32> {
+        self.cbor_expect_type(Type::Special)?;
+        let (_, sz) = self.cbor_len()?;
+        if sz < 8 {
+            return self.float().map(|f| f as f32);
+        }
+        let f = float::decode(self.u64(1)?, sz);
+        let single = float::f64_to_f32_exact(f).ok_or(Error::ExpectedF32)?;
+        self.advance(1 + sz)?;
+        Ok(single)
     }
 
-    /// Deserialize a value of type `T` and check that there is no
-    /// trailing data.
-    pub fn deserialize_complete<T>(&mut self) -> Result<T>
-    where
-        T: Deserialize,
-    {
-        let v = self.deserialize()?;
-        if self.0.fill_buf()?.len() > 0 {
-            Err(Error::TrailingData)
-        } else {
-            Ok(v)
+    /// Read a boolean, or the unsigned integers 0 and 1 if
+    /// [`set_lenient`](#method.set_lenient) is enabled.
+    pub fn bool(&mut self) -> Result<bool> {
+        if self.lenient && self.cbor_type()? == Type::UnsignedInteger {
+            return match self.unsigned_integer()? {
+                0 => Ok(false),
+                1 => Ok(true),
+                _ => Err(Error::ExpectedBool),
+            };
+        }
+        match self.special()? {
+            Special::Bool(b) => Ok(b),
+            _ => Err(Error::ExpectedBool),
         }
     }
-}
-
-// deserialisation macro
-
-macro_rules! deserialize_array {
-    ( $( $x:expr ),* ) => {
-        $(
-            impl Deserialize for [u8; $x] {
-                fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
-                    let mut bytes = [0u8; $x];
-
-                    let len = raw.array()?;
-                    match len {
-                        Len::Indefinite => {
-                            return Err(Error::WrongLen($x, len, "static array"));
-                        },
-                        Len::Len(x) => {
-                            if x != $x {
-                                return Err(Error::WrongLen($x, len, "static array"));
-                            }
-                        }
-                    }
 
-                    for byte in bytes.iter_mut() {
-                        *byte = Deserialize::deserialize(raw)?;
-                    }
-                    Ok(bytes)
-                }
+    /// Read any kind of number from the `Deserializer`: an unsigned or
+    /// negative integer, a floating point number or a bignum (tags 2 and 3).
+    ///
+    /// Useful when the same field may be encoded with different numeric
+    /// types, as is often the case of payloads converted from JSON.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use cbor_event::{de::*, Number};
+    ///
+    /// let vec = vec![0x18, 0x2a, 0x38, 0x29, 0xf9, 0x3e, 0x00, 0xc2, 0x41, 0x01];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.number().unwrap(), Number::U64(42));
+    /// assert_eq!(raw.number().unwrap(), Number::NegU64(41));
+    /// assert_eq!(raw.number().unwrap(), Number::F64(1.5));
+    /// assert_eq!(raw.number().unwrap(), Number::BigInt(false, vec![1]));
+    /// ```
+    pub fn number(&mut self) -> Result<Number> {
+        let (len, sz) = self.cbor_len()?;
+        match (self.cbor_type()?, len) {
+            (Type::UnsignedInteger, Len::Len(v)) => {
+                self.advance(1 + sz)?;
+                Ok(Number::U64(v))
             }
-        )*
+            (Type::NegativeInteger, Len::Len(v)) => {
+                self.advance(1 + sz)?;
+                Ok(Number::NegU64(v))
+            }
+            (Type::Tag, Len::Len(tag)) if tag == 2 || tag == 3 => {
+                self.advance(1 + sz)?;
+                Ok(Number::BigInt(tag == 3, self.bytes()?))
+            }
+            // half, single and double precision floats
+            (Type::Special, Len::Len(v)) if sz >= 2 => {
+                self.advance(1 + sz)?;
+                Ok(Number::F64(float::decode(v, sz)))
+            }
+            (Type::Special, Len::Len(v)) => Err(Error::CustomError(format!(
+                "Expected a number, received simple value {}",
+                v
+            ))),
+            (Type::Tag, Len::Len(tag)) => Err(Error::CustomError(format!(
+                "Expected a number, received tag {}",
+                tag
+            ))),
+            (t, Len::Indefinite) => Err(Error::IndefiniteLenNotSupported(t)),
+            (t, _) => Err(Error::Expected(Type::UnsignedInteger, t)),
+        }
     }
-}
 
-deserialize_array!(
-    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
-    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
-    51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64
-);
+    /// Read a bignum (tags 2 and 3): whether it is negative and the big
+    /// endian bytes of `n`, as in [`Number::BigInt`](../enum.Number.html).
+    /// The value is `n` if positive, `-1 - n` otherwise.
+    ///
+    /// The function fails if the next object is not tagged 2 or 3 or if
+    /// the tagged object is not `Type::Bytes`.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0xc3, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// assert_eq!(raw.bignum().unwrap(), (true, vec![0x01, 0, 0, 0, 0, 0, 0, 0, 0]));
+    /// ```
+    pub fn bignum(&mut self) -> Result<(bool, Vec<u8>)> {
+        self.cbor_expect_type(Type::Tag)?;
+        match self.cbor_len()? {
+            (Len::Len(tag), sz) if tag == 2 || tag == 3 => {
+                self.advance(1 + sz)?;
+                Ok((tag == 3, self.bytes()?))
+            }
+            (len, _) => Err(Error::CustomError(format!(
+                "Expected a bignum, received tag {:?}",
+                len
+            ))),
+        }
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use std::io::Cursor;
+    // consume the next CBOR object, appending its encoding to `bytes`
+    pub(crate) fn raw_item_into(&mut self, bytes: &mut Vec<u8>) -> Result<()> {
+        scan::walk_item(&mut Recorder { raw: self, bytes })
+    }
 
-    #[test]
+    /// consume the next CBOR object, nested objects included, returning
+    /// its encoding exactly as read. Useful to hash or verify the signature
+    /// of an object, see also [`RawCbor`](../struct.RawCbor.html).
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // [{1: 2}, h'5167']
+    /// let vec = vec![0x82, 0xa1, 0x01, 0x02, 0x42, 0x51, 0x67];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// assert_eq!(raw.read_raw_value().unwrap(), vec![0xa1, 0x01, 0x02]);
+    /// assert_eq!(raw.bytes().unwrap(), vec![0x51, 0x67]);
+    /// ```
+    pub fn read_raw_value(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.raw_item_into(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// consume the next CBOR object, decoding it with `f` and returning
+    /// its encoding exactly as read along with the result of `f`.
+    ///
+    /// `f` is given a `Deserializer` over the encoding of the object, with
+    /// the options and the [`position`](#method.position) of this one, and
+    /// must consume it entirely.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // [[1, 2], h'5167']: a body and its signature
+    /// let vec = vec![0x82, 0x82, 0x01, 0x02, 0x42, 0x51, 0x67];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// let (body, body_bytes) = raw
+    ///     .read_raw_value_with(|raw| raw.deserialize::<Vec<u64>>())
+    ///     .unwrap();
+    /// assert_eq!(body, vec![1, 2]);
+    /// assert_eq!(body_bytes, vec![0x82, 0x01, 0x02]);
+    /// ```
+    pub fn read_raw_value_with<T, F>(&mut self, f: F) -> Result<(T, Vec<u8>)>
+    where
+        F: FnOnce(&mut Deserializer<&[u8]>) -> Result<T>,
+    {
+        let start = self.position;
+        let bytes = self.read_raw_value()?;
+        let mut raw = self.nested(&bytes, start);
+        let value = f(&mut raw)?;
+        raw.expect_end().map_err(|error| raw.locate(error))?;
+        Ok((value, bytes))
+    }
+
+    /// consume the elements left in an array, returning the encoding of
+    /// each of them. `len` is the length returned by
+    /// [`array`](#method.array) and `read` the number of elements already
+    /// decoded. The `Special::Break` ending an indefinite array is consumed
+    /// but not returned.
+    ///
+    /// Useful to keep the elements a decoder does not know about, as is,
+    /// so they can be written back later with
+    /// [`write_raw_bytes`](../se/struct.Serializer.html#method.write_raw_bytes).
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x83, 0x01, 0x82, 0x02, 0x03, 0x61, 0x61, 0x18, 0x2a];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// let len = raw.array().unwrap();
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 1);
+    /// let rest = raw.remaining_raw(len, 1).unwrap();
+    /// assert_eq!(rest, vec![vec![0x82, 0x02, 0x03], vec![0x61, 0x61]]);
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 42);
+    /// ```
+    pub fn remaining_raw(&mut self, len: Len, read: u64) -> Result<Vec<Vec<u8>>> {
+        let mut items = Vec::new();
+        self.remaining_items(len, read, |raw| {
+            let mut item = Vec::new();
+            raw.raw_item_into(&mut item)?;
+            items.push(item);
+            Ok(())
+        })?;
+        Ok(items)
+    }
+
+    /// consume the entries left in a map, returning the encoding of the
+    /// key and of the value of each of them. `len` is the length returned
+    /// by [`map`](#method.map) and `read` the number of entries already
+    /// decoded. See [`remaining_raw`](#method.remaining_raw).
+    pub fn remaining_raw_entries(
+        &mut self,
+        len: Len,
+        read: u64,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut entries = Vec::new();
+        self.remaining_items(len, read, |raw| {
+            let mut key = Vec::new();
+            raw.raw_item_into(&mut key)?;
+            let mut value = Vec::new();
+            raw.raw_item_into(&mut value)?;
+            entries.push((key, value));
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+
+    // call `f` for each of the items left in a container of length `len`
+    // of which `read` items were already decoded
+    fn remaining_items<F>(&mut self, len: Len, read: u64, f: F) -> Result<()>
+    where
+        F: FnMut(&mut Self) -> Result<()>,
+    {
+        match len {
+            Len::Indefinite => self.internal_items_with(len, f),
+            Len::Len(len) if read <= len => self.internal_items_with(Len::Len(len - read), f),
+            Len::Len(len) => Err(Error::WrongLen(len, Len::Len(read), "items already read")),
+        }
+    }
+
+    /// consume the next CBOR object, checking it is well-formed without
+    /// decoding it.
+    ///
+    /// The payloads of byte and text strings are skipped without being
+    /// copied and the text strings are not checked to be valid UTF-8.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x82, 0x43, 0x01, 0x02, 0x03, 0x9f, 0xff, 0x18, 0x2a];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.validate_item().unwrap();
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 42);
+    /// ```
+    pub fn validate_item(&mut self) -> Result<()> {
+        scan::walk_item(self)
+    }
+
+    /// consume the next CBOR object without decoding it, nested objects
+    /// included. Same as [`validate_item`](#method.validate_item), except
+    /// the object is captured if
+    /// [`set_preserve_unknown`](#method.set_preserve_unknown) is enabled.
+    /// Nested objects are walked with an explicit stack, not the call
+    /// stack.
+    ///
+    /// When the reader implements `Seek`, prefer
+    /// [`skip_value_seek`](#method.skip_value_seek) which does not read
+    /// the payloads of large strings.
+    pub fn skip_value(&mut self) -> Result<()> {
+        if self.unknown.is_none() {
+            return scan::walk_item(self);
+        }
+        let element = self.deserialize()?;
+        if let Some(unknown) = self.unknown.as_mut() {
+            unknown.elements.push(element);
+        }
+        Ok(())
+    }
+
+    /// consume the next `n` CBOR objects, see
+    /// [`skip_value`](#method.skip_value).
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// // [1, [2, 3], {"a": 4}, 5]
+    /// let vec = vec![0x84, 0x01, 0x82, 0x02, 0x03, 0xa1, 0x61, 0x61, 0x04, 0x05];
+    /// let mut raw = Deserializer::from(&vec[..]);
+    ///
+    /// raw.array().unwrap();
+    /// raw.skip_n(3).unwrap();
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 5);
+    /// ```
+    pub fn skip_n(&mut self, n: u64) -> Result<()> {
+        for _ in 0..n {
+            self.skip_value()?;
+        }
+        Ok(())
+    }
+
+    /// decode into `value`, reusing its allocations, see
+    /// [`DeserializeInPlace`](./trait.DeserializeInPlace.html).
+    pub fn deserialize_in_place<T>(&mut self, value: &mut T) -> Result<()>
+    where
+        T: DeserializeInPlace,
+    {
+        value.deserialize_in_place(self)
+    }
+
+    pub fn deserialize<T>(&mut self) -> Result<T>
+    where
+        T: Deserialize,
+    {
+        let result = self
+            .scoped(Deserialize::deserialize)
+            .map_err(|error| self.locate(error));
+        #[cfg(feature = "tracing")]
+        {
+            if let Err(ref error) = result {
+                ::tracing::debug!(
+                    target: "cbor_event",
+                    target_type = ::std::any::type_name::<T>(),
+                    %error,
+                    "decoding failed"
+                );
+            }
+        }
+        result
+    }
+
+    /// Deserialize a value of type `T` and check that there is no
+    /// trailing data.
+    pub fn deserialize_complete<T>(&mut self) -> Result<T>
+    where
+        T: Deserialize,
+    {
+        let v = self.deserialize()?;
+        self.expect_end().map_err(|error| self.locate(error))?;
+        Ok(v)
+    }
+
+    // wrap `error` in `Error::At` if `set_error_offsets` is enabled and it
+    // is not located yet
+    fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::At(..) => error,
+            error if self.error_offsets => Error::At(self.position, Box::new(error)),
+            error => error,
+        }
+    }
+
+    /// iterate over the objects left in the input, decoded as `T`, as in
+    /// a CBOR sequence (RFC 8742). See
+    /// [`SequenceReader`](../sequence/struct.SequenceReader.html).
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    ///
+    /// let vec = vec![0x01, 0x18, 0x2a];
+    /// let items = Deserializer::from(&vec[..])
+    ///     .into_iter::<u64>()
+    ///     .collect::<cbor_event::Result<Vec<_>>>()
+    ///     .unwrap();
+    /// assert_eq!(items, vec![1, 42]);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T: Deserialize>(self) -> SequenceReader<T, R> {
+        SequenceReader::new(self)
+    }
+
+    // whether the input is exhausted, interrupted calls are retried
+    pub(crate) fn at_end(&mut self) -> Result<bool> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.is_empty()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    // fail with `Error::TrailingData` unless the input is exhausted. An
+    // empty `fill_buf` is confirmed with a `read` as some readers return
+    // an empty buffer before the end of their input, interrupted calls are
+    // retried.
+    pub(crate) fn expect_end(&mut self) -> Result<()> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) if !buf.is_empty() => return Err(Error::TrailingData),
+                Ok(_) => break,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        loop {
+            match self.reader.read(&mut [0]) {
+                Ok(0) => return Ok(()),
+                Ok(_) => return Err(Error::TrailingData),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// decode a `T` from the beginning of `bytes`, the bytes following it are
+/// ignored. See [`from_slice_complete`](./fn.from_slice_complete.html)
+/// to reject them.
+///
+/// ```
+/// assert_eq!(cbor_event::from_slice::<u64>(&[0x18, 0x2a, 0x00]).unwrap(), 42);
+/// ```
+pub fn from_slice<T: Deserialize>(bytes: &[u8]) -> Result<T> {
+    Deserializer::from_slice(bytes).deserialize()
+}
+
+/// decode a `T` from `bytes`, failing with `Error::TrailingData` if it
+/// does not use all the bytes.
+///
+/// ```
+/// use cbor_event::de::from_slice_complete;
+///
+/// assert_eq!(from_slice_complete::<String>(&[0x61, 0x61]).unwrap(), "a");
+/// assert!(from_slice_complete::<String>(&[0x61, 0x61, 0x00]).is_err());
+/// ```
+pub fn from_slice_complete<T: Deserialize>(bytes: &[u8]) -> Result<T> {
+    Deserializer::from_slice(bytes).deserialize_complete()
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead + Seek> Deserializer<R> {
+    /// consume the next CBOR object like [`skip_value`](#method.skip_value)
+    /// but seek past the payloads of the byte and text strings larger than
+    /// what is buffered instead of reading them.
+    ///
+    /// The payloads are not read at all so this is much faster to scan
+    /// files with large embedded blobs. A string ending past the end of
+    /// the input is still reported as an error.
+    ///
+    /// ```
+    /// use cbor_event::de::*;
+    /// use std::io::{BufReader, Cursor};
+    ///
+    /// // [h'00...00' (1MB), 42]
+    /// let mut vec = vec![0x82, 0x5a, 0x00, 0x10, 0x00, 0x00];
+    /// vec.resize(vec.len() + 0x10_0000, 0);
+    /// vec.extend_from_slice(&[0x18, 0x2a]);
+    /// let mut raw = Deserializer::from(BufReader::new(Cursor::new(vec)));
+    ///
+    /// raw.array().unwrap();
+    /// raw.skip_value_seek().unwrap();
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 42);
+    /// ```
+    pub fn skip_value_seek(&mut self) -> Result<()> {
+        scan::walk_item(&mut Seeker(self))
+    }
+}
+
+// scan source seeking past the large payloads
+#[cfg(feature = "std")]
+struct Seeker<'a, R: 'a>(&'a mut Deserializer<R>);
+#[cfg(feature = "std")]
+impl<'a, R: BufRead + Seek> scan::Source for Seeker<'a, R> {
+    fn header(&mut self) -> Result<(Type, Len, usize)> {
+        scan::Source::header(self.0)
+    }
+    fn consume_header(&mut self, len: usize) -> Result<()> {
+        self.0.advance(len)
+    }
+    fn consume_payload(&mut self, len: u64) -> Result<()> {
+        let buffered = self.0.reader.fill_buf()?.len() as u64;
+        if len <= buffered {
+            return self.0.advance(len as usize);
+        }
+        let reader = &mut self.0.reader;
+        // seeking past the end of the input does not fail: seek to the last
+        // byte of the payload and check it can be read.
+        let offset = len - 1;
+        if offset > i64::MAX as u64 {
+            return Err(Error::NotEnough(0, usize::MAX));
+        }
+        reader.seek(SeekFrom::Current(offset as i64))?;
+        if reader.fill_buf()?.is_empty() {
+            return Err(Error::NotEnough(0, len as usize));
+        }
+        reader.consume(1);
+        self.0.position += len;
+        Ok(())
+    }
+}
+
+// deserialisation macro
+
+impl<R: BufRead> scan::Source for Deserializer<R> {
+    fn header(&mut self) -> Result<(Type, Len, usize)> {
+        let cbor_type = self.cbor_type()?;
+        let (len, len_sz) = self.cbor_len()?;
+        Ok((cbor_type, len, 1 + len_sz))
+    }
+    fn consume_header(&mut self, len: usize) -> Result<()> {
+        self.advance(len)
+    }
+    fn consume_payload(&mut self, len: u64) -> Result<()> {
+        let mut left = len;
+        while left > 0 {
+            let available = self.reader.fill_buf()?.len();
+            if available == 0 {
+                return Err(Error::NotEnough((len - left) as usize, len as usize));
+            }
+            let n = (available as u64).min(left) as usize;
+            self.advance(n)?;
+            left -= n as u64;
+        }
+        Ok(())
+    }
+}
+
+// scan source copying the bytes consumed from the deserializer
+struct Recorder<'a, R: 'a> {
+    raw: &'a mut Deserializer<R>,
+    bytes: &'a mut Vec<u8>,
+}
+impl<'a, R: BufRead> scan::Source for Recorder<'a, R> {
+    fn header(&mut self) -> Result<(Type, Len, usize)> {
+        scan::Source::header(self.raw)
+    }
+    fn consume_header(&mut self, len: usize) -> Result<()> {
+        // `header` made sure the whole header is buffered
+        let buf = self.raw.reader.fill_buf()?;
+        self.bytes.extend_from_slice(&buf[..len]);
+        self.raw.advance(len)
+    }
+    fn consume_payload(&mut self, len: u64) -> Result<()> {
+        self.raw.read_payload(len, self.bytes)
+    }
+}
+
+// incremental UTF-8 validation of a text received in pieces, a character
+// may be split between two pieces
+#[derive(Default)]
+struct Utf8Validator {
+    // start of a character continued in the next piece
+    pending: [u8; 4],
+    pending_len: usize,
+}
+impl Utf8Validator {
+    fn push(&mut self, mut bytes: &[u8]) -> Result<()> {
+        use std::str::from_utf8;
+
+        if self.pending_len > 0 {
+            let take = (4 - self.pending_len).min(bytes.len());
+            let mut joined = self.pending;
+            joined[self.pending_len..self.pending_len + take].copy_from_slice(&bytes[..take]);
+            let joined = &joined[..self.pending_len + take];
+            let used = match from_utf8(joined) {
+                Ok(_) => take,
+                Err(e) if e.valid_up_to() > 0 => e.valid_up_to() - self.pending_len,
+                Err(e) if e.error_len().is_none() => {
+                    // still incomplete: `bytes` is shorter than the character
+                    self.pending[..joined.len()].copy_from_slice(joined);
+                    self.pending_len = joined.len();
+                    return Ok(());
+                }
+                Err(_) => return Err(invalid_utf8(joined)),
+            };
+            self.pending_len = 0;
+            bytes = &bytes[used..];
+        }
+        match from_utf8(bytes) {
+            Ok(_) => Ok(()),
+            Err(e) if e.error_len().is_none() => {
+                let rest = &bytes[e.valid_up_to()..];
+                self.pending[..rest.len()].copy_from_slice(rest);
+                self.pending_len = rest.len();
+                Ok(())
+            }
+            Err(_) => Err(invalid_utf8(bytes)),
+        }
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.pending_len > 0 {
+            Err(invalid_utf8(&self.pending[..self.pending_len]))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) fn invalid_utf8(bytes: &[u8]) -> Error {
+    match String::from_utf8(bytes.to_vec()) {
+        Err(e) => Error::InvalidTextError(e),
+        Ok(text) => Error::CustomError(format!("valid UTF-8 reported as invalid: {:?}", text)),
+    }
+}
+
+// fill a fixed size array of bytes from either a byte string of the exact
+// same length or an array of unsigned integers.
+fn deserialize_byte_array<R: BufRead>(raw: &mut Deserializer<R>, bytes: &mut [u8]) -> Result<()> {
+    let expected = bytes.len() as u64;
+    if raw.cbor_type()? == Type::Bytes {
+        if let (Len::Len(len), _) = raw.cbor_len()? {
+            // do not read the content of a byte string of the wrong size
+            if len != expected {
+                return Err(Error::WrongLen(expected, Len::Len(len), "static array"));
+            }
+        }
+        let content = raw.bytes()?;
+        if content.len() != bytes.len() {
+            return Err(Error::WrongLen(
+                expected,
+                Len::Len(content.len() as u64),
+                "static array",
+            ));
+        }
+        bytes.copy_from_slice(&content);
+        return Ok(());
+    }
+
+    let len = raw.array()?;
+    match len {
+        Len::Len(x) if x == expected => {}
+        _ => return Err(Error::WrongLen(expected, len, "static array")),
+    }
+    for byte in bytes.iter_mut() {
+        *byte = Deserialize::deserialize(raw)?;
+    }
+    Ok(())
+}
+
+macro_rules! deserialize_array {
+    ( $( $x:expr ),* ) => {
+        $(
+            /// decoded from either a byte string of the same length or an
+            /// array of unsigned integers.
+            impl Deserialize for [u8; $x] {
+                fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+                    let mut bytes = [0u8; $x];
+                    deserialize_byte_array(raw, &mut bytes)?;
+                    Ok(bytes)
+                }
+            }
+            impl Deserialize for ByteString<[u8; $x]> {
+                fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+                    raw.deserialize().map(ByteString)
+                }
+            }
+        )*
+    }
+}
+
+deserialize_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64
+);
+
+/// tuples whose elements are decoded in order from an array of the same
+/// length, see
+/// [`Deserializer::tuple_elems`](./struct.Deserializer.html#method.tuple_elems).
+pub trait DeserializeTuple: Sized {
+    /// number of elements of the tuple
+    const LEN: u64;
+
+    /// decode the elements, the array header has already been read.
+    fn deserialize_elems<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self>;
+}
+
+macro_rules! deserialize_tuple {
+    ( $( ($len:expr; $( $t:ident ),+) ),* ) => {
+        $(
+            impl<$( $t: Deserialize ),+> DeserializeTuple for ($( $t, )+) {
+                const LEN: u64 = $len;
+
+                fn deserialize_elems<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+                    Ok(($( raw.deserialize::<$t>()?, )+))
+                }
+            }
+        )*
+    }
+}
+
+deserialize_tuple!(
+    (1; A),
+    (2; A, B),
+    (3; A, B, C),
+    (4; A, B, C, D),
+    (5; A, B, C, D, E),
+    (6; A, B, C, D, E, F),
+    (7; A, B, C, D, E, F, G),
+    (8; A, B, C, D, E, F, G, H)
+);
+
+// fail unless the encoding of `key` comes strictly after the `previous`
+// key of the map, then make it the previous one.
+fn check_key_order(previous: &mut Option<Vec<u8>>, key: Vec<u8>) -> Result<&[u8]> {
+    match previous.as_deref().map(|previous| previous.cmp(&key[..])) {
+        Some(Ordering::Equal) => return Err(Error::DuplicateKey),
+        Some(Ordering::Greater) => return Err(Error::NonCanonical(ViolationKind::UnsortedKey)),
+        _ => {}
+    }
+    Ok(previous.insert(key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
     fn negative_integer() {
         let vec = vec![0x38, 0x29];
         let mut raw = Deserializer::from(Cursor::new(vec));
@@ -761,6 +3080,18 @@ mod test {
         let integer = raw.negative_integer().unwrap();
 
         assert_eq!(integer, -42);
+
+        let vec = vec![0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(raw.negative_integer().unwrap(), i64::MIN);
+
+        // values below `i64::MIN` do not fit
+        let vec = vec![0x3b, 0x80, 0, 0, 0, 0, 0, 0, 0];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(matches!(raw.negative_integer(), Err(Error::ExpectedI64)));
+        let vec = vec![0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(matches!(raw.negative_integer(), Err(Error::ExpectedI64)));
     }
 
     #[test]
@@ -896,6 +3227,14 @@ mod test {
         assert_eq!(bools, &[false, true, false]);
     }
 
+    #[test]
+    fn vec_u64_indefinite() {
+        let vec = vec![0x9f, 0x01, 0x18, 0x2a, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let integers = Vec::<u64>::deserialize(&mut raw).unwrap();
+        assert_eq!(integers, &[1, 42]);
+    }
+
     #[test]
     fn complex_array() {
         let vec = vec![
@@ -921,8 +3260,7 @@ mod test {
 
         assert_eq!(0x10, raw.unsigned_integer().unwrap());
 
-        const GARBAGE_LEN: usize = 7;
-        // assert_eq!(GARBAGE_LEN, raw.len());
+        // 7 bytes of garbage are left in the buffer
     }
 
     #[test]
@@ -956,6 +3294,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn btreemap_bool_definite() {
         let vec = vec![0xa2, 0xf4, 0xf5, 0xf5, 0xf4];
         let mut raw = Deserializer::from(Cursor::new(vec));
@@ -965,6 +3304,7 @@ mod test {
         assert_eq!(boolmap[&true], false);
     }
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn btreemap_bool_indefinite() {
         let vec = vec![0xbf, 0xf4, 0xf5, 0xf5, 0xf4, 0xff];
         let mut raw = Deserializer::from(Cursor::new(vec));
@@ -975,36 +3315,1329 @@ mod test {
     }
 
     #[test]
-    fn tag() {
+    fn vec_of_pairs() {
+        use se::Serializer;
+
+        // {_ 2: "b", 1: "a", 2: "c"}
         let vec = vec![
-            0xD8, 0x18, 0x52, 0x73, 0x6F, 0x6D, 0x65, 0x20, 0x72, 0x61, 0x6E, 0x64, 0x6F, 0x6D,
-            0x20, 0x73, 0x74, 0x72, 0x69, 0x6E, 0x67,
+            0xbf, 0x02, 0x61, 0x62, 0x01, 0x61, 0x61, 0x02, 0x61, 0x63, 0xff,
         ];
         let mut raw = Deserializer::from(Cursor::new(vec));
+        let pairs = Vec::<(u64, String)>::deserialize(&mut raw).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                (2, "b".to_owned()),
+                (1, "a".to_owned()),
+                (2, "c".to_owned())
+            ]
+        );
 
-        let tag = raw.tag().unwrap();
-
-        assert_eq!(24, tag);
-        let tagged = raw.bytes().unwrap();
-        assert_eq!(b"some random string", &*tagged);
+        let mut se = Serializer::new_vec();
+        se.serialize(&pairs).unwrap();
+        assert_eq!(
+            se.finalize(),
+            vec![0xa3, 0x02, 0x61, 0x62, 0x01, 0x61, 0x61, 0x02, 0x61, 0x63]
+        );
     }
 
     #[test]
-    fn tag2() {
-        let vec = vec![
-            0x82, 0xd8, 0x18, 0x53, 0x52, 0x73, 0x6f, 0x6d, 0x65, 0x20, 0x72, 0x61, 0x6e, 0x64,
-            0x6f, 0x6d, 0x20, 0x73, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x1a, 0x71, 0xad, 0x58, 0x36,
-        ];
-        let mut raw = Deserializer::from(Cursor::new(vec));
+    fn trailing_data_behind_empty_buffer() {
+        use std::io::Read;
 
-        let len = raw.array().unwrap();
-        assert_eq!(len, Len::Len(2));
+        // reader whose next `fill_buf` is interrupted or returns an empty
+        // buffer before the end of the input
+        struct Stuttering {
+            bytes: Vec<u8>,
+            interrupt: bool,
+            stutter: bool,
+        }
+        impl Read for Stuttering {
+            fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+                let n = self.bytes.len().min(out.len());
+                out[..n].copy_from_slice(&self.bytes[..n]);
+                self.consume(n);
+                Ok(n)
+            }
+        }
+        impl BufRead for Stuttering {
+            fn fill_buf(&mut self) -> io::Result<&[u8]> {
+                if self.interrupt {
+                    self.interrupt = false;
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                if self.stutter {
+                    self.stutter = false;
+                    return Ok(&[]);
+                }
+                Ok(&self.bytes)
+            }
+            fn consume(&mut self, amt: usize) {
+                self.bytes.drain(..amt);
+            }
+        }
 
-        let tag = raw.tag().unwrap();
+        let mut raw = Deserializer::from(Stuttering {
+            bytes: vec![0x01, 0x02],
+            interrupt: false,
+            stutter: false,
+        });
+        assert_eq!(raw.unsigned_integer().unwrap(), 1);
+        raw.as_mut_ref().stutter = true;
+        assert!(matches!(raw.expect_end(), Err(Error::TrailingData)));
+
+        let mut raw = Deserializer::from(Stuttering {
+            bytes: vec![0x01],
+            interrupt: false,
+            stutter: false,
+        });
+        assert_eq!(raw.unsigned_integer().unwrap(), 1);
+        raw.as_mut_ref().interrupt = true;
+        raw.as_mut_ref().stutter = true;
+        raw.expect_end().unwrap();
+    }
+
+    #[test]
+    fn lenient() {
+        // [1.5 (half), -3, 0, 2]
+        let vec = vec![0x84, 0xf9, 0x3e, 0x00, 0x22, 0x00, 0x02];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        raw.array().unwrap();
+        assert_eq!(raw.deserialize::<f64>().unwrap(), 1.5);
+        assert!(matches!(
+            raw.deserialize::<f64>(),
+            Err(Error::Expected(Type::Special, Type::NegativeInteger))
+        ));
+
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.set_lenient(true);
+        raw.array().unwrap();
+        assert_eq!(raw.deserialize::<f64>().unwrap(), 1.5);
+        let (f, b) = raw
+            .with_dyn(|raw| Ok((raw.deserialize::<f64>()?, raw.bool()?)))
+            .unwrap();
+        assert_eq!((f, b), (-3.0, false));
+        assert!(matches!(raw.bool(), Err(Error::ExpectedBool)));
+
+        assert!(Deserializer::from(&[0xf5][..]).bool().unwrap());
+        assert!(matches!(
+            Deserializer::from(&[0xf6][..]).bool(),
+            Err(Error::ExpectedBool)
+        ));
+        assert!(matches!(
+            Deserializer::from(&[0x01][..]).bool(),
+            Err(Error::Expected(Type::Special, Type::UnsignedInteger))
+        ));
+    }
+
+    #[test]
+    fn deserialize_in_place() {
+        use se::Serializer;
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&ByteString(vec![0xaa; 100]))
+            .unwrap()
+            .write_array(Len::Indefinite)
+            .unwrap()
+            .write_text("some long enough text")
+            .unwrap()
+            .write_special(Special::Break)
+            .unwrap()
+            .serialize(&ByteString(vec![0xbb; 10]))
+            .unwrap()
+            .write_array(Len::Len(2))
+            .unwrap()
+            .write_text("a")
+            .unwrap()
+            .write_text("b")
+            .unwrap();
+        let bytes = se.finalize();
+        let mut raw = Deserializer::from(&bytes[..]);
+
+        let mut buffer = ByteString(Vec::new());
+        let mut texts: Vec<String> = Vec::new();
+        raw.deserialize_in_place(&mut buffer).unwrap();
+        raw.deserialize_in_place(&mut texts).unwrap();
+        let (buffer_ptr, text_ptr) = (buffer.0.as_ptr(), texts[0].as_ptr());
+
+        raw.deserialize_in_place(&mut buffer).unwrap();
+        raw.deserialize_in_place(&mut texts).unwrap();
+        assert_eq!(buffer.0, vec![0xbb; 10]);
+        assert_eq!(texts, vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(buffer.0.as_ptr(), buffer_ptr);
+        assert_eq!(texts[0].as_ptr(), text_ptr);
+    }
+
+    #[test]
+    fn take() {
+        // [h'010203' (crossing the limit)], 4
+        let vec = vec![0x81, 0x43, 0x01, 0x02, 0x03, 0x04];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.set_definite_only(true);
+        {
+            let mut frame = raw.take(4);
+            assert!(frame.definite_only());
+            assert_eq!(frame.array().unwrap(), Len::Len(1));
+            assert!(matches!(frame.bytes(), Err(Error::NotEnough(..))));
+        }
+
+        let mut raw = Deserializer::from(Cursor::new(vec![0x81, 0x01, 0x02]));
+        let value: Vec<u64> = raw.take(2).deserialize_complete().unwrap();
+        assert_eq!(value, vec![1]);
+        assert!(raw.take(0).deserialize_complete::<u64>().is_err());
+        assert_eq!(raw.unsigned_integer().unwrap(), 2);
+
+        // [1, 2] in a frame of 3 bytes, then [3, 4]
+        let vec = vec![0x82, 0x01, 0x02, 0x82, 0x03, 0x04];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.set_preserve_unknown(true);
+        {
+            let mut frame = raw.take(3);
+            assert_eq!(frame.array().unwrap(), Len::Len(2));
+            frame.unsigned_integer().unwrap();
+            frame.skip_value().unwrap();
+        }
+        raw.array().unwrap();
+        raw.skip_value().unwrap();
+        let unknown = raw.take_unknown();
+        assert_eq!(
+            unknown.elements,
+            vec![
+                RawCbor::new(vec![0x02]).unwrap(),
+                RawCbor::new(vec![0x03]).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn preserve_unknown() {
+        // [{"a": 1, 2: h'00', "b": [3]}, 4, 5]
+        let vec = vec![
+            0x83, 0xa3, 0x61, 0x61, 0x01, 0x02, 0x41, 0x00, 0x61, 0x62, 0x81, 0x03, 0x04, 0x05,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        raw.set_preserve_unknown(true);
+        raw.array().unwrap();
+        let keys = [ObjectKey::Text("a".to_owned())];
+        let mut a = None;
+        raw.with_dyn(|raw| {
+            raw.map_project(&keys, |_, raw| {
+                a = Some(raw.unsigned_integer()?);
+                Ok(())
+            })
+        })
+        .unwrap();
+        assert_eq!(a, Some(1));
+        let unknown = raw.take_unknown();
+        let entries = unknown
+            .entries
+            .iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                (vec![0x02], vec![0x41, 0x00]),
+                (vec![0x61, 0x62], vec![0x81, 0x03])
+            ]
+        );
+        assert!(unknown.elements.is_empty());
+
+        assert_eq!(raw.unsigned_integer().unwrap(), 4);
+        raw.skip_value().unwrap();
+        let unknown = raw.take_unknown();
+        assert_eq!(unknown.elements, vec![RawCbor::new(vec![0x05]).unwrap()]);
+        assert!(raw.take_unknown().is_empty());
+
+        // nothing is captured by default
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.skip_value().unwrap();
+        assert!(!raw.preserve_unknown());
+        assert!(raw.take_unknown().is_empty());
+    }
+
+    #[test]
+    fn definite_only() {
+        // [_ {1: (_ h'01')}], then {2: [_ ]}
+        let vec = vec![
+            0x9f, 0xa1, 0x01, 0x5f, 0x41, 0x01, 0xff, 0xff, 0xa1, 0x02, 0x9f, 0xff,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.set_definite_only(true);
+        assert!(matches!(
+            raw.array(),
+            Err(Error::IndefiniteLenForbidden(Type::Array))
+        ));
+        raw.set_definite_only(false).array().unwrap();
+        raw.set_definite_only(true);
+        assert_eq!(raw.map().unwrap(), Len::Len(1));
+        assert_eq!(raw.unsigned_integer().unwrap(), 1);
+        let res = raw.with_dyn(|raw| raw.bytes());
+        assert!(matches!(
+            res,
+            Err(Error::IndefiniteLenForbidden(Type::Bytes))
+        ));
+    }
+
+    #[test]
+    fn canonical() {
+        fn decode<T: Deserialize>(vec: &[u8]) -> Result<T> {
+            let mut raw = Deserializer::from(vec);
+            raw.set_canonical(true);
+            raw.deserialize_complete()
+        }
+
+        // {10: -1, "b": [1.5, 100000.0]}
+        let vec = [
+            0xa2, 0x0a, 0x20, 0x61, 0x62, 0x82, 0xf9, 0x3e, 0x00, 0xfa, 0x47, 0xc3, 0x50, 0x00,
+        ];
+        assert!(decode::<Value>(&vec).is_ok());
+        assert!(decode::<BTreeMap<u64, u64>>(&[0xa2, 0x01, 0x01, 0x02, 0x02]).is_ok());
+
+        assert!(matches!(
+            decode::<u64>(&[0x19, 0x00, 0xff]),
+            Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(3, 2)))
+        ));
+        assert!(matches!(
+            decode::<f64>(&[0xfa, 0x3f, 0xc0, 0x00, 0x00]),
+            Err(Error::NonCanonical(ViolationKind::OversizedFloat(5, 3)))
+        ));
+        assert!(matches!(
+            decode::<Vec<u64>>(&[0x9f, 0xff]),
+            Err(Error::NonCanonical(ViolationKind::IndefiniteLength))
+        ));
+        assert!(matches!(
+            decode::<Value>(&[0x81, 0x78, 0x00]),
+            Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(2, 1)))
+        ));
+        assert!(matches!(
+            decode::<BTreeMap<u64, u64>>(&[0xa2, 0x02, 0x02, 0x01, 0x01]),
+            Err(Error::NonCanonical(ViolationKind::UnsortedKey))
+        ));
+        assert!(matches!(
+            decode::<Value>(&[0xa2, 0x61, 0x62, 0x01, 0x0a, 0x01]),
+            Err(Error::NonCanonical(ViolationKind::UnsortedKey))
+        ));
+        assert!(matches!(
+            decode::<Vec<(u64, u64)>>(&[0xa2, 0x01, 0x01, 0x01, 0x02]),
+            Err(Error::DuplicateKey)
+        ));
+
+        // only the keys of the projected maps are decoded, all are checked
+        let mut raw = Deserializer::from(&[0xa2, 0x02, 0x02, 0x01, 0x01][..]);
+        raw.set_canonical(true).set_preserve_unknown(true);
+        let res = raw.int_keyed_map_with(&mut []);
+        assert!(matches!(
+            res,
+            Err(Error::NonCanonical(ViolationKind::UnsortedKey))
+        ));
+    }
+
+    #[test]
+    fn map_entries_with() {
+        // {_ 1: "a", 2: [3]}
+        let vec = vec![0xbf, 0x01, 0x61, 0x61, 0x02, 0x81, 0x03, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let mut keys = Vec::new();
+        raw.map_entries_with(|raw, key: u64| {
+            keys.push(key);
+            raw.skip_value()
+        })
+        .unwrap();
+        assert_eq!(keys, vec![1, 2]);
+
+        // the key is not a text
+        let vec = vec![0xa1, 0x01, 0x61, 0x61];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(raw
+            .map_entries_with(|raw, _: String| raw.skip_value())
+            .is_err());
+    }
+
+    #[test]
+    fn int_keyed_map() {
+        use se::Serializer;
+
+        let mut se = Serializer::new_vec();
+        se.write_int_keyed_map(&[(1, &"a".to_owned()), (0, &[1u64, 2]), (30, &true)])
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(
+            bytes,
+            vec![0xa3, 0x01, 0x61, 0x61, 0x00, 0x82, 0x01, 0x02, 0x18, 0x1e, 0xf5]
+        );
+
+        let (mut numbers, mut text) = (None, None);
+        Deserializer::from(&bytes[..])
+            .int_keyed_map_with(&mut [
+                (0, &mut |raw| {
+                    numbers = Some(raw.deserialize::<Vec<u64>>()?);
+                    Ok(())
+                }),
+                (1, &mut |raw| {
+                    text = Some(raw.text()?);
+                    Ok(())
+                }),
+            ])
+            .unwrap();
+        assert_eq!(numbers, Some(vec![1, 2]));
+        assert_eq!(text.as_deref(), Some("a"));
+
+        // {"a": 0} has no integer key to dispatch
+        let mut called = false;
+        Deserializer::from(&[0xa1, 0x61, 0x61, 0x00][..])
+            .int_keyed_map_with(&mut [(0, &mut |raw| {
+                called = true;
+                raw.skip_value()
+            })])
+            .unwrap();
+        assert!(!called);
+
+        let mut se = Serializer::new_vec();
+        se.set_fixed_width(Some(Sz::Two))
+            .write_int_keyed_map(&[(0, &1u64)])
+            .unwrap();
+        assert_eq!(se.finalize(), vec![0xb9, 0, 1, 0x19, 0, 0, 0x19, 0, 1]);
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn indexmap_order() {
+        use se::Serializer;
+
+        // {_ 2: "b", 1: "a"}
+        let vec = vec![0xbf, 0x02, 0x61, 0x62, 0x01, 0x61, 0x61, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let map = IndexMap::<u64, String>::deserialize(&mut raw).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&2, &1]);
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&map).unwrap();
+        assert_eq!(
+            se.finalize(),
+            vec![0xa2, 0x02, 0x61, 0x62, 0x01, 0x61, 0x61]
+        );
+
+        // [3, 1, 2]
+        let vec = vec![0x83, 0x03, 0x01, 0x02];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let set = IndexSet::<u64>::deserialize(&mut raw).unwrap();
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![&3, &1, &2]);
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&set).unwrap();
+        assert_eq!(se.finalize(), vec![0x83, 0x03, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn sets() {
+        use se::Serializer;
+
+        let set: BTreeSet<u64> = vec![3, 1, 2].into_iter().collect();
+        assert!(::test_encode_decode(&set).unwrap());
+        let hash_set: HashSet<u64> = set.iter().cloned().collect();
+        assert!(::test_encode_decode(&hash_set).unwrap());
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&set).unwrap();
+        assert_eq!(
+            se.finalize(),
+            vec![0xd9, 0x01, 0x02, 0x83, 0x01, 0x02, 0x03]
+        );
+
+        // [_ 2, 1, 2] without the set tag, 24(h'')
+        let bytes = [0x9f, 0x02, 0x01, 0x02, 0xff, 0xd8, 0x18, 0x40];
+        let mut raw = Deserializer::from(&bytes[..]);
+        let set = BTreeSet::<u64>::deserialize(&mut raw).unwrap();
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(matches!(
+            BTreeSet::<u64>::deserialize(&mut raw),
+            Err(Error::ExpectedSetTag)
+        ));
+    }
+
+    #[test]
+    fn signed_integers() {
+        for n in [0, 1, -1, 23, -24, 255, -256, i64::MAX, i64::MIN] {
+            ::test_encode_decode(&n).unwrap();
+            ::test_encode_decode(&(n as i8)).unwrap();
+            ::test_encode_decode(&(n as i16)).unwrap();
+            ::test_encode_decode(&(n as i32)).unwrap();
+        }
+        assert_eq!(::to_vec(&-1i8).unwrap(), [0x20]);
+        assert_eq!(::to_vec(&1i8).unwrap(), [0x01]);
+
+        // -2^64, the smallest negative integer
+        let bytes = [0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(bytes.to_vec()));
+        assert_eq!(raw.integer().unwrap(), -1 - u64::MAX as i128);
+        assert!(matches!(
+            ::from_slice::<i64>(&bytes),
+            Err(Error::ExpectedI64)
+        ));
+        assert!(matches!(
+            ::from_slice::<i8>(&[0x18, 0x80]),
+            Err(Error::ExpectedI8)
+        ));
+        assert_eq!(::from_slice::<i8>(&[0x38, 0x7f]).unwrap(), i8::MIN);
+        assert!(matches!(
+            ::from_slice::<i16>(&[0x60]),
+            Err(Error::Expected(Type::UnsignedInteger, Type::Text))
+        ));
+    }
+
+    #[test]
+    fn integers_128() {
+        for value in [0, 23, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX].iter() {
+            assert!(::test_encode_decode(value).unwrap());
+        }
+        let values = [
+            i128::MIN,
+            -2 - u64::MAX as i128,
+            -1 - u64::MAX as i128,
+            -1,
+            0,
+            u64::MAX as i128 + 1,
+            i128::MAX,
+        ];
+        for value in values.iter() {
+            assert!(::test_encode_decode(value).unwrap());
+        }
+
+        // 2(h'00010000000000000000'), 2(h'0100..00') (2^128), -1
+        let mut bytes = vec![0xc2, 0x4a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+        bytes.extend_from_slice(&[0xc2, 0x51, 0x01]);
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.push(0x20);
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(raw.deserialize::<u128>().unwrap(), 1 << 64);
+        assert!(matches!(
+            raw.deserialize::<u128>(),
+            Err(Error::ExpectedU128)
+        ));
+        assert!(raw.deserialize::<u128>().is_err());
+
+        // -1 - i128::MAX
+        let mut raw = Deserializer::from(
+            &[
+                0xc3, 0x50, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ][..],
+        );
+        assert!(matches!(
+            raw.deserialize::<i128>(),
+            Err(Error::ExpectedI128)
+        ));
+    }
+
+    #[cfg(feature = "num-bigint")]
+    #[test]
+    fn bigints() {
+        use num_bigint::{BigInt, BigUint};
+
+        let big = BigUint::from(u128::MAX) * 3u32;
+        for value in [BigUint::from(0u32), BigUint::from(u64::MAX), big.clone()].iter() {
+            assert!(::test_encode_decode(value).unwrap());
+        }
+        let values = [
+            BigInt::from(0),
+            BigInt::from(-1),
+            -BigInt::from(u64::MAX) - 1,
+            -BigInt::from(u64::MAX) - 2,
+            BigInt::from(big.clone()),
+            -BigInt::from(big),
+        ];
+        for value in values.iter() {
+            assert!(::test_encode_decode(value).unwrap());
+            // encoded as the same value in an `i128` when it fits
+            let bytes = ::to_vec(value).unwrap();
+            if let Ok(small) = ::from_slice::<i128>(&bytes) {
+                assert_eq!(&BigInt::from(small), value);
+                assert_eq!(::to_vec(&small).unwrap(), bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn max_depth() {
+        // 10000 nested arrays
+        let mut bytes = vec![0x81; 10_000];
+        bytes.push(0x01);
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(64);
+        assert!(matches!(
+            raw.deserialize::<Value>(),
+            Err(Error::MaxDepthExceeded)
+        ));
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(64);
+        assert!(matches!(raw.borrowed_value(), Err(Error::MaxDepthExceeded)));
+
+        // [[1], 24([2]), {3: [4]}], siblings do not add up
+        let bytes = [
+            0x83, 0x81, 0x01, 0xd8, 0x18, 0x81, 0x02, 0xa1, 0x03, 0x81, 0x04,
+        ];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(3);
+        raw.deserialize::<Value>().unwrap();
+        assert_eq!(raw.depth(), 0);
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(2);
+        assert!(matches!(
+            raw.deserialize::<Value>(),
+            Err(Error::MaxDepthExceeded)
+        ));
+
+        // [[1], [2], [3]]
+        let bytes = [0x83, 0x81, 0x01, 0x81, 0x02, 0x81, 0x03];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(2);
+        raw.deserialize::<Vec<Vec<u64>>>().unwrap();
+        assert_eq!(raw.depth(), 0);
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(0);
+        assert!(matches!(raw.array(), Err(Error::MaxDepthExceeded)));
+
+        // 10000 empty arrays read by hand, siblings do not add up
+        let bytes = vec![0x80; 10_000];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(1);
+        for _ in 0..10_000 {
+            assert_eq!(raw.array().unwrap(), Len::Len(0));
+        }
+        assert_eq!(raw.depth(), 1);
+    }
+
+    #[test]
+    fn ranges() {
+        use se::Serializer;
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&(1u64..24))
+            .unwrap()
+            .serialize(&(2u64..=3))
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, vec![0x82, 0x01, 0x18, 0x18, 0x82, 0x02, 0x03]);
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(Range::<u64>::deserialize(&mut raw).unwrap(), 1..24);
+        assert_eq!(RangeInclusive::<u64>::deserialize(&mut raw).unwrap(), 2..=3);
+
+        let mut raw = Deserializer::from(&[0x83, 0x01, 0x02, 0x03][..]);
+        assert!(Range::<u64>::deserialize(&mut raw).is_err());
+    }
+
+    #[test]
+    fn dyn_deserializer() {
+        fn deserialize_pair(raw: &mut DynDeserializer) -> Result<(u64, String)> {
+            raw.tuple(2, "pair")?;
+            Ok((raw.deserialize()?, raw.deserialize()?))
+        }
+
+        let vec = vec![0x82, 0x82, 0x01, 0x61, 0x61, 0x82, 0x02, 0x61, 0x62, 0x03];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.array().unwrap();
+        let first = raw.with_dyn(deserialize_pair).unwrap();
+        let second = raw.with_dyn(deserialize_pair).unwrap();
+        assert_eq!(first, (1, "a".to_owned()));
+        assert_eq!(second, (2, "b".to_owned()));
+        // the reader is shared: the next item is available afterward
+        assert_eq!(raw.unsigned_integer().unwrap(), 3);
+    }
+
+    #[test]
+    fn byte_array() {
+        let vec = vec![0x83, 0x01, 0x02, 0x03, 0x43, 0x01, 0x02, 0x03];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(raw.deserialize::<[u8; 3]>().unwrap(), [1, 2, 3]);
+        assert_eq!(raw.deserialize::<[u8; 3]>().unwrap(), [1, 2, 3]);
+
+        let vec = vec![0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(raw.deserialize::<[u8; 3]>().unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn byte_array_wrong_len() {
+        let vec = vec![0x43, 0x01, 0x02, 0x03];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        match raw.deserialize::<[u8; 4]>() {
+            Err(Error::WrongLen(4, Len::Len(3), _)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        let vec = vec![0x5f, 0x41, 0x01, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(raw.deserialize::<[u8; 2]>().is_err());
+    }
+
+    #[test]
+    fn tag() {
+        let vec = vec![
+            0xD8, 0x18, 0x52, 0x73, 0x6F, 0x6D, 0x65, 0x20, 0x72, 0x61, 0x6E, 0x64, 0x6F, 0x6D,
+            0x20, 0x73, 0x74, 0x72, 0x69, 0x6E, 0x67,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+
+        let tag = raw.tag().unwrap();
+
+        assert_eq!(24, tag);
+        let tagged = raw.bytes().unwrap();
+        assert_eq!(b"some random string", &*tagged);
+    }
+
+    #[test]
+    fn tag2() {
+        let vec = vec![
+            0x82, 0xd8, 0x18, 0x53, 0x52, 0x73, 0x6f, 0x6d, 0x65, 0x20, 0x72, 0x61, 0x6e, 0x64,
+            0x6f, 0x6d, 0x20, 0x73, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x1a, 0x71, 0xad, 0x58, 0x36,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+
+        let len = raw.array().unwrap();
+        assert_eq!(len, Len::Len(2));
+
+        let tag = raw.tag().unwrap();
         assert!(tag == 24);
         let _ = raw.bytes().unwrap();
 
         let crc = raw.unsigned_integer().unwrap();
         assert!(crc as u32 == 0x71AD5836);
     }
+
+    #[test]
+    fn number() {
+        let vec = vec![
+            0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3b, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xfa, 0x47, 0xc3, 0x50, 0x00, 0xfb, 0x3f, 0xf1, 0x99, 0x99,
+            0x99, 0x99, 0x99, 0x9a, 0xc3, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+
+        assert_eq!(raw.number().unwrap(), Number::U64(u64::MAX));
+        assert_eq!(raw.number().unwrap(), Number::NegU64(u64::MAX));
+        assert_eq!(raw.number().unwrap(), Number::F64(100000.0));
+        assert_eq!(raw.number().unwrap(), Number::F64(1.1));
+        assert_eq!(
+            raw.number().unwrap(),
+            Number::BigInt(true, vec![1, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn number_wrong_type() {
+        for bytes in [
+            &[0x41, 0x00][..],
+            &[0xf5],
+            &[0xd8, 0x18, 0x00],
+            &[0x9f, 0xff],
+        ]
+        .iter()
+        {
+            let mut raw = Deserializer::from(*bytes);
+            assert!(raw.number().is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn floats() {
+        // RFC 8949 Appendix A
+        let vec = vec![
+            0xf9, 0x00, 0x01, // 5.960464477539063e-8
+            0xf9, 0xfb, 0xff, // -65504.0
+            0xf9, 0x7c, 0x00, // Infinity
+            0xf9, 0x7e, 0x00, // NaN
+            0xfa, 0x7f, 0x7f, 0xff, 0xff, // 3.4028234663852886e+38
+            0xfa, 0xff, 0x80, 0x00, 0x00, // -Infinity
+            0xfb, 0xc0, 0x10, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, // -4.1
+        ];
+        let mut raw = Deserializer::from(&vec[..]);
+        assert_eq!(raw.special().unwrap(), Special::Float(5.960464477539063e-8));
+        assert_eq!(raw.special().unwrap(), Special::Float(-65504.0));
+        assert_eq!(raw.float().unwrap(), f64::INFINITY);
+        assert!(raw.float32().unwrap().is_nan());
+        assert_eq!(raw.float32().unwrap(), f32::MAX);
+        assert_eq!(raw.special().unwrap(), Special::Float(f64::NEG_INFINITY));
+        assert_eq!(raw.float().unwrap(), -4.1);
+
+        assert!(matches!(
+            Deserializer::from(&[0x01][..]).float(),
+            Err(Error::Expected(Type::Special, Type::UnsignedInteger))
+        ));
+        for bytes in [&[0xf5][..], &[0xf8, 0x20], &[0xff]].iter() {
+            let mut raw = Deserializer::from(*bytes);
+            assert!(
+                matches!(raw.float(), Err(Error::ExpectedFloat)),
+                "{:?}",
+                bytes
+            );
+            assert!(
+                matches!(raw.float32(), Err(Error::ExpectedFloat)),
+                "{:?}",
+                bytes
+            );
+        }
+        // 1.1 as a double
+        let bytes = [0xfb, 0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a];
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert!(matches!(raw.float32(), Err(Error::ExpectedF32)));
+        assert_eq!(raw.float().unwrap(), 1.1);
+    }
+
+    #[test]
+    fn sz() {
+        let vec = vec![
+            0x1b, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x39, 0x01, 0x00, 0x99, 0x00, 0x01, 0xbf, 0xda, 0, 0,
+            0, 0x18, 0x7f, 0x61, 0x61, 0x78, 0x01, 0x62, 0xff, 0x58, 0x00, 0xff,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+
+        assert_eq!(raw.unsigned_integer_sz().unwrap(), (1, Sz::Eight));
+        assert_eq!(raw.negative_integer_sz().unwrap(), (-257, Sz::Two));
+        assert_eq!(raw.array_sz().unwrap(), LenSz::Len(1, Sz::Two));
+        assert_eq!(raw.map_sz().unwrap(), LenSz::Indefinite);
+        assert_eq!(raw.tag_sz().unwrap(), (24, Sz::Four));
+        assert_eq!(
+            raw.text_sz().unwrap(),
+            (
+                "ab".to_owned(),
+                StringLenSz::Indefinite(vec![(1, Sz::Inline), (1, Sz::One)])
+            )
+        );
+        assert_eq!(raw.bytes_sz().unwrap(), (vec![], StringLenSz::Len(Sz::One)));
+        assert!(raw.special_break().unwrap());
+    }
+
+    #[test]
+    fn negative_integer_sz_full_range() {
+        let vec = vec![0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(
+            raw.negative_integer_sz().unwrap(),
+            (-1 - u64::MAX as i128, Sz::Eight)
+        );
+    }
+
+    #[test]
+    fn bytes_truncated() {
+        let vec = vec![0x43, 0x01, 0x02];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(raw.bytes().is_err());
+    }
+
+    #[test]
+    fn remaining_raw() {
+        // [_ 1, (_ h'01', h'02'), {"a": 24(2)}] followed by 3
+        let vec = vec![
+            0x9f, 0x01, 0x5f, 0x41, 0x01, 0x41, 0x02, 0xff, 0xa1, 0x61, 0x61, 0xd8, 0x18, 0x02,
+            0xff, 0x03,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let len = raw.array().unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 1);
+        let rest = raw.remaining_raw(len, 1).unwrap();
+        assert_eq!(
+            rest,
+            vec![
+                vec![0x5f, 0x41, 0x01, 0x41, 0x02, 0xff],
+                vec![0xa1, 0x61, 0x61, 0xd8, 0x18, 0x02],
+            ]
+        );
+        assert_eq!(raw.unsigned_integer().unwrap(), 3);
+    }
+
+    #[test]
+    fn remaining_raw_entries() {
+        // {1: "a", 2: [3]}
+        let vec = vec![0xa2, 0x01, 0x61, 0x61, 0x02, 0x81, 0x03];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let len = raw.map().unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 1);
+        assert_eq!(raw.text().unwrap(), "a");
+        let rest = raw.remaining_raw_entries(len, 1).unwrap();
+        assert_eq!(rest, vec![(vec![0x02], vec![0x81, 0x03])]);
+        assert!(raw.remaining_raw(Len::Len(1), 2).is_err());
+    }
+
+    #[test]
+    fn remaining_raw_truncated() {
+        let vec = vec![0x82, 0x01, 0x42, 0x01];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let len = raw.array().unwrap();
+        assert!(raw.remaining_raw(len, 0).is_err());
+    }
+
+    #[test]
+    fn position() {
+        // [(_ h'0102', h'03'), "abc", 1000, [h'04']], {1: ["x", 2]}
+        let vec = vec![
+            0x84, 0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff, 0x63, 0x61, 0x62, 0x63, 0x19, 0x03,
+            0xe8, 0x81, 0x41, 0x04, 0xa1, 0x01, 0x82, 0x61, 0x78, 0x02,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        raw.array().unwrap();
+        raw.bytes().unwrap();
+        assert_eq!(raw.position(), 8);
+        raw.with_dyn(|raw| raw.text()).unwrap();
+        assert_eq!(raw.position(), 12);
+        raw.skip_value_seek().unwrap();
+        raw.skip_value().unwrap();
+        assert_eq!(raw.position(), 18);
+
+        raw.set_error_offsets(true);
+        match raw.deserialize::<BTreeMap<u64, Vec<String>>>() {
+            Err(Error::At(23, error)) => assert!(matches!(*error, Error::Expected(..))),
+            result => panic!("{:?}", result),
+        }
+
+        let mut raw = Deserializer::from(&vec[..]);
+        raw.set_error_offsets(true);
+        assert!(matches!(raw.deserialize::<u64>(), Err(Error::At(0, _))));
+        match raw.deserialize_complete::<Value>() {
+            Err(Error::At(18, error)) => assert!(matches!(*error, Error::TrailingData)),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn read_raw_value() {
+        // [_ 1, 24(h'02')], 3
+        let vec = vec![0x9f, 0x01, 0xd8, 0x18, 0x41, 0x02, 0xff, 0x03];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        let (items, bytes) = raw
+            .read_raw_value_with(|raw| {
+                let len = raw.array()?;
+                raw.remaining_raw(len, 0)
+            })
+            .unwrap();
+        assert_eq!(items, vec![vec![0x01], vec![0xd8, 0x18, 0x41, 0x02]]);
+        assert_eq!(bytes, &vec[..7]);
+        assert_eq!(raw.unsigned_integer().unwrap(), 3);
+
+        // the object is consumed even if `f` fails
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        let res = raw.read_raw_value_with(|raw| raw.array());
+        assert!(matches!(res, Err(Error::TrailingData)));
+        assert_eq!(raw.position(), 7);
+
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.set_error_offsets(true).array().unwrap();
+        raw.skip_value().unwrap();
+        match raw.read_raw_value_with(|raw| raw.deserialize::<u64>()) {
+            Err(Error::At(2, error)) => {
+                assert!(matches!(*error, Error::Expected(_, Type::Tag)))
+            }
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn from_slice() {
+        let bytes = [0x82, 0x61, 0x61, 0x60, 0x00];
+        assert_eq!(
+            super::from_slice::<Vec<String>>(&bytes).unwrap(),
+            vec!["a".to_owned(), String::new()]
+        );
+        assert!(matches!(
+            from_slice_complete::<Vec<String>>(&bytes),
+            Err(Error::TrailingData)
+        ));
+        assert_eq!(
+            from_slice_complete::<Vec<String>>(&bytes[..4]).unwrap(),
+            vec!["a".to_owned(), String::new()]
+        );
+
+        let mut raw = Deserializer::from_slice(&bytes);
+        assert_eq!(raw.array().unwrap(), Len::Len(2));
+        assert_eq!(raw.text().unwrap(), "a");
+        raw.text().unwrap();
+        assert_eq!(raw.as_ref(), &[0x00]);
+
+        assert!(matches!(
+            super::from_slice::<u64>(&[]),
+            Err(Error::NotEnough(..))
+        ));
+        assert!(super::from_slice::<Vec<String>>(&bytes[..3]).is_err());
+        assert!(matches!(
+            super::from_slice::<String>(&bytes),
+            Err(Error::Expected(Type::Text, Type::Array))
+        ));
+        assert!(from_slice_complete::<u64>(&[]).is_err());
+    }
+
+    #[test]
+    fn from_cbor_bytes() {
+        assert_eq!(
+            Vec::<u64>::from_cbor_bytes(&[0x82, 0x01, 0x02]).unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(String::from_cbor_bytes(&[0x60]).unwrap(), "");
+        assert!(matches!(
+            u64::from_cbor_bytes(&[0x01, 0x02]),
+            Err(Error::TrailingData)
+        ));
+        assert!(matches!(
+            u64::from_cbor_bytes(&[]),
+            Err(Error::NotEnough(..))
+        ));
+        assert!(matches!(
+            u64::from_cbor_bytes(&[0x61, 0x61]),
+            Err(Error::Expected(Type::UnsignedInteger, Type::Text))
+        ));
+        // truncated array
+        assert!(Vec::<u64>::from_cbor_bytes(&[0x82, 0x01]).is_err());
+    }
+
+    #[test]
+    fn peek_remaining() {
+        let vec = vec![0x82, 0x01, 0x02];
+        let mut raw = Deserializer::from(&vec[..]);
+        assert_eq!(raw.peek_remaining(0).unwrap(), &[] as &[u8]);
+        assert_eq!(raw.peek_remaining(2).unwrap(), &[0x82, 0x01]);
+        // peeking does not consume
+        assert_eq!(raw.peek_remaining(16).unwrap(), &[0x82, 0x01, 0x02]);
+        assert_eq!(raw.array().unwrap(), Len::Len(2));
+        assert_eq!(raw.peek_remaining(16).unwrap(), &[0x01, 0x02]);
+        raw.unsigned_integer().unwrap();
+        raw.unsigned_integer().unwrap();
+        assert_eq!(raw.peek_remaining(16).unwrap(), &[] as &[u8]);
+
+        // limited to the buffered bytes
+        let reader = std::io::BufReader::with_capacity(2, Cursor::new(vec));
+        let mut raw = Deserializer::from(reader);
+        assert_eq!(raw.peek_remaining(16).unwrap(), &[0x82, 0x01]);
+
+        // the reader fails
+        struct Failing;
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::Other.into())
+            }
+        }
+        let mut raw = Deserializer::from(std::io::BufReader::new(Failing));
+        assert!(matches!(raw.peek_remaining(1), Err(Error::IoError(_))));
+    }
+
+    #[test]
+    fn skip_value() {
+        // [{_ "a": (_ h'0102', h'03')}, 24(-1)] followed by 7
+        let vec = vec![
+            0x82, 0xbf, 0x61, 0x61, 0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff, 0xff, 0xd8, 0x18,
+            0x20, 0x07,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        raw.skip_value().unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 7);
+
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        raw.array().unwrap();
+        raw.skip_n(0).unwrap();
+        raw.skip_n(2).unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 7);
+        assert!(raw.skip_n(1).is_err());
+
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        raw.skip_value_seek().unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 7);
+    }
+
+    #[test]
+    fn skip_value_items() {
+        // each item is followed by 7
+        let items: [&[u8]; 12] = [
+            &[0x00],
+            &[0x3b, 0, 0, 0, 0, 0, 0, 0, 0],
+            &[0xf5],
+            &[0xf9, 0x3c, 0x00],
+            &[0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0],
+            &[0x40],
+            &[0x63, 0x61, 0x62, 0x63],
+            &[0x7f, 0x61, 0x61, 0x60, 0xff],
+            &[0x80],
+            &[0x9f, 0x9f, 0xff, 0xa0, 0xff],
+            &[0xa2, 0x01, 0x81, 0x02, 0x61, 0x61, 0xbf, 0xff],
+            &[0xc1, 0xd8, 0x18, 0x82, 0xc2, 0x41, 0x01, 0xf6],
+        ];
+        for item in items.iter() {
+            let mut vec = item.to_vec();
+            vec.push(0x07);
+            let mut raw = Deserializer::from(&vec[..]);
+            raw.skip_value().unwrap();
+            assert_eq!(raw.unsigned_integer().unwrap(), 7, "{:02x?}", item);
+        }
+    }
+
+    #[test]
+    fn skip_value_malformed() {
+        let items: [&[u8]; 9] = [
+            // empty, truncated header, payload, array and indefinite map
+            &[],
+            &[0x19, 0x01],
+            &[0x43, 0x01, 0x02],
+            &[0x82, 0x01],
+            &[0xbf, 0x01, 0x02],
+            // break outside of an indefinite object
+            &[0xff],
+            // reserved additional information
+            &[0x1c],
+            // chunk of the wrong type
+            &[0x5f, 0x61, 0x61, 0xff],
+            // odd number of items in an indefinite map
+            &[0xbf, 0x01, 0xff],
+        ];
+        for item in items.iter() {
+            let mut raw = Deserializer::from(*item);
+            assert!(raw.skip_value().is_err(), "{:02x?}", item);
+        }
+
+        // fails on the second item, after consuming the first one
+        let mut raw = Deserializer::from(&[0x01, 0xff, 0x02][..]);
+        assert!(raw.skip_n(2).is_err());
+        assert_eq!(raw.as_ref(), &[0xff, 0x02]);
+    }
+
+    #[test]
+    fn skip_value_seek_large_strings() {
+        use std::io::BufReader;
+
+        // [(_ h'..' (100 bytes), h'..' (5000 bytes)), "..." (3000 bytes)] followed by 7
+        let mut se = ::se::Serializer::new_vec();
+        se.write_array(Len::Len(2)).unwrap();
+        let mut vec = se.finalize();
+        vec.extend_from_slice(&[0x5f, 0x58, 100]);
+        vec.resize(vec.len() + 100, 1);
+        vec.extend_from_slice(&[0x59, 0x13, 0x88]);
+        vec.resize(vec.len() + 5000, 2);
+        vec.push(0xff);
+        vec.extend_from_slice(&[0x79, 0x0b, 0xb8]);
+        vec.resize(vec.len() + 3000, b'a');
+        vec.push(0x07);
+
+        let reader = BufReader::with_capacity(64, Cursor::new(vec.clone()));
+        let mut raw = Deserializer::from(reader);
+        raw.skip_value_seek().unwrap();
+        assert_eq!(raw.unsigned_integer().unwrap(), 7);
+
+        // the last string ends past the end of the input
+        vec.truncate(vec.len() - 2);
+        let reader = BufReader::with_capacity(64, Cursor::new(vec));
+        let mut raw = Deserializer::from(reader);
+        assert!(raw.skip_value_seek().is_err());
+    }
+
+    #[test]
+    fn project() {
+        // {_ 1: [2, 3], [0]: 4, "a": {"b": 5}, h'00': 6, 1: 7}
+        let vec = vec![
+            0xbf, 0x01, 0x82, 0x02, 0x03, 0x81, 0x00, 0x04, 0x61, 0x61, 0xa1, 0x61, 0x62, 0x05,
+            0x41, 0x00, 0x06, 0x01, 0x07, 0xff, 0x08,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let keys = [
+            ObjectKey::Bytes(vec![0]),
+            ObjectKey::Integer(1),
+            ObjectKey::Text("b".to_owned()),
+        ];
+        let values = raw.project(&keys).unwrap();
+        assert_eq!(values, vec![Some(Value::U64(6)), Some(Value::U64(7)), None]);
+        assert_eq!(raw.unsigned_integer().unwrap(), 8);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{field, span, subscriber, Event, Metadata, Subscriber};
+
+        // records the message of every event
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        struct Message<'a>(&'a mut String);
+        impl<'a> field::Visit for Message<'a> {
+            fn record_debug(&mut self, field: &field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{:?}", value);
+                }
+            }
+        }
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                event.record(&mut Message(&mut message));
+                self.0.lock().unwrap().push(message);
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        subscriber::with_default(Recorder(messages.clone()), || {
+            // [_ 1(2)] followed by a truncated array
+            let vec = vec![0x9f, 0xc1, 0x02, 0xff, 0x81];
+            let mut raw = Deserializer::from(Cursor::new(vec));
+            raw.deserialize::<Vec<Value>>().unwrap();
+            raw.deserialize::<Vec<u64>>().unwrap_err();
+        });
+        let messages = messages.lock().unwrap();
+        assert_eq!(
+            *messages,
+            vec![
+                "container begin",
+                "tag",
+                "container end",
+                "container begin",
+                "decoding failed",
+            ]
+        );
+    }
+
+    #[test]
+    fn text_to_writer() {
+        use std::io::BufReader;
+
+        // "a€😀" in one chunk, then in 3 chunks
+        let text = "a\u{20ac}\u{1f600}";
+        let mut se = ::se::Serializer::new_vec();
+        se.write_text(text).unwrap();
+        let mut vec = se.finalize();
+        vec.extend_from_slice(&[0x7f, 0x61, 0x61, 0x63, 0xe2, 0x82, 0xac, 0x64]);
+        vec.extend_from_slice("\u{1f600}".as_bytes());
+        vec.push(0xff);
+
+        // buffers of every size split the characters everywhere
+        for capacity in 1..10 {
+            let mut raw = Deserializer::from(BufReader::with_capacity(capacity, &vec[..]));
+            for _ in 0..2 {
+                let mut out = Vec::new();
+                assert_eq!(raw.text_to_writer(&mut out).unwrap(), 8);
+                assert_eq!(out, text.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn text_to_writer_invalid() {
+        use std::io::BufReader;
+
+        // character split between two chunks
+        let vec = [0x7f, 0x62, 0x61, 0xe2, 0x62, 0x82, 0xac, 0xff];
+        let mut raw = Deserializer::from(&vec[..]);
+        assert!(raw.text_to_writer(&mut Vec::new()).is_err());
+
+        // truncated and invalid characters
+        for vec in [vec![0x62, 0x61, 0xe2], vec![0x63, 0xe2, 0x28, 0xa1]].iter() {
+            let mut raw = Deserializer::from(BufReader::with_capacity(1, &vec[..]));
+            assert!(raw.text_to_writer(&mut Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn bytes_to_writer() {
+        let vec = vec![0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff, 0x41, 0x04];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let mut out = Vec::new();
+        assert_eq!(raw.bytes_to_writer(&mut out).unwrap(), 3);
+        assert_eq!(raw.bytes_to_writer(&mut out).unwrap(), 1);
+        assert_eq!(out, vec![1, 2, 3, 4]);
+        assert!(raw.text_to_writer(&mut out).is_err());
+    }
+
+    #[test]
+    fn bytes_reader() {
+        use std::io::{BufReader, Read};
+
+        // (_ h'0102', h'', h'03'), h'04', h'05'
+        let vec = vec![
+            0x5f, 0x42, 0x01, 0x02, 0x40, 0x41, 0x03, 0xff, 0x41, 0x04, 0x41, 0x05,
+        ];
+        for capacity in 1..10 {
+            let mut raw = Deserializer::from(BufReader::with_capacity(capacity, &vec[..]));
+            let mut out = Vec::new();
+            raw.bytes_reader().unwrap().read_to_end(&mut out).unwrap();
+            assert_eq!(out, vec![1, 2, 3]);
+            let mut reader = raw.bytes_reader().unwrap();
+            let mut byte = [0; 1];
+            assert_eq!(reader.read(&mut byte).unwrap(), 1);
+            assert_eq!(reader.read(&mut byte).unwrap(), 0);
+            assert_eq!(raw.bytes().unwrap(), vec![5]);
+        }
+
+        // truncated, chunk of the wrong type
+        for vec in [
+            vec![0x43, 0x01, 0x02],
+            vec![0x5f, 0x41, 0x01, 0x61, 0x61, 0xff],
+        ]
+        .iter()
+        {
+            let mut raw = Deserializer::from(&vec[..]);
+            let mut reader = raw.bytes_reader().unwrap();
+            assert!(reader.read_to_end(&mut Vec::new()).is_err());
+        }
+        assert!(Deserializer::from(&[0x61, 0x61][..])
+            .bytes_reader()
+            .is_err());
+    }
+
+    #[test]
+    fn text_lossy() {
+        // "a€" split between two chunks, followed by "b\xe2"
+        let vec = vec![
+            0x7f, 0x62, 0x61, 0xe2, 0x62, 0x82, 0xac, 0xff, 0x62, 0x62, 0xe2,
+        ];
+        let mut raw = Deserializer::from(Cursor::new(vec.clone()));
+        assert!(raw.text().is_err());
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(raw.text_lossy().unwrap(), "a\u{20ac}");
+        assert_eq!(raw.text_lossy().unwrap(), "b\u{fffd}");
+        assert!(raw.text_lossy().is_err());
+    }
+
+    #[test]
+    fn text_bytes() {
+        let vec = vec![0x7f, 0x61, 0xe2, 0x62, 0x82, 0xac, 0xff, 0x41, 0x00];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert_eq!(raw.text_bytes().unwrap(), vec![0xe2, 0x82, 0xac]);
+        assert!(raw.text_bytes().is_err());
+    }
+
+    #[test]
+    fn unsigned_as() {
+        // 0x1_0000_0001 three times, then 7
+        let mut vec = Vec::new();
+        for _ in 0..3 {
+            vec.extend_from_slice(&[0x1b, 0, 0, 0, 0x01, 0, 0, 0, 0x01]);
+        }
+        vec.push(0x07);
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        assert!(matches!(
+            raw.unsigned_as::<u32>(OverflowPolicy::Error),
+            Err(Error::ExpectedU32)
+        ));
+        assert_eq!(
+            raw.unsigned_as::<u16>(OverflowPolicy::Saturate).unwrap(),
+            u16::MAX
+        );
+        assert_eq!(raw.unsigned_as::<u32>(OverflowPolicy::Truncate).unwrap(), 1);
+        assert_eq!(raw.unsigned_as::<u8>(OverflowPolicy::Error).unwrap(), 7);
+    }
+
+    #[test]
+    fn tuple_elems() {
+        let vec = vec![0x82, 0x01, 0x61, 0x61, 0x9f, 0x01, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let (a, b): (u8, String) = raw.tuple_elems("Pair").unwrap();
+        assert_eq!((a, b.as_str()), (1, "a"));
+        assert!(matches!(
+            raw.tuple_elems::<(u8,)>("Single"),
+            Err(Error::WrongLen(1, Len::Indefinite, "Single"))
+        ));
+    }
 }
@@ -0,0 +1,553 @@
+//! Diagnostic notation of encoded CBOR
+//!
+//! [`to_diag`] renders an encoded object in the diagnostic notation of
+//! RFC 8949 (section 8) on one line, e.g. `{1: "text", 2: h'deadbeef'}`.
+//! The `Display` implementation of [`Value`] renders the same notation.
+//!
+//! A [`PrettyPrinter`] renders large payloads readably in logs: one object
+//! per line with indentation, the objects too deep elided, long strings
+//! abbreviated and, optionally, the offset of the objects at the beginning
+//! of the lines. [`DebugCbor`] formats encoded bytes with `{}` and `{:?}`,
+//! to pass them to logging macros.
+//!
+//! [`to_diag`]: ./fn.to_diag.html
+//! [`Value`]: ../enum.Value.html
+//! [`DebugCbor`]: ./struct.DebugCbor.html
+//! [`PrettyPrinter`]: ./struct.PrettyPrinter.html
+//!
+//! ```
+//! use cbor_event::diag::PrettyPrinter;
+//!
+//! // {1: [_ h'0102030405'], 2: [[0]]}
+//! let bytes = [
+//!     0xa2, 0x01, 0x9f, 0x45, 0x01, 0x02, 0x03, 0x04, 0x05, 0xff, 0x02, 0x81, 0x81, 0x00,
+//! ];
+//!
+//! let printer = PrettyPrinter::new()
+//!     .indent(2)
+//!     .max_depth(3)
+//!     .max_string_bytes(2)
+//!     .offsets(true);
+//! assert_eq!(
+//!     printer.print(&bytes).unwrap(),
+//!     "00000000  {\n\
+//!      00000001    1: [_\n\
+//!      00000003      h'0102…' (5 bytes)\n\
+//!      \x20           ],\n\
+//!      0000000a    2: [\n\
+//!      0000000c      […] (1 items)\n\
+//!      \x20           ]\n\
+//!      \x20         }"
+//! );
+//! ```
+
+use de::Deserializer;
+use error::Error;
+use float;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use se::ToCborBytes;
+use std::{
+    fmt::{self, Write},
+    io::BufRead,
+};
+use types::Type;
+use value::Value;
+
+/// render the CBOR object in `bytes` in diagnostic notation, on one line.
+///
+/// Fails if `bytes` is not exactly one well-formed CBOR object or if a text
+/// string is not valid UTF-8.
+///
+/// ```
+/// use cbor_event::diag::to_diag;
+///
+/// let bytes = [0xa2, 0x01, 0x64, 0x74, 0x65, 0x78, 0x74, 0x02, 0xc1, 0xf9, 0x3e, 0x00];
+/// assert_eq!(to_diag(&bytes).unwrap(), r#"{1: "text", 2: 1(1.5)}"#);
+/// ```
+pub fn to_diag(bytes: &[u8]) -> Result<String> {
+    PrettyPrinter::new().print(bytes)
+}
+
+/// configurable diagnostic notation printer, see the
+/// [module documentation](./index.html).
+///
+/// By default it renders everything on one line, like
+/// [`to_diag`](./fn.to_diag.html).
+#[derive(Debug, Clone, Default)]
+pub struct PrettyPrinter {
+    indent: Option<usize>,
+    max_depth: Option<usize>,
+    max_string_bytes: Option<usize>,
+    offsets: bool,
+}
+impl PrettyPrinter {
+    pub fn new() -> Self {
+        PrettyPrinter::default()
+    }
+
+    /// put the elements of arrays and the entries of maps on their own line,
+    /// indented by `indent` spaces per nesting level.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    /// elide the content of the arrays, maps and tags deeper than
+    /// `max_depth` (the top level object is at depth 1), only giving the
+    /// number of elements, e.g. `[…] (3 items)`.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// abbreviate the byte and text strings longer than `max_bytes` bytes,
+    /// e.g. `h'abcd…' (1024 bytes)`. The chunks of indefinite length
+    /// strings are abbreviated separately.
+    pub fn max_string_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_string_bytes = Some(max_bytes);
+        self
+    }
+
+    /// start every line with the offset (in hexadecimal) of the object it
+    /// shows. Only used with [`indent`](#method.indent).
+    pub fn offsets(mut self, offsets: bool) -> Self {
+        self.offsets = offsets;
+        self
+    }
+
+    /// render the CBOR object in `bytes`.
+    ///
+    /// Fails if `bytes` is not exactly one well-formed CBOR object or if a
+    /// text string is not valid UTF-8.
+    pub fn print(&self, bytes: &[u8]) -> Result<String> {
+        let layout = scan::Layout::new(bytes)?;
+        let mut printer = Printer {
+            options: self,
+            bytes,
+            out: String::new(),
+        };
+        printer.line(Some(0), 0);
+        printer.print(&layout)?;
+        Ok(printer.out)
+    }
+
+    /// consume the next CBOR object of `raw` and render it, to dump a
+    /// stream while decoding it. The offsets are relative to the beginning
+    /// of the object.
+    ///
+    /// ```
+    /// use cbor_event::{de::Deserializer, diag::PrettyPrinter};
+    ///
+    /// let bytes = [0x01, 0x82, 0x61, 0x61, 0xf5];
+    /// let mut raw = Deserializer::from(&bytes[..]);
+    ///
+    /// assert_eq!(raw.unsigned_integer().unwrap(), 1);
+    /// assert_eq!(PrettyPrinter::new().print_next(&mut raw).unwrap(), r#"["a", true]"#);
+    /// ```
+    pub fn print_next<R: BufRead>(&self, raw: &mut Deserializer<R>) -> Result<String> {
+        self.print(&raw.read_raw_value()?)
+    }
+
+    /// render `value` as encoded by a
+    /// [`Serializer`](../se/struct.Serializer.html). On one line, this is
+    /// the same as the `Display` implementation of `Value`.
+    ///
+    /// ```
+    /// use cbor_event::{diag::PrettyPrinter, Value};
+    ///
+    /// let value = Value::Array(vec![Value::U64(1), Value::I64(-1)]);
+    /// assert_eq!(
+    ///     PrettyPrinter::new().indent(2).print_value(&value).unwrap(),
+    ///     "[\n  1,\n  -1\n]"
+    /// );
+    /// ```
+    pub fn print_value(&self, value: &Value) -> Result<String> {
+        self.print(&value.to_cbor_bytes()?)
+    }
+}
+
+/// display encoded CBOR in diagnostic notation, for logs.
+///
+/// Both `Display` and `Debug` render the diagnostic notation, on one line
+/// or indented with the alternate flag (`{:#}`). Malformed input is shown
+/// in hexadecimal instead of failing, so it can be logged unconditionally.
+///
+/// ```
+/// use cbor_event::diag::DebugCbor;
+///
+/// let bytes = [0x82, 0x01, 0x61, 0x61];
+/// assert_eq!(format!("{}", DebugCbor(&bytes)), r#"[1, "a"]"#);
+/// assert_eq!(format!("{:?}", DebugCbor(&bytes[..3])), "<invalid CBOR: 820161>");
+/// ```
+#[derive(Clone, Copy)]
+pub struct DebugCbor<'a>(pub &'a [u8]);
+impl<'a> fmt::Display for DebugCbor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let printer = if f.alternate() {
+            PrettyPrinter::new().indent(2)
+        } else {
+            PrettyPrinter::new()
+        };
+        match printer.print(self.0) {
+            Ok(diag) => f.write_str(&diag),
+            Err(_) => {
+                f.write_str("<invalid CBOR: ")?;
+                for byte in self.0 {
+                    write!(f, "{:02x}", byte)?;
+                }
+                f.write_str(">")
+            }
+        }
+    }
+}
+impl<'a> fmt::Debug for DebugCbor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// container, tag or indefinite length string being printed
+struct Frame {
+    cbor_type: Type,
+    // nesting level of the object
+    depth: usize,
+    // number of children, and of children printed so far
+    children: usize,
+    count: usize,
+}
+
+struct Printer<'a> {
+    options: &'a PrettyPrinter,
+    bytes: &'a [u8],
+    out: String,
+}
+impl<'a> Printer<'a> {
+    // start a new line for the object at `offset` (none for closing
+    // brackets) with `level` levels of indentation
+    fn line(&mut self, offset: Option<usize>, level: usize) {
+        let indent = match self.options.indent {
+            None => return,
+            Some(indent) => indent,
+        };
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        if self.options.offsets {
+            match offset {
+                Some(offset) => write!(self.out, "{:08x}  ", offset).unwrap(),
+                None => self.out.push_str("          "),
+            }
+        }
+        for _ in 0..indent * level {
+            self.out.push(' ');
+        }
+    }
+
+    fn elided(&self, depth: usize) -> bool {
+        self.options.max_depth.is_some_and(|max| depth > max)
+    }
+
+    // print the objects of `layout` with an explicit stack of the
+    // containers, tags and indefinite length strings being printed
+    fn print(&mut self, layout: &scan::Layout) -> Result<()> {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut index = 0;
+        loop {
+            let depth = match stack.last_mut() {
+                None => 1,
+                Some(frame) => {
+                    self.separator(frame, layout.items()[index].pos);
+                    frame.count += 1;
+                    match frame.cbor_type {
+                        Type::Bytes | Type::Text => frame.depth,
+                        _ => frame.depth + 1,
+                    }
+                }
+            };
+            index = self.item(layout, index, depth, &mut stack)?;
+            while let Some(frame) = stack.last() {
+                if frame.count < frame.children {
+                    break;
+                }
+                self.close(frame);
+                stack.pop();
+            }
+            if stack.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    // print the object at `index`, at nesting level `depth`, pushing a
+    // frame if its children follow. Returns the index of the next object
+    // to print.
+    fn item(
+        &mut self,
+        layout: &scan::Layout,
+        index: usize,
+        depth: usize,
+        stack: &mut Vec<Frame>,
+    ) -> Result<usize> {
+        let item = &layout.items()[index];
+        let (cbor_type, len, sz) = (item.cbor_type, item.len, item.sz);
+        let start = item.pos + sz;
+        let mut frame = Frame {
+            cbor_type,
+            depth,
+            children: 0,
+            count: 0,
+        };
+        match (cbor_type, len) {
+            (Type::UnsignedInteger, Len::Len(value)) => write!(self.out, "{}", value).unwrap(),
+            (Type::NegativeInteger, Len::Len(value)) => {
+                write!(self.out, "{}", -1 - value as i128).unwrap()
+            }
+            (Type::Bytes, Len::Len(_)) | (Type::Text, Len::Len(_)) => {
+                self.string(cbor_type, &self.bytes[start..item.end])?;
+            }
+            (Type::Bytes, Len::Indefinite) | (Type::Text, Len::Indefinite) => {
+                self.out.push_str("(_ ");
+                frame.children = layout.children(index).count();
+            }
+            (Type::Array, _) | (Type::Map, _) => {
+                let children = layout.children(index).count();
+                if self.elided(depth + 1) {
+                    match cbor_type {
+                        Type::Array => write!(self.out, "[…] ({} items)", children),
+                        _ => write!(self.out, "{{…}} ({} entries)", children / 2),
+                    }
+                    .unwrap();
+                    return Ok(item.next);
+                }
+                self.out
+                    .push(if cbor_type == Type::Array { '[' } else { '{' });
+                match (len, self.options.indent) {
+                    (Len::Indefinite, None) => self.out.push_str("_ "),
+                    (Len::Indefinite, Some(_)) => self.out.push('_'),
+                    _ => {}
+                }
+                frame.children = children;
+            }
+            (Type::Tag, Len::Len(tag)) => {
+                write!(self.out, "{}(", tag).unwrap();
+                if self.elided(depth + 1) {
+                    self.out.push_str("…)");
+                    return Ok(item.next);
+                }
+                frame.children = 1;
+            }
+            (Type::Special, Len::Len(value)) => match sz {
+                1 | 2 => match value {
+                    20 => self.out.push_str("false"),
+                    21 => self.out.push_str("true"),
+                    22 => self.out.push_str("null"),
+                    23 => self.out.push_str("undefined"),
+                    _ => write!(self.out, "simple({})", value).unwrap(),
+                },
+                _ => write_float(&mut self.out, float::decode(value, sz - 1)).unwrap(),
+            },
+            (_, Len::Indefinite) => return Err(Error::IndefiniteLenNotSupported(cbor_type)),
+        }
+        match cbor_type {
+            Type::Bytes | Type::Text if len != Len::Indefinite => {}
+            Type::Bytes | Type::Text | Type::Array | Type::Map | Type::Tag => {
+                if frame.children == 0 {
+                    self.close(&frame);
+                } else {
+                    stack.push(frame);
+                }
+            }
+            _ => {}
+        }
+        Ok(index + 1)
+    }
+
+    // print what precedes the next child, at `pos`, of `frame`
+    fn separator(&mut self, frame: &Frame, pos: usize) {
+        match frame.cbor_type {
+            Type::Array | Type::Map => {
+                let items = if frame.cbor_type == Type::Map { 2 } else { 1 };
+                if frame.count % items == 0 {
+                    if frame.count > 0 {
+                        self.out.push(',');
+                    }
+                    if self.options.indent.is_some() {
+                        self.line(Some(pos), frame.depth);
+                    } else if frame.count > 0 {
+                        self.out.push(' ');
+                    }
+                } else {
+                    self.out.push_str(": ");
+                }
+            }
+            Type::Bytes | Type::Text if frame.count > 0 => self.out.push_str(", "),
+            _ => {}
+        }
+    }
+
+    // print the end of `frame` once its children are printed
+    fn close(&mut self, frame: &Frame) {
+        match frame.cbor_type {
+            Type::Array | Type::Map => {
+                if frame.count > 0 {
+                    self.line(None, frame.depth - 1);
+                }
+                self.out.push(if frame.cbor_type == Type::Array {
+                    ']'
+                } else {
+                    '}'
+                });
+            }
+            _ => self.out.push(')'),
+        }
+    }
+
+    fn string(&mut self, cbor_type: Type, payload: &[u8]) -> Result<()> {
+        let max = self.options.max_string_bytes.unwrap_or(usize::MAX);
+        let shown = &payload[..payload.len().min(max)];
+        if cbor_type == Type::Bytes {
+            self.out.push_str("h'");
+            for byte in shown {
+                write!(self.out, "{:02x}", byte).unwrap();
+            }
+        } else {
+            let text = String::from_utf8(payload.to_vec())?;
+            let mut cut = shown.len();
+            while !text.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            self.out.push('"');
+            escape(&mut self.out, &text[..cut]).unwrap();
+        }
+        if shown.len() < payload.len() {
+            self.out.push('…');
+        }
+        self.out
+            .push(if cbor_type == Type::Bytes { '\'' } else { '"' });
+        if shown.len() < payload.len() {
+            write!(self.out, " ({} bytes)", payload.len()).unwrap();
+        }
+        Ok(())
+    }
+}
+
+// write a floating point value in diagnostic notation
+pub(crate) fn write_float<W: Write>(out: &mut W, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        out.write_str("NaN")
+    } else if value.is_infinite() {
+        out.write_str(if value > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        write!(out, "{:?}", value)
+    }
+}
+
+// escape a text string as in JSON
+pub(crate) fn escape<W: Write>(out: &mut W, text: &str) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '"' => out.write_str("\\\""),
+            '\\' => out.write_str("\\\\"),
+            '\n' => out.write_str("\\n"),
+            '\r' => out.write_str("\\r"),
+            '\t' => out.write_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32),
+            c => out.write_char(c),
+        }?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn one_line() {
+        // [_ 0, -500, (_ h'01', h''), "a\"\n", [], {_ }, 24([true, null]), 1.5, -Infinity, simple(32)]
+        let bytes = [
+            0x9f, 0x00, 0x39, 0x01, 0xf3, 0x5f, 0x41, 0x01, 0x40, 0xff, 0x63, 0x61, 0x22, 0x0a,
+            0x80, 0xbf, 0xff, 0xd8, 0x18, 0x82, 0xf5, 0xf6, 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0,
+            0xf9, 0xfc, 0x00, 0xf8, 0x20, 0xff,
+        ];
+        assert_eq!(
+            to_diag(&bytes).unwrap(),
+            r#"[_ 0, -500, (_ h'01', h''), "a\"\n", [], {_ }, 24([true, null]), 1.5, -Infinity, simple(32)]"#
+        );
+    }
+
+    #[test]
+    fn elisions() {
+        // 1([{"é": 2}, [1, 2, 3], "aé"])
+        let bytes = [
+            0xc1, 0x83, 0xa1, 0x62, 0xc3, 0xa9, 0x02, 0x83, 0x01, 0x02, 0x03, 0x63, 0x61, 0xc3,
+            0xa9,
+        ];
+        let printer = PrettyPrinter::new().max_depth(3).max_string_bytes(2);
+        assert_eq!(
+            printer.print(&bytes).unwrap(),
+            r#"1([{…} (1 entries), […] (3 items), "a…" (3 bytes)])"#
+        );
+        assert_eq!(
+            PrettyPrinter::new().max_depth(1).print(&bytes).unwrap(),
+            "1(…)"
+        );
+    }
+
+    #[test]
+    fn indentation() {
+        // {"a": [1, []], "b": {}}
+        let bytes = [0xa2, 0x61, 0x61, 0x82, 0x01, 0x80, 0x61, 0x62, 0xa0];
+        assert_eq!(
+            PrettyPrinter::new().indent(2).print(&bytes).unwrap(),
+            "{\n  \"a\": [\n    1,\n    []\n  ],\n  \"b\": {}\n}"
+        );
+    }
+
+    #[test]
+    fn values() {
+        let bytes = [
+            0x9f, 0x00, 0x39, 0x01, 0xf3, 0x63, 0x61, 0x22, 0x0a, 0xbf, 0xff, 0xd8, 0x18, 0x82,
+            0xf5, 0xf6, 0xff,
+        ];
+        let value: Value = Deserializer::from(&bytes[..]).deserialize().unwrap();
+        let diag = PrettyPrinter::new().print_value(&value).unwrap();
+        assert_eq!(diag, r#"[_ 0, -500, "a\"\n", {_ }, 24([true, null])]"#);
+        assert_eq!(diag, value.to_string());
+        assert_eq!(diag, to_diag(&bytes).unwrap());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        // 1([_ [_ … 0 …]])
+        let mut bytes = vec![0xc1];
+        bytes.extend(vec![0x9f; 100_000]);
+        bytes.push(0x00);
+        bytes.extend(vec![0xff; 100_000]);
+        assert_eq!(
+            to_diag(&bytes).unwrap(),
+            format!("1({}0{})", "[_ ".repeat(100_000), "]".repeat(100_000))
+        );
+
+        let printer = PrettyPrinter::new().indent(1).max_depth(3);
+        assert_eq!(printer.print(&bytes).unwrap(), "1([_\n  […] (1 items)\n ])");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(to_diag(&[0x82, 0x00]).is_err());
+        assert!(to_diag(&[0x61, 0xff]).is_err());
+    }
+
+    #[test]
+    fn debug_cbor() {
+        let bytes = [0xa1, 0x01, 0x80];
+        assert_eq!(format!("{:?}", DebugCbor(&bytes)), "{1: []}");
+        assert_eq!(format!("{:#}", DebugCbor(&bytes)), "{\n  1: []\n}");
+        assert_eq!(format!("{}", DebugCbor(&[])), "<invalid CBOR: >");
+    }
+}
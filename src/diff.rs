@@ -0,0 +1,412 @@
+//! Structural comparison of encoded CBOR
+//!
+//! Comparing two hex dumps when a test fails does not tell much. The
+//! functions here walk two encoded objects in lockstep and report where
+//! they differ: the [`Path`] to the object, its offset in both buffers and
+//! whether the difference is in the type, the value or only in the way the
+//! value was encoded.
+//!
+//! [`Path`]: ./struct.Path.html
+//!
+//! ```
+//! use cbor_event::diff::{diff_encoded, DifferenceKind};
+//!
+//! // [1, "a", 2] and [1, "b", 2 (encoded on 2 bytes)]
+//! let a = [0x83, 0x01, 0x61, 0x61, 0x02];
+//! let b = [0x83, 0x01, 0x61, 0x62, 0x18, 0x02];
+//!
+//! let differences = diff_encoded(&a, &b).unwrap();
+//! assert_eq!(differences.len(), 2);
+//! assert_eq!(differences[0].path.to_string(), "$[1]");
+//! assert_eq!(differences[0].kind, DifferenceKind::Value);
+//! assert_eq!(differences[1].path.to_string(), "$[2]");
+//! assert_eq!(differences[1].kind, DifferenceKind::HeaderSize(1, 2));
+//! ```
+
+use float;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use std::fmt;
+use types::Type;
+
+const BREAK: u8 = 0xff;
+
+/// step from a CBOR object to one of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathElement {
+    /// the n-th element of an array
+    Index(u64),
+    /// the key of the n-th entry of a map
+    Key(u64),
+    /// the value of the n-th entry of a map
+    Value(u64),
+    /// the object tagged by a tag
+    Tagged,
+}
+
+/// location of an object within an encoded CBOR object.
+///
+/// It is displayed as `$` for the root object followed by `[n]` for array
+/// elements, `{n}.key` and `{n}` for the key and value of map entries (in
+/// order of appearance) and `.tagged` for tagged objects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path(Vec<PathElement>);
+impl Path {
+    pub fn elements(&self) -> &[PathElement] {
+        &self.0
+    }
+
+    pub(crate) fn push(&mut self, element: PathElement) {
+        self.0.push(element)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<PathElement> {
+        self.0.pop()
+    }
+}
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for element in self.0.iter() {
+            match element {
+                PathElement::Index(i) => write!(f, "[{}]", i)?,
+                PathElement::Key(i) => write!(f, "{{{}}}.key", i)?,
+                PathElement::Value(i) => write!(f, "{{{}}}", i)?,
+                PathElement::Tagged => write!(f, ".tagged")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the kind of [`Difference`](./struct.Difference.html) found. When two
+/// values are given, the first one is for the first buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// the objects are not of the same type
+    Type(Type, Type),
+    /// the objects are of the same type but their values differ (integer,
+    /// tag number, content of a string, special or floating point value).
+    Value,
+    /// arrays or maps of different number of elements (of entries for maps)
+    Length(u64, u64),
+    /// same value but encoded with a header of a different number of bytes
+    HeaderSize(usize, usize),
+    /// same value but one is encoded with a definite length and the other
+    /// one with an indefinite length (`true`)
+    Indefinite(bool, bool),
+    /// indefinite length strings of the same content split in different
+    /// chunks
+    Chunks,
+}
+
+/// a difference between two encoded CBOR objects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// path of the differing objects from the root
+    pub path: Path,
+    /// offsets of the differing objects in both buffers
+    pub offsets: (usize, usize),
+    pub kind: DifferenceKind,
+}
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (offsets {} and {}): ",
+            self.path, self.offsets.0, self.offsets.1
+        )?;
+        match self.kind {
+            DifferenceKind::Type(a, b) => write!(f, "types differ, {:?} and {:?}", a, b),
+            DifferenceKind::Value => write!(f, "values differ"),
+            DifferenceKind::Length(a, b) => write!(f, "lengths differ, {} and {}", a, b),
+            DifferenceKind::HeaderSize(a, b) => {
+                write!(f, "headers encoded on {} and {} bytes", a, b)
+            }
+            DifferenceKind::Indefinite(a, b) => write!(
+                f,
+                "{} and {} lengths",
+                if a { "indefinite" } else { "definite" },
+                if b { "indefinite" } else { "definite" }
+            ),
+            DifferenceKind::Chunks => write!(f, "strings split in different chunks"),
+        }
+    }
+}
+
+/// compare the CBOR objects encoded in `a` and `b`, returning all the
+/// differences found.
+///
+/// The children of objects of different types or of different tags are
+/// not compared. Elements of arrays and entries of maps are compared in
+/// order of appearance, up to the length of the shortest one.
+///
+/// Fails if either buffer is not exactly one well-formed CBOR object.
+pub fn diff_encoded(a: &[u8], b: &[u8]) -> Result<Vec<Difference>> {
+    let mut differences = Vec::new();
+    walk(a, b, |difference| {
+        differences.push(difference);
+        true
+    })?;
+    Ok(differences)
+}
+
+/// compare the CBOR objects encoded in `a` and `b`, returning the first
+/// difference found (if any) in order of appearance in the buffers.
+pub fn first_difference(a: &[u8], b: &[u8]) -> Result<Option<Difference>> {
+    let mut first = None;
+    walk(a, b, |difference| {
+        first = Some(difference);
+        false
+    })?;
+    Ok(first)
+}
+
+// call `f` with every difference found until it returns `false`
+fn walk<F>(a: &[u8], b: &[u8], mut f: F) -> Result<()>
+where
+    F: FnMut(Difference) -> bool,
+{
+    let layout_a = scan::Layout::new(a)?;
+    let layout_b = scan::Layout::new(b)?;
+    let (items_a, items_b) = (layout_a.items(), layout_b.items());
+
+    // the objects to compare, with the step to them from their parent
+    let mut stack = vec![(0, 0, None)];
+    let mut path = Path::default();
+    while let Some((index_a, index_b, element)) = stack.pop() {
+        let (item_a, item_b) = (&items_a[index_a], &items_b[index_b]);
+        path.0.truncate(item_a.depth.saturating_sub(1));
+        if let Some(element) = element {
+            path.push(element);
+        }
+        let (pos_a, type_a, len_a, sz_a) = (item_a.pos, item_a.cbor_type, item_a.len, item_a.sz);
+        let (pos_b, type_b, len_b, sz_b) = (item_b.pos, item_b.cbor_type, item_b.len, item_b.sz);
+
+        let kind = if type_a != type_b {
+            Some(DifferenceKind::Type(type_a, type_b))
+        } else {
+            match type_a {
+                Type::UnsignedInteger | Type::NegativeInteger | Type::Tag => {
+                    if type_a == Type::Tag {
+                        stack.push((index_a + 1, index_b + 1, Some(PathElement::Tagged)));
+                    }
+                    if len_a != len_b {
+                        Some(DifferenceKind::Value)
+                    } else {
+                        header_size(sz_a, sz_b)
+                    }
+                }
+                Type::Bytes | Type::Text => {
+                    let (content_a, chunks_a) = string(a, pos_a)?;
+                    let (content_b, chunks_b) = string(b, pos_b)?;
+                    if content_a != content_b {
+                        Some(DifferenceKind::Value)
+                    } else if len_a.indefinite() != len_b.indefinite() {
+                        Some(DifferenceKind::Indefinite(
+                            len_a.indefinite(),
+                            len_b.indefinite(),
+                        ))
+                    } else if chunks_a != chunks_b {
+                        Some(DifferenceKind::Chunks)
+                    } else {
+                        header_size(sz_a, sz_b)
+                    }
+                }
+                Type::Array | Type::Map => {
+                    let children_a = layout_a.children(index_a).collect::<Vec<_>>();
+                    let children_b = layout_b.children(index_b).collect::<Vec<_>>();
+                    for (i, (child_a, child_b)) in
+                        children_a.iter().zip(children_b.iter()).enumerate().rev()
+                    {
+                        let element = match (type_a, i % 2) {
+                            (Type::Array, _) => PathElement::Index(i as u64),
+                            (_, 0) => PathElement::Key(i as u64 / 2),
+                            _ => PathElement::Value(i as u64 / 2),
+                        };
+                        stack.push((*child_a, *child_b, Some(element)));
+                    }
+                    let (count_a, count_b) = match type_a {
+                        Type::Array => (children_a.len() as u64, children_b.len() as u64),
+                        _ => (children_a.len() as u64 / 2, children_b.len() as u64 / 2),
+                    };
+                    if count_a != count_b {
+                        Some(DifferenceKind::Length(count_a, count_b))
+                    } else if len_a.indefinite() != len_b.indefinite() {
+                        Some(DifferenceKind::Indefinite(
+                            len_a.indefinite(),
+                            len_b.indefinite(),
+                        ))
+                    } else {
+                        header_size(sz_a, sz_b)
+                    }
+                }
+                Type::Special => special(len_a, sz_a, len_b, sz_b),
+            }
+        };
+
+        if let Some(kind) = kind {
+            let difference = Difference {
+                path: path.clone(),
+                offsets: (pos_a, pos_b),
+                kind,
+            };
+            if !f(difference) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn header_size(sz_a: usize, sz_b: usize) -> Option<DifferenceKind> {
+    if sz_a != sz_b {
+        Some(DifferenceKind::HeaderSize(sz_a, sz_b))
+    } else {
+        None
+    }
+}
+
+fn special(len_a: Len, sz_a: usize, len_b: Len, sz_b: usize) -> Option<DifferenceKind> {
+    let is_float = |sz| sz > 2;
+    let value_a = match len_a {
+        Len::Len(v) => v,
+        Len::Indefinite => return None,
+    };
+    let value_b = match len_b {
+        Len::Len(v) => v,
+        Len::Indefinite => return None,
+    };
+    if is_float(sz_a) && is_float(sz_b) {
+        let float_a = float::decode(value_a, sz_a - 1);
+        let float_b = float::decode(value_b, sz_b - 1);
+        let same = float_a.to_bits() == float_b.to_bits() || (float_a.is_nan() && float_b.is_nan());
+        if !same {
+            return Some(DifferenceKind::Value);
+        }
+    } else if is_float(sz_a) != is_float(sz_b) || value_a != value_b {
+        return Some(DifferenceKind::Value);
+    }
+    header_size(sz_a, sz_b)
+}
+
+// content of the string at `pos` and the length of its chunks (if of
+// indefinite length)
+fn string(bytes: &[u8], pos: usize) -> Result<(Vec<u8>, Option<Vec<u64>>)> {
+    let (_, len, sz) = scan::parse_header(&bytes[pos..])?;
+    let mut pos = pos + sz;
+    match len {
+        Len::Len(len) => Ok((bytes[pos..pos + len as usize].to_vec(), None)),
+        Len::Indefinite => {
+            let mut content = Vec::new();
+            let mut chunks = Vec::new();
+            while bytes[pos] != BREAK {
+                let (_, len, sz) = scan::parse_header(&bytes[pos..])?;
+                let len = match len {
+                    Len::Len(len) => len,
+                    Len::Indefinite => 0,
+                };
+                pos += sz;
+                content.extend_from_slice(&bytes[pos..pos + len as usize]);
+                chunks.push(len);
+                pos += len as usize;
+            }
+            Ok((content, Some(chunks)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical() {
+        let a = [0xa2, 0x01, 0x82, 0x01, 0x02, 0x02, 0xd8, 0x18, 0x41, 0x00];
+        assert!(diff_encoded(&a, &a).unwrap().is_empty());
+        assert_eq!(first_difference(&a, &a).unwrap(), None);
+    }
+
+    #[test]
+    fn types_and_values() {
+        // {1: [1, 2], 2: 24(h'00')} and {1: [1, "b"], 3: 24(h'01')}
+        let a = [0xa2, 0x01, 0x82, 0x01, 0x02, 0x02, 0xd8, 0x18, 0x41, 0x00];
+        let b = [
+            0xa2, 0x01, 0x82, 0x01, 0x61, 0x62, 0x03, 0xd8, 0x18, 0x41, 0x01,
+        ];
+        let differences = diff_encoded(&a, &b).unwrap();
+        let found: Vec<_> = differences
+            .iter()
+            .map(|d| (d.path.to_string(), d.offsets, d.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "${0}[1]".to_owned(),
+                    (4, 4),
+                    DifferenceKind::Type(Type::UnsignedInteger, Type::Text)
+                ),
+                ("${1}.key".to_owned(), (5, 6), DifferenceKind::Value),
+                ("${1}.tagged".to_owned(), (8, 9), DifferenceKind::Value),
+            ]
+        );
+        assert_eq!(
+            first_difference(&a, &b).unwrap(),
+            Some(differences[0].clone())
+        );
+    }
+
+    #[test]
+    fn encodings() {
+        // [h'0102', [1], 1.5] and [(_ h'01', h'02'), [_ 1], 1.5 (as a double)]
+        let a = [0x83, 0x42, 0x01, 0x02, 0x81, 0x01, 0xf9, 0x3e, 0x00];
+        let b = [
+            0x83, 0x5f, 0x41, 0x01, 0x41, 0x02, 0xff, 0x9f, 0x01, 0xff, 0xfb, 0x3f, 0xf8, 0, 0, 0,
+            0, 0, 0,
+        ];
+        let kinds: Vec<_> = diff_encoded(&a, &b)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DifferenceKind::Indefinite(false, true),
+                DifferenceKind::Indefinite(false, true),
+                DifferenceKind::HeaderSize(3, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn lengths() {
+        let a = [0x82, 0x01, 0x02];
+        let b = [0x81, 0x01];
+        let differences = diff_encoded(&a, &b).unwrap();
+        assert_eq!(differences.len(), 1);
+        assert_eq!(differences[0].kind, DifferenceKind::Length(2, 1));
+        assert!(differences[0].to_string().contains("lengths differ"));
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(diff_encoded(&[0x82, 0x01], &[0x01]).is_err());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let mut a = vec![0x81; 100_000];
+        let mut b = a.clone();
+        a.push(0x01);
+        b.push(0x02);
+        let difference = first_difference(&a, &b).unwrap().unwrap();
+        assert_eq!(difference.path.elements().len(), 100_000);
+        assert_eq!(difference.offsets, (100_000, 100_000));
+        assert_eq!(difference.kind, DifferenceKind::Value);
+    }
+}
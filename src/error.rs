@@ -1,6 +1,10 @@
 use std::{error, fmt};
 
+use cancel;
 use len;
+use lint::ViolationKind;
+#[cfg(not(feature = "std"))]
+use prelude::*;
 use types::Type;
 
 /// all expected error for cbor parsing and serialising
@@ -14,6 +18,16 @@ pub enum Error {
     ExpectedI16,
     ExpectedI32,
     ExpectedI64,
+    ExpectedU128,
+    ExpectedI128,
+    /// a double precision float not representable exactly as a single.
+    ExpectedF32,
+    /// a special value other than a float, or a break.
+    ExpectedFloat,
+    /// a special value other than a boolean, or with
+    /// [`set_lenient`](../de/struct.Deserializer.html#method.set_lenient),
+    /// an integer other than 0 and 1.
+    ExpectedBool,
     /// not enough data, the first element is the actual size, the second is
     /// the expected size.
     NotEnough(usize, usize),
@@ -24,12 +38,58 @@ pub enum Error {
     /// this may happens when deserialising a [`Deserializer`](../de/struct.Deserializer.html);
     UnknownLenType(u8),
     IndefiniteLenNotSupported(Type),
+    /// an indefinite length object was found by a
+    /// [`Deserializer`](../de/struct.Deserializer.html) accepting only
+    /// definite lengths.
+    IndefiniteLenForbidden(Type),
     WrongLen(u64, len::Len, &'static str),
     InvalidTextError(::std::string::FromUtf8Error),
     CannotParse(Type, Vec<u8>),
     IoError(::std::io::Error),
     TrailingData,
     InvalidIndefiniteString,
+    /// a `Special::Break` was found outside of an indefinite length object
+    /// or where a map value was expected.
+    UnexpectedBreak,
+    /// simple values below 32 must be encoded in the initial byte.
+    InvalidSimpleValue(u8),
+    /// the [`Query`](../query/struct.Query.html) expression is malformed:
+    /// the expression and the byte offset of the problem.
+    InvalidQuery(String, usize),
+    /// the CDDL schema given to [`cddl::generate`](../cddl/fn.generate.html)
+    /// is malformed or outside of the supported subset: the unexpected
+    /// token and its line.
+    InvalidCddl(String, usize),
+    /// the value or length passed to one of the `Serializer::write_*_sz`
+    /// functions does not fit in the requested [`Sz`](../enum.Sz.html).
+    InvalidLenPassed(len::Sz),
+    /// the same key appears twice in a map where keys must be unique.
+    DuplicateKey,
+    /// the input nests more arrays, maps and tags than allowed by
+    /// [`set_max_depth`](../de/struct.Deserializer.html#method.set_max_depth).
+    MaxDepthExceeded,
+    /// decoding was aborted by a
+    /// [`CancellableReader`](../cancel/struct.CancellableReader.html).
+    Cancelled,
+    /// an IP address or prefix (tags 52 and 54) is malformed, with the
+    /// reason.
+    InvalidIpAddress(&'static str),
+    /// a date/time (tags 0 and 1) is malformed or out of range, with the
+    /// reason.
+    InvalidDateTime(&'static str),
+    /// an integer not matching any variant of an
+    /// [`IntEnum`](../int_enum/trait.IntEnum.html): the name of the enum
+    /// and the integer.
+    UnknownEnumValue(&'static str, u64),
+    /// a map decoded by a derived `Deserialize` misses an entry: the name
+    /// of the field and of the type.
+    MissingField(&'static str, &'static str),
+    /// an encoding requirement of the canonical mode of the `Serializer`
+    /// or of the `Deserializer` is not met.
+    NonCanonical(ViolationKind),
+    /// the error and the offset of the input where it happened, see
+    /// [`set_error_offsets`](../de/struct.Deserializer.html#method.set_error_offsets).
+    At(u64, Box<Error>),
 
     CustomError(String),
 }
@@ -40,7 +100,13 @@ impl From<::std::string::FromUtf8Error> for Error {
 }
 impl From<::std::io::Error> for Error {
     fn from(e: ::std::io::Error) -> Self {
-        Error::IoError(e)
+        if e.get_ref()
+            .is_some_and(|inner| inner.is::<cancel::Cancelled>())
+        {
+            Error::Cancelled
+        } else {
+            Error::IoError(e)
+        }
     }
 }
 
@@ -56,6 +122,11 @@ impl fmt::Display for Error {
             ExpectedI16 => write!(f, "Invalid cbor: expected 16bit long negative integer"),
             ExpectedI32 => write!(f, "Invalid cbor: expected 32bit long negative integer"),
             ExpectedI64 => write!(f, "Invalid cbor: expected 64bit long negative integer"),
+            ExpectedU128 => write!(f, "Invalid cbor: expected 128bit long unsigned integer"),
+            ExpectedI128 => write!(f, "Invalid cbor: expected 128bit long integer"),
+            ExpectedF32 => write!(f, "Invalid cbor: expected single precision float"),
+            ExpectedFloat => write!(f, "Invalid cbor: expected float"),
+            ExpectedBool => write!(f, "Invalid cbor: expected boolean"),
             NotEnough(got, exp) => write!(
                 f,
                 "Invalid cbor: not enough bytes, expect {} bytes but received {} bytes.",
@@ -75,6 +146,11 @@ impl fmt::Display for Error {
                 "Invalid cbor: indefinite length not supported for cbor object of type `{:?}'.",
                 t
             ),
+            IndefiniteLenForbidden(t) => write!(
+                f,
+                "Invalid cbor: indefinite length forbidden for cbor object of type `{:?}'.",
+                t
+            ),
             WrongLen(expected_len, actual_len, error_location) => write!(
                 f,
                 "Invalid cbor: expected tuple '{}' of length {} but got length {:?}.",
@@ -91,6 +167,34 @@ impl fmt::Display for Error {
             IoError(_io_error) => write!(f, "Invalid cbor: I/O error"),
             TrailingData => write!(f, "Unexpected trailing data in CBOR"),
             InvalidIndefiniteString => write!(f, "Invalid cbor: Invalid indefinite string format"),
+            UnexpectedBreak => write!(f, "Invalid cbor: unexpected break"),
+            InvalidSimpleValue(v) => {
+                write!(f, "Invalid cbor: simple value {} encoded on 2 bytes", v)
+            }
+            InvalidQuery(query, pos) => {
+                write!(f, "Invalid query `{}' at position {}", query, pos)
+            }
+            InvalidCddl(token, line) => {
+                write!(f, "Invalid CDDL: unexpected `{}' at line {}", token, line)
+            }
+            InvalidLenPassed(sz) => write!(f, "Invalid length passed: does not fit in {:?}", sz),
+            DuplicateKey => write!(f, "Invalid cbor: duplicate map key"),
+            MaxDepthExceeded => write!(f, "Invalid cbor: maximum nesting depth exceeded"),
+            Cancelled => write!(f, "Decoding cancelled"),
+            InvalidIpAddress(reason) => write!(f, "Invalid cbor: IP address, {}", reason),
+            InvalidDateTime(reason) => write!(f, "Invalid cbor: date/time, {}", reason),
+            UnknownEnumValue(name, value) => {
+                write!(
+                    f,
+                    "Invalid cbor: unknown value {} for enum `{}'",
+                    value, name
+                )
+            }
+            MissingField(field, name) => {
+                write!(f, "Invalid cbor: missing field `{}' in `{}'", field, name)
+            }
+            NonCanonical(kind) => write!(f, "Invalid cbor: not canonical, {}", kind),
+            At(offset, error) => write!(f, "at offset {}: {}", offset, error),
             CustomError(err) => write!(f, "Invalid cbor: {}", err),
         }
     }
@@ -101,7 +205,121 @@ impl error::Error for Error {
         match self {
             Error::IoError(ref error) => Some(error),
             Error::InvalidTextError(ref error) => Some(error),
+            Error::At(_, ref error) => Some(error.as_ref()),
             _ => None,
         }
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Error {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        use Error::*;
+        match self {
+            ExpectedU8 => defmt::write!(f, "Invalid cbor: expected 8bit long unsigned integer"),
+            ExpectedU16 => defmt::write!(f, "Invalid cbor: expected 16bit long unsigned integer"),
+            ExpectedU32 => defmt::write!(f, "Invalid cbor: expected 32bit long unsigned integer"),
+            ExpectedU64 => defmt::write!(f, "Invalid cbor: expected 64bit long unsigned integer"),
+            ExpectedI8 => defmt::write!(f, "Invalid cbor: expected 8bit long negative integer"),
+            ExpectedI16 => defmt::write!(f, "Invalid cbor: expected 16bit long negative integer"),
+            ExpectedI32 => defmt::write!(f, "Invalid cbor: expected 32bit long negative integer"),
+            ExpectedI64 => defmt::write!(f, "Invalid cbor: expected 64bit long negative integer"),
+            ExpectedU128 => defmt::write!(f, "Invalid cbor: expected 128bit long unsigned integer"),
+            ExpectedI128 => defmt::write!(f, "Invalid cbor: expected 128bit long integer"),
+            ExpectedF32 => defmt::write!(f, "Invalid cbor: expected single precision float"),
+            ExpectedFloat => defmt::write!(f, "Invalid cbor: expected float"),
+            ExpectedBool => defmt::write!(f, "Invalid cbor: expected boolean"),
+            NotEnough(got, exp) => defmt::write!(
+                f,
+                "Invalid cbor: not enough bytes, expect {} bytes but received {} bytes.",
+                exp,
+                got
+            ),
+            Expected(exp, got) => defmt::write!(
+                f,
+                "Invalid cbor: not the right type, expected `{}' byte received `{}'.",
+                exp,
+                got
+            ),
+            ExpectedSetTag => defmt::write!(f, "Invalid cbor: expected set tag"),
+            UnknownLenType(byte) => {
+                defmt::write!(f, "Invalid cbor: not the right sub type: {=u8:#07b}", byte)
+            }
+            IndefiniteLenNotSupported(t) => defmt::write!(
+                f,
+                "Invalid cbor: indefinite length not supported for cbor object of type `{}'.",
+                t
+            ),
+            IndefiniteLenForbidden(t) => defmt::write!(
+                f,
+                "Invalid cbor: indefinite length forbidden for cbor object of type `{}'.",
+                t
+            ),
+            WrongLen(expected_len, actual_len, error_location) => defmt::write!(
+                f,
+                "Invalid cbor: expected tuple '{=str}' of length {} but got length {}.",
+                error_location,
+                expected_len,
+                actual_len
+            ),
+            InvalidTextError(_) => {
+                defmt::write!(f, "Invalid cbor: expected a valid utf8 string text.")
+            }
+            CannotParse(t, bytes) => defmt::write!(
+                f,
+                "Invalid cbor: cannot parse the cbor object `{}' with the following bytes {=[u8]}",
+                t,
+                bytes
+            ),
+            IoError(_) => defmt::write!(f, "Invalid cbor: I/O error"),
+            TrailingData => defmt::write!(f, "Unexpected trailing data in CBOR"),
+            InvalidIndefiniteString => {
+                defmt::write!(f, "Invalid cbor: Invalid indefinite string format")
+            }
+            UnexpectedBreak => defmt::write!(f, "Invalid cbor: unexpected break"),
+            InvalidSimpleValue(v) => {
+                defmt::write!(f, "Invalid cbor: simple value {} encoded on 2 bytes", v)
+            }
+            InvalidQuery(query, pos) => {
+                defmt::write!(f, "Invalid query `{=str}' at position {}", query, pos)
+            }
+            InvalidCddl(token, line) => {
+                defmt::write!(
+                    f,
+                    "Invalid CDDL: unexpected `{=str}' at line {}",
+                    token,
+                    line
+                )
+            }
+            InvalidLenPassed(sz) => {
+                defmt::write!(f, "Invalid length passed: does not fit in {}", sz)
+            }
+            DuplicateKey => defmt::write!(f, "Invalid cbor: duplicate map key"),
+            MaxDepthExceeded => defmt::write!(f, "Invalid cbor: maximum nesting depth exceeded"),
+            Cancelled => defmt::write!(f, "Decoding cancelled"),
+            InvalidIpAddress(reason) => {
+                defmt::write!(f, "Invalid cbor: IP address, {=str}", reason)
+            }
+            InvalidDateTime(reason) => {
+                defmt::write!(f, "Invalid cbor: date/time, {=str}", reason)
+            }
+            UnknownEnumValue(name, value) => {
+                defmt::write!(
+                    f,
+                    "Invalid cbor: unknown value {} for enum `{=str}'",
+                    value,
+                    name
+                )
+            }
+            MissingField(field, name) => defmt::write!(
+                f,
+                "Invalid cbor: missing field `{=str}' in `{=str}'",
+                field,
+                name
+            ),
+            NonCanonical(kind) => defmt::write!(f, "Invalid cbor: not canonical, {}", kind),
+            At(offset, error) => defmt::write!(f, "at offset {}: {}", offset, error.as_ref()),
+            CustomError(err) => defmt::write!(f, "Invalid cbor: {=str}", err),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! IEEE 754 helpers for the CBOR floating point specials
+
+// `f64::powi` needs the standard library: build the power of two from its
+// bits, for the exponents of the normal doubles
+fn exp2(exp: i32) -> f64 {
+    f64::from_bits(((exp + 1023) as u64) << 52)
+}
+
+/// convert a half precision float (binary16) into a double, as described
+/// in RFC 8949 Appendix D.
+pub(crate) fn f16_to_f64(half: u16) -> f64 {
+    let exp = (half >> 10) & 0x1f;
+    let mant = (half & 0x3ff) as f64;
+    let value = match exp {
+        0 => mant * exp2(-24),
+        31 if mant == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mant + 1024.0) * exp2(exp as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// convert a double into a half precision float, if it can be represented
+/// exactly. All NaNs map to the canonical `0x7e00`.
+pub(crate) fn f64_to_f16_exact(value: f64) -> Option<u16> {
+    if value.is_nan() {
+        return Some(0x7e00);
+    }
+    let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+    let abs = value.abs();
+    let half = if abs.is_infinite() {
+        0x7c00
+    } else if abs < exp2(-14) {
+        // zero or subnormal: multiple of 2^-24
+        let mant = abs * exp2(24);
+        if mant as u32 as f64 != mant {
+            return None;
+        }
+        mant as u16
+    } else {
+        let bits = abs.to_bits();
+        let exp = ((bits >> 52) & 0x7ff) as i32 - 1023;
+        let mant = bits & 0x000f_ffff_ffff_ffff;
+        if exp > 15 || mant & 0x03ff_ffff_ffff != 0 {
+            return None;
+        }
+        (((exp + 15) as u16) << 10) | (mant >> 42) as u16
+    };
+    Some(sign | half)
+}
+
+/// convert a double into a single precision float, if it can be
+/// represented exactly. All NaNs map to the canonical `0x7fc00000`.
+pub(crate) fn f64_to_f32_exact(value: f64) -> Option<f32> {
+    if value.is_nan() {
+        Some(f32::from_bits(0x7fc0_0000))
+    } else if (value as f32) as f64 == value {
+        Some(value as f32)
+    } else {
+        None
+    }
+}
+
+/// decode the `value` of a floating point special encoded on `sz` bytes
+/// (2, 4 or 8).
+pub(crate) fn decode(value: u64, sz: usize) -> f64 {
+    match sz {
+        2 => f16_to_f64(value as u16),
+        4 => f32::from_bits(value as u32) as f64,
+        _ => f64::from_bits(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn half_precision() {
+        // examples from RFC 8949 Appendix A
+        assert_eq!(f16_to_f64(0x0000), 0.0);
+        assert!(f16_to_f64(0x8000).is_sign_negative());
+        assert_eq!(f16_to_f64(0x3c00), 1.0);
+        assert_eq!(f16_to_f64(0x3e00), 1.5);
+        assert_eq!(f16_to_f64(0x7bff), 65504.0);
+        assert_eq!(f16_to_f64(0x0001), 5.960464477539063e-8);
+        assert_eq!(f16_to_f64(0x0400), 0.00006103515625);
+        assert_eq!(f16_to_f64(0xc400), -4.0);
+        assert_eq!(f16_to_f64(0x7c00), f64::INFINITY);
+        assert_eq!(f16_to_f64(0xfc00), f64::NEG_INFINITY);
+        assert!(f16_to_f64(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn exact_half_precision() {
+        for half in [
+            0x0000, 0x8000, 0x0001, 0x03ff, 0x0400, 0x3c00, 0x3e00, 0x7bff, 0xc400, 0x7c00, 0xfc00,
+        ]
+        .iter()
+        {
+            assert_eq!(f64_to_f16_exact(f16_to_f64(*half)), Some(*half));
+        }
+        assert_eq!(f64_to_f16_exact(f64::NAN), Some(0x7e00));
+        assert_eq!(f64_to_f16_exact(1.1), None);
+        assert_eq!(f64_to_f16_exact(65536.0), None);
+        assert_eq!(f64_to_f16_exact(exp2(-25)), None);
+        assert_eq!(f64_to_f32_exact(100000.0), Some(100000.0));
+        assert_eq!(f64_to_f32_exact(1.1), None);
+    }
+
+    #[test]
+    fn single_and_double_precision() {
+        assert_eq!(decode(0x47c3_5000, 4), 100000.0);
+        assert_eq!(decode(0x3ff1_9999_9999_999a, 8), 1.1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Hash the bytes consumed by a [`Deserializer`](../de/struct.Deserializer.html)
+//!
+//! Protocols signing or identifying structures by the hash of their
+//! encoding need the hash of the exact bytes of a sub-object. Wrapping the
+//! reader in a [`HashingReader`] computes this hash while decoding, in a
+//! single pass over the input.
+//!
+//! Only available with the `digest` feature.
+//!
+//! [`HashingReader`]: ./struct.HashingReader.html
+//!
+//! ```
+//! # extern crate cbor_event;
+//! # extern crate sha2;
+//! use cbor_event::{de::Deserializer, hashing::HashingReader};
+//! use sha2::{Digest, Sha256};
+//!
+//! # fn main() {
+//! let bytes = [0x82, 0x43, 0x01, 0x02, 0x03, 0x18, 0x2a];
+//! let mut raw = Deserializer::from(HashingReader::<_, Sha256>::new(&bytes[..]));
+//!
+//! raw.array().unwrap();
+//! raw.as_mut_ref().begin();
+//! let body = raw.bytes().unwrap();
+//! let hash = raw.as_mut_ref().end().unwrap();
+//!
+//! assert_eq!(hash, Sha256::digest(&bytes[1..5]));
+//! # }
+//! ```
+
+use digest::{Digest, Output};
+use std::io::{self, BufRead, Read};
+
+/// reader adapter hashing the bytes consumed between a call to
+/// [`begin`](#method.begin) and a call to [`end`](#method.end).
+pub struct HashingReader<R, D> {
+    reader: R,
+    hasher: Option<D>,
+}
+impl<R, D: Digest> HashingReader<R, D> {
+    pub fn new(reader: R) -> Self {
+        HashingReader {
+            reader,
+            hasher: None,
+        }
+    }
+
+    /// start hashing the consumed bytes. If a hash was already in
+    /// progress it is discarded.
+    pub fn begin(&mut self) {
+        self.hasher = Some(D::new());
+    }
+
+    /// stop hashing and return the hash of the bytes consumed since the
+    /// last call to [`begin`](#method.begin). Returns `None` if no hash was
+    /// in progress.
+    pub fn end(&mut self) -> Option<Output<D>> {
+        self.hasher.take().map(Digest::finalize)
+    }
+
+    /// returns `true` if a hash is in progress
+    pub fn is_hashing(&self) -> bool {
+        self.hasher.is_some()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    #[inline]
+    fn update(&mut self, bytes: &[u8]) {
+        if let Some(ref mut hasher) = self.hasher {
+            hasher.update(bytes)
+        }
+    }
+}
+impl<R: Read, D: Digest> Read for HashingReader<R, D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.update(&buf[..read]);
+        Ok(read)
+    }
+}
+impl<R: BufRead, D: Digest> BufRead for HashingReader<R, D> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(ref mut hasher) = self.hasher {
+            // the consumed bytes were returned by the previous `fill_buf`,
+            // they are still buffered so this does not perform any I/O.
+            if let Ok(buf) = self.reader.fill_buf() {
+                hasher.update(&buf[..amt.min(buf.len())]);
+            }
+        }
+        self.reader.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use se::Serializer;
+    use sha2::Sha256;
+    use std::io::BufReader;
+
+    #[test]
+    fn hash_sub_object() {
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(3))
+            .unwrap()
+            .write_text("header")
+            .unwrap()
+            .write_bytes(vec![0xaa; 1000])
+            .unwrap()
+            .write_unsigned_integer(0x1234)
+            .unwrap();
+        let bytes = se.finalize();
+
+        // small internal buffer so the hashed object spans several refills
+        let reader = BufReader::with_capacity(7, &bytes[..]);
+        let mut raw = Deserializer::from(HashingReader::<_, Sha256>::new(reader));
+        raw.array().unwrap();
+        raw.text().unwrap();
+        raw.as_mut_ref().begin();
+        raw.bytes().unwrap();
+        let hash = raw.as_mut_ref().end().unwrap();
+        assert!(!raw.as_ref().is_hashing());
+        assert_eq!(raw.unsigned_integer().unwrap(), 0x1234);
+
+        assert_eq!(hash, Sha256::digest(&bytes[8..8 + 1003]));
+    }
+}
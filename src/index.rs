@@ -0,0 +1,176 @@
+//! Random access to the entries of large encoded maps
+//!
+//! Building a [`MapIndex`] walks the encoded map once, skipping the values,
+//! and records where the value of every key starts. The values can then be
+//! decoded individually, in any order, without scanning the map again.
+//!
+//! [`MapIndex`]: ./struct.MapIndex.html
+//!
+//! ```
+//! use cbor_event::{index::MapIndex, ObjectKey};
+//!
+//! // {"a": [1, 2], "b": "text", 3: h'00'}
+//! let bytes = [
+//!     0xa3, 0x61, 0x61, 0x82, 0x01, 0x02, 0x61, 0x62, 0x64, 0x74, 0x65, 0x78, 0x74, 0x03, 0x41,
+//!     0x00,
+//! ];
+//!
+//! let index = MapIndex::new(&bytes).unwrap();
+//! assert_eq!(index.len(), 3);
+//!
+//! let b: Option<String> = index.get(&ObjectKey::Text("b".to_owned())).unwrap();
+//! assert_eq!(b.as_deref(), Some("text"));
+//! assert_eq!(index.get_raw(&ObjectKey::Integer(3)).unwrap(), Some(&[0x41, 0x00][..]));
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+use len::Len;
+use result::Result;
+use scan;
+use std::collections::BTreeMap;
+use types::Type;
+use value::ObjectKey;
+
+const BREAK: u8 = 0xff;
+
+/// index of the entries of an encoded map, see the
+/// [module documentation](./index.html).
+#[derive(Debug, Clone)]
+pub struct MapIndex<'a> {
+    bytes: &'a [u8],
+    // offset of the value of each key
+    offsets: BTreeMap<ObjectKey, usize>,
+}
+impl<'a> MapIndex<'a> {
+    /// index the map encoded in `bytes`.
+    ///
+    /// Only the integer, bytes and text keys are indexed, the entries with
+    /// other keys are skipped. If a key is present more than once, its
+    /// last value is indexed.
+    ///
+    /// Fails if `bytes` is not exactly one well-formed map.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let (len, mut pos) = match scan::parse_header(bytes)? {
+            (Type::Map, len, sz) => (len, sz),
+            (t, _, _) => return Err(Error::Expected(Type::Map, t)),
+        };
+
+        let mut offsets = BTreeMap::new();
+        let mut count = 0;
+        loop {
+            match len {
+                Len::Len(len) if count >= len => break,
+                Len::Indefinite if bytes.get(pos) == Some(&BREAK) => {
+                    pos += 1;
+                    break;
+                }
+                _ => {}
+            }
+            let key_len = scan::item_len(&bytes[pos..])?;
+            let key = &bytes[pos..pos + key_len];
+            pos += key_len;
+            let value_len = scan::item_len(&bytes[pos..])?;
+            match scan::parse_header(key)?.0 {
+                Type::UnsignedInteger | Type::Bytes | Type::Text => {
+                    let key = Deserializer::from(key).deserialize_complete()?;
+                    offsets.insert(key, pos);
+                }
+                _ => {}
+            }
+            pos += value_len;
+            count += 1;
+        }
+        if pos != bytes.len() {
+            return Err(Error::TrailingData);
+        }
+        Ok(MapIndex { bytes, offsets })
+    }
+
+    /// number of indexed keys
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &ObjectKey) -> bool {
+        self.offsets.contains_key(key)
+    }
+
+    /// the indexed keys, in the order of `ObjectKey`
+    pub fn keys(&self) -> impl Iterator<Item = &ObjectKey> {
+        self.offsets.keys()
+    }
+
+    /// returns the encoded bytes of the value of `key`, `None` if the key
+    /// is not in the map.
+    pub fn get_raw(&self, key: &ObjectKey) -> Result<Option<&'a [u8]>> {
+        match self.offsets.get(key) {
+            None => Ok(None),
+            Some(&offset) => {
+                let len = scan::item_len(&self.bytes[offset..])?;
+                Ok(Some(&self.bytes[offset..offset + len]))
+            }
+        }
+    }
+
+    /// decode the value of `key`, `None` if the key is not in the map.
+    pub fn get<T: Deserialize>(&self, key: &ObjectKey) -> Result<Option<T>> {
+        match self.get_raw(key)? {
+            None => Ok(None),
+            Some(value) => Deserializer::from(value).deserialize_complete().map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use value::Value;
+
+    #[test]
+    fn large_map() {
+        let mut se = Serializer::new_vec();
+        se.write_map(Len::Indefinite).unwrap();
+        for i in 0..1000u64 {
+            se.write_unsigned_integer(i)
+                .unwrap()
+                .write_text(format!("value {}", i))
+                .unwrap();
+        }
+        // duplicate key and key that cannot be indexed
+        se.write_unsigned_integer(10)
+            .unwrap()
+            .write_special(::Special::Null)
+            .unwrap()
+            .write_array(Len::Len(0))
+            .unwrap()
+            .write_unsigned_integer(0)
+            .unwrap()
+            .write_special(::Special::Break)
+            .unwrap();
+        let bytes = se.finalize();
+
+        let index = MapIndex::new(&bytes).unwrap();
+        assert_eq!(index.len(), 1000);
+        let value: Option<String> = index.get(&ObjectKey::Integer(999)).unwrap();
+        assert_eq!(value.as_deref(), Some("value 999"));
+        let value: Option<Value> = index.get(&ObjectKey::Integer(10)).unwrap();
+        assert_eq!(value, Some(Value::Special(::Special::Null)));
+        assert!(!index.contains_key(&ObjectKey::Integer(1000)));
+        assert_eq!(index.get_raw(&ObjectKey::Integer(1000)).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(MapIndex::new(&[0x80]).is_err());
+        assert!(MapIndex::new(&[0xa1, 0x01]).is_err());
+        assert!(MapIndex::new(&[0xbf, 0x01, 0x02]).is_err());
+        assert!(MapIndex::new(&[0xa0, 0x00]).is_err());
+        assert!(MapIndex::new(&[0xa0]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+//! C-like enums encoded as unsigned integers
+//!
+//! The [`int_enum!`] macro declares an enum whose variants are encoded as
+//! the given unsigned integers, implementing [`IntEnum`], `Serialize` and
+//! `Deserialize` for it. Decoding an integer not matching any variant
+//! fails with `Error::UnknownEnumValue`, unless the enum is declared with
+//! a fallback variant: `#[unknown(Variant)]`, as its first attribute, adds
+//! a `Variant(u64)` variant keeping the unknown integers.
+//!
+//! [`int_enum!`]: ../macro.int_enum.html
+//! [`IntEnum`]: ./trait.IntEnum.html
+//!
+//! ```
+//! #[macro_use]
+//! extern crate cbor_event;
+//!
+//! use cbor_event::{de::Deserializer, se::Serializer};
+//!
+//! int_enum! {
+//!     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//!     pub enum Network {
+//!         Testnet = 0,
+//!         Mainnet = 1,
+//!     }
+//! }
+//!
+//! int_enum! {
+//!     #[unknown(Other)]
+//!     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+//!     pub enum Era {
+//!         Byron = 0,
+//!         Shelley = 2,
+//!     }
+//! }
+//!
+//! # fn main() {
+//! let mut se = Serializer::new_vec();
+//! se.serialize(&Network::Mainnet).unwrap().serialize(&Era::Other(7)).unwrap();
+//! let bytes = se.finalize();
+//! assert_eq!(bytes, [0x01, 0x07]);
+//!
+//! let mut raw = Deserializer::from(&bytes[..]);
+//! assert_eq!(raw.deserialize::<Network>().unwrap(), Network::Mainnet);
+//! assert_eq!(raw.deserialize::<Era>().unwrap(), Era::Other(7));
+//!
+//! assert!(Deserializer::from(&[0x07][..]).deserialize::<Network>().is_err());
+//! # }
+//! ```
+
+use de::Deserializer;
+use error::Error;
+use result::Result;
+use std::io::BufRead;
+
+/// an enum encoded as an unsigned integer, usually implemented with
+/// [`int_enum!`](../macro.int_enum.html).
+pub trait IntEnum: Sized {
+    /// name of the enum, in the errors
+    const NAME: &'static str;
+
+    /// the integer of the variant
+    fn to_u64(&self) -> u64;
+
+    /// the variant of the given integer, `None` if there is none
+    fn from_u64(value: u64) -> Option<Self>;
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// read an unsigned integer as a variant of `T`. Fails with
+    /// `Error::UnknownEnumValue` if it matches none.
+    pub fn int_enum<T: IntEnum>(&mut self) -> Result<T> {
+        let value = self.unsigned_integer()?;
+        T::from_u64(value).ok_or(Error::UnknownEnumValue(T::NAME, value))
+    }
+}
+
+/// declare an enum encoded as an unsigned integer, see the
+/// [`int_enum` module](./int_enum/index.html).
+///
+/// A fallback variant set with `#[unknown(Variant)]` holding the integer
+/// of another variant is encoded as this integer, it decodes to the other
+/// variant.
+#[macro_export]
+macro_rules! int_enum {
+    (
+        #[unknown($unknown:ident)]
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant,)+
+            /// an integer not matching any of the other variants
+            $unknown(u64),
+        }
+        impl $crate::int_enum::IntEnum for $name {
+            const NAME: &'static str = stringify!($name);
+
+            fn to_u64(&self) -> u64 {
+                match *self {
+                    $($name::$variant => $value,)+
+                    $name::$unknown(value) => value,
+                }
+            }
+
+            fn from_u64(value: u64) -> Option<Self> {
+                Some(match value {
+                    $(value if value == $value => $name::$variant,)+
+                    value => $name::$unknown(value),
+                })
+            }
+        }
+        $crate::int_enum!(@impls $name);
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:expr),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant,)+
+        }
+        impl $crate::int_enum::IntEnum for $name {
+            const NAME: &'static str = stringify!($name);
+
+            fn to_u64(&self) -> u64 {
+                match *self {
+                    $($name::$variant => $value,)+
+                }
+            }
+
+            fn from_u64(value: u64) -> Option<Self> {
+                match value {
+                    $(value if value == $value => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+        }
+        $crate::int_enum!(@impls $name);
+    };
+    (@impls $name:ident) => {
+        impl $crate::se::Serialize for $name {
+            fn serialize<'se, W: ::std::io::Write + Sized>(
+                &self,
+                serializer: &'se mut $crate::se::Serializer<W>,
+            ) -> $crate::Result<&'se mut $crate::se::Serializer<W>> {
+                serializer.write_unsigned_integer($crate::int_enum::IntEnum::to_u64(self))
+            }
+        }
+        impl $crate::de::Deserialize for $name {
+            fn deserialize<R: ::std::io::BufRead>(
+                raw: &mut $crate::de::Deserializer<R>,
+            ) -> $crate::Result<Self> {
+                raw.int_enum()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use de::Deserializer;
+    use error::Error;
+    use se::Serializer;
+
+    int_enum! {
+        #[derive(Debug, PartialEq)]
+        enum Color {
+            Red = 1,
+            /// variants can be documented
+            Green = 2,
+            Blue = 1 << 40,
+        }
+    }
+
+    int_enum! {
+        #[unknown(Unknown)]
+        #[derive(Debug, PartialEq)]
+        enum Shape {
+            Circle = 0,
+            Square = 4,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut se = Serializer::new_vec();
+        se.serialize(&Color::Blue)
+            .unwrap()
+            .serialize(&Shape::Square)
+            .unwrap()
+            .serialize(&Shape::Unknown(5))
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, [0x1b, 0, 0, 1, 0, 0, 0, 0, 0, 0x04, 0x05]);
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(raw.deserialize::<Color>().unwrap(), Color::Blue);
+        assert_eq!(raw.deserialize::<Shape>().unwrap(), Shape::Square);
+        assert_eq!(raw.deserialize::<Shape>().unwrap(), Shape::Unknown(5));
+    }
+
+    #[test]
+    fn unknown() {
+        match Deserializer::from(&[0x03][..]).deserialize::<Color>() {
+            Err(Error::UnknownEnumValue("Color", 3)) => {}
+            result => panic!("expected an unknown value error, got {:?}", result),
+        }
+        // not an unsigned integer
+        assert!(Deserializer::from(&[0x20][..])
+            .deserialize::<Shape>()
+            .is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! I/O traits of the [`Deserializer`] and of the [`Serializer`]
+//!
+//! With the `std` feature, enabled by default, these are the traits of
+//! `std::io`. Without it, on `#![no_std]` targets, minimal equivalents
+//! are defined here with the same names and signatures, implemented for
+//! byte slices and `Vec<u8>`. Implement [`BufRead`] or [`Write`] to decode
+//! from or encode into other buffers.
+//!
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//! [`Serializer`]: ../se/struct.Serializer.html
+//! [`BufRead`]: ./trait.BufRead.html
+//! [`Write`]: ./trait.Write.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, io::BufRead};
+//!
+//! fn first_integer<R: BufRead>(reader: R) -> cbor_event::Result<u64> {
+//!     Deserializer::from(reader).unsigned_integer()
+//! }
+//!
+//! assert_eq!(first_integer(&[0x18, 0x2a][..]).unwrap(), 42);
+//! ```
+
+#[cfg(feature = "std")]
+pub use std::io::{BufRead, Cursor, Error, ErrorKind, IoSlice, Read, Result, Take, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::{boxed::Box, vec::Vec};
+    use core::{cmp, error, fmt, ops::Deref, result};
+
+    pub type Result<T> = result::Result<T, Error>;
+
+    /// the kinds of I/O errors raised by this crate, a subset of the ones
+    /// of `std::io::ErrorKind`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        InvalidInput,
+        InvalidData,
+        Interrupted,
+        WriteZero,
+        UnexpectedEof,
+        Other,
+    }
+    impl fmt::Display for ErrorKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let description = match self {
+                ErrorKind::InvalidInput => "invalid input parameter",
+                ErrorKind::InvalidData => "invalid data",
+                ErrorKind::Interrupted => "operation interrupted",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::Other => "other error",
+            };
+            f.write_str(description)
+        }
+    }
+
+    /// an I/O error: its kind and, optionally, the error causing it.
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        error: Option<Box<dyn error::Error + Send + Sync>>,
+    }
+    impl Error {
+        pub fn new<E>(kind: ErrorKind, error: E) -> Self
+        where
+            E: Into<Box<dyn error::Error + Send + Sync>>,
+        {
+            Error {
+                kind,
+                error: Some(error.into()),
+            }
+        }
+
+        pub fn other<E>(error: E) -> Self
+        where
+            E: Into<Box<dyn error::Error + Send + Sync>>,
+        {
+            Error::new(ErrorKind::Other, error)
+        }
+
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+
+        pub fn get_ref(&self) -> Option<&(dyn error::Error + Send + Sync + 'static)> {
+            self.error.as_deref()
+        }
+
+        pub fn into_inner(self) -> Option<Box<dyn error::Error + Send + Sync>> {
+            self.error
+        }
+    }
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Error { kind, error: None }
+        }
+    }
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.error {
+                Some(ref error) => error.fmt(f),
+                None => self.kind.fmt(f),
+            }
+        }
+    }
+    impl error::Error for Error {
+        fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+            match self.error {
+                Some(ref error) => Some(error.as_ref()),
+                None => None,
+            }
+        }
+    }
+
+    pub trait Read {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf) {
+                    Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                    Ok(n) => buf = &mut buf[n..],
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let start = buf.len();
+            let mut chunk = [0; 256];
+            loop {
+                match self.read(&mut chunk) {
+                    Ok(0) => return Ok(buf.len() - start),
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        fn by_ref(&mut self) -> &mut Self
+        where
+            Self: Sized,
+        {
+            self
+        }
+
+        fn take(self, limit: u64) -> Take<Self>
+        where
+            Self: Sized,
+        {
+            Take { inner: self, limit }
+        }
+    }
+
+    pub trait BufRead: Read {
+        fn fill_buf(&mut self) -> Result<&[u8]>;
+        fn consume(&mut self, amt: usize);
+    }
+
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+            match bufs.iter().find(|buf| !buf.is_empty()) {
+                Some(buf) => self.write(buf),
+                None => self.write(&[]),
+            }
+        }
+
+        fn flush(&mut self) -> Result<()>;
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => buf = &buf[n..],
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+
+        fn by_ref(&mut self) -> &mut Self
+        where
+            Self: Sized,
+        {
+            self
+        }
+    }
+
+    /// a buffer of a vectored write.
+    #[derive(Clone, Copy, Debug)]
+    pub struct IoSlice<'a>(&'a [u8]);
+    impl<'a> IoSlice<'a> {
+        pub fn new(buf: &'a [u8]) -> Self {
+            IoSlice(buf)
+        }
+    }
+    impl<'a> Deref for IoSlice<'a> {
+        type Target = [u8];
+        fn deref(&self) -> &[u8] {
+            self.0
+        }
+    }
+
+    /// reader limited to a number of bytes, see `Read::take`.
+    #[derive(Debug)]
+    pub struct Take<R> {
+        inner: R,
+        limit: u64,
+    }
+    impl<R> Take<R> {
+        /// number of bytes that can still be read
+        pub fn limit(&self) -> u64 {
+            self.limit
+        }
+
+        pub fn into_inner(self) -> R {
+            self.inner
+        }
+
+        pub fn get_ref(&self) -> &R {
+            &self.inner
+        }
+    }
+    impl<R: Read> Read for Take<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let max = cmp::min(buf.len() as u64, self.limit) as usize;
+            let n = self.inner.read(&mut buf[..max])?;
+            self.limit -= n as u64;
+            Ok(n)
+        }
+    }
+    impl<R: BufRead> BufRead for Take<R> {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            if self.limit == 0 {
+                return Ok(&[]);
+            }
+            let buf = self.inner.fill_buf()?;
+            let max = cmp::min(buf.len() as u64, self.limit) as usize;
+            Ok(&buf[..max])
+        }
+        fn consume(&mut self, amt: usize) {
+            let amt = cmp::min(amt as u64, self.limit) as usize;
+            self.limit -= amt as u64;
+            self.inner.consume(amt)
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = cmp::min(buf.len(), self.len());
+            buf[..n].copy_from_slice(&self[..n]);
+            *self = &self[n..];
+            Ok(n)
+        }
+    }
+    impl BufRead for &[u8] {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            Ok(self)
+        }
+        fn consume(&mut self, amt: usize) {
+            *self = &self[amt..];
+        }
+    }
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+    impl<R: BufRead + ?Sized> BufRead for &mut R {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            (**self).fill_buf()
+        }
+        fn consume(&mut self, amt: usize) {
+            (**self).consume(amt)
+        }
+    }
+    impl<R: Read + ?Sized> Read for Box<R> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+    impl<R: BufRead + ?Sized> BufRead for Box<R> {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            (**self).fill_buf()
+        }
+        fn consume(&mut self, amt: usize) {
+            (**self).consume(amt)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+            let len = bufs.iter().map(|buf| buf.len()).sum();
+            self.reserve(len);
+            for buf in bufs {
+                self.extend_from_slice(buf);
+            }
+            Ok(len)
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = cmp::min(buf.len(), self.len());
+            let (head, tail) = core::mem::take(self).split_at_mut(n);
+            head.copy_from_slice(&buf[..n]);
+            *self = tail;
+            Ok(n)
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+            (**self).write_vectored(bufs)
+        }
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+    impl<W: Write + ?Sized> Write for Box<W> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+            (**self).write_vectored(bufs)
+        }
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+}
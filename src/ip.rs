@@ -0,0 +1,380 @@
+//! IP addresses and prefixes, tags 52 and 54 (RFC 9164)
+//!
+//! An IPv4 (tag 52) or IPv6 (tag 54) value is encoded in one of three
+//! formats:
+//!
+//! * an address: the bytes of the address, `52(h'c0000201')`;
+//! * a prefix: the prefix length and the bytes of the prefix without its
+//!   trailing zero bytes, `52([24, h'c00002'])`;
+//! * an interface: an address and the length of the prefix of its network,
+//!   `52([h'c0000201', 24])`. Interfaces with a zone identifier are not
+//!   supported.
+//!
+//! `IpAddr`, `Ipv4Addr` and `Ipv6Addr` are encoded as addresses, [`Ip`]
+//! represents any of the three formats.
+//!
+//! [`Ip`]: ./enum.Ip.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, ip::Ip, se::Serializer};
+//! use std::net::Ipv4Addr;
+//!
+//! let mut se = Serializer::new_vec();
+//! se.write_ip_prefix(Ipv4Addr::new(192, 0, 2, 0).into(), 24).unwrap();
+//! let bytes = se.finalize();
+//! assert_eq!(bytes, vec![0xd8, 0x34, 0x82, 0x18, 0x18, 0x43, 0xc0, 0x00, 0x02]);
+//!
+//! let ip: Ip = Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//! assert_eq!(ip, Ip::Prefix(Ipv4Addr::new(192, 0, 2, 0).into(), 24));
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::{
+    io::{BufRead, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+use types::Type;
+
+/// tag of the IPv4 addresses and prefixes
+pub const TAG_IPV4: u64 = 52;
+/// tag of the IPv6 addresses and prefixes
+pub const TAG_IPV6: u64 = 54;
+
+/// IP value in any of the formats of RFC 9164, see the
+/// [module documentation](./index.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ip {
+    Address(IpAddr),
+    /// network prefix and its length in bits, the bits of the address
+    /// following the prefix are zero.
+    Prefix(IpAddr, u8),
+    /// address of an interface and the length in bits of the prefix of its
+    /// network.
+    Interface(IpAddr, u8),
+}
+
+fn tag_of(address: &IpAddr) -> u64 {
+    match address {
+        IpAddr::V4(_) => TAG_IPV4,
+        IpAddr::V6(_) => TAG_IPV6,
+    }
+}
+
+fn octets(address: &IpAddr) -> Vec<u8> {
+    match address {
+        IpAddr::V4(address) => address.octets().to_vec(),
+        IpAddr::V6(address) => address.octets().to_vec(),
+    }
+}
+
+// address of the family of `tag` from `bytes`, padded with zeros
+fn address_from(tag: u64, bytes: &[u8]) -> IpAddr {
+    if tag == TAG_IPV4 {
+        let mut octets = [0; 4];
+        octets[..bytes.len()].copy_from_slice(bytes);
+        IpAddr::V4(Ipv4Addr::from(octets))
+    } else {
+        let mut octets = [0; 16];
+        octets[..bytes.len()].copy_from_slice(bytes);
+        IpAddr::V6(Ipv6Addr::from(octets))
+    }
+}
+
+fn max_prefix_len(tag: u64) -> u8 {
+    if tag == TAG_IPV4 {
+        32
+    } else {
+        128
+    }
+}
+
+fn check_prefix_len(tag: u64, prefix_len: u8) -> Result<()> {
+    if prefix_len > max_prefix_len(tag) {
+        Err(Error::InvalidIpAddress("prefix length too long"))
+    } else {
+        Ok(())
+    }
+}
+
+impl<W: Write + Sized> Serializer<W> {
+    /// write an IP address, `52(h'c0000201')`
+    pub fn write_ip_address(&mut self, address: IpAddr) -> Result<&mut Self> {
+        self.write_tag(tag_of(&address))?
+            .write_bytes(octets(&address))
+    }
+
+    /// write an IP network prefix, `52([24, h'c00002'])`. The bits of the
+    /// address following the prefix are ignored.
+    ///
+    /// Fails if `prefix_len` is longer than the address.
+    pub fn write_ip_prefix(&mut self, address: IpAddr, prefix_len: u8) -> Result<&mut Self> {
+        let tag = tag_of(&address);
+        check_prefix_len(tag, prefix_len)?;
+        let mut bytes = octets(&address);
+        bytes.truncate((prefix_len as usize + 7) / 8);
+        if prefix_len % 8 != 0 {
+            *bytes.last_mut().unwrap() &= 0xff << (8 - prefix_len % 8);
+        }
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        self.write_tag(tag)?
+            .write_array(Len::Len(2))?
+            .write_unsigned_integer(prefix_len as u64)?
+            .write_bytes(bytes)
+    }
+
+    /// write the IP address of an interface and the prefix length of its
+    /// network, `52([h'c0000201', 24])`.
+    ///
+    /// Fails if `prefix_len` is longer than the address.
+    pub fn write_ip_interface(&mut self, address: IpAddr, prefix_len: u8) -> Result<&mut Self> {
+        let tag = tag_of(&address);
+        check_prefix_len(tag, prefix_len)?;
+        self.write_tag(tag)?
+            .write_array(Len::Len(2))?
+            .write_bytes(octets(&address))?
+            .write_unsigned_integer(prefix_len as u64)
+    }
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// read an IP value in any of the formats of RFC 9164.
+    pub fn ip(&mut self) -> Result<Ip> {
+        let tag = self.tag()?;
+        if tag != TAG_IPV4 && tag != TAG_IPV6 {
+            return Err(Error::InvalidIpAddress("expected tag 52 or 54"));
+        }
+        match self.cbor_type()? {
+            Type::Bytes => Ok(Ip::Address(self.ip_address_bytes(tag)?)),
+            Type::Array => {
+                if self.array()? != Len::Len(2) {
+                    return Err(Error::InvalidIpAddress(
+                        "expected an array of 2 elements, zone identifiers are not supported",
+                    ));
+                }
+                match self.cbor_type()? {
+                    Type::Bytes => {
+                        let address = self.ip_address_bytes(tag)?;
+                        let prefix_len = self.ip_prefix_len(tag)?;
+                        Ok(Ip::Interface(address, prefix_len))
+                    }
+                    _ => {
+                        let prefix_len = self.ip_prefix_len(tag)?;
+                        let bytes = self.bytes()?;
+                        let max_len = (prefix_len as usize + 7) / 8;
+                        if bytes.len() > max_len {
+                            return Err(Error::InvalidIpAddress("prefix longer than its length"));
+                        }
+                        if bytes.last() == Some(&0) {
+                            return Err(Error::InvalidIpAddress("prefix with trailing zero bytes"));
+                        }
+                        if bytes.len() == max_len
+                            && prefix_len % 8 != 0
+                            && bytes[max_len - 1] & (0xff >> (prefix_len % 8)) != 0
+                        {
+                            return Err(Error::InvalidIpAddress("prefix longer than its length"));
+                        }
+                        Ok(Ip::Prefix(address_from(tag, &bytes), prefix_len))
+                    }
+                }
+            }
+            t => Err(Error::Expected(Type::Bytes, t)),
+        }
+    }
+
+    /// read an IP address, failing if the value is a prefix or an interface.
+    pub fn ip_address(&mut self) -> Result<IpAddr> {
+        match self.ip()? {
+            Ip::Address(address) => Ok(address),
+            _ => Err(Error::InvalidIpAddress("expected an address")),
+        }
+    }
+
+    /// read an IP network prefix and its length.
+    pub fn ip_prefix(&mut self) -> Result<(IpAddr, u8)> {
+        match self.ip()? {
+            Ip::Prefix(address, prefix_len) => Ok((address, prefix_len)),
+            _ => Err(Error::InvalidIpAddress("expected a prefix")),
+        }
+    }
+
+    /// read the IP address of an interface and the prefix length of its
+    /// network.
+    pub fn ip_interface(&mut self) -> Result<(IpAddr, u8)> {
+        match self.ip()? {
+            Ip::Interface(address, prefix_len) => Ok((address, prefix_len)),
+            _ => Err(Error::InvalidIpAddress("expected an interface")),
+        }
+    }
+
+    fn ip_address_bytes(&mut self, tag: u64) -> Result<IpAddr> {
+        let bytes = self.bytes()?;
+        if bytes.len() != max_prefix_len(tag) as usize / 8 {
+            return Err(Error::InvalidIpAddress("invalid address length"));
+        }
+        Ok(address_from(tag, &bytes))
+    }
+
+    fn ip_prefix_len(&mut self, tag: u64) -> Result<u8> {
+        let prefix_len = self.unsigned_integer()?;
+        if prefix_len > max_prefix_len(tag) as u64 {
+            return Err(Error::InvalidIpAddress("prefix length too long"));
+        }
+        Ok(prefix_len as u8)
+    }
+}
+
+impl Serialize for Ip {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        match *self {
+            Ip::Address(address) => serializer.write_ip_address(address),
+            Ip::Prefix(address, prefix_len) => serializer.write_ip_prefix(address, prefix_len),
+            Ip::Interface(address, prefix_len) => {
+                serializer.write_ip_interface(address, prefix_len)
+            }
+        }
+    }
+}
+impl Deserialize for Ip {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.ip()
+    }
+}
+
+impl Serialize for IpAddr {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_ip_address(*self)
+    }
+}
+impl Deserialize for IpAddr {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        raw.ip_address()
+    }
+}
+
+impl Serialize for Ipv4Addr {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_ip_address(IpAddr::V4(*self))
+    }
+}
+impl Deserialize for Ipv4Addr {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.ip_address()? {
+            IpAddr::V4(address) => Ok(address),
+            IpAddr::V6(_) => Err(Error::InvalidIpAddress("expected an IPv4 address")),
+        }
+    }
+}
+
+impl Serialize for Ipv6Addr {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_ip_address(IpAddr::V6(*self))
+    }
+}
+impl Deserialize for Ipv6Addr {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        match raw.ip_address()? {
+            IpAddr::V6(address) => Ok(address),
+            IpAddr::V4(_) => Err(Error::InvalidIpAddress("expected an IPv6 address")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(ip: Ip) -> Vec<u8> {
+        let mut se = Serializer::new_vec();
+        se.serialize(&ip).unwrap();
+        se.finalize()
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Ip> {
+        Deserializer::from(bytes).deserialize_complete()
+    }
+
+    #[test]
+    fn rfc_examples() {
+        let v4 = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let examples = [
+            (
+                Ip::Address(v4),
+                vec![0xd8, 0x34, 0x44, 0xc0, 0x00, 0x02, 0x01],
+            ),
+            (
+                Ip::Interface(v4, 24),
+                vec![0xd8, 0x34, 0x82, 0x44, 0xc0, 0x00, 0x02, 0x01, 0x18, 0x18],
+            ),
+            (
+                Ip::Prefix("2001:db8:1234::".parse().unwrap(), 48),
+                vec![
+                    0xd8, 0x36, 0x82, 0x18, 0x30, 0x46, 0x20, 0x01, 0x0d, 0xb8, 0x12, 0x34,
+                ],
+            ),
+        ];
+        for (ip, bytes) in examples.iter() {
+            assert_eq!(&encode(*ip), bytes);
+            assert_eq!(decode(bytes).unwrap(), *ip);
+        }
+    }
+
+    #[test]
+    fn prefix_is_masked() {
+        // 192.0.2.255/25 is written as 192.0.2.128/25
+        let bytes = encode(Ip::Prefix(Ipv4Addr::new(192, 0, 2, 255).into(), 25));
+        assert_eq!(
+            bytes,
+            vec![0xd8, 0x34, 0x82, 0x18, 0x19, 0x44, 0xc0, 0x00, 0x02, 0x80]
+        );
+        // 0.0.0.0/0
+        let bytes = encode(Ip::Prefix(Ipv4Addr::new(10, 0, 0, 0).into(), 0));
+        assert_eq!(bytes, vec![0xd8, 0x34, 0x82, 0x00, 0x40]);
+        assert_eq!(
+            decode(&bytes).unwrap(),
+            Ip::Prefix(Ipv4Addr::new(0, 0, 0, 0).into(), 0)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        // trailing zero byte
+        assert!(decode(&[0xd8, 0x34, 0x82, 0x18, 0x18, 0x43, 0xc0, 0x02, 0x00]).is_err());
+        // bits set after the prefix
+        assert!(decode(&[0xd8, 0x34, 0x82, 0x18, 0x17, 0x43, 0xc0, 0x00, 0x03]).is_err());
+        // prefix length too long
+        assert!(decode(&[0xd8, 0x34, 0x82, 0x18, 0x21, 0x41, 0xc0]).is_err());
+        // address of the wrong length
+        assert!(decode(&[0xd8, 0x36, 0x44, 0xc0, 0x00, 0x02, 0x01]).is_err());
+        // wrong tag
+        assert!(decode(&[0xd8, 0x35, 0x44, 0xc0, 0x00, 0x02, 0x01]).is_err());
+        assert!(
+            Deserializer::from(&[0xd8, 0x34, 0x44, 0xc0, 0x00, 0x02, 0x01][..])
+                .deserialize::<Ipv6Addr>()
+                .is_err()
+        );
+        let mut se = Serializer::new_vec();
+        assert!(se
+            .write_ip_interface(Ipv4Addr::new(192, 0, 2, 1).into(), 33)
+            .is_err());
+    }
+}
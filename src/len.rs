@@ -1,10 +1,15 @@
+#[cfg(not(feature = "std"))]
+use prelude::*;
+
 /// CBOR len: either a fixed size or an indefinite length.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Len {
     Indefinite,
     Len(u64),
 }
 impl Len {
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_null(&self) -> bool {
         match self {
             Len::Len(0) => true,
@@ -23,3 +28,88 @@ impl Len {
         self == &Len::Indefinite
     }
 }
+
+/// number of bytes following the initial byte to encode an integer, a
+/// length or a tag. CBOR allows encoding a value on more bytes than
+/// necessary, this tells which size was used.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Sz {
+    /// encoded in the initial byte (values below 24)
+    Inline,
+    One,
+    Two,
+    Four,
+    Eight,
+}
+impl Sz {
+    /// the shortest encoding of the given value
+    pub fn canonical(value: u64) -> Self {
+        match value {
+            0..=super::MAX_INLINE_ENCODING => Sz::Inline,
+            24..=0xff => Sz::One,
+            0x100..=0xffff => Sz::Two,
+            0x1_0000..=0xffff_ffff => Sz::Four,
+            _ => Sz::Eight,
+        }
+    }
+
+    /// number of bytes following the initial byte
+    pub fn bytes_following(self) -> usize {
+        match self {
+            Sz::Inline => 0,
+            Sz::One => 1,
+            Sz::Two => 2,
+            Sz::Four => 4,
+            Sz::Eight => 8,
+        }
+    }
+
+    /// returns `true` if `value` can be encoded with this size
+    pub fn fits(self, value: u64) -> bool {
+        match self {
+            Sz::Inline => value <= super::MAX_INLINE_ENCODING,
+            Sz::One => value <= 0xff,
+            Sz::Two => value <= 0xffff,
+            Sz::Four => value <= 0xffff_ffff,
+            Sz::Eight => true,
+        }
+    }
+
+    // from the number of bytes following the initial byte as returned by
+    // `Deserializer::cbor_len`
+    pub(crate) fn from_bytes_following(len: usize) -> Self {
+        match len {
+            0 => Sz::Inline,
+            1 => Sz::One,
+            2 => Sz::Two,
+            4 => Sz::Four,
+            _ => Sz::Eight,
+        }
+    }
+}
+
+/// [`Len`](./enum.Len.html) of an array or a map with the size of its
+/// encoding.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum LenSz {
+    Indefinite,
+    Len(u64, Sz),
+}
+impl From<LenSz> for Len {
+    fn from(len: LenSz) -> Self {
+        match len {
+            LenSz::Indefinite => Len::Indefinite,
+            LenSz::Len(len, _) => Len::Len(len),
+        }
+    }
+}
+
+/// how the length of a byte or text string was encoded: the size of the
+/// definite length or the length and size of each chunk of an indefinite
+/// length string.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StringLenSz {
+    Len(Sz),
+    Indefinite(Vec<(u64, Sz)>),
+}
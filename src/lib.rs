@@ -15,10 +15,11 @@
 //! Here is the list of supported CBOR primary [`Type`]:
 //!
 //! - Unsigned and Negative Integers;
-//! - Bytes and UTF8 String (**finite length only**);
+//! - Bytes and UTF8 String (of finite and indefinite length);
 //! - Array and Map (of finite and indefinite size);
 //! - Tag;
-//! - Specials (`bool`, `null`... **except floating points**).
+//! - Specials (`bool`, `null`... and half, single and double precision
+//!   floating points).
 //!
 //! ## Raw deserialisation: [`Deserializer`]
 //!
@@ -58,25 +59,183 @@
 //! # let bytes = serializer.finalize();
 //! # assert_eq!(bytes, [0x2b].as_ref());
 //! ```
+//!
+//! [`to_vec`] and [`from_slice`] encode and decode a single object in one
+//! call:
+//!
+//! ```
+//! let bytes = cbor_event::to_vec(&[1u64, 2]).unwrap();
+//! let numbers: Vec<u64> = cbor_event::from_slice(&bytes).unwrap();
+//! # assert_eq!(numbers, vec![1, 2]);
+//! ```
+//!
+//! [`to_vec`]: ./se/fn.to_vec.html
+//! [`from_slice`]: ./de/fn.from_slice.html
+//!
+//! ## Untrusted input
+//!
+//! Decoding never panics, whatever the input: malformed or truncated data
+//! is reported as an [`Error`]. The lengths announced in the headers are
+//! not trusted either, nothing is allocated ahead of the bytes actually
+//! read. This is checked by the fuzz targets of the `fuzz` directory (run
+//! with `cargo fuzz run deserializer`).
+//!
+//! [`skip_value`] and [`validate_item`] walk nested objects with an
+//! explicit stack, on the heap, whatever their depth. The decoding of
+//! [`Value`] does too, but a `Value` is dropped, cloned, formatted with
+//! `Debug` and encoded recursively: bound its depth with
+//! [`set_max_depth`]. `BorrowedValue` is decoded recursively, within at
+//! most [`DEFAULT_MAX_DEPTH`] nested arrays, maps and tags by default.
+//! Other decoders of nested types, like the ones of nested `Vec`s, are
+//! recursive too and use stack space proportional to the nesting depth of
+//! the input: bound it with [`set_max_depth`].
+//!
+//! [`set_max_depth`]: ./de/struct.Deserializer.html#method.set_max_depth
+//! [`DEFAULT_MAX_DEPTH`]: ./de/constant.DEFAULT_MAX_DEPTH.html
+//! [`skip_value`]: ./de/struct.Deserializer.html#method.skip_value
+//! [`validate_item`]: ./de/struct.Deserializer.html#method.validate_item
+//! [`Value`]: ./enum.Value.html
+//!
+//! ## `no_std`
+//!
+//! Without the default `std` feature the crate is `#![no_std]` and only
+//! needs `alloc`. The decoders and encoders then work with the minimal
+//! [`io`](./io/index.html) traits of the crate, implemented for byte
+//! slices and `Vec<u8>`. The modules built on the standard library, like
+//! [`pool`](./pool/index.html) or the integrations with other crates, are
+//! not available.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
 #[cfg(test)]
 #[macro_use]
 extern crate quickcheck;
+#[cfg(feature = "bumpalo")]
+extern crate bumpalo;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "derive")]
+extern crate cbor_event_derive;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "ciborium")]
+extern crate ciborium;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "digest")]
+extern crate digest;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
+#[cfg(feature = "minicbor")]
+extern crate minicbor;
+#[cfg(feature = "num-bigint")]
+extern crate num_bigint;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(any(feature = "ciborium", feature = "serde"))]
+extern crate serde as serde_crate;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(test)]
+extern crate sha2;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
 
+#[cfg(feature = "bumpalo")]
+pub mod arena;
+#[cfg(feature = "tokio")]
+pub mod async_de;
+mod borrowed;
+#[cfg(feature = "bytes")]
+pub mod buf;
+pub mod cancel;
+pub mod canonical;
+pub mod cddl;
+pub mod chunks;
+#[cfg(feature = "ciborium")]
+pub mod ciborium_compat;
+pub mod datetime;
 pub mod de;
+pub mod diag;
+pub mod diff;
 mod error;
+mod float;
+#[cfg(feature = "digest")]
+pub mod hashing;
+pub mod index;
+pub mod int_enum;
+pub mod io;
+pub mod ip;
 mod len;
+pub mod lint;
 mod macros;
+#[cfg(feature = "minicbor")]
+pub mod minicbor_compat;
+mod ordered_map;
+#[cfg(feature = "std")]
+pub mod pool;
+pub mod progress;
+pub mod push;
+pub mod query;
+mod raw;
+pub mod redact;
 mod result;
+mod scan;
 pub mod se;
+#[cfg(feature = "zeroize")]
+pub mod secret;
+pub mod sequence;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod sink;
+pub mod stats;
+mod stitch;
+pub mod token;
+pub mod transcode;
 mod types;
 mod value;
 
-pub use de::Deserialize;
+// stands in for the standard library without the `std` feature, so the
+// `std::` paths of the crate resolve to `core`, `alloc` and the `io`
+// traits of the crate
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use alloc::{borrow, collections, fmt, slice, str, string, sync, vec};
+    pub use core::*;
+    pub use io;
+}
+// the part of the standard prelude `core` does not have
+#[cfg(not(feature = "std"))]
+mod prelude {
+    pub use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        string::{String, ToString},
+        vec::Vec,
+    };
+}
+
+pub use borrowed::{BorrowedKey, BorrowedValue};
+#[cfg(feature = "derive")]
+pub use cbor_event_derive::{Deserialize, Serialize};
+pub use de::{from_slice, from_slice_complete, Deserialize, DeserializeInPlace, FromCborBytes};
 pub use error::Error;
 pub use len::*;
+pub use ordered_map::OrderedMap;
+pub use raw::RawCbor;
 pub use result::Result;
-pub use se::Serialize;
+pub use scan::{parse_header, validate, validate_reader};
+pub use se::{to_vec, Serialize, ToCborBytes};
 pub use types::*;
 pub use value::{ObjectKey, Value};
 
@@ -96,7 +255,7 @@ pub fn test_encode_decode<V: Sized + PartialEq + Serialize + Deserialize>(v: &V)
     v.serialize(&mut se)?;
     let bytes = se.finalize();
 
-    let mut raw = de::Deserializer::from(std::io::Cursor::new(bytes));
+    let mut raw = de::Deserializer::from(&bytes[..]);
     let v_ = Deserialize::deserialize(&mut raw)?;
 
     Ok(v == &v_)
@@ -0,0 +1,334 @@
+//! Canonical encoding linter
+//!
+//! Audit the encoding produced by another implementation against the core
+//! deterministic encoding requirements of RFC 8949 (section 4.2.1). Rather
+//! than failing on the first problem, [`lint_canonical`] walks the whole
+//! buffer and reports every violation with its offset.
+//!
+//! [`lint_canonical`]: ./fn.lint_canonical.html
+//!
+//! ```
+//! use cbor_event::lint::{lint_canonical, ViolationKind};
+//!
+//! // {"b": 1, "a": 2 (encoded on 3 bytes)}
+//! let bytes = [0xa2, 0x61, 0x62, 0x01, 0x61, 0x61, 0x19, 0x00, 0x02];
+//!
+//! let violations = lint_canonical(&bytes).unwrap();
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].offset, 4);
+//! assert_eq!(violations[0].kind, ViolationKind::UnsortedKey);
+//! assert_eq!(violations[1].offset, 6);
+//! assert_eq!(violations[1].kind, ViolationKind::NonMinimalHeader(3, 1));
+//! ```
+
+use canonical::KeyOrder;
+use float;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use std::cmp::Ordering;
+use std::fmt;
+use types::Type;
+
+/// a canonical encoding requirement not met by the encoded object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// integer, length or tag encoded on more bytes than necessary: the
+    /// size of the header and the minimal size.
+    NonMinimalHeader(usize, usize),
+    /// string, array or map of indefinite length
+    IndefiniteLength,
+    /// map key not sorted after the previous key in the
+    /// [`KeyOrder`](../canonical/enum.KeyOrder.html) checked, the bytewise
+    /// lexicographic order of their encodings by default
+    UnsortedKey,
+    /// map key already present in the map
+    DuplicateKey,
+    /// floating point value encoded on more bytes than necessary to
+    /// represent it exactly: the size of the encoding and the minimal size.
+    OversizedFloat(usize, usize),
+}
+
+/// violation of the canonical encoding at a given offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    /// offset of the offending object (the key for map keys violations)
+    pub offset: usize,
+    pub kind: ViolationKind,
+}
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ViolationKind::NonMinimalHeader(sz, min) => {
+                write!(f, "header encoded on {} bytes instead of {}", sz, min)
+            }
+            ViolationKind::IndefiniteLength => write!(f, "indefinite length"),
+            ViolationKind::UnsortedKey => write!(f, "map key out of order"),
+            ViolationKind::DuplicateKey => write!(f, "duplicate map key"),
+            ViolationKind::OversizedFloat(sz, min) => {
+                write!(f, "float encoded on {} bytes instead of {}", sz, min)
+            }
+        }
+    }
+}
+#[cfg(feature = "defmt")]
+impl defmt::Format for ViolationKind {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match *self {
+            ViolationKind::NonMinimalHeader(sz, min) => {
+                defmt::write!(f, "header encoded on {} bytes instead of {}", sz, min)
+            }
+            ViolationKind::IndefiniteLength => defmt::write!(f, "indefinite length"),
+            ViolationKind::UnsortedKey => defmt::write!(f, "map key out of order"),
+            ViolationKind::DuplicateKey => defmt::write!(f, "duplicate map key"),
+            ViolationKind::OversizedFloat(sz, min) => {
+                defmt::write!(f, "float encoded on {} bytes instead of {}", sz, min)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "offset {}: {}", self.offset, self.kind)
+    }
+}
+
+/// list all the violations of the canonical encoding in `bytes`, in order
+/// of appearance. An empty list means the encoding is canonical.
+///
+/// Fails if `bytes` is not exactly one well-formed CBOR object.
+pub fn lint_canonical(bytes: &[u8]) -> Result<Vec<Violation>> {
+    lint_canonical_with(bytes, KeyOrder::Lexicographic)
+}
+
+/// same as [`lint_canonical`](./fn.lint_canonical.html) but checking the
+/// map keys are sorted in the given order.
+///
+/// ```
+/// use cbor_event::{canonical::KeyOrder, lint::lint_canonical_with};
+///
+/// // {"a": 0, 1000: 0}
+/// let bytes = [0xa2, 0x61, 0x61, 0x00, 0x19, 0x03, 0xe8, 0x00];
+///
+/// assert_eq!(lint_canonical_with(&bytes, KeyOrder::Lexicographic).unwrap().len(), 1);
+/// assert!(lint_canonical_with(&bytes, KeyOrder::LengthFirst).unwrap().is_empty());
+/// ```
+pub fn lint_canonical_with(bytes: &[u8], order: KeyOrder) -> Result<Vec<Violation>> {
+    let layout = scan::Layout::new(bytes)?;
+
+    let mut violations = Vec::new();
+    for (index, item) in layout.items().iter().enumerate() {
+        let (cbor_type, len, sz) = (item.cbor_type, item.len, item.sz);
+        let mut report = |kind| {
+            violations.push(Violation {
+                offset: item.pos,
+                kind,
+            })
+        };
+
+        if cbor_type == Type::Special {
+            if let Len::Len(value) = len {
+                if sz > 2 {
+                    let min = float_size(float::decode(value, sz - 1));
+                    if min < sz {
+                        report(ViolationKind::OversizedFloat(sz, min));
+                    }
+                }
+            }
+            continue;
+        }
+
+        match len {
+            Len::Indefinite => report(ViolationKind::IndefiniteLength),
+            Len::Len(value) => {
+                let min = header_size(value);
+                if min < sz {
+                    report(ViolationKind::NonMinimalHeader(sz, min));
+                }
+            }
+        }
+
+        if cbor_type == Type::Map {
+            check_keys(bytes, &layout, index, order, &mut violations);
+        }
+    }
+    violations.sort_by_key(|violation| violation.offset);
+    Ok(violations)
+}
+
+// number of bytes of the minimal header for the given value
+pub(crate) fn header_size(value: u64) -> usize {
+    match value {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+// number of bytes of the shortest floating point encoding of `value`
+pub(crate) fn float_size(value: f64) -> usize {
+    if float::f64_to_f16_exact(value).is_some() {
+        3
+    } else if float::f64_to_f32_exact(value).is_some() {
+        5
+    } else {
+        9
+    }
+}
+
+// check the keys (every other child) of the map at `index` are sorted and
+// unique
+fn check_keys(
+    bytes: &[u8],
+    layout: &scan::Layout,
+    index: usize,
+    order: KeyOrder,
+    violations: &mut Vec<Violation>,
+) {
+    let items = layout.items();
+    let mut keys = Vec::new();
+    for key in layout.children(index).step_by(2) {
+        let key = &items[key];
+        keys.push((&bytes[key.pos..key.end], key.pos));
+    }
+
+    for pair in keys.windows(2) {
+        if order.compare(pair[1].0, pair[0].0) == Ordering::Less {
+            violations.push(Violation {
+                offset: pair[1].1,
+                kind: ViolationKind::UnsortedKey,
+            });
+        }
+    }
+
+    // stable sort: the first occurrence of a key stays first
+    keys.sort_by(|a, b| a.0.cmp(b.0));
+    for pair in keys.windows(2) {
+        if pair[1].0 == pair[0].0 {
+            violations.push(Violation {
+                offset: pair[1].1,
+                kind: ViolationKind::DuplicateKey,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn kinds(bytes: &[u8]) -> Vec<(usize, ViolationKind)> {
+        lint_canonical(bytes)
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.offset, v.kind))
+            .collect()
+    }
+
+    #[test]
+    fn canonical() {
+        let bytes = [
+            0xa3, 0x01, 0x18, 0x18, 0x20, 0xf9, 0x3e, 0x00, 0x61, 0x61, 0xc1, 0x1a, 0x00, 0x01,
+            0x00, 0x00,
+        ];
+        assert!(lint_canonical(&bytes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn headers() {
+        // [0 on 2 bytes, 1(h'' with length on 3 bytes), -1 on 9 bytes]
+        let bytes = [
+            0x83, 0x18, 0x00, 0xd8, 0x01, 0x59, 0x00, 0x00, 0x3b, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            kinds(&bytes),
+            vec![
+                (1, ViolationKind::NonMinimalHeader(2, 1)),
+                (3, ViolationKind::NonMinimalHeader(2, 1)),
+                (5, ViolationKind::NonMinimalHeader(3, 1)),
+                (8, ViolationKind::NonMinimalHeader(9, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn indefinite() {
+        // [_ (_ "a"), {_ }]
+        let bytes = [0x9f, 0x7f, 0x61, 0x61, 0xff, 0xbf, 0xff, 0xff];
+        assert_eq!(
+            kinds(&bytes),
+            vec![
+                (0, ViolationKind::IndefiniteLength),
+                (1, ViolationKind::IndefiniteLength),
+                (5, ViolationKind::IndefiniteLength),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_keys() {
+        // {2: 0, 1: 0, 2: 0, 1: 0}
+        let bytes = [0xa4, 0x02, 0x00, 0x01, 0x00, 0x02, 0x00, 0x01, 0x00];
+        assert_eq!(
+            kinds(&bytes),
+            vec![
+                (3, ViolationKind::UnsortedKey),
+                (5, ViolationKind::DuplicateKey),
+                (7, ViolationKind::UnsortedKey),
+                (7, ViolationKind::DuplicateKey),
+            ]
+        );
+    }
+
+    #[test]
+    fn length_first() {
+        // {10: 0, "a": 0, 1000: 0, 24: 0}
+        let bytes = [
+            0xa4, 0x0a, 0x00, 0x61, 0x61, 0x00, 0x19, 0x03, 0xe8, 0x00, 0x18, 0x18, 0x00,
+        ];
+        let violations = lint_canonical_with(&bytes, KeyOrder::LengthFirst).unwrap();
+        assert_eq!(
+            violations,
+            vec![Violation {
+                offset: 10,
+                kind: ViolationKind::UnsortedKey
+            }]
+        );
+    }
+
+    #[test]
+    fn floats() {
+        // [1.5 as a double, 100000.0 as a double, 1.1 as a double, NaN as a float]
+        let bytes = [
+            0x84, 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xfb, 0x40, 0xf8, 0x6a, 0, 0, 0, 0, 0, 0xfb,
+            0x3f, 0xf1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a, 0xfa, 0x7f, 0xc0, 0x00, 0x00,
+        ];
+        assert_eq!(
+            kinds(&bytes),
+            vec![
+                (1, ViolationKind::OversizedFloat(9, 3)),
+                (10, ViolationKind::OversizedFloat(9, 5)),
+                (28, ViolationKind::OversizedFloat(5, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(lint_canonical(&[0x82, 0x00]).is_err());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        // 100000 nested maps {0: {0: ... {0: 0, 0: 0}}}, the innermost
+        // one with a duplicate key
+        let mut bytes = [0xa1, 0x00].repeat(100_000);
+        bytes.extend_from_slice(&[0xa2, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(kinds(&bytes), vec![(200_003, ViolationKind::DuplicateKey)]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Interoperability with minicbor
+//!
+//! To migrate a code base one type at a time: [`Minicbor`] wraps a type
+//! implementing minicbor's `Encode` and `Decode` so it can be written and
+//! read by this crate, [`CborEvent`] wraps a type implementing
+//! `Serialize` and `Deserialize` so minicbor can encode and decode it.
+//! Both go through a temporary buffer holding the encoded value.
+//!
+//! [`Minicbor`]: ./struct.Minicbor.html
+//! [`CborEvent`]: ./struct.CborEvent.html
+//!
+//! ```
+//! # extern crate cbor_event;
+//! # extern crate minicbor;
+//! use cbor_event::{de::Deserializer, minicbor_compat::{CborEvent, Minicbor}, se::Serializer, Value};
+//!
+//! # fn main() {
+//! // a minicbor type in a cbor_event pipeline
+//! let mut se = Serializer::new_vec();
+//! se.serialize(&Minicbor(vec!["a".to_owned(), "b".to_owned()])).unwrap();
+//! let bytes = se.finalize();
+//! let Minicbor(decoded): Minicbor<Vec<String>> =
+//!     Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//! assert_eq!(decoded, ["a", "b"]);
+//!
+//! // and the other way around
+//! let bytes = minicbor::to_vec(CborEvent(Value::U64(42))).unwrap();
+//! let CborEvent(value): CborEvent<Value> = minicbor::decode(&bytes).unwrap();
+//! assert_eq!(value, Value::U64(42));
+//! # }
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+use minicbor::{self, encode::Write as MinicborWrite};
+use result::Result;
+use se::{Serialize, Serializer};
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+/// a type implementing minicbor's `Encode` and `Decode`, serialised and
+/// deserialised by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Minicbor<T>(pub T);
+
+fn minicbor_error<E: fmt::Display>(error: E) -> Error {
+    Error::CustomError(format!("minicbor: {}", error))
+}
+
+impl<T: minicbor::Encode<()>> Serialize for Minicbor<T> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        let bytes = minicbor::to_vec(&self.0).map_err(minicbor_error)?;
+        serializer.write_raw_bytes(&bytes)
+    }
+}
+impl<T: for<'b> minicbor::Decode<'b, ()>> Deserialize for Minicbor<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        raw.raw_item_into(&mut bytes)?;
+        let mut decoder = minicbor::Decoder::new(&bytes);
+        let value = T::decode(&mut decoder, &mut ()).map_err(minicbor_error)?;
+        if decoder.position() != bytes.len() {
+            return Err(Error::TrailingData);
+        }
+        Ok(Minicbor(value))
+    }
+}
+
+/// a type implementing `Serialize` and `Deserialize`, encoded and decoded
+/// by minicbor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CborEvent<T>(pub T);
+
+impl<C, T: Serialize> minicbor::Encode<C> for CborEvent<T> {
+    fn encode<W: MinicborWrite>(
+        &self,
+        e: &mut minicbor::Encoder<W>,
+        _ctx: &mut C,
+    ) -> ::std::result::Result<(), minicbor::encode::Error<W::Error>> {
+        let mut serializer = Serializer::new_vec();
+        serializer
+            .serialize(&self.0)
+            .map_err(minicbor::encode::Error::message)?;
+        e.writer_mut()
+            .write_all(&serializer.finalize())
+            .map_err(minicbor::encode::Error::write)
+    }
+}
+impl<'b, C, T: Deserialize> minicbor::Decode<'b, C> for CborEvent<T> {
+    fn decode(
+        d: &mut minicbor::Decoder<'b>,
+        _ctx: &mut C,
+    ) -> ::std::result::Result<Self, minicbor::decode::Error> {
+        let start = d.position();
+        d.skip()?;
+        Deserializer::from(&d.input()[start..d.position()])
+            .deserialize_complete()
+            .map(CborEvent)
+            .map_err(minicbor::decode::Error::message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use value::Value;
+
+    #[test]
+    fn nested() {
+        // cbor_event values inside minicbor types, inside a cbor_event array
+        let inner = vec![
+            CborEvent(Value::Text("x".to_owned())),
+            CborEvent(Value::U64(1)),
+        ];
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(2))
+            .unwrap()
+            .serialize(&Minicbor((7u8, inner)))
+            .unwrap()
+            .serialize(&true)
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, [0x82, 0x82, 0x07, 0x82, 0x61, 0x78, 0x01, 0xf5]);
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.array().unwrap();
+        let Minicbor((n, values)): Minicbor<(u8, Vec<CborEvent<Value>>)> =
+            raw.deserialize().unwrap();
+        assert_eq!(n, 7);
+        assert_eq!(values[1].0, Value::U64(1));
+        assert!(raw.bool().unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        // not a string
+        assert!(Deserializer::from(&[0x01][..])
+            .deserialize::<Minicbor<String>>()
+            .is_err());
+        assert!(minicbor::decode::<CborEvent<String>>(&[0x01]).is_err());
+    }
+}
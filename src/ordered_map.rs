@@ -0,0 +1,191 @@
+use de::{Deserialize, Deserializer};
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::{
+    borrow::Borrow,
+    io::{BufRead, Write},
+    iter::FromIterator,
+    slice, vec,
+};
+
+/// CBOR map preserving the order of the entries and allowing duplicate
+/// keys.
+///
+/// Unlike a `BTreeMap`, decoding then encoding an `OrderedMap` gives the
+/// entries back in the order they were received, duplicates included.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, se::Serializer, OrderedMap};
+///
+/// // {2: "b", 1: "a", 2: "c"}
+/// let bytes = vec![0xa3, 0x02, 0x61, 0x62, 0x01, 0x61, 0x61, 0x02, 0x61, 0x63];
+///
+/// let mut raw = Deserializer::from(&bytes[..]);
+/// let map: OrderedMap<u64, String> = raw.deserialize_complete().unwrap();
+/// assert_eq!(map.get(&2), Some(&"b".to_owned()));
+/// assert_eq!(map.get_all(&2).count(), 2);
+///
+/// let mut se = Serializer::new_vec();
+/// se.serialize(&map).unwrap();
+/// assert_eq!(se.finalize(), bytes);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderedMap<K, V>(Vec<(K, V)>);
+impl<K, V> OrderedMap<K, V> {
+    pub fn new() -> Self {
+        OrderedMap(Vec::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        OrderedMap(Vec::with_capacity(capacity))
+    }
+
+    /// append an entry, even if the key is already present
+    pub fn insert(&mut self, key: K, value: V) {
+        self.0.push((key, value))
+    }
+
+    /// value of the first entry with the given key
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.0
+            .iter()
+            .find(|(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    /// values of all the entries with the given key, in order
+    pub fn get_all<'a, Q>(&'a self, key: &'a Q) -> impl Iterator<Item = &'a V> + 'a
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k.borrow() == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// number of entries, duplicates included
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (K, V)> {
+        self.0.iter()
+    }
+
+    pub fn as_slice(&self) -> &[(K, V)] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.0
+    }
+}
+impl<K, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        OrderedMap::new()
+    }
+}
+impl<K, V> From<Vec<(K, V)>> for OrderedMap<K, V> {
+    fn from(entries: Vec<(K, V)>) -> Self {
+        OrderedMap(entries)
+    }
+}
+impl<K, V> FromIterator<(K, V)> for OrderedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        OrderedMap(iter.into_iter().collect())
+    }
+}
+impl<K, V> Extend<(K, V)> for OrderedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<(K, V)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+impl<'a, K, V> IntoIterator for &'a OrderedMap<K, V> {
+    type Item = &'a (K, V);
+    type IntoIter = slice::Iter<'a, (K, V)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<K: Serialize, V: Serialize> Serialize for OrderedMap<K, V> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        self.0.serialize(serializer)
+    }
+}
+impl<K: Deserialize, V: Deserialize> Deserialize for OrderedMap<K, V> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        Vec::deserialize(raw).map(OrderedMap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indefinite_map_with_duplicates() {
+        // {_ "b": 1, "a": 2, "b": 3}
+        let bytes = vec![
+            0xbf, 0x61, 0x62, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62, 0x03, 0xff,
+        ];
+        let mut raw = Deserializer::from(&bytes[..]);
+        let map: OrderedMap<String, u64> = raw.deserialize_complete().unwrap();
+
+        let entries: Vec<_> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(entries, vec![("b", 1), ("a", 2), ("b", 3)]);
+        assert_eq!(map.get("b"), Some(&1));
+        assert_eq!(map.get_all("b").cloned().collect::<Vec<_>>(), vec![1, 3]);
+        assert!(!map.contains_key("c"));
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&map).unwrap();
+        assert_eq!(
+            se.finalize(),
+            vec![0xa3, 0x61, 0x62, 0x01, 0x61, 0x61, 0x02, 0x61, 0x62, 0x03]
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let map: OrderedMap<u64, u64> = OrderedMap::default();
+        let mut se = Serializer::new_vec();
+        se.serialize(&map).unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, vec![0xa0]);
+        let decoded: OrderedMap<u64, u64> = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+        assert!(decoded.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+//! Pool of reusable byte buffers
+//!
+//! Services encoding or decoding many messages keep allocating and freeing
+//! buffers of similar sizes. A [`BufferPool`] keeps the allocations of the
+//! buffers once they are no longer used so they can be handed out again.
+//!
+//! [`BufferPool`]: ./struct.BufferPool.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, pool::BufferPool, se::Serializer};
+//! use std::io::Cursor;
+//!
+//! let pool = BufferPool::new(16, 4096);
+//!
+//! let mut serializer = Serializer::new_pooled(&pool);
+//! serializer.write_bytes(b"some bytes").unwrap();
+//! let encoded = serializer.finalize();
+//!
+//! let mut raw = Deserializer::from(Cursor::new(&encoded[..]));
+//! let bytes = raw.bytes_pooled(&pool).unwrap();
+//! assert_eq!(&bytes[..], b"some bytes");
+//! ```
+
+use std::{
+    fmt, io,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// thread safe pool of `Vec<u8>`.
+///
+/// Buffers are borrowed with [`get`](#method.get) and go back to the pool
+/// when the returned [`PooledBuffer`](./struct.PooledBuffer.html) is dropped.
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    buffer_capacity: usize,
+}
+impl BufferPool {
+    /// create a new pool keeping at most `max_buffers` idle buffers. New
+    /// buffers are allocated with `buffer_capacity` bytes of capacity.
+    pub fn new(max_buffers: usize, buffer_capacity: usize) -> Self {
+        BufferPool {
+            buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+            max_buffers,
+            buffer_capacity,
+        }
+    }
+
+    /// borrow an empty buffer from the pool, allocating a new one if the
+    /// pool has no idle buffer.
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buffer = self
+            .lock()
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.buffer_capacity));
+        PooledBuffer {
+            buffer: Some(buffer),
+            pool: self,
+        }
+    }
+
+    /// number of idle buffers in the pool
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> ::std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        // a panic while holding the lock cannot leave the list of buffers
+        // in an inconsistent state, ignore the poisoning
+        self.buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn put_back(&self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        let mut buffers = self.lock();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+}
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_buffers", &self.max_buffers)
+            .field("buffer_capacity", &self.buffer_capacity)
+            .finish()
+    }
+}
+
+/// a `Vec<u8>` borrowed from a [`BufferPool`](./struct.BufferPool.html).
+///
+/// The buffer is returned to the pool on drop, use
+/// [`detach`](#method.detach) to keep it instead.
+pub struct PooledBuffer<'a> {
+    // always `Some` until dropped or detached
+    buffer: Option<Vec<u8>>,
+    pool: &'a BufferPool,
+}
+impl<'a> PooledBuffer<'a> {
+    /// take the buffer out of the pool's management, it will not be
+    /// returned to the pool.
+    pub fn detach(mut self) -> Vec<u8> {
+        self.buffer.take().unwrap_or_default()
+    }
+}
+impl<'a> Deref for PooledBuffer<'a> {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        self.buffer
+            .as_ref()
+            .expect("buffer is present until dropped")
+    }
+}
+impl<'a> DerefMut for PooledBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buffer
+            .as_mut()
+            .expect("buffer is present until dropped")
+    }
+}
+impl<'a> AsRef<[u8]> for PooledBuffer<'a> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+impl<'a> fmt::Debug for PooledBuffer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.deref(), f)
+    }
+}
+impl<'a> Drop for PooledBuffer<'a> {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.put_back(buffer)
+        }
+    }
+}
+impl<'a> io::Write for PooledBuffer<'a> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deref_mut().write(buf)
+    }
+    #[inline]
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.deref_mut().write_vectored(bufs)
+    }
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.deref_mut().write_all(buf)
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use se::Serializer;
+    use std::io::Cursor;
+
+    #[test]
+    fn buffers_are_reused() {
+        let pool = BufferPool::new(2, 64);
+        let ptr = {
+            let mut buffer = pool.get();
+            buffer.extend_from_slice(&[1, 2, 3]);
+            buffer.as_ptr()
+        };
+        assert_eq!(pool.idle(), 1);
+
+        let buffer = pool.get();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(pool.idle(), 0);
+    }
+
+    #[test]
+    fn pool_is_bounded() {
+        let pool = BufferPool::new(1, 64);
+        let first = pool.get();
+        let second = pool.get();
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn detached_buffers_are_not_returned() {
+        let pool = BufferPool::new(1, 64);
+        let mut serializer = Serializer::new_pooled(&pool);
+        serializer.write_unsigned_integer(0).unwrap();
+        assert_eq!(serializer.finalize().detach(), vec![0]);
+        assert_eq!(pool.idle(), 0);
+    }
+
+    #[test]
+    fn bytes_pooled_indefinite() {
+        let pool = BufferPool::new(1, 64);
+        let vec = vec![0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff];
+        let mut raw = Deserializer::from(Cursor::new(vec));
+        let bytes = raw.bytes_pooled(&pool).unwrap();
+        assert_eq!(&bytes[..], [1, 2, 3].as_ref());
+    }
+}
@@ -0,0 +1,137 @@
+//! Report the progress of long running decodings
+//!
+//! Wrapping the reader of a [`Deserializer`] in a [`ProgressReader`] calls
+//! a function with the number of bytes consumed so far every time a given
+//! amount of input has been decoded. Useful to drive progress bars or
+//! metrics while importing large files.
+//!
+//! The current offset is also available at any time with
+//! [`position`](./struct.ProgressReader.html#method.position), for example
+//! to report progress after every top level object.
+//!
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//! [`ProgressReader`]: ./struct.ProgressReader.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, progress::ProgressReader};
+//!
+//! // sequence of 100 byte strings of 99 bytes each
+//! let mut bytes = Vec::new();
+//! for _ in 0..100 {
+//!     bytes.extend_from_slice(&[0x58, 0x61]);
+//!     bytes.extend_from_slice(&[0; 97]);
+//! }
+//!
+//! let mut reports = Vec::new();
+//! let reader = ProgressReader::new(&bytes[..], 2500, |offset| reports.push(offset));
+//! let mut raw = Deserializer::from(reader);
+//! while raw.as_ref().position() < bytes.len() as u64 {
+//!     raw.skip_value().unwrap();
+//! }
+//! drop(raw);
+//!
+//! assert_eq!(reports, vec![2574, 5148, 7722]);
+//! ```
+
+use std::io::{self, BufRead, Read};
+
+/// reader adapter calling a function every `interval` consumed bytes, see
+/// the [module documentation](./index.html).
+pub struct ProgressReader<R, F> {
+    reader: R,
+    position: u64,
+    interval: u64,
+    next_report: u64,
+    callback: F,
+}
+impl<R, F: FnMut(u64)> ProgressReader<R, F> {
+    /// call `callback` with the number of bytes consumed so far every time
+    /// at least `interval` more bytes have been consumed since the last
+    /// call.
+    ///
+    /// # Panics
+    ///
+    /// if `interval` is `0`.
+    pub fn new(reader: R, interval: u64, callback: F) -> Self {
+        assert!(interval > 0, "the progress interval cannot be 0");
+        ProgressReader {
+            reader,
+            position: 0,
+            interval,
+            next_report: interval,
+            callback,
+        }
+    }
+
+    /// number of bytes consumed so far
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn advance(&mut self, amt: usize) {
+        self.position += amt as u64;
+        if self.position >= self.next_report {
+            (self.callback)(self.position);
+            self.next_report = self.position + self.interval;
+        }
+    }
+}
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.advance(read);
+        Ok(read)
+    }
+}
+impl<R: BufRead, F: FnMut(u64)> BufRead for ProgressReader<R, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+        self.advance(amt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use se::Serializer;
+    use std::io::BufReader;
+
+    #[test]
+    fn reports() {
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Len(3))
+            .unwrap()
+            .write_bytes(vec![0; 1000])
+            .unwrap()
+            .write_text("small")
+            .unwrap()
+            .write_bytes(vec![0; 1000])
+            .unwrap();
+        let bytes = se.finalize();
+
+        let mut reports = Vec::new();
+        {
+            let reader = BufReader::with_capacity(16, &bytes[..]);
+            let reader = ProgressReader::new(reader, 500, |offset| reports.push(offset));
+            let mut raw = Deserializer::from(reader);
+            raw.validate_item().unwrap();
+            assert_eq!(raw.as_ref().position(), bytes.len() as u64);
+        }
+        assert!(reports.windows(2).all(|pair| pair[1] >= pair[0] + 500));
+        assert!(reports.len() >= 3);
+        assert!(*reports.last().unwrap() <= bytes.len() as u64);
+    }
+}
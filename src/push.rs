@@ -0,0 +1,182 @@
+//! Push style decoding of partial input
+//!
+//! Non blocking network code receives the input in chunks of arbitrary
+//! sizes and cannot wait for the rest of an object inside a blocking read.
+//! A [`PushDecoder`] is fed the chunks as they arrive and returns the
+//! objects once they are complete, or how many more bytes it needs at the
+//! least before it can make progress.
+//!
+//! The decoder only keeps the bytes of the object being received and
+//! resumes scanning where it stopped: feeding an object byte per byte
+//! costs the same as feeding it at once.
+//!
+//! [`PushDecoder`]: ./struct.PushDecoder.html
+//!
+//! ```
+//! use cbor_event::push::{Decoded, PushDecoder};
+//!
+//! let mut decoder = PushDecoder::new();
+//!
+//! // "hello" followed by the start of [1, 2]
+//! decoder.feed(&[0x65, 0x68, 0x65]);
+//! assert_eq!(decoder.decode::<String>().unwrap(), Decoded::NeedMoreData(3));
+//! decoder.feed(&[0x6c, 0x6c, 0x6f, 0x82, 0x01]);
+//! assert_eq!(decoder.decode::<String>().unwrap(), Decoded::Item("hello".to_owned()));
+//! assert_eq!(decoder.decode::<Vec<u64>>().unwrap(), Decoded::NeedMoreData(1));
+//! decoder.feed(&[0x02]);
+//! assert_eq!(decoder.decode::<Vec<u64>>().unwrap(), Decoded::Item(vec![1, 2]));
+//! assert!(decoder.is_empty());
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan::{self, Walker};
+
+/// result of [`PushDecoder::decode`](./struct.PushDecoder.html#method.decode)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded<T> {
+    /// the next object of the input, complete
+    Item(T),
+    /// the next object is incomplete: at least that many more bytes must
+    /// be fed before it can be decoded.
+    NeedMoreData(usize),
+}
+
+/// incremental decoder of a stream of CBOR objects, see the
+/// [module documentation](./index.html).
+///
+/// After `decode` returned an error the input cannot be resynchronised and
+/// the decoder should be discarded.
+#[derive(Default)]
+pub struct PushDecoder {
+    buffer: Vec<u8>,
+    // offset of the first byte of `buffer` not walked yet
+    pos: usize,
+    // bytes of the payload of a string still to receive
+    payload_left: u64,
+    walker: Walker,
+}
+impl PushDecoder {
+    pub fn new() -> Self {
+        PushDecoder::default()
+    }
+
+    /// append a chunk of input
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk)
+    }
+
+    /// number of bytes fed but not yet returned as part of an object
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// returns `true` if there is no partially received object
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// decode the next object if all its bytes have been fed.
+    ///
+    /// Fails if the input is not well-formed or if the object cannot be
+    /// decoded as a `T`.
+    pub fn decode<T: Deserialize>(&mut self) -> Result<Decoded<T>> {
+        loop {
+            if self.payload_left > 0 {
+                let available = (self.buffer.len() - self.pos) as u64;
+                let n = available.min(self.payload_left);
+                self.pos += n as usize;
+                self.payload_left -= n;
+                if self.payload_left > 0 {
+                    return Ok(Decoded::NeedMoreData(self.payload_left as usize));
+                }
+            }
+            if self.walker.is_finished() {
+                let item = Deserializer::from(&self.buffer[..self.pos]).deserialize_complete();
+                self.buffer.drain(..self.pos);
+                self.pos = 0;
+                self.walker = Walker::default();
+                return item.map(Decoded::Item);
+            }
+            let (cbor_type, len, sz) = match scan::parse_header(&self.buffer[self.pos..]) {
+                Err(Error::NotEnough(got, expected)) => {
+                    return Ok(Decoded::NeedMoreData(expected - got))
+                }
+                header => header?,
+            };
+            self.payload_left = self.walker.header(cbor_type, len, sz)?;
+            self.pos += sz;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use value::Value;
+
+    #[test]
+    fn byte_per_byte() {
+        let mut se = Serializer::new_vec();
+        se.write_array(::Len::Indefinite)
+            .unwrap()
+            .write_bytes(vec![0xaa; 300])
+            .unwrap()
+            .write_tag(1)
+            .unwrap()
+            .write_map(::Len::Len(1))
+            .unwrap()
+            .write_text("key")
+            .unwrap()
+            .write_unsigned_integer(0x1_0000_0000)
+            .unwrap()
+            .write_special(::Special::Break)
+            .unwrap()
+            .write_negative_integer(-1000)
+            .unwrap();
+        let bytes = se.finalize();
+        let mut expected = ::sequence::decode::<Value>(&bytes).unwrap().into_iter();
+
+        let mut decoder = PushDecoder::new();
+        let mut needed = 0;
+        for byte in bytes.iter() {
+            decoder.feed(&[*byte]);
+            needed = needed.max(1) - 1;
+            if needed > 0 {
+                continue;
+            }
+            match decoder.decode::<Value>().unwrap() {
+                Decoded::Item(item) => assert_eq!(Some(item), expected.next()),
+                Decoded::NeedMoreData(n) => needed = n,
+            }
+        }
+        assert_eq!(expected.next(), None);
+        assert!(decoder.is_empty());
+        assert_eq!(decoder.decode::<Value>().unwrap(), Decoded::NeedMoreData(1));
+    }
+
+    #[test]
+    fn several_items_in_one_chunk() {
+        let mut decoder = PushDecoder::new();
+        decoder.feed(&[0x01, 0x02, 0x19]);
+        assert_eq!(decoder.decode::<u64>().unwrap(), Decoded::Item(1));
+        assert_eq!(decoder.decode::<u64>().unwrap(), Decoded::Item(2));
+        assert_eq!(decoder.decode::<u64>().unwrap(), Decoded::NeedMoreData(2));
+        assert_eq!(decoder.buffered(), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        let mut decoder = PushDecoder::new();
+        decoder.feed(&[0x81, 0xff]);
+        assert!(decoder.decode::<Value>().is_err());
+
+        let mut decoder = PushDecoder::new();
+        decoder.feed(&[0x61, 0x61]);
+        assert!(decoder.decode::<u64>().is_err());
+    }
+}
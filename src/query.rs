@@ -0,0 +1,305 @@
+//! Query encoded CBOR without decoding it
+//!
+//! A [`Query`] navigates the encoded bytes directly: the siblings of the
+//! objects on the path are skipped by length arithmetic, only the matching
+//! object is returned (as encoded bytes or decoded into a [`Value`]).
+//!
+//! # Syntax
+//!
+//! A query is a sequence of steps, optionally starting with `$`:
+//!
+//! - `.name` or `["name"]`: the value of the map entry whose key is the
+//!   text `name`;
+//! - `[n]`: the n-th element of an array, or the value of the map entry
+//!   whose key is the integer `n` (which may be negative).
+//!
+//! The first `.` may be omitted (`a.b` is the same as `.a.b`). Tags are
+//! transparent: a step applied to a tagged object applies to its content.
+//!
+//! [`Query`]: ./struct.Query.html
+//! [`Value`]: ../enum.Value.html
+//!
+//! ```
+//! use cbor_event::{query::{query, query_value}, Value};
+//!
+//! // {"header": {"id": 42}, "items": [h'00', h'0102']}
+//! let bytes = [
+//!     0xa2, 0x66, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72, 0xa1, 0x62, 0x69, 0x64, 0x18, 0x2a,
+//!     0x65, 0x69, 0x74, 0x65, 0x6d, 0x73, 0x82, 0x41, 0x00, 0x42, 0x01, 0x02,
+//! ];
+//!
+//! assert_eq!(query(&bytes, "items[1]").unwrap(), Some(&[0x42, 0x01, 0x02][..]));
+//! assert_eq!(query_value(&bytes, "$.header.id").unwrap(), Some(Value::U64(42)));
+//! assert_eq!(query(&bytes, "header.name").unwrap(), None);
+//! ```
+
+use de::Deserializer;
+use error::Error;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use std::str::FromStr;
+use types::Type;
+use value::Value;
+
+const BREAK: u8 = 0xff;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(i128),
+}
+
+/// a parsed query expression, see the [module documentation](./index.html)
+/// for the syntax.
+///
+/// Parsing the expression once is cheaper when running the same query on
+/// many objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query(Vec<Step>);
+impl Query {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let invalid = |pos| Error::InvalidQuery(expression.to_owned(), pos);
+        let mut steps = Vec::new();
+        let mut rest = expression.strip_prefix('$').unwrap_or(expression);
+        // the first `.` may only be omitted without `$`
+        let mut first = rest.len() == expression.len();
+        while !rest.is_empty() {
+            let pos = expression.len() - rest.len();
+            if let Some(bracket) = rest.strip_prefix('[') {
+                let end = bracket.find(']').ok_or_else(|| invalid(pos))?;
+                let inner = &bracket[..end];
+                let step = if inner.len() >= 2 && inner.starts_with('"') && inner.ends_with('"') {
+                    Step::Key(inner[1..inner.len() - 1].to_owned())
+                } else {
+                    Step::Index(inner.parse().map_err(|_| invalid(pos + 1))?)
+                };
+                steps.push(step);
+                rest = &bracket[end + 1..];
+            } else {
+                let name = match rest.strip_prefix('.') {
+                    Some(name) => name,
+                    None if first => rest,
+                    None => return Err(invalid(pos)),
+                };
+                let end = name.find(['.', '[']).unwrap_or(name.len());
+                if end == 0 {
+                    return Err(invalid(pos));
+                }
+                steps.push(Step::Key(name[..end].to_owned()));
+                rest = &name[end..];
+            }
+            first = false;
+        }
+        Ok(Query(steps))
+    }
+
+    /// returns the encoded bytes of the object matching the query, `None`
+    /// if there is none.
+    ///
+    /// Only the objects skipped on the way are checked to be well-formed.
+    pub fn find<'a>(&self, bytes: &'a [u8]) -> Result<Option<&'a [u8]>> {
+        let mut pos = 0;
+        for step in self.0.iter() {
+            pos = skip_tags(bytes, pos)?;
+            let found = match scan::parse_header(&bytes[pos..])? {
+                (Type::Array, len, sz) => match step {
+                    Step::Index(index) if *index >= 0 => {
+                        find_element(bytes, pos + sz, len, *index as u64)?
+                    }
+                    _ => None,
+                },
+                (Type::Map, len, sz) => find_entry(bytes, pos + sz, len, step)?,
+                _ => None,
+            };
+            match found {
+                Some(found) => pos = found,
+                None => return Ok(None),
+            }
+        }
+        let len = scan::item_len(&bytes[pos..])?;
+        Ok(Some(&bytes[pos..pos + len]))
+    }
+
+    /// decode the object matching the query, `None` if there is none.
+    pub fn find_value(&self, bytes: &[u8]) -> Result<Option<Value>> {
+        match self.find(bytes)? {
+            None => Ok(None),
+            Some(item) => Deserializer::from(item).deserialize_complete().map(Some),
+        }
+    }
+}
+impl FromStr for Query {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        Query::parse(s)
+    }
+}
+
+/// returns the encoded bytes of the object matching the query
+/// `expression`, see [`Query::find`](./struct.Query.html#method.find).
+pub fn query<'a>(bytes: &'a [u8], expression: &str) -> Result<Option<&'a [u8]>> {
+    Query::parse(expression)?.find(bytes)
+}
+
+/// decode the object matching the query `expression`, see
+/// [`Query::find_value`](./struct.Query.html#method.find_value).
+pub fn query_value(bytes: &[u8], expression: &str) -> Result<Option<Value>> {
+    Query::parse(expression)?.find_value(bytes)
+}
+
+fn skip_tags(bytes: &[u8], mut pos: usize) -> Result<usize> {
+    loop {
+        match scan::parse_header(&bytes[pos..])? {
+            (Type::Tag, _, sz) => pos += sz,
+            _ => return Ok(pos),
+        }
+    }
+}
+
+// returns true if there is no more items in the container being iterated
+fn at_end(bytes: &[u8], pos: usize, len: Len, count: u64) -> Result<bool> {
+    match len {
+        Len::Len(len) => Ok(count >= len),
+        Len::Indefinite => match bytes.get(pos) {
+            None => Err(Error::NotEnough(bytes.len(), pos + 1)),
+            Some(byte) => Ok(*byte == BREAK),
+        },
+    }
+}
+
+fn find_element(bytes: &[u8], mut pos: usize, len: Len, index: u64) -> Result<Option<usize>> {
+    let mut count = 0;
+    while !at_end(bytes, pos, len, count)? {
+        if count == index {
+            return Ok(Some(pos));
+        }
+        pos += scan::item_len(&bytes[pos..])?;
+        count += 1;
+    }
+    Ok(None)
+}
+
+fn find_entry(bytes: &[u8], mut pos: usize, len: Len, step: &Step) -> Result<Option<usize>> {
+    let mut count = 0;
+    while !at_end(bytes, pos, len, count)? {
+        let key_len = scan::item_len(&bytes[pos..])?;
+        let matches = key_matches(&bytes[pos..pos + key_len], step)?;
+        pos += key_len;
+        if matches {
+            return Ok(Some(pos));
+        }
+        pos += scan::item_len(&bytes[pos..])?;
+        count += 1;
+    }
+    Ok(None)
+}
+
+fn key_matches(key: &[u8], step: &Step) -> Result<bool> {
+    let matches = match (scan::parse_header(key)?, step) {
+        ((Type::Text, Len::Len(_), sz), Step::Key(name)) => &key[sz..] == name.as_bytes(),
+        ((Type::Text, Len::Indefinite, _), Step::Key(name)) => {
+            Deserializer::from(key).text()? == *name
+        }
+        ((Type::UnsignedInteger, Len::Len(value), _), Step::Index(index)) => {
+            value as i128 == *index
+        }
+        ((Type::NegativeInteger, Len::Len(value), _), Step::Index(index)) => {
+            -1 - value as i128 == *index
+        }
+        _ => false,
+    };
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use types::Special;
+
+    #[test]
+    fn parse() {
+        let expected = Query(vec![
+            Step::Key("a".to_owned()),
+            Step::Index(-2),
+            Step::Key("b.c".to_owned()),
+            Step::Key("d".to_owned()),
+        ]);
+        assert_eq!(Query::parse("a[-2][\"b.c\"].d").unwrap(), expected);
+        assert_eq!("$.a[-2][\"b.c\"].d".parse::<Query>().unwrap(), expected);
+        assert_eq!(Query::parse("$").unwrap(), Query(vec![]));
+        assert_eq!(Query::parse("").unwrap(), Query(vec![]));
+
+        for invalid in ["a..b", "a[", "a[x]", "a.", "$a", "[0]b"].iter() {
+            assert!(Query::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    fn record() -> Vec<u8> {
+        // {"id": 1, -1: [_ "x", 24(["y"])], 2: {"k": null}}
+        let mut se = Serializer::new_vec();
+        se.write_map(Len::Len(3))
+            .unwrap()
+            .write_text("id")
+            .unwrap()
+            .write_unsigned_integer(1)
+            .unwrap()
+            .write_negative_integer(-1)
+            .unwrap()
+            .write_array(Len::Indefinite)
+            .unwrap()
+            .write_text("x")
+            .unwrap()
+            .write_tag(24)
+            .unwrap()
+            .write_array(Len::Len(1))
+            .unwrap()
+            .write_text("y")
+            .unwrap()
+            .write_special(Special::Break)
+            .unwrap()
+            .write_unsigned_integer(2)
+            .unwrap()
+            .write_map(Len::Len(1))
+            .unwrap()
+            .write_text("k")
+            .unwrap()
+            .write_special(Special::Null)
+            .unwrap();
+        se.finalize()
+    }
+
+    #[test]
+    fn find() {
+        let bytes = record();
+        assert_eq!(query(&bytes, "").unwrap(), Some(&bytes[..]));
+        assert_eq!(query(&bytes, "id").unwrap(), Some(&[0x01][..]));
+        assert_eq!(query(&bytes, "[-1][1]").unwrap(), Some(&bytes[9..14]));
+        assert_eq!(
+            query_value(&bytes, "[-1][1][0]").unwrap(),
+            Some(Value::Text("y".to_owned()))
+        );
+        assert_eq!(
+            query_value(&bytes, "[2].k").unwrap(),
+            Some(Value::Special(Special::Null))
+        );
+    }
+
+    #[test]
+    fn not_found() {
+        let bytes = record();
+        for missing in ["name", "[1]", "[-1][2]", "[-1].x", "id.x", "[2][0]"].iter() {
+            assert_eq!(query(&bytes, missing).unwrap(), None, "{}", missing);
+        }
+    }
+
+    #[test]
+    fn text_keys_of_indefinite_length() {
+        // {(_ "a", "b"): 1}
+        let bytes = [0xa1, 0x7f, 0x61, 0x61, 0x61, 0x62, 0xff, 0x01];
+        assert_eq!(query(&bytes, "ab").unwrap(), Some(&[0x01][..]));
+    }
+}
@@ -0,0 +1,123 @@
+use de::{Deserialize, Deserializer};
+use diag::DebugCbor;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use se::{Serialize, Serializer};
+use std::{
+    fmt,
+    io::{BufRead, Write},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// one complete, well-formed, encoded CBOR object.
+///
+/// The natural representation of an opaque field passed through as is:
+/// decoding it captures the exact bytes of the object and encoding it
+/// writes them back verbatim, so they can be hashed or signed. Cloning is
+/// cheap, the bytes are shared.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, se::Serializer, RawCbor};
+///
+/// // [1, {"a": h'00'}]
+/// let bytes = vec![0x82, 0x01, 0xa1, 0x61, 0x61, 0x41, 0x00];
+///
+/// let mut raw = Deserializer::from(&bytes[..]);
+/// raw.array().unwrap();
+/// assert_eq!(raw.unsigned_integer().unwrap(), 1);
+/// let extension: RawCbor = raw.deserialize().unwrap();
+/// assert_eq!(&extension[..], &bytes[2..]);
+///
+/// let mut se = Serializer::new_vec();
+/// se.write_array(cbor_event::Len::Len(2)).unwrap()
+///     .serialize(&1u64).unwrap()
+///     .serialize(&extension).unwrap();
+/// assert_eq!(se.finalize(), bytes);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RawCbor(Arc<[u8]>);
+impl RawCbor {
+    /// take `bytes` if they hold exactly one well-formed CBOR object.
+    pub fn new(bytes: Vec<u8>) -> Result<Self> {
+        scan::validate(&bytes)?;
+        Ok(RawCbor(bytes.into()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// decode the object as a `T`.
+    pub fn decode<T: Deserialize>(&self) -> Result<T> {
+        Deserializer::from(self.as_bytes()).deserialize_complete()
+    }
+}
+
+impl Deref for RawCbor {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+impl AsRef<[u8]> for RawCbor {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+impl fmt::Debug for RawCbor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RawCbor({})", DebugCbor(&self.0))
+    }
+}
+
+impl Serialize for RawCbor {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_raw_bytes(&self.0)
+    }
+}
+impl Deserialize for RawCbor {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        raw.raw_item_into(&mut bytes)?;
+        Ok(RawCbor(bytes.into()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use error::Error;
+
+    #[test]
+    fn new() {
+        let raw = RawCbor::new(vec![0x82, 0x01, 0x02]).unwrap();
+        assert_eq!(raw.decode::<Vec<u64>>().unwrap(), vec![1, 2]);
+        assert_eq!(format!("{:?}", raw), "RawCbor([1, 2])");
+
+        assert!(RawCbor::new(vec![0x82, 0x01]).is_err());
+        match RawCbor::new(vec![0x01, 0x02]) {
+            Err(Error::TrailingData) => {}
+            result => panic!("expected trailing data error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn indefinite_round_trip() {
+        // [_ "a", (_ h'01', h'02')]
+        let bytes = vec![0x9f, 0x61, 0x61, 0x5f, 0x41, 0x01, 0x41, 0x02, 0xff, 0xff];
+        let raw: RawCbor = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+        assert_eq!(raw.as_bytes(), &bytes[..]);
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&raw.clone()).unwrap();
+        assert_eq!(se.finalize(), bytes);
+    }
+}
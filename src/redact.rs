@@ -0,0 +1,252 @@
+//! Structure preserving redaction of encoded CBOR
+//!
+//! Payloads attached to bug reports often carry keys, personal data or
+//! simply megabytes of irrelevant content. A [`Redactor`] copies an
+//! encoded object replacing the large strings, and the objects at given
+//! [`Query`] paths, with short placeholders recording the size of what was
+//! removed. Everything else, including the non canonical encodings, is
+//! copied byte for byte.
+//!
+//! [`Redactor`]: ./struct.Redactor.html
+//! [`Query`]: ../query/struct.Query.html
+//!
+//! ```
+//! use cbor_event::{query::Query, redact::Redactor, Value};
+//!
+//! // {"key": h'0102...20', "name": "alice"}
+//! let mut bytes = vec![0xa2, 0x63, 0x6b, 0x65, 0x79, 0x58, 0x20];
+//! bytes.extend(1..=32);
+//! bytes.extend(&[0x64, 0x6e, 0x61, 0x6d, 0x65, 0x65, 0x61, 0x6c, 0x69, 0x63, 0x65]);
+//!
+//! let redactor = Redactor::new()
+//!     .threshold(16)
+//!     .path(Query::parse("name").unwrap());
+//! let redacted = redactor.redact(&bytes).unwrap();
+//!
+//! let query = Query::parse("key").unwrap();
+//! assert_eq!(
+//!     query.find_value(&redacted).unwrap(),
+//!     Some(Value::Bytes(b"<redacted 32 bytes>".to_vec()))
+//! );
+//! let query = Query::parse("name").unwrap();
+//! assert_eq!(
+//!     query.find_value(&redacted).unwrap(),
+//!     Some(Value::Text("<redacted 5 bytes>".to_owned()))
+//! );
+//! ```
+
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use query::Query;
+use result::Result;
+use scan;
+use se::Serializer;
+use types::Type;
+
+/// copy encoded CBOR objects replacing sensitive content with placeholders
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    threshold: Option<u64>,
+    paths: Vec<Query>,
+}
+impl Redactor {
+    /// a redactor copying objects as they are, configure it with
+    /// [`threshold`](#method.threshold) and [`path`](#method.path).
+    pub fn new() -> Self {
+        Redactor::default()
+    }
+
+    /// redact the byte and text strings of more than `len` bytes. They are
+    /// replaced by a string of the same type `<redacted N bytes>` where
+    /// `N` is the size of their content.
+    pub fn threshold(mut self, len: u64) -> Self {
+        self.threshold = Some(len);
+        self
+    }
+
+    /// redact the object matching `query`. Strings are replaced as for
+    /// [`threshold`](#method.threshold), other objects are replaced by
+    /// the text `<redacted N bytes>` where `N` is the size of their
+    /// encoding.
+    pub fn path(mut self, query: Query) -> Self {
+        self.paths.push(query);
+        self
+    }
+
+    /// copy the CBOR object encoded in `bytes` with the configured
+    /// content redacted.
+    ///
+    /// Fails if `bytes` is not exactly one well-formed CBOR object.
+    pub fn redact(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let layout = scan::Layout::new(bytes)?;
+        let items = layout.items();
+
+        // encoded ranges of the objects matched by the paths
+        let mut matched = Vec::new();
+        for query in self.paths.iter() {
+            if let Some(item) = query.find(bytes)? {
+                let offset = item.as_ptr() as usize - bytes.as_ptr() as usize;
+                matched.push((offset, item.len()));
+            }
+        }
+
+        // (offset, encoded length, placeholder) in order of appearance
+        let mut replacements = Vec::new();
+        let mut index = 0;
+        while index < items.len() {
+            let item = &items[index];
+            let is_string = item.cbor_type == Type::Bytes || item.cbor_type == Type::Text;
+            let size = if is_string {
+                layout.string_len(index)
+            } else {
+                0
+            };
+
+            if let Some(&(_, item_len)) = matched.iter().find(|(offset, _)| *offset == item.pos) {
+                let placeholder = if is_string {
+                    placeholder(item.cbor_type, size)?
+                } else {
+                    placeholder(Type::Text, item_len as u64)?
+                };
+                replacements.push((item.pos, item_len, placeholder));
+                index = item.next;
+                continue;
+            }
+            if is_string {
+                if self.threshold.is_some_and(|threshold| size > threshold) {
+                    let placeholder = placeholder(item.cbor_type, size)?;
+                    replacements.push((item.pos, item.end - item.pos, placeholder));
+                }
+                index = item.next;
+                continue;
+            }
+            index += 1;
+        }
+
+        let mut redacted = Vec::with_capacity(bytes.len());
+        let mut copied = 0;
+        for (offset, item_len, placeholder) in replacements {
+            redacted.extend_from_slice(&bytes[copied..offset]);
+            redacted.extend_from_slice(&placeholder);
+            copied = offset + item_len;
+        }
+        redacted.extend_from_slice(&bytes[copied..]);
+        Ok(redacted)
+    }
+}
+
+fn placeholder(cbor_type: Type, size: u64) -> Result<Vec<u8>> {
+    let text = format!("<redacted {} bytes>", size);
+    let mut se = Serializer::new_vec();
+    if cbor_type == Type::Bytes {
+        se.write_bytes(text.as_bytes())?;
+    } else {
+        se.write_text(text)?;
+    }
+    Ok(se.finalize())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use de::Deserializer;
+    use len::Len;
+    use value::Value;
+
+    fn payload() -> Vec<u8> {
+        // [{_ "secret": {"a": 1}}, 24((_ h'0102', h'030405')), "ok"]
+        let mut se = Serializer::new_vec();
+        se.write_array(Len::Len(3))
+            .unwrap()
+            .write_map(Len::Indefinite)
+            .unwrap()
+            .write_text("secret")
+            .unwrap()
+            .write_map(Len::Len(1))
+            .unwrap()
+            .write_text("a")
+            .unwrap()
+            .write_unsigned_integer(1)
+            .unwrap()
+            .write_special(::Special::Break)
+            .unwrap()
+            .write_tag(24)
+            .unwrap();
+        let mut bytes = se.finalize();
+        bytes.extend_from_slice(&[0x5f, 0x42, 0x01, 0x02, 0x43, 0x03, 0x04, 0x05, 0xff]);
+        bytes.extend_from_slice(&[0x62, 0x6f, 0x6b]);
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Value {
+        Deserializer::from(bytes).deserialize_complete().unwrap()
+    }
+
+    #[test]
+    fn nothing_to_redact() {
+        let bytes = payload();
+        assert_eq!(Redactor::new().redact(&bytes).unwrap(), bytes);
+        assert_eq!(Redactor::new().threshold(6).redact(&bytes).unwrap(), bytes);
+    }
+
+    #[test]
+    fn threshold() {
+        let bytes = payload();
+        let redacted = Redactor::new().threshold(2).redact(&bytes).unwrap();
+        let expected = Value::Array(vec![
+            Value::IObject(
+                vec![(
+                    ::ObjectKey::Text("<redacted 6 bytes>".to_owned()),
+                    Value::Object(
+                        vec![(::ObjectKey::Text("a".to_owned()), Value::U64(1))]
+                            .into_iter()
+                            .collect(),
+                    ),
+                )]
+                .into_iter()
+                .collect(),
+            ),
+            Value::Tag(24, Box::new(Value::Bytes(b"<redacted 5 bytes>".to_vec()))),
+            Value::Text("ok".to_owned()),
+        ]);
+        assert_eq!(decode(&redacted), expected);
+    }
+
+    #[test]
+    fn paths() {
+        let bytes = payload();
+        let redacted = Redactor::new()
+            .path(Query::parse("[0].secret").unwrap())
+            .path(Query::parse("[2]").unwrap())
+            .path(Query::parse("[3]").unwrap())
+            .redact(&bytes)
+            .unwrap();
+        // the prefix up to the redacted map is preserved
+        assert_eq!(&redacted[..9], &bytes[..9]);
+        // the map is replaced by its encoded size, the text by its length
+        assert_eq!(
+            ::query::query_value(&redacted, "[0].secret").unwrap(),
+            Some(Value::Text("<redacted 4 bytes>".to_owned()))
+        );
+        assert_eq!(
+            ::query::query_value(&redacted, "[2]").unwrap(),
+            Some(Value::Text("<redacted 2 bytes>".to_owned()))
+        );
+        decode(&redacted);
+    }
+
+    #[test]
+    fn deeply_nested() {
+        // 100000 nested arrays around a byte string of 32 bytes
+        let mut bytes = vec![0x81; 100_000];
+        bytes.push(0x58);
+        bytes.push(32);
+        bytes.extend_from_slice(&[0; 32]);
+        let redacted = Redactor::new().threshold(16).redact(&bytes).unwrap();
+        assert_eq!(&redacted[..100_000], &bytes[..100_000]);
+        assert_eq!(
+            &redacted[100_000..],
+            placeholder(Type::Bytes, 32).unwrap().as_slice()
+        );
+    }
+}
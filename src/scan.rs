@@ -0,0 +1,605 @@
+//! scanning of encoded CBOR held in memory
+//!
+//! The functions here work directly on the encoded bytes: they only walk
+//! the headers of the objects (skipping the payloads by length arithmetic)
+//! and never allocate for the decoded values.
+
+use de::Deserializer;
+use error::Error;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use std::io::BufRead;
+use types::Type;
+
+/// parse the header of the CBOR object at the beginning of `bytes`.
+///
+/// Returns the [`Type`] of the object, its [`Len`] (the value for integers,
+/// tags and specials) and the number of bytes of the header. Only the
+/// header is read: framing code can inspect objects without a
+/// [`Deserializer`].
+///
+/// [`Type`]: ./enum.Type.html
+/// [`Len`]: ./enum.Len.html
+/// [`Deserializer`]: ./de/struct.Deserializer.html
+///
+/// ```
+/// use cbor_event::{parse_header, Len, Type};
+///
+/// // a byte string of 300 bytes, only partially received
+/// let bytes = [0x59, 0x01, 0x2c, 0x00, 0x00];
+/// assert_eq!(parse_header(&bytes).unwrap(), (Type::Bytes, Len::Len(300), 3));
+///
+/// assert_eq!(parse_header(&[0x9f]).unwrap(), (Type::Array, Len::Indefinite, 1));
+/// assert!(parse_header(&[0x19, 0x01]).is_err());
+/// ```
+pub fn parse_header(bytes: &[u8]) -> Result<(Type, Len, usize)> {
+    let byte = match bytes.first() {
+        None => return Err(Error::NotEnough(0, 1)),
+        Some(byte) => *byte,
+    };
+    let cbor_type = Type::from(byte);
+    let info = byte & 0b0001_1111;
+    let sz = match info {
+        0x00..=0x17 => return Ok((cbor_type, Len::Len(info as u64), 1)),
+        0x18 => 1,
+        0x19 => 2,
+        0x1a => 4,
+        0x1b => 8,
+        0x1f => return Ok((cbor_type, Len::Indefinite, 1)),
+        _ => return Err(Error::UnknownLenType(info)),
+    };
+    if bytes.len() < 1 + sz {
+        return Err(Error::NotEnough(bytes.len(), 1 + sz));
+    }
+    let value = bytes[1..=sz]
+        .iter()
+        .fold(0u64, |acc, byte| acc << 8 | *byte as u64);
+    Ok((cbor_type, Len::Len(value), 1 + sz))
+}
+
+/// source of encoded CBOR walked by [`walk_item`](./fn.walk_item.html)
+pub(crate) trait Source {
+    /// parse the header of the next object without consuming it, see
+    /// [`parse_header`](./fn.parse_header.html).
+    fn header(&mut self) -> Result<(Type, Len, usize)>;
+
+    /// consume the `len` bytes of the header returned by `header`.
+    fn consume_header(&mut self, len: usize) -> Result<()>;
+
+    /// consume the `len` bytes of the payload of a bytes or text.
+    fn consume_payload(&mut self, len: u64) -> Result<()>;
+}
+
+struct SliceSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Source for SliceSource<'a> {
+    fn header(&mut self) -> Result<(Type, Len, usize)> {
+        parse_header(&self.bytes[self.pos..])
+    }
+    fn consume_header(&mut self, len: usize) -> Result<()> {
+        self.pos += len;
+        Ok(())
+    }
+    fn consume_payload(&mut self, len: u64) -> Result<()> {
+        let available = (self.bytes.len() - self.pos) as u64;
+        if len > available {
+            return Err(Error::NotEnough(
+                self.bytes.len(),
+                self.pos.saturating_add(len as usize),
+            ));
+        }
+        self.pos += len as usize;
+        Ok(())
+    }
+}
+
+// what is left to read to complete the container being walked
+enum Pending {
+    // number of items left in a definite length array, map or tag
+    Items(u64),
+    // items read so far in an indefinite length array (`false`) or map (`true`)
+    Indefinite(bool, u64),
+    // chunks of an indefinite length bytes or text
+    Chunks(Type),
+}
+
+/// state of the walk of one CBOR object, fed with the headers of the object
+/// one after the other. Can be suspended between any two headers, so the
+/// same logic serves readers and incremental decoding.
+#[derive(Default)]
+pub(crate) struct Walker {
+    stack: Vec<Pending>,
+    finished: bool,
+}
+impl Walker {
+    /// account for the next header of the object, checking it is
+    /// well-formed (RFC 8949 section 5.3.1). Returns the size of the payload
+    /// following the header.
+    pub(crate) fn header(&mut self, cbor_type: Type, len: Len, sz: usize) -> Result<u64> {
+        if let Some(Pending::Chunks(chunk_type)) = self.stack.last() {
+            let is_break = cbor_type == Type::Special && len == Len::Indefinite;
+            if !is_break && (cbor_type != *chunk_type || len == Len::Indefinite) {
+                return Err(Error::InvalidIndefiniteString);
+            }
+        }
+
+        let mut payload = 0;
+        let mut complete = true;
+        match (cbor_type, len) {
+            (Type::UnsignedInteger, Len::Indefinite)
+            | (Type::NegativeInteger, Len::Indefinite)
+            | (Type::Tag, Len::Indefinite) => {
+                return Err(Error::IndefiniteLenNotSupported(cbor_type))
+            }
+            (Type::UnsignedInteger, _) | (Type::NegativeInteger, _) => {}
+            (Type::Bytes, Len::Len(len)) | (Type::Text, Len::Len(len)) => payload = len,
+            (Type::Bytes, Len::Indefinite) | (Type::Text, Len::Indefinite) => {
+                self.stack.push(Pending::Chunks(cbor_type));
+                complete = false;
+            }
+            (Type::Array, Len::Len(0)) | (Type::Map, Len::Len(0)) => {}
+            (Type::Array, Len::Len(len)) => {
+                self.stack.push(Pending::Items(len));
+                complete = false;
+            }
+            (Type::Map, Len::Len(len)) => {
+                let len = len.checked_mul(2).ok_or(Error::NotEnough(0, usize::MAX))?;
+                self.stack.push(Pending::Items(len));
+                complete = false;
+            }
+            (Type::Tag, Len::Len(_)) => {
+                self.stack.push(Pending::Items(1));
+                complete = false;
+            }
+            (Type::Array, Len::Indefinite) | (Type::Map, Len::Indefinite) => {
+                self.stack
+                    .push(Pending::Indefinite(cbor_type == Type::Map, 0));
+                complete = false;
+            }
+            (Type::Special, Len::Indefinite) => match self.stack.pop() {
+                Some(Pending::Chunks(_)) => {}
+                Some(Pending::Indefinite(is_map, count)) if !is_map || count % 2 == 0 => {}
+                _ => return Err(Error::UnexpectedBreak),
+            },
+            (Type::Special, Len::Len(value)) => {
+                // simple values below 32 must be encoded in the initial byte
+                if sz == 2 && value < 32 {
+                    return Err(Error::InvalidSimpleValue(value as u8));
+                }
+            }
+        }
+
+        if complete {
+            self.complete_item();
+        }
+        Ok(payload)
+    }
+
+    // an item of the innermost container is complete
+    fn complete_item(&mut self) {
+        loop {
+            match self.stack.last_mut() {
+                None => {
+                    self.finished = true;
+                    return;
+                }
+                Some(Pending::Items(ref mut left)) => {
+                    *left -= 1;
+                    if *left > 0 {
+                        return;
+                    }
+                }
+                Some(Pending::Indefinite(_, ref mut count)) => {
+                    *count += 1;
+                    return;
+                }
+                Some(Pending::Chunks(_)) => return,
+            }
+            self.stack.pop();
+        }
+    }
+
+    /// returns `true` once the last header of the object has been walked
+    /// (its payload may still have to be consumed).
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// consume one complete CBOR object from the source, checking it is
+/// well-formed (RFC 8949 section 5.3.1).
+///
+/// Nested objects are walked with an explicit stack so deeply nested
+/// inputs cannot exhaust the call stack.
+pub(crate) fn walk_item<S: Source>(source: &mut S) -> Result<()> {
+    let mut walker = Walker::default();
+    while !walker.is_finished() {
+        let (cbor_type, len, sz) = source.header()?;
+        let payload = walker.header(cbor_type, len, sz)?;
+        source.consume_header(sz)?;
+        if payload > 0 {
+            source.consume_payload(payload)?;
+        }
+    }
+    Ok(())
+}
+
+/// returns the number of bytes of the well-formed CBOR object at the
+/// beginning of `bytes`.
+pub(crate) fn item_len(bytes: &[u8]) -> Result<usize> {
+    let mut source = SliceSource { bytes, pos: 0 };
+    walk_item(&mut source)?;
+    Ok(source.pos)
+}
+
+/// check `bytes` holds exactly one well-formed CBOR object.
+///
+/// Only the structure of the encoding is checked, without decoding nor
+/// allocating the values: the content of the text strings is not checked
+/// to be valid UTF-8.
+///
+/// See [`validate_reader`](./fn.validate_reader.html) for the streaming
+/// variant.
+///
+/// ```
+/// assert!(cbor_event::validate(&[0x82, 0x01, 0x9f, 0xff]).is_ok());
+///
+/// // truncated array
+/// assert!(cbor_event::validate(&[0x82, 0x01]).is_err());
+/// // trailing data
+/// assert!(cbor_event::validate(&[0x01, 0x02]).is_err());
+/// ```
+pub fn validate(bytes: &[u8]) -> Result<()> {
+    if item_len(bytes)? != bytes.len() {
+        Err(Error::TrailingData)
+    } else {
+        Ok(())
+    }
+}
+
+/// streaming variant of [`validate`](./fn.validate.html): check the reader
+/// holds exactly one well-formed CBOR object.
+///
+/// The payloads of the byte and text strings are skipped as they are
+/// read, the input is never held in memory.
+pub fn validate_reader<R: BufRead>(reader: R) -> Result<()> {
+    let mut raw = Deserializer::from(reader);
+    raw.validate_item()?;
+    raw.expect_end()
+}
+
+/// an object nested in a well-formed CBOR object, see
+/// [`Layout`](./struct.Layout.html).
+pub(crate) struct Item {
+    /// offset of the header
+    pub(crate) pos: usize,
+    /// number of bytes of the header
+    pub(crate) sz: usize,
+    pub(crate) cbor_type: Type,
+    pub(crate) len: Len,
+    /// nesting level, 0 for the top level object. The object tagged by a
+    /// tag and the chunks of a string are one level below them.
+    pub(crate) depth: usize,
+    /// offset following the object, its break included
+    pub(crate) end: usize,
+    /// index of the next object not nested in this one
+    pub(crate) next: usize,
+}
+
+/// the objects of a well-formed CBOR object, in order of appearance: the
+/// elements of arrays, the keys and values of maps (alternating), the
+/// objects tagged and the chunks of indefinite length strings follow their
+/// parent.
+///
+/// Built in one pass over the encoding with an explicit stack, the
+/// functions walking an encoded object use it to jump between objects
+/// instead of rescanning them.
+pub(crate) struct Layout {
+    items: Vec<Item>,
+}
+impl Layout {
+    /// walk the object at the beginning of `bytes`, failing if `bytes` is
+    /// not exactly one well-formed CBOR object.
+    pub(crate) fn new(bytes: &[u8]) -> Result<Self> {
+        let mut items: Vec<Item> = Vec::new();
+        let mut walker = Walker::default();
+        // the containers being walked, as in `walker.stack`
+        let mut open = Vec::new();
+        let mut pos = 0;
+        while !walker.is_finished() {
+            let (cbor_type, len, sz) = parse_header(&bytes[pos..])?;
+            let payload = walker.header(cbor_type, len, sz)?;
+            let is_break = cbor_type == Type::Special && len == Len::Indefinite;
+            if !is_break {
+                items.push(Item {
+                    pos,
+                    sz,
+                    cbor_type,
+                    len,
+                    depth: open.len(),
+                    end: 0,
+                    next: 0,
+                });
+            }
+            pos += sz;
+            if payload > (bytes.len() - pos) as u64 {
+                return Err(Error::NotEnough(
+                    bytes.len(),
+                    pos.saturating_add(payload as usize),
+                ));
+            }
+            pos += payload as usize;
+
+            let next = items.len();
+            if walker.stack.len() > open.len() {
+                open.push(next - 1);
+            } else if !is_break {
+                let item = &mut items[next - 1];
+                item.end = pos;
+                item.next = next;
+            }
+            while open.len() > walker.stack.len() {
+                let item = &mut items[open.pop().unwrap()];
+                item.end = pos;
+                item.next = next;
+            }
+        }
+        if pos != bytes.len() {
+            return Err(Error::TrailingData);
+        }
+        Ok(Layout { items })
+    }
+
+    pub(crate) fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// indexes of the objects directly nested in the object at `index`
+    pub(crate) fn children(&self, index: usize) -> Children<'_> {
+        Children {
+            items: &self.items,
+            index: index + 1,
+            end: self.items[index].next,
+        }
+    }
+
+    /// size of the content of the string at `index`, summing the chunks
+    /// of indefinite length strings.
+    pub(crate) fn string_len(&self, index: usize) -> u64 {
+        match self.items[index].len {
+            Len::Len(len) => len,
+            Len::Indefinite => self
+                .children(index)
+                .map(|chunk| match self.items[chunk].len {
+                    Len::Len(len) => len,
+                    Len::Indefinite => 0,
+                })
+                .sum(),
+        }
+    }
+}
+
+/// iterator over the children of an object, see
+/// [`Layout::children`](./struct.Layout.html#method.children).
+pub(crate) struct Children<'a> {
+    items: &'a [Item],
+    index: usize,
+    end: usize,
+}
+impl<'a> Iterator for Children<'a> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        if self.index < self.end {
+            let index = self.index;
+            self.index = self.items[index].next;
+            Some(index)
+        } else {
+            None
+        }
+    }
+}
+
+/// split a buffer containing a sequence of concatenated CBOR objects
+/// (RFC 8742) into the encoded bytes of each object.
+pub(crate) fn split_items(mut bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut items = Vec::new();
+    while !bytes.is_empty() {
+        let len = item_len(bytes)?;
+        let (item, rest) = bytes.split_at(len);
+        items.push(item);
+        bytes = rest;
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use types::Special;
+    use value::Value;
+
+    #[test]
+    fn header() {
+        assert_eq!(
+            parse_header(&[0x18, 0x40]).unwrap(),
+            (Type::UnsignedInteger, Len::Len(0x40), 2)
+        );
+        assert_eq!(
+            parse_header(&[0x9f]).unwrap(),
+            (Type::Array, Len::Indefinite, 1)
+        );
+        assert_eq!(
+            parse_header(&[0x3b, 0xff, 0, 0, 0, 0, 0, 0, 0, 0x00]).unwrap(),
+            (Type::NegativeInteger, Len::Len(0xff00_0000_0000_0000), 9)
+        );
+        assert_eq!(
+            parse_header(&[0xf7]).unwrap(),
+            (Type::Special, Len::Len(23), 1)
+        );
+    }
+
+    #[test]
+    fn truncated_header() {
+        assert!(matches!(parse_header(&[]), Err(Error::NotEnough(0, 1))));
+        assert!(matches!(parse_header(&[0x18]), Err(Error::NotEnough(1, 2))));
+        assert!(matches!(
+            parse_header(&[0x59, 0x01]),
+            Err(Error::NotEnough(2, 3))
+        ));
+        assert!(matches!(
+            parse_header(&[0x1a, 0x00]),
+            Err(Error::NotEnough(2, 5))
+        ));
+        assert!(matches!(
+            parse_header(&[0xdb, 0, 0, 0, 0, 0, 0, 0]),
+            Err(Error::NotEnough(8, 9))
+        ));
+    }
+
+    #[test]
+    fn reserved_header() {
+        for info in 28..=30u8 {
+            for major in 0..8u8 {
+                let byte = major << 5 | info;
+                assert!(
+                    matches!(
+                        parse_header(&[byte, 0, 0, 0, 0, 0, 0, 0, 0]),
+                        Err(Error::UnknownLenType(i)) if i == info
+                    ),
+                    "{:02x}",
+                    byte
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn items() {
+        assert_eq!(item_len(&[0x00, 0x01]).unwrap(), 1);
+        assert_eq!(item_len(&[0x83, 0x01, 0x82, 0x02, 0x03, 0x04]).unwrap(), 6);
+        assert_eq!(item_len(&[0xa1, 0x01, 0x9f, 0xff, 0x00]).unwrap(), 4);
+        assert_eq!(item_len(&[0x5f, 0x41, 0x00, 0x40, 0xff]).unwrap(), 5);
+        assert_eq!(item_len(&[0xd8, 0x18, 0x43, 1, 2, 3]).unwrap(), 6);
+    }
+
+    #[test]
+    fn malformed_items() {
+        // truncated
+        assert!(item_len(&[0x43, 1, 2]).is_err());
+        assert!(item_len(&[0x82, 0x01]).is_err());
+        // break outside of an indefinite object
+        assert!(item_len(&[0x81, 0xff]).is_err());
+        // odd number of items in an indefinite map
+        assert!(item_len(&[0xbf, 0x01, 0xff]).is_err());
+        // chunk of the wrong type
+        assert!(item_len(&[0x5f, 0x61, 0x61, 0xff]).is_err());
+        // two bytes simple value below 32
+        assert!(item_len(&[0xf8, 0x10]).is_err());
+    }
+
+    #[test]
+    fn layout() {
+        // [1, {_ "a": (_ h'01', h'')}, 24(2)]
+        let bytes = [
+            0x83, 0x01, 0xbf, 0x61, 0x61, 0x5f, 0x41, 0x01, 0x40, 0xff, 0xff, 0xd8, 0x18, 0x02,
+        ];
+        let layout = Layout::new(&bytes).unwrap();
+        let items = layout.items();
+        let summary = items
+            .iter()
+            .map(|item| (item.pos, item.depth, item.end, item.next))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 0, 14, 9),
+                (1, 1, 2, 2),
+                (2, 1, 11, 7),
+                (3, 2, 5, 4),
+                (5, 2, 10, 7),
+                (6, 3, 8, 6),
+                (8, 3, 9, 7),
+                (11, 1, 14, 9),
+                (13, 2, 14, 9),
+            ]
+        );
+        assert_eq!(layout.children(0).collect::<Vec<_>>(), vec![1, 2, 7]);
+        assert_eq!(layout.children(2).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(layout.children(7).collect::<Vec<_>>(), vec![8]);
+        assert_eq!(layout.children(1).count(), 0);
+        assert_eq!(layout.string_len(4), 1);
+        assert_eq!(layout.string_len(3), 1);
+
+        assert!(Layout::new(&bytes[..13]).is_err());
+        assert!(Layout::new(&[0x01, 0x02]).is_err());
+        assert!(Layout::new(&[0x42, 0x01]).is_err());
+
+        // linear in the size of the input, whatever the nesting
+        let mut bytes = vec![0x81; 100_000];
+        bytes.push(0x00);
+        let layout = Layout::new(&bytes).unwrap();
+        assert_eq!(layout.items().len(), 100_001);
+        assert_eq!(layout.items()[100_000].depth, 100_000);
+        assert!(layout.items().iter().all(|item| item.end == bytes.len()));
+    }
+
+    #[test]
+    fn validate_slice_and_reader() {
+        let valid: &[&[u8]] = &[
+            &[0x00],
+            &[0xa1, 0x61, 0x61, 0x5f, 0x41, 0x00, 0xff],
+            &[0xf8, 0x20],
+            &[0xfb, 0, 0, 0, 0, 0, 0, 0, 0],
+        ];
+        let invalid: &[&[u8]] = &[
+            &[],
+            &[0x00, 0x00],
+            &[0xff],
+            &[0x9f, 0x01],
+            &[0x7f, 0x7f, 0xff, 0xff],
+            &[0x3f],
+            &[0xdf, 0x00],
+        ];
+        for bytes in valid {
+            assert!(validate(bytes).is_ok(), "{:?}", bytes);
+            assert!(validate_reader(*bytes).is_ok(), "{:?}", bytes);
+        }
+        for bytes in invalid {
+            assert!(validate(bytes).is_err(), "{:?}", bytes);
+            assert!(validate_reader(*bytes).is_err(), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn split() {
+        let mut se = Serializer::new_vec();
+        se.write_unsigned_integer(1)
+            .unwrap()
+            .write_text("text")
+            .unwrap()
+            .write_special(Special::Null)
+            .unwrap();
+        let bytes = se.finalize();
+        let items = split_items(&bytes).unwrap();
+        assert_eq!(items, vec![&[0x01][..], &bytes[1..6], &[0xf6][..]]);
+    }
+
+    #[test]
+    fn nested_value() {
+        let value = Value::Array(vec![
+            Value::IArray(vec![Value::U64(1), Value::Bytes(vec![0; 300])]),
+            Value::Tag(24, Box::new(Value::Text("text".to_owned()))),
+            Value::U64(0x00ff_ffff_ffff),
+        ]);
+        let mut se = Serializer::new_vec();
+        se.serialize(&value).unwrap();
+        se.write_unsigned_integer(0).unwrap();
+        let bytes = se.finalize();
+        assert_eq!(item_len(&bytes).unwrap(), bytes.len() - 1);
+    }
+}
@@ -1,9 +1,31 @@
 //! CBOR serialisation tooling
-use std::io::Write;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(feature = "num-bigint")]
+use std::convert::TryFrom;
+use std::{
+    cmp,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{self, IoSlice, Write},
+    ops::{Range, RangeInclusive},
+};
 
-use len::Len;
+use canonical;
+use error::Error;
+#[cfg(feature = "indexmap")]
+use indexmap::{IndexMap, IndexSet};
+use len::{Len, LenSz, StringLenSz, Sz};
+use lint::{self, ViolationKind};
+#[cfg(feature = "num-bigint")]
+use num_bigint::{BigInt, BigUint, Sign};
+#[cfg(feature = "std")]
+use pool::{BufferPool, PooledBuffer};
+#[cfg(not(feature = "std"))]
+use prelude::*;
 use result::Result;
-use types::{Special, Type};
+use sink::InlineBuffer;
+use types::{ByteString, Special, Type};
 
 pub trait Serialize {
     fn serialize<'a, W: Write + Sized>(
@@ -11,7 +33,58 @@ pub trait Serialize {
         serializer: &'a mut Serializer<W>,
     ) -> Result<&'a mut Serializer<W>>;
 }
-impl<'a, T: Serialize> Serialize for &'a T {
+
+/// object safe version of [`Serialize`](./trait.Serialize.html),
+/// implemented for all the `Serialize` types, to mix values of different
+/// types in one slice, see
+/// [`write_int_keyed_map`](./struct.Serializer.html#method.write_int_keyed_map).
+pub trait DynSerialize {
+    fn serialize_dyn(&self, serializer: &mut Serializer<&mut dyn Write>) -> Result<()>;
+}
+impl<T: Serialize> DynSerialize for T {
+    fn serialize_dyn(&self, serializer: &mut Serializer<&mut dyn Write>) -> Result<()> {
+        serializer.serialize(self).map(|_| ())
+    }
+}
+impl Serialize for dyn DynSerialize + '_ {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        self.serialize_dyn(&mut serializer.as_dyn())?;
+        Ok(serializer)
+    }
+}
+
+/// encode any [`Serialize`](./trait.Serialize.html) type into a new
+/// buffer.
+///
+/// ```
+/// use cbor_event::ToCborBytes;
+///
+/// assert_eq!(42u64.to_cbor_bytes().unwrap(), vec![0x18, 0x2a]);
+/// ```
+pub trait ToCborBytes {
+    fn to_cbor_bytes(&self) -> Result<Vec<u8>>;
+}
+impl<T: Serialize> ToCborBytes for T {
+    fn to_cbor_bytes(&self) -> Result<Vec<u8>> {
+        to_vec(self)
+    }
+}
+
+/// encode `value` into a new buffer.
+///
+/// ```
+/// assert_eq!(cbor_event::to_vec(&"a".to_owned()).unwrap(), vec![0x61, 0x61]);
+/// ```
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut se = Serializer::new_vec();
+    value.serialize(&mut se)?;
+    Ok(se.finalize())
+}
+
+impl<T: Serialize> Serialize for &T {
     fn serialize<'se, W: Write + Sized>(
         &self,
         serializer: &'se mut Serializer<W>,
@@ -51,6 +124,101 @@ impl Serialize for u8 {
         serializer.write_unsigned_integer((*self) as u64)
     }
 }
+impl Serialize for i64 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer(*self)
+    }
+}
+impl Serialize for i32 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer((*self) as i64)
+    }
+}
+impl Serialize for i16 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer((*self) as i64)
+    }
+}
+impl Serialize for i8 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer((*self) as i64)
+    }
+}
+/// encoded as an unsigned integer if it fits in 64 bits, as a bignum (tag
+/// 2) otherwise
+impl Serialize for u128 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer_parts(false, *self)
+    }
+}
+/// encoded as an integer if it fits in 64 bits, as a bignum (tags 2 and 3)
+/// otherwise, see [`write_integer_i128`](./struct.Serializer.html#method.write_integer_i128)
+impl Serialize for i128 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_integer_i128(*self)
+    }
+}
+/// encoded as an unsigned integer if it fits in 64 bits, as a bignum (tag
+/// 2) otherwise
+#[cfg(feature = "num-bigint")]
+impl Serialize for BigUint {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        match u64::try_from(self) {
+            Ok(n) => serializer.write_unsigned_integer(n),
+            Err(_) => serializer.write_bignum(false, &self.to_bytes_be()),
+        }
+    }
+}
+/// encoded as an integer if it fits in 64 bits, as a bignum (tags 2 and 3)
+/// otherwise
+#[cfg(feature = "num-bigint")]
+impl Serialize for BigInt {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        // negative values encode `-1 - value`
+        let (negative, n) = match self.sign() {
+            Sign::Minus => (true, (-self - 1u32).magnitude().clone()),
+            _ => (false, self.magnitude().clone()),
+        };
+        match u64::try_from(&n) {
+            Ok(n) => serializer.write_integer_parts(negative, n as u128),
+            Err(_) => serializer.write_bignum(negative, &n.to_bytes_be()),
+        }
+    }
+}
+/// encoded as a double precision float, or on the shortest exact size in
+/// [canonical mode](./struct.Serializer.html#method.set_canonical)
+impl Serialize for f64 {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_special(Special::Float(*self))
+    }
+}
 impl Serialize for bool {
     fn serialize<'a, W: Write + Sized>(
         &self,
@@ -67,7 +235,7 @@ impl Serialize for String {
         serializer.write_text(self)
     }
 }
-impl<'a> Serialize for &'a [u8] {
+impl Serialize for &[u8] {
     fn serialize<'b, W: Write + Sized>(
         &self,
         serializer: &'b mut Serializer<W>,
@@ -75,6 +243,14 @@ impl<'a> Serialize for &'a [u8] {
         serializer.write_bytes(self)
     }
 }
+impl<T: AsRef<[u8]>> Serialize for ByteString<T> {
+    fn serialize<'b, W: Write + Sized>(
+        &self,
+        serializer: &'b mut Serializer<W>,
+    ) -> Result<&'b mut Serializer<W>> {
+        serializer.write_bytes(self.0.as_ref())
+    }
+}
 impl<'a, A, B> Serialize for (&'a A, &'a B)
 where
     A: Serialize,
@@ -108,6 +284,100 @@ where
     }
 }
 
+/// encoded as a CBOR map of definite length, in order, duplicates included
+impl<K: Serialize, V: Serialize> Serialize for Vec<(K, V)> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_map(Len::Len(self.len() as u64))?;
+        let mut entries = MapEntries::new(serializer);
+        for (k, v) in self.iter() {
+            entries.write(serializer, k, v)?;
+        }
+        entries.finish(serializer)?;
+        Ok(serializer)
+    }
+}
+
+/// encoded as a CBOR map of definite length, in insertion order
+#[cfg(feature = "indexmap")]
+impl<K: Serialize, V: Serialize, S> Serialize for IndexMap<K, V, S> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_map(Len::Len(self.len() as u64))?;
+        let mut entries = MapEntries::new(serializer);
+        for (k, v) in self.iter() {
+            entries.write(serializer, k, v)?;
+        }
+        entries.finish(serializer)?;
+        Ok(serializer)
+    }
+}
+/// encoded as a CBOR array of definite length, in insertion order
+#[cfg(feature = "indexmap")]
+impl<T: Serialize, S> Serialize for IndexSet<T, S> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.write_array(Len::Len(self.len() as u64))?;
+        for element in self.iter() {
+            serializer.serialize(element)?;
+        }
+        Ok(serializer)
+    }
+}
+
+/// encoded as a set: the set tag (258) and an array of definite length, in
+/// order
+impl<T: Serialize> Serialize for BTreeSet<T> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.serialize_set(self)
+    }
+}
+/// encoded as a set: the set tag (258) and an array of definite length, in
+/// the iteration order of the set
+#[cfg(feature = "std")]
+impl<T: Serialize, S> Serialize for HashSet<T, S> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer.serialize_set(self)
+    }
+}
+
+/// encoded as a 2 elements array `[start, end]`
+impl<T: Serialize> Serialize for Range<T> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer
+            .write_array(Len::Len(2))?
+            .serialize(&self.start)?
+            .serialize(&self.end)
+    }
+}
+/// encoded as a 2 elements array `[start, end]`
+impl<T: Serialize> Serialize for RangeInclusive<T> {
+    fn serialize<'a, W: Write + Sized>(
+        &self,
+        serializer: &'a mut Serializer<W>,
+    ) -> Result<&'a mut Serializer<W>> {
+        serializer
+            .write_array(Len::Len(2))?
+            .serialize(self.start())?
+            .serialize(self.end())
+    }
+}
+
 impl<T> Serialize for Option<T>
 where
     T: Serialize,
@@ -138,10 +408,11 @@ where
     W: Write + Sized,
 {
     serializer.write_map(Len::Len(data.len() as u64))?;
+    let mut entries = MapEntries::new(serializer);
     for element in data {
-        Serialize::serialize(element.0, serializer)?;
-        Serialize::serialize(element.1, serializer)?;
+        entries.write(serializer, element.0, element.1)?;
     }
+    entries.finish(serializer)?;
     Ok(serializer)
 }
 
@@ -178,10 +449,11 @@ where
     W: Write + Sized,
 {
     serializer.write_map(Len::Indefinite)?;
+    let mut entries = MapEntries::new(serializer);
     for element in data {
-        Serialize::serialize(element.0, serializer)?;
-        Serialize::serialize(element.1, serializer)?;
+        entries.write(serializer, element.0, element.1)?;
     }
+    entries.finish(serializer)?;
     serializer.write_special(Special::Break)
 }
 
@@ -216,7 +488,7 @@ where
 /// let mut serializer = Serializer::new_vec();
 /// let mut se = Serializer::new_vec();
 /// 0u32.serialize(&mut se).unwrap();
-/// serializer.write_bytes(&se.finalize()).unwrap();
+/// serializer.write_bytes(se.finalize()).unwrap();
 /// ```
 ///
 pub fn serialize_cbor_in_cbor<T, W>(
@@ -229,7 +501,279 @@ where
 {
     let mut se = Serializer::new_vec();
     data.serialize(&mut se)?;
-    serializer.write_bytes(&se.finalize())
+    serializer.write_bytes(se.finalize())
+}
+
+// encode the type and the length (or the value for integers) of a cbor
+// object, returns the buffer and the number of bytes used.
+#[inline]
+pub(crate) fn encode_header(cbor_type: Type, len: u64) -> ([u8; 9], usize) {
+    encode_header_sz(cbor_type, len, Sz::canonical(len))
+}
+
+// counts the bytes of formatted text
+struct FmtCounter(u64);
+impl fmt::Write for FmtCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len() as u64;
+        Ok(())
+    }
+}
+
+// writes formatted text to an `io::Write`, keeping the I/O error
+struct FmtWriter<'a, W: 'a> {
+    sink: &'a mut W,
+    written: u64,
+    error: Option<io::Error>,
+}
+impl<'a, W: Write> fmt::Write for FmtWriter<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.sink.write_all(s.as_bytes()) {
+            Ok(()) => {
+                self.written += s.len() as u64;
+                Ok(())
+            }
+            Err(e) => {
+                self.error = Some(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+fn fmt_error() -> Error {
+    Error::CustomError("a formatting trait implementation returned an error".to_owned())
+}
+
+// same as `encode_header` but with the given size, `len` must fit in it.
+#[inline]
+fn encode_header_sz(cbor_type: Type, len: u64, sz: Sz) -> ([u8; 9], usize) {
+    let mut header = [0; 9];
+    match sz {
+        Sz::Inline => header[0] = cbor_type.to_byte(len as u8),
+        Sz::One => {
+            header[0] = cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U8);
+            header[1] = len as u8;
+        }
+        Sz::Two => {
+            header[0] = cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U16);
+            header[1..3].copy_from_slice(&(len as u16).to_be_bytes());
+        }
+        Sz::Four => {
+            header[0] = cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U32);
+            header[1..5].copy_from_slice(&(len as u32).to_be_bytes());
+        }
+        Sz::Eight => {
+            header[0] = cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U64);
+            header[1..9].copy_from_slice(&len.to_be_bytes());
+        }
+    }
+    (header, 1 + sz.bytes_following())
+}
+
+// `Write::write_all_vectored` is not stable yet, write `header` then
+// `payload` with as few vectored writes as the sink allows
+fn write_all_vectored<W: Write>(
+    w: &mut W,
+    mut header: &[u8],
+    mut payload: &[u8],
+) -> io::Result<()> {
+    while !header.is_empty() || !payload.is_empty() {
+        match w.write_vectored(&[IoSlice::new(header), IoSlice::new(payload)]) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ))
+            }
+            Ok(n) => {
+                let skip = cmp::min(n, header.len());
+                header = &header[skip..];
+                payload = &payload[n - skip..];
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+// the sink of a `Serializer`, gathering the small writes (headers, short
+// payloads) in a buffer of `capacity` bytes flushed when full. Payloads not
+// fitting in the buffer bypass it. Disabled if `capacity` is 0. The buffer
+// is flushed on drop, ignoring the errors.
+#[derive(Debug)]
+struct Staging<W: Write> {
+    // only `None` once taken back by `into_inner`
+    inner: Option<W>,
+    buffer: Vec<u8>,
+    capacity: usize,
+}
+impl<W: Write> Staging<W> {
+    fn new(inner: W, capacity: usize) -> Self {
+        Staging {
+            inner: Some(inner),
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    // write the buffer to the sink and return it, with the sink on error
+    // too
+    fn into_inner(mut self) -> (W, io::Result<()>) {
+        let result = self.flush_buffer();
+        self.buffer.clear();
+        (self.inner.take().unwrap(), result)
+    }
+
+    // write the buffer to the sink, keeping what was not written on error
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Ok(());
+            }
+            let inner = self.inner.as_mut().unwrap();
+            match inner.write(&self.buffer[written..]) {
+                Ok(0) => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ))
+                }
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buffer.drain(..written);
+        result
+    }
+}
+impl<W: Write> Drop for Staging<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() && !self.buffer.is_empty() {
+            let _ = self.flush_buffer();
+        }
+    }
+}
+impl<W: Write> Write for Staging<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.capacity == 0 {
+            return self.get_mut().write(buf);
+        }
+        if self.buffer.len() + buf.len() > self.capacity {
+            self.flush_buffer()?;
+        }
+        if buf.len() >= self.capacity {
+            self.get_mut().write(buf)
+        } else {
+            self.buffer.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        if self.capacity == 0 {
+            return self.get_mut().write_vectored(bufs);
+        }
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        if self.buffer.len() + len > self.capacity {
+            self.flush_buffer()?;
+        }
+        if len >= self.capacity {
+            self.get_mut().write_vectored(bufs)
+        } else {
+            for buf in bufs {
+                self.buffer.extend_from_slice(buf);
+            }
+            Ok(len)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.get_mut().flush()
+    }
+}
+
+// the entries of a map written from a collection: written as given,
+// checking for duplicate keys if enabled, or sorted by their encoded keys
+// in canonical mode
+pub(crate) enum MapEntries {
+    InOrder,
+    // the canonical encodings of the keys already written
+    Unique(BTreeSet<Vec<u8>>),
+    // the encoded values by encoded keys, written by `finish`
+    Sorted(BTreeMap<Vec<u8>, Vec<u8>>),
+}
+impl MapEntries {
+    pub(crate) fn new<W: Write>(serializer: &Serializer<W>) -> Self {
+        if serializer.canonical {
+            MapEntries::Sorted(BTreeMap::new())
+        } else if serializer.check_duplicate_keys {
+            MapEntries::Unique(BTreeSet::new())
+        } else {
+            MapEntries::InOrder
+        }
+    }
+
+    pub(crate) fn write<K, V, W>(
+        &mut self,
+        serializer: &mut Serializer<W>,
+        key: &K,
+        value: &V,
+    ) -> Result<()>
+    where
+        K: Serialize + ?Sized,
+        V: Serialize + ?Sized,
+        W: Write,
+    {
+        match self {
+            MapEntries::InOrder => {
+                key.serialize(serializer)?;
+                value.serialize(serializer)?;
+            }
+            MapEntries::Unique(keys) => {
+                let mut key_serializer = serializer.nested();
+                key.serialize(&mut key_serializer)?;
+                let bytes = key_serializer.finalize();
+                if !keys.insert(canonical::canonicalize(&bytes)?) {
+                    return Err(Error::DuplicateKey);
+                }
+                serializer.write_raw_bytes(&bytes)?;
+                value.serialize(serializer)?;
+            }
+            MapEntries::Sorted(entries) => {
+                let mut key_serializer = serializer.nested();
+                key.serialize(&mut key_serializer)?;
+                let mut value_serializer = serializer.nested();
+                value.serialize(&mut value_serializer)?;
+                let key = key_serializer.finalize();
+                if entries.insert(key, value_serializer.finalize()).is_some() {
+                    return Err(Error::DuplicateKey);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish<W: Write>(self, serializer: &mut Serializer<W>) -> Result<()> {
+        if let MapEntries::Sorted(entries) = self {
+            for (key, value) in entries {
+                serializer.write_raw_bytes(&key)?.write_raw_bytes(&value)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 // use a default capacity when allocating the Serializer to avoid small reallocation
@@ -242,7 +786,15 @@ const DEFAULT_CAPACITY: usize = 512;
 /// [`std::io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html).
 ///
 #[derive(Debug)]
-pub struct Serializer<W: Write + Sized>(W);
+pub struct Serializer<W: Write + Sized> {
+    sink: Staging<W>,
+    // size used for all integers, lengths and tags, shortest if `None`
+    fixed_width: Option<Sz>,
+    // fail on duplicate keys in the maps written from collections
+    check_duplicate_keys: bool,
+    // deterministic encoding of RFC 8949
+    canonical: bool,
+}
 impl Serializer<Vec<u8>> {
     /// create a new serializer.
     ///
@@ -255,6 +807,78 @@ impl Serializer<Vec<u8>> {
     pub fn new_vec() -> Self {
         Serializer::new(Vec::with_capacity(DEFAULT_CAPACITY))
     }
+
+    /// remember the current position of the output, to go back to it with
+    /// [`rollback`](#method.rollback).
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.sink.get_ref().len() + self.sink.buffer.len())
+    }
+
+    /// discard everything written since the given checkpoint, for example
+    /// after a failed attempt at serialising one of several alternatives.
+    ///
+    /// Rolling back to a checkpoint taken after the current position does
+    /// nothing.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Len};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_array(Len::Len(1)).unwrap();
+    ///
+    /// let checkpoint = serializer.checkpoint();
+    /// serializer.write_array(Len::Len(2)).unwrap().write_text("half").unwrap();
+    /// serializer.rollback(checkpoint);
+    ///
+    /// serializer.write_unsigned_integer(0).unwrap();
+    /// assert_eq!(serializer.finalize(), vec![0x81, 0x00]);
+    /// ```
+    #[inline]
+    pub fn rollback(&mut self, checkpoint: Checkpoint) -> &mut Self {
+        let written = self.sink.get_ref().len();
+        self.sink
+            .buffer
+            .truncate(checkpoint.0.saturating_sub(written));
+        self.sink.get_mut().truncate(checkpoint.0);
+        self
+    }
+}
+
+/// position in the output of a `Serializer`, see
+/// [`Serializer::checkpoint`](./struct.Serializer.html#method.checkpoint).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Checkpoint(usize);
+impl Serializer<InlineBuffer> {
+    /// create a new serializer writing into an [`InlineBuffer`]: small outputs
+    /// are kept on the stack and only spill to the heap when they exceed
+    /// [`INLINE_CAPACITY`] bytes.
+    ///
+    /// [`InlineBuffer`]: ../sink/struct.InlineBuffer.html
+    /// [`INLINE_CAPACITY`]: ../sink/constant.INLINE_CAPACITY.html
+    ///
+    /// ```
+    /// use cbor_event::se::{Serializer};
+    ///
+    /// let serializer = Serializer::new_inline();
+    /// ```
+    #[inline]
+    pub fn new_inline() -> Self {
+        Serializer::new(InlineBuffer::new())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Serializer<PooledBuffer<'a>> {
+    /// create a new serializer writing into a buffer borrowed from the
+    /// given [`BufferPool`](../pool/struct.BufferPool.html).
+    ///
+    /// The buffer returned by [`finalize`](#method.finalize) goes back to
+    /// the pool once dropped.
+    #[inline]
+    pub fn new_pooled(pool: &'a BufferPool) -> Self {
+        Serializer::new(pool.get())
+    }
 }
 
 impl<W: Write + Sized> Serializer<W> {
@@ -264,13 +888,163 @@ impl<W: Write + Sized> Serializer<W> {
     /// to add already CBOR encoded data or to add any bytes that may suite
     /// your protocol.
     pub fn write_raw_bytes(&mut self, bytes: &[u8]) -> Result<&mut Self> {
-        self.0.write_all(bytes)?;
+        self.sink.write_all(bytes)?;
         Ok(self)
     }
 
     #[inline]
     pub fn new(w: W) -> Self {
-        Serializer(w)
+        Serializer::with_write_buffer(w, 0)
+    }
+
+    /// create a serializer gathering its writes in a buffer of `capacity`
+    /// bytes, written to `w` once full. Avoids handing each 1 to 9 bytes
+    /// header on its own to sinks where every write is costly, like
+    /// sockets and files. Payloads larger than the buffer are written
+    /// directly. A `capacity` of 0 disables the buffer.
+    ///
+    /// The buffered bytes are written by [`flush`](#method.flush) and
+    /// [`try_finalize`](#method.try_finalize), which report the errors.
+    /// [`finalize`](#method.finalize) and dropping the serializer write
+    /// them too, but ignore the errors: as with `std::io::BufWriter`, call
+    /// `flush` first to know whether all the bytes reached the sink.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut file = Vec::new();
+    /// let mut serializer = Serializer::with_write_buffer(&mut file, 64);
+    /// serializer.write_unsigned_integer(1).unwrap();
+    /// serializer.write_text("one").unwrap();
+    ///
+    /// serializer.flush().unwrap();
+    /// assert_eq!(serializer.write_buffer_capacity(), 64);
+    /// # drop(serializer);
+    /// # assert_eq!(file, [0x01, 0x63, 0x6f, 0x6e, 0x65]);
+    /// ```
+    pub fn with_write_buffer(w: W, capacity: usize) -> Self {
+        Serializer {
+            sink: Staging::new(w, capacity),
+            fixed_width: None,
+            check_duplicate_keys: false,
+            canonical: false,
+        }
+    }
+
+    /// the capacity set with
+    /// [`with_write_buffer`](#method.with_write_buffer), 0 if not buffered
+    pub fn write_buffer_capacity(&self) -> usize {
+        self.sink.capacity
+    }
+
+    /// write the buffered bytes to the sink and flush it.
+    pub fn flush(&mut self) -> Result<&mut Self> {
+        self.sink.flush()?;
+        Ok(self)
+    }
+
+    /// encode all the following integers, lengths and tags on the given
+    /// size instead of the shortest one (`None` to go back to the shortest
+    /// encoding). Writing a value which does not fit in the size fails.
+    ///
+    /// Values encoded on a known size can later be patched in place, for
+    /// example the length of an array whose number of elements is not
+    /// known before it is written.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Len, Sz};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer
+    ///     .set_fixed_width(Some(Sz::Four))
+    ///     .write_array(Len::Len(1)).unwrap()
+    ///     .write_unsigned_integer(2).unwrap();
+    ///
+    /// assert_eq!(
+    ///     serializer.finalize(),
+    ///     [0x9a, 0, 0, 0, 1, 0x1a, 0, 0, 0, 2].as_ref()
+    /// );
+    /// ```
+    pub fn set_fixed_width(&mut self, sz: Option<Sz>) -> &mut Self {
+        self.fixed_width = sz;
+        self
+    }
+
+    /// the size set with [`set_fixed_width`](#method.set_fixed_width)
+    pub fn fixed_width(&self) -> Option<Sz> {
+        self.fixed_width
+    }
+
+    /// fail with `Error::DuplicateKey` when writing a map with the same
+    /// key twice, compared by their canonical encodings.
+    ///
+    /// Only the maps written from collections are checked: the `Serialize`
+    /// implementations of `Vec<(K, V)>`, `OrderedMap` and `IndexMap`,
+    /// [`serialize_fixed_map`](./fn.serialize_fixed_map.html),
+    /// [`serialize_indefinite_map`](./fn.serialize_indefinite_map.html) and
+    /// [`write_int_keyed_map`](#method.write_int_keyed_map). The keys are
+    /// encoded in a temporary buffer first.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Error};
+    ///
+    /// let entries = vec![("a".to_owned(), 1u64), ("a".to_owned(), 2u64)];
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.set_check_duplicate_keys(true);
+    ///
+    /// assert!(matches!(serializer.serialize(&entries), Err(Error::DuplicateKey)));
+    /// ```
+    pub fn set_check_duplicate_keys(&mut self, check: bool) -> &mut Self {
+        self.check_duplicate_keys = check;
+        self
+    }
+
+    /// whether [`set_check_duplicate_keys`](#method.set_check_duplicate_keys)
+    /// is enabled
+    pub fn check_duplicate_keys(&self) -> bool {
+        self.check_duplicate_keys
+    }
+
+    /// produce the deterministic encoding of RFC 8949 (section 4.2.1),
+    /// as needed to hash or sign the output:
+    ///
+    /// * integers, lengths and tags are written on the shortest size,
+    ///   writing them on another size fails;
+    /// * writing an indefinite length fails;
+    /// * floats are written on the shortest size representing them exactly;
+    /// * the maps written from collections, the ones listed in
+    ///   [`set_check_duplicate_keys`](#method.set_check_duplicate_keys)
+    ///   and the ones of `Value`, are sorted in the bytewise order of their
+    ///   encoded keys, which must be unique. The entries are encoded in
+    ///   temporary buffers first.
+    ///
+    /// The errors are `Error::NonCanonical` and `Error::DuplicateKey`.
+    /// Maps written entry by entry after
+    /// [`write_map`](#method.write_map) and the bytes given to
+    /// [`write_raw_bytes`](#method.write_raw_bytes) are written as given.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Error, Len};
+    ///
+    /// let entries = vec![("bb".to_owned(), 1.5), ("a".to_owned(), 100000.0)];
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.set_canonical(true).serialize(&entries).unwrap();
+    /// assert!(matches!(serializer.write_array(Len::Indefinite), Err(Error::NonCanonical(_))));
+    ///
+    /// // {"a": 100000.0 (as a single), "bb": 1.5 (as a half)}
+    /// assert_eq!(
+    ///     serializer.finalize(),
+    ///     [0xa2, 0x61, 0x61, 0xfa, 0x47, 0xc3, 0x50, 0x00, 0x62, 0x62, 0x62, 0xf9, 0x3e, 0x00]
+    /// );
+    /// ```
+    pub fn set_canonical(&mut self, canonical: bool) -> &mut Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// whether [`set_canonical`](#method.set_canonical) is enabled
+    pub fn canonical(&self) -> bool {
+        self.canonical
     }
 
     /// finalize the serializer, returning the serializer bytes
@@ -284,71 +1058,118 @@ impl<W: Write + Sized> Serializer<W> {
     ///
     /// # assert!(bytes.is_empty());
     /// ```
+    ///
+    /// The bytes kept by the [write buffer](#method.with_write_buffer) are
+    /// written to the sink first, ignoring the errors. Call
+    /// [`flush`](#method.flush) before, or use
+    /// [`try_finalize`](#method.try_finalize), to handle them.
     #[inline]
     pub fn finalize(self) -> W {
-        self.0
+        self.sink.into_inner().0
+    }
+
+    /// finalize the serializer, failing if the bytes kept by the
+    /// [write buffer](#method.with_write_buffer) cannot be written to the
+    /// sink.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut file = Vec::new();
+    /// let mut serializer = Serializer::with_write_buffer(&mut file, 64);
+    /// serializer.write_unsigned_integer(1).unwrap();
+    ///
+    /// serializer.try_finalize().unwrap();
+    /// assert_eq!(file, [0x01]);
+    /// ```
+    pub fn try_finalize(self) -> Result<W> {
+        let (inner, result) = self.sink.into_inner();
+        result?;
+        Ok(inner)
+    }
+
+    // a serializer into a new buffer, with the same settings
+    fn nested(&self) -> Serializer<Vec<u8>> {
+        let mut serializer = Serializer::new(Vec::new());
+        serializer.fixed_width = self.fixed_width;
+        serializer.check_duplicate_keys = self.check_duplicate_keys;
+        serializer.canonical = self.canonical;
+        serializer
+    }
+
+    // a serializer writing into this one, with the same settings
+    fn as_dyn(&mut self) -> Serializer<&mut dyn Write> {
+        let mut serializer = Serializer::new(&mut self.sink as &mut dyn Write);
+        serializer.fixed_width = self.fixed_width;
+        serializer.check_duplicate_keys = self.check_duplicate_keys;
+        serializer.canonical = self.canonical;
+        serializer
     }
 
     #[inline]
     fn write_u8(&mut self, value: u8) -> Result<&mut Self> {
-        self.0.write_all(&[value][..])?;
+        self.sink.write_all(&[value][..])?;
         Ok(self)
     }
 
     #[inline]
-    fn write_u16(&mut self, value: u16) -> Result<&mut Self> {
-        self.0
-            .write_all(&[((value & 0xFF_00) >> 8) as u8, (value & 0x00_FF) as u8][..])?;
+    fn write_type(&mut self, cbor_type: Type, len: u64) -> Result<&mut Self> {
+        let (header, sz) = self.encode_header(cbor_type, len)?;
+        self.sink.write_all(&header[..sz])?;
         Ok(self)
     }
 
+    // encode the header with the fixed width if set
     #[inline]
-    fn write_u32(&mut self, value: u32) -> Result<&mut Self> {
-        self.0.write_all(
-            &[
-                ((value & 0xFF_00_00_00) >> 24) as u8,
-                ((value & 0x00_FF_00_00) >> 16) as u8,
-                ((value & 0x00_00_FF_00) >> 8) as u8,
-                (value & 0x00_00_00_FF) as u8,
-            ][..],
-        )?;
-        Ok(self)
+    fn encode_header(&self, cbor_type: Type, len: u64) -> Result<([u8; 9], usize)> {
+        match self.fixed_width {
+            None => Ok(encode_header(cbor_type, len)),
+            Some(sz) if sz.fits(len) => {
+                self.check_shortest(len, sz)?;
+                Ok(encode_header_sz(cbor_type, len, sz))
+            }
+            Some(sz) => Err(Error::InvalidLenPassed(sz)),
+        }
     }
 
-    #[inline]
-    fn write_u64(&mut self, value: u64) -> Result<&mut Self> {
-        self.0.write_all(
-            &[
-                ((value & 0xFF_00_00_00_00_00_00_00) >> 56) as u8,
-                ((value & 0x00_FF_00_00_00_00_00_00) >> 48) as u8,
-                ((value & 0x00_00_FF_00_00_00_00_00) >> 40) as u8,
-                ((value & 0x00_00_00_FF_00_00_00_00) >> 32) as u8,
-                ((value & 0x00_00_00_00_FF_00_00_00) >> 24) as u8,
-                ((value & 0x00_00_00_00_00_FF_00_00) >> 16) as u8,
-                ((value & 0x00_00_00_00_00_00_FF_00) >> 8) as u8,
-                (value & 0x00_00_00_00_00_00_00_FF) as u8,
-            ][..],
-        )?;
+    // in canonical mode, fail if `sz` is not the shortest size for `value`
+    fn check_shortest(&self, value: u64, sz: Sz) -> Result<()> {
+        if self.canonical && sz != Sz::canonical(value) {
+            return Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(
+                1 + sz.bytes_following(),
+                lint::header_size(value),
+            )));
+        }
+        Ok(())
+    }
+
+    // the header of an indefinite length string, array or map
+    pub(crate) fn write_indefinite(&mut self, cbor_type: Type) -> Result<&mut Self> {
+        if self.canonical {
+            return Err(Error::NonCanonical(ViolationKind::IndefiniteLength));
+        }
+        self.write_u8(cbor_type.to_byte(0x1f))
+    }
+
+    // same as `write_type` but with the given size
+    fn write_type_sz(&mut self, cbor_type: Type, len: u64, sz: Sz) -> Result<&mut Self> {
+        if !sz.fits(len) {
+            return Err(Error::InvalidLenPassed(sz));
+        }
+        self.check_shortest(len, sz)?;
+        let (header, sz) = encode_header_sz(cbor_type, len, sz);
+        self.sink.write_all(&header[..sz])?;
         Ok(self)
     }
 
+    // write the header of the given type followed by the payload. Both are
+    // handed to the sink in a single vectored write so sinks supporting it
+    // (sockets, files...) do not see a tiny write for the header alone.
     #[inline]
-    fn write_type(&mut self, cbor_type: Type, len: u64) -> Result<&mut Self> {
-        if len <= super::MAX_INLINE_ENCODING {
-            self.write_u8(cbor_type.to_byte(len as u8))
-        } else if len < 0x1_00 {
-            self.write_u8(cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U8))
-                .and_then(|s| s.write_u8(len as u8))
-        } else if len < 0x1_00_00 {
-            self.write_u8(cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U16))
-                .and_then(|s| s.write_u16(len as u16))
-        } else if len < 0x1_00_00_00_00 {
-            self.write_u8(cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U32))
-                .and_then(|s| s.write_u32(len as u32))
-        } else {
-            self.write_u8(cbor_type.to_byte(super::CBOR_PAYLOAD_LENGTH_U64))
-                .and_then(|s| s.write_u64(len))
-        }
+    fn write_type_and_payload(&mut self, cbor_type: Type, payload: &[u8]) -> Result<&mut Self> {
+        let (header, sz) = self.encode_header(cbor_type, payload.len() as u64)?;
+        write_all_vectored(&mut self.sink, &header[..sz], payload)?;
+        Ok(self)
     }
 
     /// serialise the given unsigned integer
@@ -369,22 +1190,109 @@ impl<W: Write + Sized> Serializer<W> {
         self.write_type(Type::UnsignedInteger, value)
     }
 
-    /// write a negative integer
-    ///
-    /// This function fails if one tries to write a non negative value.
+    /// write a negative integer
+    ///
+    /// This function fails if one tries to write a non negative value.
+    ///
+    /// ```
+    /// use cbor_event::se::{Serializer};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_negative_integer(-12)
+    ///     .expect("write a negative integer");
+    ///
+    /// # let bytes = serializer.finalize();
+    /// # assert_eq!(bytes, [0x2b].as_ref());
+    /// ```
+    pub fn write_negative_integer(&mut self, value: i64) -> Result<&mut Self> {
+        self.write_type(Type::NegativeInteger, (-value - 1) as u64)
+    }
+
+    /// write a signed integer, as an unsigned or a negative integer
+    /// depending on its sign.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_integer(12).unwrap().write_integer(-12).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), [0x0c, 0x2b].as_ref());
+    /// ```
+    pub fn write_integer(&mut self, value: i64) -> Result<&mut Self> {
+        self.write_integer_i128(value as i128)
+    }
+
+    /// write a signed integer, as an unsigned or a negative integer if it
+    /// fits in 64 bits, as a bignum (tag 2 or 3 followed by the big endian
+    /// bytes of the magnitude) otherwise. See
+    /// [`Deserializer::number`](../de/struct.Deserializer.html#method.number)
+    /// to read all of them back.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer
+    ///     .write_integer_i128(-1 - u64::MAX as i128).unwrap()
+    ///     .write_integer_i128(1 << 64).unwrap();
+    ///
+    /// assert_eq!(
+    ///     serializer.finalize(),
+    ///     [
+    ///         0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    ///         0xc2, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ///     ].as_ref()
+    /// );
+    /// ```
+    pub fn write_integer_i128(&mut self, value: i128) -> Result<&mut Self> {
+        // negative integers and negative bignums both encode `-1 - value`
+        if value >= 0 {
+            self.write_integer_parts(false, value as u128)
+        } else {
+            self.write_integer_parts(true, (-1 - value) as u128)
+        }
+    }
+
+    // write `n` if positive, `-1 - n` otherwise, as an integer if it fits
+    // in 64 bits and as a bignum otherwise
+    fn write_integer_parts(&mut self, negative: bool, n: u128) -> Result<&mut Self> {
+        if n <= u64::MAX as u128 {
+            let cbor_type = if negative {
+                Type::NegativeInteger
+            } else {
+                Type::UnsignedInteger
+            };
+            return self.write_type(cbor_type, n as u64);
+        }
+        let bytes = n.to_be_bytes();
+        let start = bytes
+            .iter()
+            .position(|byte| *byte != 0)
+            .unwrap_or(bytes.len());
+        self.write_bignum(negative, &bytes[start..])
+    }
+
+    /// write a bignum (tags 2 and 3): the tag and the big endian bytes of
+    /// `n`, as given. The value is `n` if `negative` is false and `-1 - n`
+    /// otherwise, as in [`Number::BigInt`](../enum.Number.html).
     ///
     /// ```
-    /// use cbor_event::se::{Serializer};
+    /// use cbor_event::se::Serializer;
     ///
     /// let mut serializer = Serializer::new_vec();
-    /// serializer.write_negative_integer(-12)
-    ///     .expect("write a negative integer");
+    /// serializer
+    ///     .write_bignum(false, &[0x01, 0x00]).unwrap()
+    ///     .write_bignum(true, &[0x01, 0x00]).unwrap();
     ///
-    /// # let bytes = serializer.finalize();
-    /// # assert_eq!(bytes, [0x2b].as_ref());
+    /// assert_eq!(
+    ///     serializer.finalize(),
+    ///     vec![0xc2, 0x42, 0x01, 0x00, 0xc3, 0x42, 0x01, 0x00]
+    /// );
     /// ```
-    pub fn write_negative_integer(&mut self, value: i64) -> Result<&mut Self> {
-        self.write_type(Type::NegativeInteger, (-value - 1) as u64)
+    pub fn write_bignum(&mut self, negative: bool, n: &[u8]) -> Result<&mut Self> {
+        let tag = if negative { 3 } else { 2 };
+        self.write_tag(tag)?.write_bytes(n)
     }
 
     /// write the given object as bytes
@@ -400,12 +1308,7 @@ impl<W: Write + Sized> Serializer<W> {
     /// # assert_eq!(bytes, [0x44, 0,1,2,3].as_ref());
     /// ```
     pub fn write_bytes<B: AsRef<[u8]>>(&mut self, bytes: B) -> Result<&mut Self> {
-        let bytes = bytes.as_ref();
-        self.write_type(Type::Bytes, bytes.len() as u64)
-            .and_then(|s| {
-                s.0.write_all(bytes)?;
-                Ok(s)
-            })
+        self.write_type_and_payload(Type::Bytes, bytes.as_ref())
     }
 
     /// write the given object as text
@@ -421,12 +1324,209 @@ impl<W: Write + Sized> Serializer<W> {
     /// # assert_eq!(bytes, [0x6b, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x77, 0x6F, 0x72, 0x6C, 0x64].as_ref());
     /// ```
     pub fn write_text<S: AsRef<str>>(&mut self, text: S) -> Result<&mut Self> {
-        let bytes = text.as_ref().as_bytes();
-        self.write_type(Type::Text, bytes.len() as u64)
-            .and_then(|s| {
-                s.0.write_all(bytes)?;
-                Ok(s)
-            })
+        self.write_type_and_payload(Type::Text, text.as_ref().as_bytes())
+    }
+
+    /// write formatted text directly into the sink, without allocating a
+    /// temporary `String`.
+    ///
+    /// The arguments are formatted twice: once to compute the length of
+    /// the text, once to write it. They must therefore format the same way
+    /// both times (which is the case unless a `Display` implementation
+    /// has side effects), otherwise the output is invalid and `WrongLen` is
+    /// returned.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_text_fmt(format_args!("{}-{}", "id", 42)).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), [0x65, 0x69, 0x64, 0x2d, 0x34, 0x32].as_ref());
+    /// ```
+    pub fn write_text_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<&mut Self> {
+        let len = match args.as_str() {
+            Some(text) => return self.write_text(text),
+            None => {
+                let mut counter = FmtCounter(0);
+                fmt::write(&mut counter, args).map_err(|_| fmt_error())?;
+                counter.0
+            }
+        };
+        self.write_type(Type::Text, len)?;
+
+        let mut writer = FmtWriter {
+            sink: &mut self.sink,
+            written: 0,
+            error: None,
+        };
+        if fmt::write(&mut writer, args).is_err() {
+            return Err(writer.error.map_or_else(fmt_error, Error::IoError));
+        }
+        if writer.written != len {
+            return Err(Error::WrongLen(
+                len,
+                Len::Len(writer.written),
+                "write_text_fmt",
+            ));
+        }
+        Ok(self)
+    }
+
+    /// write bytes already known to be valid UTF-8 as text, without
+    /// converting them to a `str` first.
+    ///
+    /// Saves validating again data just built from `String`s or checked
+    /// elsewhere. The content is only checked in debug builds: passing
+    /// invalid UTF-8 produces a text that conforming decoders reject.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let name = String::from("hello");
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_text_unchecked(name.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), [0x65, 0x68, 0x65, 0x6c, 0x6c, 0x6f].as_ref());
+    /// ```
+    pub fn write_text_unchecked<B: AsRef<[u8]>>(&mut self, text: B) -> Result<&mut Self> {
+        let text = text.as_ref();
+        debug_assert!(
+            ::std::str::from_utf8(text).is_ok(),
+            "write_text_unchecked called with invalid UTF-8"
+        );
+        self.write_type_and_payload(Type::Text, text)
+    }
+
+    // write a Bytes or a Text with its length encoded as given
+    fn write_string_sz(
+        &mut self,
+        cbor_type: Type,
+        payload: &[u8],
+        len_sz: &StringLenSz,
+    ) -> Result<&mut Self> {
+        match len_sz {
+            StringLenSz::Len(sz) => {
+                let len = payload.len() as u64;
+                if !sz.fits(len) {
+                    return Err(Error::InvalidLenPassed(*sz));
+                }
+                self.check_shortest(len, *sz)?;
+                let (header, sz) = encode_header_sz(cbor_type, len, *sz);
+                write_all_vectored(&mut self.sink, &header[..sz], payload)?;
+            }
+            StringLenSz::Indefinite(chunks) => {
+                let total: u64 = chunks.iter().map(|(len, _)| *len).sum();
+                if total != payload.len() as u64 {
+                    return Err(Error::WrongLen(
+                        payload.len() as u64,
+                        Len::Len(total),
+                        "chunks of an indefinite length string",
+                    ));
+                }
+                self.write_indefinite(cbor_type)?;
+                let mut start = 0;
+                for (len, sz) in chunks.iter() {
+                    let end = start + *len as usize;
+                    self.write_type_sz(cbor_type, *len, *sz)?;
+                    self.sink.write_all(&payload[start..end])?;
+                    start = end;
+                }
+                self.write_u8(Type::Special.to_byte(0x1f))?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// write the given unsigned integer encoded on the given size. Fails
+    /// if the value does not fit in it.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Sz};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer.write_unsigned_integer_sz(1, Sz::Four).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), [0x1a, 0, 0, 0, 1].as_ref());
+    /// ```
+    pub fn write_unsigned_integer_sz(&mut self, value: u64, sz: Sz) -> Result<&mut Self> {
+        self.write_type_sz(Type::UnsignedInteger, value, sz)
+    }
+
+    /// write the given negative integer encoded on the given size. Fails
+    /// if the value is not negative, if it is below `-2^64` or if it does
+    /// not fit in the given size.
+    pub fn write_negative_integer_sz(&mut self, value: i128, sz: Sz) -> Result<&mut Self> {
+        if value >= 0 || value < -1 - u64::MAX as i128 {
+            return Err(Error::InvalidLenPassed(sz));
+        }
+        self.write_type_sz(Type::NegativeInteger, (-1 - value) as u64, sz)
+    }
+
+    /// write the given bytes with the length encoded as given. For
+    /// indefinite length the bytes are split in chunks of the given
+    /// lengths, they must add up to the length of the bytes.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, StringLenSz, Sz};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// let len_sz = StringLenSz::Indefinite(vec![(1, Sz::One), (2, Sz::Inline)]);
+    /// serializer.write_bytes_sz(&[1, 2, 3], len_sz).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), [0x5f, 0x58, 1, 1, 0x42, 2, 3, 0xff].as_ref());
+    /// ```
+    pub fn write_bytes_sz<B: AsRef<[u8]>>(
+        &mut self,
+        bytes: B,
+        len_sz: StringLenSz,
+    ) -> Result<&mut Self> {
+        self.write_string_sz(Type::Bytes, bytes.as_ref(), &len_sz)
+    }
+
+    /// write the given text with the length encoded as given, see
+    /// [`write_bytes_sz`](#method.write_bytes_sz). Chunks may not split a
+    /// UTF-8 character.
+    pub fn write_text_sz<S: AsRef<str>>(
+        &mut self,
+        text: S,
+        len_sz: StringLenSz,
+    ) -> Result<&mut Self> {
+        let text = text.as_ref();
+        if let StringLenSz::Indefinite(ref chunks) = len_sz {
+            let mut end = 0;
+            for (len, _) in chunks.iter() {
+                end += *len as usize;
+                if !text.is_char_boundary(end) {
+                    return Err(Error::InvalidIndefiniteString);
+                }
+            }
+        }
+        self.write_string_sz(Type::Text, text.as_bytes(), &len_sz)
+    }
+
+    /// start to write an array with the length encoded as given, see
+    /// [`write_array`](#method.write_array).
+    pub fn write_array_sz(&mut self, len: LenSz) -> Result<&mut Self> {
+        match len {
+            LenSz::Indefinite => self.write_indefinite(Type::Array),
+            LenSz::Len(len, sz) => self.write_type_sz(Type::Array, len, sz),
+        }
+    }
+
+    /// start to write a map with the length encoded as given, see
+    /// [`write_map`](#method.write_map).
+    pub fn write_map_sz(&mut self, len: LenSz) -> Result<&mut Self> {
+        match len {
+            LenSz::Indefinite => self.write_indefinite(Type::Map),
+            LenSz::Len(len, sz) => self.write_type_sz(Type::Map, len, sz),
+        }
+    }
+
+    /// write a tag encoded on the given size, see
+    /// [`write_tag`](#method.write_tag).
+    pub fn write_tag_sz(&mut self, tag: u64, sz: Sz) -> Result<&mut Self> {
+        self.write_type_sz(Type::Tag, tag, sz)
     }
 
     /// start to write an array
@@ -470,8 +1570,8 @@ impl<W: Write + Sized> Serializer<W> {
     ///
     pub fn write_array(&mut self, len: Len) -> Result<&mut Self> {
         match len {
-            Len::Indefinite => self.write_u8(Type::Array.to_byte(0x1f)),
-            Len::Len(len) => self.write_type(Type::Array, len as u64),
+            Len::Indefinite => self.write_indefinite(Type::Array),
+            Len::Len(len) => self.write_type(Type::Array, len),
         }
     }
 
@@ -524,9 +1624,39 @@ impl<W: Write + Sized> Serializer<W> {
     ///
     pub fn write_map(&mut self, len: Len) -> Result<&mut Self> {
         match len {
-            Len::Indefinite => self.write_u8(Type::Map.to_byte(0x1f)),
-            Len::Len(len) => self.write_type(Type::Map, len as u64),
+            Len::Indefinite => self.write_indefinite(Type::Map),
+            Len::Len(len) => self.write_type(Type::Map, len),
+        }
+    }
+
+    /// write a map of definite length with small integer keys, the compact
+    /// encoding of structures used by many protocols. The entries are
+    /// written in the given order.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer
+    ///     .write_int_keyed_map(&[(0, &42u64), (1, &"name".to_owned()), (2, &true)])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     serializer.finalize(),
+    ///     vec![0xa3, 0x00, 0x18, 0x2a, 0x01, 0x64, 0x6e, 0x61, 0x6d, 0x65, 0x02, 0xf5]
+    /// );
+    /// ```
+    pub fn write_int_keyed_map(
+        &mut self,
+        entries: &[(u64, &dyn DynSerialize)],
+    ) -> Result<&mut Self> {
+        self.write_map(Len::Len(entries.len() as u64))?;
+        let mut map = MapEntries::new(self);
+        for (key, value) in entries {
+            map.write(self, key, *value)?;
         }
+        map.finish(self)?;
+        Ok(self)
     }
 
     /// write a tag
@@ -558,8 +1688,31 @@ impl<W: Write + Sized> Serializer<W> {
         self.write_type(Type::Tag, 258)
     }
 
+    /// write the header of a finite set: the set tag (258) followed by
+    /// an array of the given length. You are responsible for writing the
+    /// `len` elements, and the `Special::Break` if indefinite, after it.
+    ///
+    /// ```
+    /// use cbor_event::{se::Serializer, Len};
+    ///
+    /// let mut serializer = Serializer::new_vec();
+    /// serializer
+    ///     .write_set(Len::Len(2)).unwrap()
+    ///     .write_unsigned_integer(1).unwrap()
+    ///     .write_unsigned_integer(2).unwrap();
+    ///
+    /// assert_eq!(serializer.finalize(), vec![0xd9, 0x01, 0x02, 0x82, 0x01, 0x02]);
+    /// ```
+    pub fn write_set(&mut self, len: Len) -> Result<&mut Self> {
+        self.write_set_tag()?.write_array(len)
+    }
+
     /// write a special value in cbor
     ///
+    /// A `Special::Float` is written as a double precision float, NaN
+    /// payloads included, or on the shortest size representing it exactly
+    /// in [canonical mode](#method.set_canonical).
+    ///
     /// # Example
     ///
     /// ```
@@ -587,10 +1740,15 @@ impl<W: Write + Sized> Serializer<W> {
             Special::Unassigned(v) => self
                 .write_u8(Type::Special.to_byte(0x18))
                 .and_then(|s| s.write_u8(v)),
-            Special::Float(f) => unimplemented!(
-                "we currently do not support floating point serialisation, cannot serialize: {}",
-                f
-            ),
+            Special::Float(f) if self.canonical => {
+                let mut bytes = Vec::with_capacity(9);
+                canonical::write_float(f, &mut bytes);
+                self.write_raw_bytes(&bytes)
+            }
+            Special::Float(f) => {
+                self.write_u8(Type::Special.to_byte(0x1b))?;
+                self.write_raw_bytes(&f.to_bits().to_be_bytes())
+            }
             Special::Break => self.write_u8(Type::Special.to_byte(0x1f)),
         }
     }
@@ -599,6 +1757,111 @@ impl<W: Write + Sized> Serializer<W> {
     pub fn serialize<T: Serialize>(&mut self, t: &T) -> Result<&mut Self> {
         Serialize::serialize(t, self)
     }
+
+    /// Write the items of `iter` as an array, without collecting them.
+    ///
+    /// `len_hint` is the bounds on the number of items, as returned by
+    /// `Iterator::size_hint`. The array is written with a definite length
+    /// when the hint is exact and with an indefinite length otherwise. An
+    /// iterator yielding a different number of items than its exact hint
+    /// is a `WrongLen` error, the items are already written then.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let numbers = vec![1u64, 2, 3, 4];
+    /// let mut se = Serializer::new_vec();
+    ///
+    /// let all = numbers.iter();
+    /// se.serialize_iter(all.size_hint(), all).unwrap();
+    /// let even = numbers.iter().filter(|n| *n % 2 == 0);
+    /// se.serialize_iter(even.size_hint(), even).unwrap();
+    ///
+    /// assert_eq!(
+    ///     se.finalize(),
+    ///     vec![0x84, 0x01, 0x02, 0x03, 0x04, 0x9f, 0x02, 0x04, 0xff]
+    /// );
+    /// ```
+    pub fn serialize_iter<I>(
+        &mut self,
+        len_hint: (usize, Option<usize>),
+        iter: I,
+    ) -> Result<&mut Self>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let exact = match len_hint {
+            (lower, Some(upper)) if lower == upper => Some(lower as u64),
+            _ => None,
+        };
+        match exact {
+            Some(len) => self.write_array(Len::Len(len))?,
+            None => self.write_array(Len::Indefinite)?,
+        };
+        let mut count = 0;
+        for item in iter {
+            self.serialize(&item)?;
+            count += 1;
+        }
+        match exact {
+            None => self.write_special(Special::Break),
+            Some(len) if len == count => Ok(self),
+            Some(len) => Err(Error::WrongLen(len, Len::Len(count), "serialize_iter")),
+        }
+    }
+
+    /// Write the items of `iter` one after the other, without enclosing
+    /// array, as a CBOR sequence (RFC 8742). See
+    /// [`Deserializer::into_iter`](../de/struct.Deserializer.html#method.into_iter)
+    /// to read them back.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut se = Serializer::new_vec();
+    /// se.write_sequence(&[1u64, 42]).unwrap();
+    ///
+    /// assert_eq!(se.finalize(), vec![0x01, 0x18, 0x2a]);
+    /// ```
+    pub fn write_sequence<I>(&mut self, iter: I) -> Result<&mut Self>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        for item in iter {
+            self.serialize(&item)?;
+        }
+        Ok(self)
+    }
+
+    /// Write the items of `iter` as a finite set: the set tag (258)
+    /// followed by the items in an array, see
+    /// [`serialize_iter`](#method.serialize_iter) for its length.
+    ///
+    /// The items are written in the order of the iterator, it is up to the
+    /// caller to not yield duplicates.
+    ///
+    /// ```
+    /// use cbor_event::se::Serializer;
+    ///
+    /// let mut se = Serializer::new_vec();
+    /// se.serialize_set(&[3u64, 1, 2]).unwrap();
+    ///
+    /// assert_eq!(
+    ///     se.finalize(),
+    ///     vec![0xd9, 0x01, 0x02, 0x83, 0x03, 0x01, 0x02]
+    /// );
+    /// ```
+    pub fn serialize_set<I>(&mut self, iter: I) -> Result<&mut Self>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        let iter = iter.into_iter();
+        let len_hint = iter.size_hint();
+        self.write_set_tag()?.serialize_iter(len_hint, iter)
+    }
 }
 
 // macro derivation for rust array of bytes
@@ -735,6 +1998,52 @@ mod test {
     }
 
     #[test]
+    fn integer() {
+        use de::Deserializer;
+        use types::Number;
+
+        let values = [
+            0,
+            -1,
+            i64::MAX as i128,
+            i64::MIN as i128,
+            u64::MAX as i128,
+            -1 - u64::MAX as i128,
+            u64::MAX as i128 + 1,
+            -2 - u64::MAX as i128,
+            i128::MAX,
+            i128::MIN,
+        ];
+        let mut serializer = Serializer::new_vec();
+        serializer.write_integer(i64::MIN).unwrap();
+        for value in values.iter() {
+            serializer.write_integer_i128(*value).unwrap();
+        }
+        let bytes = serializer.finalize();
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(raw.number().unwrap(), Number::NegU64(i64::MAX as u64));
+        for value in values.iter() {
+            let decoded = match raw.number().unwrap() {
+                Number::U64(v) => v as i128,
+                Number::NegU64(v) => -1 - v as i128,
+                Number::BigInt(negative, bytes) => {
+                    assert!(bytes.len() > 8 && bytes[0] != 0);
+                    let magnitude = bytes.iter().fold(0u128, |acc, b| acc << 8 | *b as u128);
+                    if negative {
+                        -1 - magnitude as i128
+                    } else {
+                        magnitude as i128
+                    }
+                }
+                number => panic!("unexpected {:?}", number),
+            };
+            assert_eq!(decoded, *value);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn bytes_0() {
         let mut serializer = Serializer::new_vec();
         serializer
@@ -745,6 +2054,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn bytes_1() {
         let mut serializer = Serializer::new_vec();
         serializer
@@ -754,6 +2064,186 @@ mod test {
         assert_eq!(bytes, [0x41, 0b101010].as_ref());
     }
 
+    // sink accepting at most `max` bytes per call, recording the number of calls
+    struct Chunky {
+        bytes: Vec<u8>,
+        calls: usize,
+        max: usize,
+    }
+    impl Write for Chunky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_vectored(&[IoSlice::new(buf)])
+        }
+        fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+            self.calls += 1;
+            let mut written = 0;
+            for buf in bufs {
+                let n = buf.len().min(self.max - written);
+                self.bytes.extend_from_slice(&buf[..n]);
+                written += n;
+            }
+            Ok(written)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bytes_vectored_single_write() {
+        let mut serializer = Serializer::new(Chunky {
+            bytes: Vec::new(),
+            calls: 0,
+            max: usize::MAX,
+        });
+        serializer.write_bytes(vec![0; 300]).expect("write bytes");
+        let sink = serializer.finalize();
+        assert_eq!(sink.calls, 1);
+        assert_eq!(&sink.bytes[..3], [0x59, 0x01, 0x2c].as_ref());
+        assert_eq!(sink.bytes.len(), 303);
+    }
+
+    #[test]
+    fn text_vectored_partial_writes() {
+        let mut serializer = Serializer::new(Chunky {
+            bytes: Vec::new(),
+            calls: 0,
+            max: 2,
+        });
+        serializer.write_text("hello world").expect("write text");
+        let mut expected = Serializer::new_vec();
+        expected.write_text("hello world").unwrap();
+        assert_eq!(serializer.finalize().bytes, expected.finalize());
+    }
+
+    #[test]
+    fn write_buffer() {
+        let mut serializer = Serializer::with_write_buffer(
+            Chunky {
+                bytes: Vec::new(),
+                calls: 0,
+                max: 3,
+            },
+            16,
+        );
+        serializer.write_array(Len::Len(10)).unwrap();
+        for i in 0..9 {
+            serializer.write_unsigned_integer(i * 100).unwrap();
+        }
+        serializer.flush().unwrap();
+        // 15 bytes written when the buffer was full, 9 more on flush, 3
+        // bytes per call
+        assert_eq!(serializer.sink.get_ref().bytes.len(), 24);
+        assert_eq!(serializer.sink.get_ref().calls, 8);
+        // too large for the buffer, written directly once what is buffered
+        // has been flushed
+        serializer.write_special(Special::Bool(true)).unwrap();
+        serializer.write_bytes(vec![0xaa; 20]).unwrap();
+        assert_eq!(
+            &serializer.sink.get_ref().bytes[24..26],
+            [0xf5, 0x54].as_ref()
+        );
+        serializer.write_special(Special::Null).unwrap();
+        let sink = serializer.finalize();
+
+        let mut expected = Serializer::new_vec();
+        expected.write_array(Len::Len(10)).unwrap();
+        for i in 0..9 {
+            expected.write_unsigned_integer(i * 100).unwrap();
+        }
+        expected.write_special(Special::Bool(true)).unwrap();
+        expected.write_bytes(vec![0xaa; 20]).unwrap();
+        expected.write_special(Special::Null).unwrap();
+        assert_eq!(sink.bytes, expected.finalize());
+
+        // rolling back across what has been flushed
+        let mut serializer = Serializer::with_write_buffer(Vec::new(), 4);
+        serializer.write_unsigned_integer(1).unwrap();
+        let checkpoint = serializer.checkpoint();
+        serializer
+            .write_text("flushed")
+            .unwrap()
+            .write_special(Special::Bool(true))
+            .unwrap();
+        serializer
+            .rollback(checkpoint)
+            .write_special(Special::Bool(false))
+            .unwrap();
+        assert_eq!(serializer.finalize(), vec![0x01, 0xf4]);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        use value::Value;
+
+        // the same key, with definite and indefinite lengths
+        let entries = vec![
+            (Value::Array(vec![Value::U64(1)]), 0u64),
+            (Value::IArray(vec![Value::U64(1)]), 1u64),
+        ];
+        let mut serializer = Serializer::new_vec();
+        serializer.serialize(&entries).unwrap();
+        serializer.set_check_duplicate_keys(true);
+        assert!(matches!(
+            serializer.serialize(&entries),
+            Err(Error::DuplicateKey)
+        ));
+
+        let entries = [(1u64, 2u64), (2, 1)];
+        let mut serializer = Serializer::new_vec();
+        serializer.set_check_duplicate_keys(true);
+        serialize_indefinite_map(entries.iter().map(|(k, v)| (k, v)), &mut serializer).unwrap();
+        assert!(matches!(
+            serializer.write_int_keyed_map(&[(3, &true), (3, &false)]),
+            Err(Error::DuplicateKey)
+        ));
+        let mut expected = Serializer::new_vec();
+        serialize_indefinite_map(entries.iter().map(|(k, v)| (k, v)), &mut expected).unwrap();
+        // the first entry has been written
+        expected
+            .write_map(Len::Len(2))
+            .unwrap()
+            .write_unsigned_integer(3)
+            .unwrap()
+            .write_special(Special::Bool(true))
+            .unwrap();
+        assert_eq!(serializer.finalize(), expected.finalize());
+    }
+
+    #[test]
+    fn write_buffer_finalize() {
+        // the buffered bytes cannot be written
+        let failing = || {
+            let mut serializer = Serializer::with_write_buffer(
+                Chunky {
+                    bytes: Vec::new(),
+                    calls: 0,
+                    max: 0,
+                },
+                16,
+            );
+            serializer.write_unsigned_integer(1).unwrap();
+            serializer
+        };
+        assert!(matches!(failing().flush(), Err(Error::IoError(_))));
+        assert!(matches!(failing().try_finalize(), Err(Error::IoError(_))));
+        let sink = failing().finalize();
+        assert!(sink.bytes.is_empty());
+        assert_eq!(sink.calls, 1);
+
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::with_write_buffer(&mut bytes, 16);
+        serializer.write_text("a").unwrap();
+        assert_eq!(serializer.try_finalize().unwrap(), &[0x61, 0x61]);
+
+        // dropping the serializer writes the buffered bytes
+        let mut bytes = Vec::new();
+        let mut serializer = Serializer::with_write_buffer(&mut bytes, 16);
+        serializer.write_text("dropped").unwrap();
+        drop(serializer);
+        assert_eq!(bytes, [0x67, 0x64, 0x72, 0x6f, 0x70, 0x70, 0x65, 0x64]);
+    }
+
     fn test_special(cbor_type: Special, result: &[u8]) -> bool {
         let mut serializer = Serializer::new_vec();
         serializer
@@ -794,4 +2284,273 @@ mod test {
         assert!(test_special(Special::Unassigned(19), [0xf3].as_ref()));
         assert!(test_special(Special::Unassigned(24), [0xf8, 0x18].as_ref()));
     }
+
+    #[test]
+    fn sz_round_trip() {
+        use de::Deserializer;
+
+        let bytes_sz = StringLenSz::Indefinite(vec![(0, Sz::Two), (3, Sz::Inline)]);
+        let text_sz = StringLenSz::Len(Sz::Eight);
+        let mut serializer = Serializer::new_vec();
+        serializer
+            .write_array_sz(LenSz::Len(4, Sz::One))
+            .unwrap()
+            .write_unsigned_integer_sz(0, Sz::Eight)
+            .unwrap()
+            .write_negative_integer_sz(-1 - u64::MAX as i128, Sz::Eight)
+            .unwrap()
+            .write_tag_sz(24, Sz::Two)
+            .unwrap()
+            .write_bytes_sz([1, 2, 3], bytes_sz.clone())
+            .unwrap()
+            .write_map_sz(LenSz::Indefinite)
+            .unwrap()
+            .write_text_sz("text", text_sz.clone())
+            .unwrap()
+            .write_special(Special::Null)
+            .unwrap()
+            .write_special(Special::Break)
+            .unwrap();
+        let bytes = serializer.finalize();
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(raw.array_sz().unwrap(), LenSz::Len(4, Sz::One));
+        assert_eq!(raw.unsigned_integer_sz().unwrap(), (0, Sz::Eight));
+        assert_eq!(
+            raw.negative_integer_sz().unwrap(),
+            (-1 - u64::MAX as i128, Sz::Eight)
+        );
+        assert_eq!(raw.tag_sz().unwrap(), (24, Sz::Two));
+        assert_eq!(raw.bytes_sz().unwrap(), (vec![1, 2, 3], bytes_sz));
+        assert_eq!(raw.map_sz().unwrap(), LenSz::Indefinite);
+        assert_eq!(raw.text_sz().unwrap(), ("text".to_owned(), text_sz));
+        assert_eq!(raw.special().unwrap(), Special::Null);
+        assert!(raw.special_break().unwrap());
+    }
+
+    #[test]
+    fn sz_invalid() {
+        let mut serializer = Serializer::new_vec();
+        assert!(serializer
+            .write_unsigned_integer_sz(24, Sz::Inline)
+            .is_err());
+        assert!(serializer.write_tag_sz(0x1_0000, Sz::Two).is_err());
+        assert!(serializer.write_negative_integer_sz(0, Sz::Inline).is_err());
+        assert!(serializer
+            .write_array_sz(LenSz::Len(0x100, Sz::One))
+            .is_err());
+        assert!(serializer
+            .write_bytes_sz([1, 2], StringLenSz::Indefinite(vec![(1, Sz::Inline)]))
+            .is_err());
+        assert!(serializer
+            .write_text_sz(
+                "é",
+                StringLenSz::Indefinite(vec![(1, Sz::Inline), (1, Sz::Inline)])
+            )
+            .is_err());
+        assert!(serializer.finalize().is_empty());
+    }
+
+    #[test]
+    fn fixed_width() {
+        let mut serializer = Serializer::new_vec();
+        serializer.set_fixed_width(Some(Sz::Two));
+        serializer
+            .write_tag(1)
+            .unwrap()
+            .write_bytes([0xaa])
+            .unwrap()
+            .write_negative_integer(-1)
+            .unwrap();
+        assert!(serializer.write_unsigned_integer(0x1_0000).is_err());
+        assert_eq!(serializer.fixed_width(), Some(Sz::Two));
+        serializer
+            .set_fixed_width(None)
+            .write_unsigned_integer(0x1_0000)
+            .unwrap();
+        assert_eq!(
+            serializer.finalize(),
+            [0xd9, 0, 1, 0x59, 0, 1, 0xaa, 0x39, 0, 0, 0x1a, 0, 1, 0, 0].as_ref()
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        use value::{ObjectKey, Value};
+
+        let mut serializer = Serializer::new_vec();
+        serializer.set_canonical(true);
+        // {"b": [1.5, 100000.0], 10: -1}: the integer key sorts first
+        let entries = vec![
+            (
+                ObjectKey::Text("b".to_owned()),
+                Value::Array(vec![
+                    Value::Special(Special::Float(1.5)),
+                    Value::Special(Special::Float(100000.0)),
+                ]),
+            ),
+            (ObjectKey::Integer(10), Value::I64(-1)),
+        ];
+        serializer.serialize(&entries).unwrap();
+        assert!(matches!(
+            serializer.write_array(Len::Indefinite),
+            Err(Error::NonCanonical(ViolationKind::IndefiniteLength))
+        ));
+        serializer.set_fixed_width(Some(Sz::Two));
+        assert!(matches!(
+            serializer.write_unsigned_integer(1),
+            Err(Error::NonCanonical(ViolationKind::NonMinimalHeader(3, 1)))
+        ));
+        let duplicate = vec![(1u64, 1u64), (1, 2)];
+        assert!(matches!(
+            serializer.set_fixed_width(None).serialize(&duplicate),
+            Err(Error::DuplicateKey)
+        ));
+        // the header of the map with the duplicate key is written
+        assert_eq!(
+            serializer.finalize(),
+            [
+                0xa2, 0x0a, 0x20, 0x61, 0x62, 0x82, 0xf9, 0x3e, 0x00, 0xfa, 0x47, 0xc3, 0x50, 0x00,
+                0xa2
+            ]
+            .as_ref()
+        );
+    }
+
+    #[test]
+    fn rollback_failed_alternative() {
+        let mut se = Serializer::new_vec();
+        se.write_array(Len::Len(2)).unwrap();
+        let checkpoint = se.checkpoint();
+        assert!(se.serialize_iter((3, Some(3)), vec![1u64, 2]).is_err());
+        se.rollback(checkpoint)
+            .serialize_iter((2, Some(2)), vec![1u64, 2])
+            .unwrap();
+        // rolling back to a later position does nothing
+        let later = se.checkpoint();
+        se.rollback(checkpoint).rollback(later);
+        se.write_unsigned_integer(3).unwrap();
+        assert_eq!(se.finalize(), vec![0x82, 0x03]);
+    }
+
+    #[test]
+    fn text_unchecked() {
+        let mut se = Serializer::new_vec();
+        se.write_text_unchecked("\u{20ac}").unwrap();
+        assert_eq!(se.finalize(), vec![0x63, 0xe2, 0x82, 0xac]);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic]
+    fn text_unchecked_invalid() {
+        Serializer::new_vec().write_text_unchecked([0xff]).unwrap();
+    }
+
+    #[test]
+    fn text_fmt() {
+        let mut se = Serializer::new_vec();
+        se.write_text_fmt(format_args!("literal"))
+            .unwrap()
+            .write_text_fmt(format_args!("{:>30}|{:.3}", "\u{20ac}", 1.0 / 3.0))
+            .unwrap();
+        let bytes = se.finalize();
+
+        let mut raw = ::de::Deserializer::from(&bytes[..]);
+        assert_eq!(raw.text().unwrap(), "literal");
+        assert_eq!(
+            raw.text().unwrap(),
+            format!("{:>30}|{:.3}", "\u{20ac}", 1.0 / 3.0)
+        );
+    }
+
+    #[test]
+    fn text_fmt_inconsistent() {
+        use std::cell::Cell;
+
+        // formats differently every time
+        struct Counter(Cell<u32>);
+        impl fmt::Display for Counter {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.set(self.0.get() * 10 + 1);
+                write!(f, "{}", self.0.get())
+            }
+        }
+        let counter = Counter(Cell::new(0));
+        let mut se = Serializer::new_vec();
+        assert!(se.write_text_fmt(format_args!("{}", counter)).is_err());
+    }
+
+    struct Failing;
+    impl Serialize for Failing {
+        fn serialize<'se, W: Write + Sized>(
+            &self,
+            _: &'se mut Serializer<W>,
+        ) -> Result<&'se mut Serializer<W>> {
+            Err(Error::CustomError("failing".to_owned()))
+        }
+    }
+
+    #[test]
+    fn to_vec() {
+        assert_eq!(super::to_vec(&0u64).unwrap(), vec![0x00]);
+        assert_eq!(super::to_vec(&[1u64, 2]).unwrap(), vec![0x82, 0x01, 0x02]);
+        // unsized
+        let text: &dyn DynSerialize = &"a".to_owned();
+        assert_eq!(super::to_vec(text).unwrap(), vec![0x61, 0x61]);
+        assert!(matches!(
+            super::to_vec(&Failing),
+            Err(Error::CustomError(_))
+        ));
+    }
+
+    #[test]
+    fn to_cbor_bytes() {
+        assert_eq!(
+            vec![(1u64, true)].to_cbor_bytes().unwrap(),
+            vec![0xa1, 0x01, 0xf5]
+        );
+        assert_eq!(String::new().to_cbor_bytes().unwrap(), vec![0x60]);
+        assert_eq!(
+            (&&ByteString([1u8])).to_cbor_bytes().unwrap(),
+            vec![0x41, 0x01]
+        );
+
+        assert!(matches!(
+            Failing.to_cbor_bytes(),
+            Err(Error::CustomError(_))
+        ));
+    }
+
+    #[test]
+    fn serialize_iter_wrong_hint() {
+        let mut se = Serializer::new_vec();
+        assert!(matches!(
+            se.serialize_iter((3, Some(3)), vec![1u64, 2]),
+            Err(Error::WrongLen(3, Len::Len(2), "serialize_iter"))
+        ));
+        let mut se = Serializer::new_vec();
+        se.serialize_iter((0, None), Vec::<u64>::new()).unwrap();
+        assert_eq!(se.finalize(), vec![0x9f, 0xff]);
+    }
+
+    #[test]
+    fn byte_string() {
+        let mut se = Serializer::new_vec();
+        se.serialize(&ByteString([1u8, 2]))
+            .unwrap()
+            .serialize(&[1u8, 2])
+            .unwrap()
+            .serialize(&ByteString(vec![3u8]))
+            .unwrap();
+        let bytes = se.finalize();
+        assert_eq!(bytes, vec![0x42, 0x01, 0x02, 0x82, 0x01, 0x02, 0x41, 0x03]);
+
+        let mut raw = ::de::Deserializer::from(&bytes[..]);
+        assert_eq!(
+            raw.deserialize::<ByteString<[u8; 2]>>().unwrap(),
+            ByteString([1, 2])
+        );
+        assert!(raw.deserialize::<ByteString<[u8; 3]>>().is_err());
+    }
 }
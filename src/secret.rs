@@ -0,0 +1,205 @@
+//! Decoding of key material with `zeroize`
+//!
+//! [`SecretBytes`] and [`SecretString`] hold sensitive byte strings and
+//! texts. Their content is wiped from memory when they are dropped, and
+//! decoding them does not leave copies behind: the buffer growing while
+//! the payload is read is zeroized every time it is reallocated, and on
+//! decoding errors.
+//!
+//! The buffer of the reader itself is not wiped: decode from a slice, or
+//! from a reader whose buffer is zeroized by the caller.
+//!
+//! [`SecretBytes`]: ./struct.SecretBytes.html
+//! [`SecretString`]: ./struct.SecretString.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, secret::SecretBytes};
+//!
+//! let bytes = [0x44, 0xde, 0xad, 0xbe, 0xef];
+//! let key: SecretBytes = Deserializer::from(&bytes[..]).deserialize_complete().unwrap();
+//!
+//! assert_eq!(key.as_bytes(), &[0xde, 0xad, 0xbe, 0xef]);
+//! assert_eq!(format!("{:?}", key), "SecretBytes(4 bytes)");
+//! ```
+
+use de::{Deserialize, Deserializer};
+use error::Error;
+use result::Result;
+use se::{Serialize, Serializer};
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+    mem,
+};
+use zeroize::Zeroize;
+
+/// sensitive bytes, zeroized on drop. See the
+/// [module documentation](./index.html).
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretBytes(Vec<u8>);
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+}
+impl Zeroize for SecretBytes {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.0.len())
+    }
+}
+
+/// sensitive text, zeroized on drop. See the
+/// [module documentation](./index.html).
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+impl SecretString {
+    pub fn new(text: String) -> Self {
+        SecretString(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+impl From<String> for SecretString {
+    fn from(text: String) -> Self {
+        SecretString(text)
+    }
+}
+impl Zeroize for SecretString {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString({} bytes)", self.0.len())
+    }
+}
+
+// appends to a buffer, zeroizing the previous allocation when it grows
+struct ZeroizingWriter<'a>(&'a mut Vec<u8>);
+impl<'a> Write for ZeroizingWriter<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let buf = &mut *self.0;
+        if buf.capacity() - buf.len() < data.len() {
+            let mut grown = Vec::with_capacity((buf.len() + data.len()).max(2 * buf.capacity()));
+            grown.extend_from_slice(buf);
+            buf.zeroize();
+            *buf = grown;
+        }
+        buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Serialize for SecretBytes {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_bytes(&self.0)
+    }
+}
+impl Deserialize for SecretBytes {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut secret = SecretBytes::default();
+        raw.bytes_to_writer(&mut ZeroizingWriter(&mut secret.0))?;
+        Ok(secret)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serializer.write_text(&self.0)
+    }
+}
+impl Deserialize for SecretString {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        let mut secret = SecretBytes::default();
+        raw.text_to_writer(&mut ZeroizingWriter(&mut secret.0))?;
+        // checked by `text_to_writer`, the buffer is moved without copy
+        match String::from_utf8(mem::take(&mut secret.0)) {
+            Ok(text) => Ok(SecretString(text)),
+            Err(error) => {
+                let mut bytes = error.into_bytes();
+                bytes.zeroize();
+                Err(Error::CustomError("invalid UTF-8 secret text".to_owned()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // [(_ h'0102', h'03'), "secret"]
+        let bytes = [
+            0x82, 0x5f, 0x42, 0x01, 0x02, 0x41, 0x03, 0xff, 0x66, 0x73, 0x65, 0x63, 0x72, 0x65,
+            0x74,
+        ];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.array().unwrap();
+        let key: SecretBytes = raw.deserialize().unwrap();
+        let password: SecretString = raw.deserialize().unwrap();
+        assert_eq!(key.as_bytes(), &[1, 2, 3]);
+        assert_eq!(password.as_str(), "secret");
+        assert_eq!(format!("{:?}", password), "SecretString(6 bytes)");
+
+        let mut se = Serializer::new_vec();
+        se.serialize(&key).unwrap().serialize(&password).unwrap();
+        // the indefinite byte string is written back with a definite length
+        let mut expected = vec![0x43, 0x01, 0x02, 0x03];
+        expected.extend_from_slice(&bytes[8..]);
+        assert_eq!(se.finalize(), expected);
+    }
+
+    #[test]
+    fn growing_buffer() {
+        let mut buf = Vec::new();
+        let mut writer = ZeroizingWriter(&mut buf);
+        for i in 0..100u8 {
+            writer.write_all(&[i; 3]).unwrap();
+        }
+        assert_eq!(buf.len(), 300);
+        assert_eq!(&buf[297..], &[99; 3]);
+
+        let invalid = [0x62, 0xc3, 0x28];
+        assert!(Deserializer::from(&invalid[..])
+            .deserialize::<SecretString>()
+            .is_err());
+    }
+}
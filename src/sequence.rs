@@ -0,0 +1,280 @@
+//! CBOR sequences (RFC 8742)
+//!
+//! A CBOR sequence is the concatenation of zero or more encoded CBOR
+//! objects, without any enclosing array. It is a common format for logs
+//! and archive files.
+//!
+//! A [`SequenceIndex`] records where every object of a sequence starts
+//! and ends, to access them randomly or to hand them to several threads,
+//! without decoding them up front. A [`SequenceReader`] decodes the
+//! objects one by one from any reader, like a file too large to be loaded
+//! at once. [`Serializer::write_sequence`] writes a sequence.
+//!
+//! [`SequenceIndex`]: ./struct.SequenceIndex.html
+//! [`SequenceReader`]: ./struct.SequenceReader.html
+//! [`Serializer::write_sequence`]: ../se/struct.Serializer.html#method.write_sequence
+//!
+//! ```
+//! use cbor_event::sequence::SequenceIndex;
+//!
+//! // 1, "text", [2, 3]
+//! let bytes = [0x01, 0x64, 0x74, 0x65, 0x78, 0x74, 0x82, 0x02, 0x03];
+//!
+//! let index = SequenceIndex::new(&bytes).unwrap();
+//! assert_eq!(index.items(), &[(0, 1), (1, 5), (6, 3)]);
+//! assert_eq!(index.get::<Vec<u64>>(2).unwrap(), Some(vec![2, 3]));
+//! assert_eq!(index.get_raw(1), Some(&bytes[1..6]));
+//! assert_eq!(index.get_raw(3), None);
+//! ```
+
+use de::{Deserialize, Deserializer};
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use scan;
+use std::{io::BufRead, marker::PhantomData};
+
+/// offsets of the objects of a CBOR sequence, see the
+/// [module documentation](./index.html).
+#[derive(Debug, Clone)]
+pub struct SequenceIndex<'a> {
+    bytes: &'a [u8],
+    // offset and length of each object
+    items: Vec<(usize, usize)>,
+}
+impl<'a> SequenceIndex<'a> {
+    /// index the objects of the CBOR sequence held in `bytes`.
+    ///
+    /// Only the headers are walked, the payloads of the strings are
+    /// skipped. Fails if an object is not well-formed or is truncated.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        let mut items = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let len = scan::item_len(&bytes[pos..])?;
+            items.push((pos, len));
+            pos += len;
+        }
+        Ok(SequenceIndex { bytes, items })
+    }
+
+    /// number of objects in the sequence
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// the offset and length of every object, in the order of the sequence
+    pub fn items(&self) -> &[(usize, usize)] {
+        &self.items
+    }
+
+    /// returns the encoded bytes of the `i`-th object, `None` if out of
+    /// bounds.
+    pub fn get_raw(&self, i: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes;
+        self.items
+            .get(i)
+            .map(|&(offset, len)| &bytes[offset..offset + len])
+    }
+
+    /// decode the `i`-th object, `None` if out of bounds.
+    pub fn get<T: Deserialize>(&self, i: usize) -> Result<Option<T>> {
+        self.get_raw(i).map(decode_item).transpose()
+    }
+
+    /// iterate over the encoded bytes of the objects
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        let bytes = self.bytes;
+        self.items
+            .iter()
+            .map(move |&(offset, len)| &bytes[offset..offset + len])
+    }
+}
+
+/// iterator decoding the objects of a CBOR sequence one at a time, see
+/// [`Deserializer::into_iter`](../de/struct.Deserializer.html#method.into_iter).
+///
+/// The iteration stops at the end of the input when it falls between two
+/// objects. An object cut by the end of the input, or any other error, is
+/// returned as an `Err` and ends the iteration: the position in the input
+/// is unknown then.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, sequence::SequenceReader};
+///
+/// // 1, [2], then a truncated array
+/// let bytes = [0x01, 0x81, 0x02, 0x82, 0x03];
+///
+/// let mut items = SequenceReader::<Vec<u64>, _>::new(Deserializer::from(&bytes[1..3]));
+/// assert_eq!(items.next().unwrap().unwrap(), vec![2]);
+/// assert!(items.next().is_none());
+///
+/// let items: Vec<_> = Deserializer::from(&bytes[1..]).into_iter::<Vec<u64>>().collect();
+/// assert_eq!(items.len(), 2);
+/// assert!(items[1].is_err());
+/// ```
+pub struct SequenceReader<T, R> {
+    raw: Deserializer<R>,
+    // an error ended the iteration
+    failed: bool,
+    item: PhantomData<fn() -> T>,
+}
+impl<T, R> SequenceReader<T, R> {
+    pub fn new(raw: Deserializer<R>) -> Self {
+        SequenceReader {
+            raw,
+            failed: false,
+            item: PhantomData,
+        }
+    }
+
+    /// the `Deserializer` the objects are read from
+    pub fn deserializer(&self) -> &Deserializer<R> {
+        &self.raw
+    }
+
+    pub fn into_inner(self) -> Deserializer<R> {
+        self.raw
+    }
+}
+impl<T: Deserialize, R: BufRead> Iterator for SequenceReader<T, R> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.failed {
+            return None;
+        }
+        let item = match self.raw.at_end() {
+            Ok(true) => return None,
+            Ok(false) => self.raw.deserialize(),
+            Err(error) => Err(error),
+        };
+        self.failed = item.is_err();
+        Some(item)
+    }
+}
+
+/// decode all the objects of the CBOR sequence held in `bytes`.
+///
+/// ```
+/// use cbor_event::sequence;
+///
+/// let items: Vec<u64> = sequence::decode(&[0x01, 0x18, 0x2a, 0x00]).unwrap();
+/// assert_eq!(items, vec![1, 42, 0]);
+/// ```
+pub fn decode<T: Deserialize>(bytes: &[u8]) -> Result<Vec<T>> {
+    scan::split_items(bytes)?
+        .into_iter()
+        .map(decode_item)
+        .collect()
+}
+
+/// decode all the objects of the CBOR sequence held in `bytes` in
+/// parallel.
+///
+/// The buffer is first split at the objects' boundaries with a fast scan
+/// of the CBOR headers (the payloads are not read), then every object is
+/// decoded on the [`rayon`](https://docs.rs/rayon) thread pool. The
+/// returned objects are in the order of the sequence.
+///
+/// Only available with the `rayon` feature.
+#[cfg(feature = "rayon")]
+pub fn par_decode<T: Deserialize + Send>(bytes: &[u8]) -> Result<Vec<T>> {
+    use rayon::prelude::*;
+
+    scan::split_items(bytes)?
+        .into_par_iter()
+        .map(decode_item)
+        .collect()
+}
+
+fn decode_item<T: Deserialize>(item: &[u8]) -> Result<T> {
+    Deserializer::from(item).deserialize_complete()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::{serialize_fixed_array, Serializer};
+
+    fn sequence(len: u64) -> Vec<u8> {
+        let mut se = Serializer::new_vec();
+        for i in 0..len {
+            serialize_fixed_array(vec![i; (i % 7) as usize].iter(), &mut se).unwrap();
+        }
+        se.finalize()
+    }
+
+    #[test]
+    fn decode_sequence() {
+        let items: Vec<Vec<u64>> = decode(&sequence(100)).unwrap();
+        assert_eq!(items.len(), 100);
+        assert_eq!(items[15], vec![15; 1]);
+    }
+
+    #[test]
+    fn decode_truncated_sequence() {
+        let bytes = sequence(10);
+        assert!(decode::<Vec<u64>>(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn index_sequence() {
+        let bytes = sequence(50);
+        let index = SequenceIndex::new(&bytes).unwrap();
+        assert_eq!(index.len(), 50);
+        let items = index
+            .iter()
+            .map(decode_item)
+            .collect::<Result<Vec<Vec<u64>>>>()
+            .unwrap();
+        assert_eq!(items, decode::<Vec<u64>>(&bytes).unwrap());
+        let (offset, len) = index.items()[49];
+        assert_eq!(offset + len, bytes.len());
+
+        let bytes = sequence(10);
+        assert!(SequenceIndex::new(&bytes[..bytes.len() - 1]).is_err());
+        assert!(SequenceIndex::new(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_sequence() {
+        let bytes = sequence(100);
+        let items = Deserializer::from(&bytes[..])
+            .into_iter()
+            .collect::<Result<Vec<Vec<u64>>>>()
+            .unwrap();
+        assert_eq!(items, decode::<Vec<u64>>(&bytes).unwrap());
+        assert!(Deserializer::from(&[][..])
+            .into_iter::<u64>()
+            .next()
+            .is_none());
+
+        // the last object is truncated, then the iteration stops
+        let mut items = Deserializer::from(&bytes[..bytes.len() - 1]).into_iter::<Vec<u64>>();
+        assert_eq!(items.by_ref().take(99).filter(Result::is_ok).count(), 99);
+        assert!(items.next().unwrap().is_err());
+        assert!(items.next().is_none());
+
+        let mut se = Serializer::new_vec();
+        se.write_sequence(0..100u64).unwrap();
+        let bytes = se.finalize();
+        let items = Deserializer::from(&bytes[..])
+            .into_iter::<u64>()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_decode_sequence() {
+        let bytes = sequence(1000);
+        let items: Vec<Vec<u64>> = par_decode(&bytes).unwrap();
+        assert_eq!(items, decode::<Vec<u64>>(&bytes).unwrap());
+    }
+}
@@ -0,0 +1,803 @@
+//! Serde interoperability
+//!
+//! Drive this crate's [`Serializer`] and [`Deserializer`] from serde's
+//! data model, without an intermediate buffer: [`serialize`] and
+//! [`deserialize`] write or read one serde type at the current position,
+//! so serde-derived types can be mixed with hand written encoders and
+//! decoders in the same stream. [`Serde`] wraps a serde type so it
+//! implements this crate's `Serialize` and `Deserialize`.
+//!
+//! The data model is mapped like ciborium's:
+//!
+//! - structs and maps are maps, with the field names as text keys;
+//! - tuples, tuple structs and sequences are arrays, indefinite if their
+//!   length is not known in advance;
+//! - `None` and `()` are `null`, `Some` and newtypes are their content;
+//! - unit variants are their name as text, the other variants a map of
+//!   one entry from their name to their content;
+//! - `i128` and `u128` outside of 64 bits are bignums (tags 2 and 3).
+//!
+//! Other tags are skipped when decoding, the tagged object is decoded in
+//! their place. The decoded strings are owned: types borrowing from the
+//! input, like `&str`, are not supported.
+//!
+//! [`Serializer`]: ../se/struct.Serializer.html
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//! [`serialize`]: ./fn.serialize.html
+//! [`deserialize`]: ./fn.deserialize.html
+//! [`Serde`]: ./struct.Serde.html
+//!
+//! ```
+//! # extern crate cbor_event;
+//! # extern crate serde;
+//! use cbor_event::{de::Deserializer, se::Serializer, Len};
+//! use std::collections::BTreeMap;
+//!
+//! # fn main() {
+//! let mut map = BTreeMap::new();
+//! map.insert("a".to_owned(), vec![1u32, 2]);
+//!
+//! // a serde type in an array written by hand
+//! let mut se = Serializer::new_vec();
+//! se.write_array(Len::Len(2)).unwrap().write_text("map").unwrap();
+//! cbor_event::serde::serialize(&map, &mut se).unwrap();
+//! let bytes = se.finalize();
+//!
+//! let mut raw = Deserializer::from(&bytes[..]);
+//! assert_eq!(raw.array().unwrap(), Len::Len(2));
+//! assert_eq!(raw.text().unwrap(), "map");
+//! let decoded: BTreeMap<String, Vec<u32>> = cbor_event::serde::deserialize(&mut raw).unwrap();
+//! assert_eq!(decoded, map);
+//! # }
+//! ```
+
+use de::{self, Deserializer};
+use error::Error;
+use len::Len;
+use result::Result;
+use se::{self, Serializer};
+use serde_crate::{
+    de::{
+        DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{
+        SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant,
+    },
+};
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+use types::{Special, Type};
+
+/// write `value` to `writer`, see [`serialize`](./fn.serialize.html).
+pub fn to_writer<T, W>(value: &T, writer: W) -> Result<()>
+where
+    T: ?Sized + ::serde_crate::Serialize,
+    W: Write + Sized,
+{
+    let mut serializer = Serializer::new(writer);
+    serialize(value, &mut serializer)?;
+    serializer.flush()?;
+    Ok(())
+}
+
+/// read a `T` from `reader`, see [`deserialize`](./fn.deserialize.html).
+/// The bytes following the object are left in the reader.
+pub fn from_reader<T, R>(reader: R) -> Result<T>
+where
+    T: DeserializeOwned,
+    R: BufRead,
+{
+    deserialize(&mut Deserializer::from(reader))
+}
+
+/// write `value` with serde at the current position of the serializer.
+pub fn serialize<T, W>(value: &T, serializer: &mut Serializer<W>) -> Result<()>
+where
+    T: ?Sized + ::serde_crate::Serialize,
+    W: Write + Sized,
+{
+    value.serialize(Ser(serializer))
+}
+
+/// read a `T` with serde at the current position of the deserializer.
+///
+/// The containers count towards the
+/// [`max_depth`](../de/struct.Deserializer.html#method.set_max_depth) of
+/// the deserializer.
+pub fn deserialize<T, R>(raw: &mut Deserializer<R>) -> Result<T>
+where
+    T: DeserializeOwned,
+    R: BufRead,
+{
+    raw.scoped(|raw| T::deserialize(De(raw)))
+}
+
+/// a type implementing serde's `Serialize` and `Deserialize`, serialised
+/// and deserialised by this crate with
+/// [`serialize`](./fn.serialize.html) and
+/// [`deserialize`](./fn.deserialize.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Serde<T>(pub T);
+
+impl<T: ::serde_crate::Serialize> se::Serialize for Serde<T> {
+    fn serialize<'se, W: Write + Sized>(
+        &self,
+        serializer: &'se mut Serializer<W>,
+    ) -> Result<&'se mut Serializer<W>> {
+        serialize(&self.0, serializer)?;
+        Ok(serializer)
+    }
+}
+impl<T: DeserializeOwned> de::Deserialize for Serde<T> {
+    fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
+        deserialize(raw).map(Serde)
+    }
+}
+
+impl ::serde_crate::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::CustomError(msg.to_string())
+    }
+}
+impl ::serde_crate::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::CustomError(msg.to_string())
+    }
+}
+
+struct Ser<'a, W: Write + 'a>(&'a mut Serializer<W>);
+
+// the containers: `indefinite` if a break must be written at the end
+struct Compound<'a, W: Write + 'a> {
+    serializer: &'a mut Serializer<W>,
+    indefinite: bool,
+}
+
+impl<'a, W: Write + Sized> Ser<'a, W> {
+    fn compound(self, len: Option<usize>, map: bool) -> Result<Compound<'a, W>> {
+        let len = len.map_or(Len::Indefinite, |len| Len::Len(len as u64));
+        if map {
+            self.0.write_map(len)?;
+        } else {
+            self.0.write_array(len)?;
+        }
+        Ok(Compound {
+            serializer: self.0,
+            indefinite: len == Len::Indefinite,
+        })
+    }
+
+    // the map of one entry holding the content of a variant
+    fn variant(self, variant: &'static str) -> Result<Self> {
+        self.0.write_map(Len::Len(1))?.write_text(variant)?;
+        Ok(self)
+    }
+}
+
+impl<'a, W: Write + Sized> ::serde_crate::Serializer for Ser<'a, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.0.write_special(Special::Bool(v)).map(|_| ())
+    }
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.0.write_integer(v).map(|_| ())
+    }
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.0.serialize(&v).map(|_| ())
+    }
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.0.write_unsigned_integer(v).map(|_| ())
+    }
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.0.serialize(&v).map(|_| ())
+    }
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(v as f64)
+    }
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.0.write_special(Special::Float(v)).map(|_| ())
+    }
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.0.write_text(v).map(|_| ())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.0.write_bytes(v).map(|_| ())
+    }
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+    fn serialize_some<T: ?Sized + ::serde_crate::Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<()> {
+        self.0.write_special(Special::Null).map(|_| ())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: ?Sized + ::serde_crate::Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + ::serde_crate::Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self.variant(variant)?)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.compound(len, false)
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>> {
+        self.compound(Some(len), false)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.compound(Some(len), false)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.variant(variant)?.compound(Some(len), false)
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.compound(len, true)
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.compound(Some(len), true)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.variant(variant)?.compound(Some(len), true)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, W: Write + Sized> Compound<'a, W> {
+    fn element<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(Ser(self.serializer))
+    }
+
+    fn end(self) -> Result<()> {
+        if self.indefinite {
+            self.serializer.write_special(Special::Break)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W: Write + Sized> SerializeSeq for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeTuple for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeTupleStruct for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeTupleVariant for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeMap for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + ::serde_crate::Serialize>(&mut self, key: &T) -> Result<()> {
+        self.element(key)
+    }
+    fn serialize_value<T: ?Sized + ::serde_crate::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeStruct for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ::serde_crate::Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serializer.write_text(key)?;
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+impl<'a, W: Write + Sized> SerializeStructVariant for Compound<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + ::serde_crate::Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serializer.write_text(key)?;
+        self.element(value)
+    }
+    fn end(self) -> Result<()> {
+        Compound::end(self)
+    }
+}
+
+struct De<'a, R: 'a>(&'a mut Deserializer<R>);
+
+impl<'a, R: BufRead> De<'a, R> {
+    // whether the next object is `null` or `undefined`
+    fn is_null(&mut self) -> Result<bool> {
+        Ok(match self.0.peek_remaining(1)? {
+            [0xf6] | [0xf7] => true,
+            [_] => false,
+            [] => return Err(Error::NotEnough(0, 1)),
+            _ => unreachable!(),
+        })
+    }
+
+    fn array<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.0.scoped(|raw| {
+            let len = raw.array()?;
+            let mut items = Items::new(raw, len);
+            let value = visitor.visit_seq(&mut items)?;
+            items.end()?;
+            Ok(value)
+        })
+    }
+
+    fn map<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.0.scoped(|raw| {
+            let len = raw.map()?;
+            let mut items = Items::new(raw, len);
+            let value = visitor.visit_map(&mut items)?;
+            items.end()?;
+            Ok(value)
+        })
+    }
+}
+
+impl<'de, 'a, R: BufRead> ::serde_crate::Deserializer<'de> for De<'a, R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.0.cbor_type()? {
+            Type::UnsignedInteger => visitor.visit_u64(self.0.unsigned_integer()?),
+            Type::NegativeInteger => {
+                let n = self.0.integer()?;
+                if n < i64::MIN as i128 {
+                    visitor.visit_i128(n)
+                } else {
+                    visitor.visit_i64(n as i64)
+                }
+            }
+            Type::Bytes => visitor.visit_byte_buf(self.0.bytes()?),
+            Type::Text => visitor.visit_string(self.0.text()?),
+            Type::Array => self.array(visitor),
+            Type::Map => self.map(visitor),
+            Type::Tag => self.0.scoped(|raw| {
+                raw.tag()?;
+                De(raw).deserialize_any(visitor)
+            }),
+            Type::Special => match self.0.special()? {
+                Special::Bool(b) => visitor.visit_bool(b),
+                Special::Null | Special::Undefined => visitor.visit_unit(),
+                Special::Float(f) => visitor.visit_f64(f),
+                Special::Break => Err(Error::UnexpectedBreak),
+                Special::Unassigned(v) => Err(Error::CustomError(format!(
+                    "serde: unsupported simple value {}",
+                    v
+                ))),
+            },
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.0.deserialize()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.0.deserialize()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value> {
+        if self.is_null()? {
+            self.0.special()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.0.cbor_type()? {
+            Type::Text => visitor.visit_enum(self.0.text()?.into_deserializer()),
+            Type::Map => self.0.scoped(|raw| {
+                let len = raw.map()?;
+                if len == Len::Len(1) {
+                    visitor.visit_enum(Variant(raw))
+                } else if len == Len::Indefinite && !raw.special_break()? {
+                    let value = visitor.visit_enum(Variant(&mut *raw))?;
+                    if raw.special_break()? {
+                        Ok(value)
+                    } else {
+                        Err(Error::WrongLen(1, Len::Indefinite, "serde enum"))
+                    }
+                } else {
+                    Err(Error::WrongLen(1, len, "serde enum"))
+                }
+            }),
+            t => Err(Error::Expected(Type::Map, t)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.0.skip_value()?;
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+// the items of an array or of a map, the entries of a map being read as
+// a key followed by a value
+struct Items<'a, R: 'a> {
+    raw: &'a mut Deserializer<R>,
+    len: Len,
+    read: u64,
+    ended: bool,
+}
+
+impl<'a, R: BufRead> Items<'a, R> {
+    fn new(raw: &'a mut Deserializer<R>, len: Len) -> Self {
+        Items {
+            raw,
+            len,
+            read: 0,
+            ended: false,
+        }
+    }
+
+    fn has_next(&mut self) -> Result<bool> {
+        if !self.ended {
+            self.ended = match self.len {
+                Len::Len(len) => self.read == len,
+                Len::Indefinite => self.raw.special_break()?,
+            };
+        }
+        Ok(!self.ended)
+    }
+
+    fn next<'de, T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value> {
+        self.read += 1;
+        self.raw.scoped(|raw| seed.deserialize(De(raw)))
+    }
+
+    // fails if the visitor did not read all the items
+    fn end(mut self) -> Result<()> {
+        if self.has_next()? {
+            Err(Error::WrongLen(self.read, self.len, "serde"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'de, 'a, R: BufRead> SeqAccess<'de> for Items<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.has_next()? {
+            self.next(seed).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.len {
+            Len::Len(len) => Some((len - self.read) as usize),
+            Len::Indefinite => None,
+        }
+    }
+}
+
+impl<'de, 'a, R: BufRead> MapAccess<'de> for Items<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.has_next()? {
+            self.next(seed).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        self.raw.scoped(|raw| seed.deserialize(De(raw)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.len {
+            Len::Len(len) => Some((len - self.read) as usize),
+            Len::Indefinite => None,
+        }
+    }
+}
+
+// the only entry of the map holding a variant: its name and its content
+struct Variant<'a, R: 'a>(&'a mut Deserializer<R>);
+
+impl<'de, 'a, R: BufRead> EnumAccess<'de> for Variant<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(De(&mut *self.0))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R: BufRead> VariantAccess<'de> for Variant<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        ::serde_crate::Deserialize::deserialize(De(self.0))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(De(self.0))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        De(self.0).array(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        De(self.0).map(visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Point {
+        x: i32,
+        y: Option<u8>,
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(Point, u64),
+        Square { corner: Point, side: f64 },
+        Polygon(Vec<Point>),
+        Big(i128),
+    }
+
+    fn round_trip<T>(value: &T) -> Vec<u8>
+    where
+        T: ::serde_crate::Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        let mut bytes = Vec::new();
+        to_writer(value, &mut bytes).unwrap();
+        let decoded: T = from_reader(&bytes[..]).unwrap();
+        assert_eq!(&decoded, value);
+        bytes
+    }
+
+    fn point() -> Point {
+        Point {
+            x: -3,
+            y: None,
+            name: "a".to_owned(),
+        }
+    }
+
+    #[test]
+    fn data_model() {
+        assert_eq!(
+            round_trip(&point()),
+            [0xa3, 0x61, 0x78, 0x22, 0x61, 0x79, 0xf6, 0x64, 0x6e, 0x61, 0x6d, 0x65, 0x61, 0x61]
+        );
+        assert_eq!(
+            round_trip(&Shape::Empty),
+            [0x65, 0x45, 0x6d, 0x70, 0x74, 0x79]
+        );
+        round_trip(&Shape::Circle(point(), 1));
+        round_trip(&Shape::Square {
+            corner: point(),
+            side: 1.5,
+        });
+        round_trip(&Shape::Polygon(vec![point(), point()]));
+        round_trip(&Shape::Big(i128::MIN));
+        round_trip(&(true, 'é', u128::MAX, -1i8, ()));
+
+        let mut map = BTreeMap::new();
+        map.insert(1u8, vec![Some(2u16), None]);
+        assert_eq!(round_trip(&map), [0xa1, 0x01, 0x82, 0x02, 0xf6]);
+    }
+
+    #[test]
+    fn cbor_forms() {
+        // indefinite lengths, tags and an enum in an indefinite map
+        let bytes = [
+            0xbf, 0x61, 0x78, 0xc1, 0x01, 0x61, 0x79, 0x02, 0x64, 0x6e, 0x61, 0x6d, 0x65, 0x7f,
+            0x61, 0x61, 0xff, 0xff,
+        ];
+        let decoded: Point = from_reader(&bytes[..]).unwrap();
+        assert_eq!(decoded.x, 1);
+        assert_eq!(decoded.y, Some(2));
+        assert_eq!(decoded.name, "a");
+
+        let bytes = [0xbf, 0x63, 0x42, 0x69, 0x67, 0x20, 0xff];
+        assert_eq!(from_reader::<Shape, _>(&bytes[..]).unwrap(), Shape::Big(-1));
+
+        // a tuple of 2 read from an array of 3
+        assert!(from_reader::<(u8, u8), _>(&[0x83, 0x01, 0x02, 0x03][..]).is_err());
+        // unknown variant
+        assert!(from_reader::<Shape, _>(&[0x61, 0x61][..]).is_err());
+    }
+
+    #[test]
+    fn mixed() {
+        let mut se = Serializer::new_vec();
+        se.write_array(Len::Indefinite)
+            .unwrap()
+            .serialize(&Serde(point()))
+            .unwrap()
+            .write_unsigned_integer(7)
+            .unwrap()
+            .write_special(Special::Break)
+            .unwrap();
+        let bytes = se.finalize();
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        assert_eq!(raw.array().unwrap(), Len::Indefinite);
+        let Serde(decoded): Serde<Point> = raw.deserialize().unwrap();
+        assert_eq!(decoded, point());
+        assert_eq!(raw.unsigned_integer().unwrap(), 7);
+        assert!(raw.special_break().unwrap());
+    }
+
+    #[test]
+    fn max_depth() {
+        let bytes = [0x81, 0x81, 0x81, 0x00];
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(2);
+        assert!(matches!(
+            deserialize::<Vec<Vec<Vec<u8>>>, _>(&mut raw),
+            Err(Error::MaxDepthExceeded)
+        ));
+
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(3);
+        assert_eq!(
+            deserialize::<Vec<Vec<Vec<u8>>>, _>(&mut raw).unwrap(),
+            [[[0]]]
+        );
+        assert_eq!(raw.depth(), 0);
+    }
+}
@@ -0,0 +1,274 @@
+//! Output sinks for the [`Serializer`](../se/struct.Serializer.html)
+//!
+//! Any [`std::io::Write`](https://doc.rust-lang.org/std/io/trait.Write.html)
+//! can be used as a sink, the types here are specialised for common
+//! serialisation patterns.
+
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use std::{fmt, io, ops::Deref};
+
+/// number of bytes an [`InlineBuffer`] can hold before spilling to the heap.
+///
+/// [`InlineBuffer`]: ./struct.InlineBuffer.html
+pub const INLINE_CAPACITY: usize = 128;
+
+#[derive(Clone)]
+enum Storage {
+    Inline([u8; INLINE_CAPACITY], usize),
+    Heap(Vec<u8>),
+}
+
+/// byte buffer storing up to [`INLINE_CAPACITY`] bytes on the stack and
+/// spilling to a heap allocated `Vec<u8>` only when more is written.
+///
+/// This is meant for the very common case of encoding a small structure
+/// into bytes, where allocating a `Vec` with a large capacity up front is
+/// most of the cost of the serialisation.
+///
+/// [`INLINE_CAPACITY`]: ./constant.INLINE_CAPACITY.html
+///
+/// ```
+/// use cbor_event::se::Serializer;
+///
+/// let mut serializer = Serializer::new_inline();
+/// serializer.write_unsigned_integer(42).unwrap();
+///
+/// let bytes = serializer.finalize();
+/// assert!(bytes.is_inline());
+/// assert_eq!(bytes.as_slice(), [0x18, 0x2a].as_ref());
+/// ```
+#[derive(Clone)]
+pub struct InlineBuffer(Storage);
+impl InlineBuffer {
+    #[inline]
+    pub fn new() -> Self {
+        InlineBuffer(Storage::Inline([0; INLINE_CAPACITY], 0))
+    }
+
+    /// returns `true` if the content has not been moved to the heap
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Storage::Inline(..))
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        match self.0 {
+            Storage::Inline(ref bytes, len) => &bytes[..len],
+            Storage::Heap(ref bytes) => bytes.as_slice(),
+        }
+    }
+
+    /// convert the buffer into a `Vec<u8>`, this allocates if the content
+    /// is still inline.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.0 {
+            Storage::Inline(bytes, len) => bytes[..len].to_vec(),
+            Storage::Heap(bytes) => bytes,
+        }
+    }
+
+    /// remove all the content of the buffer, keeping the heap allocation
+    /// (if any) for future use.
+    pub fn clear(&mut self) {
+        match self.0 {
+            Storage::Inline(_, ref mut len) => *len = 0,
+            Storage::Heap(ref mut bytes) => bytes.clear(),
+        }
+    }
+
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        let spilled = match self.0 {
+            Storage::Inline(ref mut bytes, ref mut len) => {
+                if *len + data.len() <= INLINE_CAPACITY {
+                    bytes[*len..*len + data.len()].copy_from_slice(data);
+                    *len += data.len();
+                    return;
+                }
+                let mut heap = Vec::with_capacity((*len + data.len()).max(2 * INLINE_CAPACITY));
+                heap.extend_from_slice(&bytes[..*len]);
+                heap
+            }
+            Storage::Heap(ref mut bytes) => {
+                bytes.extend_from_slice(data);
+                return;
+            }
+        };
+        self.0 = Storage::Heap(spilled);
+        self.extend_from_slice(data)
+    }
+}
+impl Default for InlineBuffer {
+    fn default() -> Self {
+        InlineBuffer::new()
+    }
+}
+impl Deref for InlineBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+impl AsRef<[u8]> for InlineBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+impl PartialEq for InlineBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+impl Eq for InlineBuffer {}
+impl fmt::Debug for InlineBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+impl From<InlineBuffer> for Vec<u8> {
+    fn from(buffer: InlineBuffer) -> Self {
+        buffer.into_vec()
+    }
+}
+impl io::Write for InlineBuffer {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.extend_from_slice(buf);
+        Ok(())
+    }
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let mut written = 0;
+        for buf in bufs {
+            self.extend_from_slice(buf);
+            written += buf.len();
+        }
+        Ok(written)
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// sink writing the output to two writers at once, e.g. a socket and an
+/// audit log, or a buffer and a hasher.
+///
+/// Every write is written entirely to the first writer then to the
+/// second, so the serialisation is done only once and the finished buffer
+/// is never copied.
+///
+/// ```
+/// use cbor_event::{se::Serializer, sink::Tee};
+///
+/// let mut serializer = Serializer::new(Tee::new(Vec::new(), Vec::new()));
+/// serializer.write_text("audited").unwrap();
+///
+/// let (sent, log) = serializer.finalize().into_inner();
+/// assert_eq!(sent, log);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+impl<A: io::Write, B: io::Write> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Tee { first, second }
+    }
+
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+impl<A: io::Write, B: io::Write> io::Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use std::io::Write;
+
+    #[test]
+    fn inline_buffer_stays_inline() {
+        let mut buffer = InlineBuffer::new();
+        buffer.write_all(&[1; INLINE_CAPACITY]).unwrap();
+        assert!(buffer.is_inline());
+        assert_eq!(buffer.len(), INLINE_CAPACITY);
+    }
+
+    #[test]
+    fn inline_buffer_spills() {
+        let mut buffer = InlineBuffer::new();
+        buffer.write_all(&[1; 100]).unwrap();
+        buffer.write_all(&[2; 100]).unwrap();
+        assert!(!buffer.is_inline());
+        assert_eq!(&buffer[..100], [1; 100].as_ref());
+        assert_eq!(&buffer[100..], [2; 100].as_ref());
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn serializer_inline_matches_vec() {
+        let text = "some text long enough to spill out of the inline storage ".repeat(4);
+        let mut inline = Serializer::new_inline();
+        let mut vec = Serializer::new_vec();
+        inline.write_text(&text).unwrap();
+        vec.write_text(&text).unwrap();
+        assert_eq!(inline.finalize().into_vec(), vec.finalize());
+    }
+
+    #[test]
+    fn tee_duplicates_output() {
+        let text = "long enough to be written in several calls ".repeat(10);
+        let mut serializer = Serializer::new(Tee::new(InlineBuffer::new(), Vec::new()));
+        serializer
+            .write_array(::Len::Indefinite)
+            .unwrap()
+            .write_text(&text)
+            .unwrap()
+            .write_special(::Special::Break)
+            .unwrap();
+        let (inline, vec) = serializer.finalize().into_inner();
+
+        let mut expected = Serializer::new_vec();
+        expected
+            .write_array(::Len::Indefinite)
+            .unwrap()
+            .write_text(&text)
+            .unwrap()
+            .write_special(::Special::Break)
+            .unwrap();
+        let expected = expected.finalize();
+        assert_eq!(inline.as_slice(), &expected[..]);
+        assert_eq!(vec, expected);
+    }
+}
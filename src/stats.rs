@@ -0,0 +1,193 @@
+//! Statistics on the content of encoded CBOR
+//!
+//! [`stats`] walks an encoded object and summarises what it is made of:
+//! how many objects of each type, how deep the nesting goes, how large
+//! the biggest strings and containers are. Useful to understand what
+//! dominates the size of a payload or to choose decoder limits.
+//!
+//! [`stats`]: ./fn.stats.html
+//!
+//! ```
+//! use cbor_event::{stats::stats, Type};
+//!
+//! // [1, [h'0102', "text"], 24(h'')]
+//! let bytes = [0x83, 0x01, 0x82, 0x42, 0x01, 0x02, 0x64, 0x74, 0x65, 0x78, 0x74, 0xd8, 0x18, 0x40];
+//!
+//! let stats = stats(&bytes).unwrap();
+//! assert_eq!(stats.count(Type::Array), 2);
+//! assert_eq!(stats.count(Type::Bytes), 2);
+//! assert_eq!(stats.max_depth, 3);
+//! assert_eq!(stats.largest_text, 4);
+//! ```
+
+use len::Len;
+use result::Result;
+use scan;
+use std::collections::BTreeMap;
+use types::Type;
+
+/// statistics collected by [`stats`](./fn.stats.html)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    // number of objects per major type
+    counts: [u64; 8],
+    /// deepest nesting level: the top level object is at depth 1, the
+    /// elements of an array, entries of a map or content of a tag are one
+    /// level deeper than their parent.
+    pub max_depth: usize,
+    /// size in bytes of the largest byte string
+    pub largest_bytes: u64,
+    /// size in bytes of the largest text string
+    pub largest_text: u64,
+    /// number of elements of the largest array
+    pub largest_array: u64,
+    /// number of entries of the largest map
+    pub largest_map: u64,
+    /// total size in bytes of the content of the byte strings
+    pub bytes_payload: u64,
+    /// total size in bytes of the content of the text strings
+    pub text_payload: u64,
+    /// number of occurrences of each tag
+    pub tags: BTreeMap<u64, u64>,
+    /// number of strings, arrays and maps of indefinite length
+    pub indefinite: u64,
+}
+impl Stats {
+    /// number of objects of the given major type. The chunks of
+    /// indefinite length strings are not counted.
+    pub fn count(&self, cbor_type: Type) -> u64 {
+        self.counts[index(cbor_type)]
+    }
+
+    /// total number of objects
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+fn index(cbor_type: Type) -> usize {
+    (cbor_type.to_byte(0) >> 5) as usize
+}
+
+/// collect the statistics of the CBOR object encoded in `bytes`.
+///
+/// Fails if `bytes` is not exactly one well-formed CBOR object.
+pub fn stats(bytes: &[u8]) -> Result<Stats> {
+    let layout = scan::Layout::new(bytes)?;
+    let items = layout.items();
+
+    let mut stats = Stats::default();
+    let mut i = 0;
+    while i < items.len() {
+        let item = &items[i];
+        stats.counts[index(item.cbor_type)] += 1;
+        stats.max_depth = stats.max_depth.max(item.depth + 1);
+        if item.len == Len::Indefinite && item.cbor_type != Type::Special {
+            stats.indefinite += 1;
+        }
+
+        match item.cbor_type {
+            Type::Bytes | Type::Text => {
+                let size = layout.string_len(i);
+                let (largest, payload) = if item.cbor_type == Type::Bytes {
+                    (&mut stats.largest_bytes, &mut stats.bytes_payload)
+                } else {
+                    (&mut stats.largest_text, &mut stats.text_payload)
+                };
+                *largest = (*largest).max(size);
+                *payload += size;
+                // the chunks are not counted
+                i = item.next;
+                continue;
+            }
+            Type::Array | Type::Map => {
+                let children = layout.children(i).count() as u64;
+                if item.cbor_type == Type::Array {
+                    stats.largest_array = stats.largest_array.max(children);
+                } else {
+                    stats.largest_map = stats.largest_map.max(children / 2);
+                }
+            }
+            Type::Tag => {
+                if let Len::Len(tag) = item.len {
+                    *stats.tags.entry(tag).or_insert(0) += 1;
+                }
+            }
+            Type::UnsignedInteger | Type::NegativeInteger | Type::Special => {}
+        }
+        i += 1;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use se::Serializer;
+    use types::Special;
+
+    #[test]
+    fn collect() {
+        let mut se = Serializer::new_vec();
+        se.write_map(Len::Indefinite)
+            .unwrap()
+            .write_text("a")
+            .unwrap()
+            .write_tag(24)
+            .unwrap()
+            .write_bytes(vec![0; 300])
+            .unwrap()
+            .write_text("b")
+            .unwrap()
+            .write_array(Len::Len(3))
+            .unwrap()
+            .write_negative_integer(-1)
+            .unwrap()
+            .write_tag(24)
+            .unwrap()
+            .write_tag(1)
+            .unwrap()
+            .write_unsigned_integer(0)
+            .unwrap()
+            .write_special(Special::Null)
+            .unwrap()
+            .write_special(Special::Break)
+            .unwrap();
+        let bytes = se.finalize();
+
+        let stats = stats(&bytes).unwrap();
+        assert_eq!(stats.count(Type::Map), 1);
+        assert_eq!(stats.count(Type::Text), 2);
+        assert_eq!(stats.count(Type::Tag), 3);
+        assert_eq!(stats.count(Type::Special), 1);
+        assert_eq!(stats.total(), 11);
+        assert_eq!(stats.max_depth, 5);
+        assert_eq!(stats.largest_bytes, 300);
+        assert_eq!(stats.largest_array, 3);
+        assert_eq!(stats.largest_map, 2);
+        assert_eq!(stats.text_payload, 2);
+        assert_eq!(stats.tags.get(&24), Some(&2));
+        assert_eq!(stats.tags.get(&1), Some(&1));
+        assert_eq!(stats.indefinite, 1);
+    }
+
+    #[test]
+    fn indefinite_strings() {
+        // (_ h'01', h'0203')
+        let bytes = [0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff];
+        let stats = stats(&bytes).unwrap();
+        assert_eq!(stats.total(), 1);
+        assert_eq!(stats.largest_bytes, 3);
+        assert_eq!(stats.indefinite, 1);
+        assert_eq!(stats.max_depth, 1);
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let mut bytes = vec![0x81; 100_000];
+        bytes.push(0x00);
+        let stats = stats(&bytes).unwrap();
+        assert_eq!(stats.count(Type::Array), 100_000);
+        assert_eq!(stats.max_depth, 100_001);
+    }
+}
@@ -0,0 +1,94 @@
+//! header buffer of the readers over non contiguous inputs
+//!
+//! `chunks::ChunkReader` and `buf::SegmentedReader` hand out their input
+//! segment by segment, copying only the CBOR headers straddling two
+//! segments, so a whole header is always available to the `Deserializer`.
+
+use std::io;
+
+// largest CBOR header: initial byte followed by a 64 bits argument
+pub(crate) const MAX_HEADER_SIZE: usize = 9;
+
+// bytes copied out of the segments when a header straddles two of them
+pub(crate) struct Stitched {
+    bytes: [u8; MAX_HEADER_SIZE],
+    pos: usize,
+    len: usize,
+    // more bytes were consumed than `fill_buf` returned
+    overrun: bool,
+}
+impl Stitched {
+    pub(crate) fn new() -> Self {
+        Stitched {
+            bytes: [0; MAX_HEADER_SIZE],
+            pos: 0,
+            len: 0,
+            overrun: false,
+        }
+    }
+
+    // true once all the stitched bytes are consumed
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos == self.len
+    }
+
+    // number of stitched bytes not consumed yet
+    pub(crate) fn len(&self) -> usize {
+        self.len - self.pos
+    }
+
+    // the stitched bytes not consumed yet
+    pub(crate) fn pending(&self) -> &[u8] {
+        &self.bytes[self.pos..self.len]
+    }
+
+    // move the bytes not consumed yet to the front, making room for the
+    // rest of the header
+    pub(crate) fn compact(&mut self) {
+        self.bytes.copy_within(self.pos..self.len, 0);
+        self.len -= self.pos;
+        self.pos = 0;
+    }
+
+    // append the start of `segment` up to a whole header, returning the
+    // number of bytes copied
+    pub(crate) fn append(&mut self, segment: &[u8]) -> usize {
+        let n = segment.len().min(MAX_HEADER_SIZE - self.len);
+        self.bytes[self.len..self.len + n].copy_from_slice(&segment[..n]);
+        self.len += n;
+        n
+    }
+
+    // true when a whole header is stitched
+    pub(crate) fn is_full(&self) -> bool {
+        self.len == MAX_HEADER_SIZE
+    }
+
+    // consume `amt` of the stitched bytes, see `overrun`
+    pub(crate) fn consume(&mut self, amt: usize) {
+        if amt > self.len() {
+            self.overrun();
+        } else {
+            self.pos += amt;
+        }
+    }
+
+    // record a `consume` beyond the bytes returned by `fill_buf`, the
+    // following reads then fail instead of returning misplaced bytes
+    pub(crate) fn overrun(&mut self) {
+        self.pos = self.len;
+        self.overrun = true;
+    }
+
+    // the error of an earlier overrun, if any
+    pub(crate) fn check(&self) -> io::Result<()> {
+        if self.overrun {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "consumed more bytes than available",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
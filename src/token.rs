@@ -0,0 +1,237 @@
+//! Pull parsing, one header at a time
+//!
+//! [`Deserializer::next_token`] reads the next header of the input and
+//! returns it as a [`Token`], with the content of the strings and of the
+//! simple values. Containers are not entered nor decoded into values:
+//! tooling like pretty printers, validators or transcoders can walk any
+//! input in constant stack space. [`Serializer::write_token`] writes the
+//! tokens back.
+//!
+//! The nesting is not tracked: the tokens following an `ArrayStart`,
+//! `MapStart` or `TagStart` are its content, and it is up to the caller to
+//! count the items of definite lengths and to match each `Break` with the
+//! indefinite length object it closes.
+//! [`validate_item`](../de/struct.Deserializer.html#method.validate_item)
+//! checks an object is well formed.
+//!
+//! [`Deserializer::next_token`]: ../de/struct.Deserializer.html#method.next_token
+//! [`Serializer::write_token`]: ../se/struct.Serializer.html#method.write_token
+//! [`Token`]: ./enum.Token.html
+//!
+//! ```
+//! use cbor_event::{de::Deserializer, se::Serializer, token::Token, Len};
+//!
+//! // [1, (_ "a", "b")]
+//! let vec = vec![0x82, 0x01, 0x7f, 0x61, 0x61, 0x61, 0x62, 0xff];
+//! let mut raw = Deserializer::from(&vec[..]);
+//! let mut se = Serializer::new_vec();
+//! let mut tokens = Vec::new();
+//! loop {
+//!     let token = raw.next_token().unwrap();
+//!     if token == Token::End {
+//!         break;
+//!     }
+//!     se.write_token(&token).unwrap();
+//!     tokens.push(token);
+//! }
+//! assert_eq!(
+//!     tokens,
+//!     [
+//!         Token::ArrayStart(Len::Len(2)),
+//!         Token::UInt(1),
+//!         Token::TextStart,
+//!         Token::TextChunk("a".to_owned()),
+//!         Token::TextChunk("b".to_owned()),
+//!         Token::Break,
+//!     ]
+//! );
+//! assert_eq!(se.finalize(), vec);
+//! ```
+
+use de::Deserializer;
+use error::Error;
+use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::Serializer;
+use std::io::{BufRead, Write};
+use types::{Special, Type};
+
+/// header of the input, see the [module documentation](./index.html).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// unsigned integer
+    UInt(u64),
+    /// negative integer `-1 - n`, from `-1` to `-2^64`
+    NegInt(u64),
+    /// start of an indefinite length byte string, followed by its chunks
+    /// and a `Break`
+    BytesStart,
+    /// definite length byte string, alone or chunk of an indefinite length
+    /// byte string
+    BytesChunk(Vec<u8>),
+    /// start of an indefinite length text string, followed by its chunks
+    /// and a `Break`
+    TextStart,
+    /// definite length text string, alone or chunk of an indefinite length
+    /// text string
+    TextChunk(String),
+    /// start of an array, followed by its items and a `Break` if the
+    /// length is indefinite
+    ArrayStart(Len),
+    /// start of a map, followed by its keys and values and a `Break` if the
+    /// length is indefinite
+    MapStart(Len),
+    /// tag, followed by the tagged object
+    TagStart(u64),
+    /// simple value or float, except the break
+    Special(Special),
+    /// end of an indefinite length object
+    Break,
+    /// end of the input
+    End,
+}
+
+impl<R: BufRead> Deserializer<R> {
+    /// read the next header of the input, see the
+    /// [`token`](../token/index.html) module.
+    ///
+    /// Returns `Token::End` once the input is exhausted, fails if it ends
+    /// in the middle of a header or of a string.
+    pub fn next_token(&mut self) -> Result<Token> {
+        if self.at_end()? {
+            return Ok(Token::End);
+        }
+        let cbor_type = self.cbor_type()?;
+        if cbor_type == Type::Special {
+            return match self.special()? {
+                Special::Break => Ok(Token::Break),
+                special => Ok(Token::Special(special)),
+            };
+        }
+        let (len, sz) = self.cbor_len()?;
+        if len == Len::Indefinite && self.definite_only() {
+            return Err(Error::IndefiniteLenForbidden(cbor_type));
+        }
+        let token = match (cbor_type, len) {
+            (Type::Bytes, Len::Len(_)) => return self.bytes().map(Token::BytesChunk),
+            (Type::Text, Len::Len(_)) => return self.text().map(Token::TextChunk),
+            (Type::UnsignedInteger, Len::Len(v)) => Token::UInt(v),
+            (Type::NegativeInteger, Len::Len(v)) => Token::NegInt(v),
+            (Type::Tag, Len::Len(tag)) => Token::TagStart(tag),
+            (Type::Bytes, Len::Indefinite) => Token::BytesStart,
+            (Type::Text, Len::Indefinite) => Token::TextStart,
+            (Type::Array, len) => Token::ArrayStart(len),
+            (Type::Map, len) => Token::MapStart(len),
+            (cbor_type, _) => return Err(Error::IndefiniteLenNotSupported(cbor_type)),
+        };
+        self.advance(1 + sz)?;
+        Ok(token)
+    }
+}
+
+impl<W: Write + Sized> Serializer<W> {
+    /// write a header read with
+    /// [`next_token`](../de/struct.Deserializer.html#method.next_token).
+    /// `Token::End` writes nothing.
+    pub fn write_token(&mut self, token: &Token) -> Result<&mut Self> {
+        match token {
+            Token::UInt(v) => self.write_unsigned_integer(*v),
+            Token::NegInt(v) => self.write_integer_i128(-1 - *v as i128),
+            Token::BytesStart => self.write_indefinite(Type::Bytes),
+            Token::BytesChunk(bytes) => self.write_bytes(bytes),
+            Token::TextStart => self.write_indefinite(Type::Text),
+            Token::TextChunk(text) => self.write_text(text),
+            Token::ArrayStart(len) => self.write_array(*len),
+            Token::MapStart(len) => self.write_map(*len),
+            Token::TagStart(tag) => self.write_tag(*tag),
+            Token::Special(special) => self.write_special(*special),
+            Token::Break => self.write_special(Special::Break),
+            Token::End => Ok(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tokens(bytes: &[u8]) -> Result<Vec<Token>> {
+        let mut raw = Deserializer::from(bytes);
+        let mut tokens = Vec::new();
+        loop {
+            match raw.next_token()? {
+                Token::End => return Ok(tokens),
+                token => tokens.push(token),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        // {_ "a": [-1, -2^64], 1(h'01'): (_ h'02', h'03'), 2: 1.5, 3: [true, null, simple(16)]}
+        let bytes = [
+            0xbf, 0x61, 0x61, 0x82, 0x20, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xc1, 0x41, 0x01, 0x5f, 0x41, 0x02, 0x41, 0x03, 0xff, 0x02, 0xf9, 0x3e, 0x00, 0x03,
+            0x83, 0xf5, 0xf6, 0xf0, 0xff,
+        ];
+        let tokens = tokens(&bytes).unwrap();
+        assert_eq!(
+            tokens,
+            [
+                Token::MapStart(Len::Indefinite),
+                Token::TextChunk("a".to_owned()),
+                Token::ArrayStart(Len::Len(2)),
+                Token::NegInt(0),
+                Token::NegInt(u64::MAX),
+                Token::TagStart(1),
+                Token::BytesChunk(vec![1]),
+                Token::BytesStart,
+                Token::BytesChunk(vec![2]),
+                Token::BytesChunk(vec![3]),
+                Token::Break,
+                Token::UInt(2),
+                Token::Special(Special::Float(1.5)),
+                Token::UInt(3),
+                Token::ArrayStart(Len::Len(3)),
+                Token::Special(Special::Bool(true)),
+                Token::Special(Special::Null),
+                Token::Special(Special::Unassigned(16)),
+                Token::Break,
+            ]
+        );
+
+        // the float is written as a double, the other headers as they were
+        let mut se = Serializer::new_vec();
+        for token in &tokens {
+            se.write_token(token).unwrap();
+        }
+        let written = se.finalize();
+        assert_eq!(written[..24], bytes[..24]);
+        assert_eq!(written[33..], bytes[27..]);
+        assert_eq!(self::tokens(&written).unwrap(), tokens);
+    }
+
+    #[test]
+    fn errors() {
+        // truncated header and string
+        assert!(tokens(&[0x19, 0x01]).is_err());
+        assert!(tokens(&[0x62, 0x61]).is_err());
+        // indefinite length integer
+        assert!(tokens(&[0x1f]).is_err());
+
+        let mut raw = Deserializer::from(&[0x9f, 0xff][..]);
+        raw.set_definite_only(true);
+        assert!(matches!(
+            raw.next_token(),
+            Err(Error::IndefiniteLenForbidden(Type::Array))
+        ));
+
+        // the tokens are not checked against each other
+        assert_eq!(
+            tokens(&[0xff, 0x81]).unwrap(),
+            [Token::Break, Token::ArrayStart(Len::Len(1))]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Streaming copy of CBOR objects from a Deserializer to a Serializer
+//!
+//! [`transcode`] pipes one complete object from a [`Deserializer`] to a
+//! [`Serializer`] object by object, without building a [`Value`]. Only the
+//! path to the current object is kept in memory (and the content of the
+//! string being copied), so filters and proxies can process payloads much
+//! larger than the memory.
+//!
+//! A [`Transcoder`] calls a hook on every object, which may keep, drop or
+//! replace it. The encoding of the objects kept is copied as is (size of
+//! the headers, indefinite lengths, chunks of strings).
+//!
+//! [`transcode`]: ./fn.transcode.html
+//! [`Transcoder`]: ./struct.Transcoder.html
+//! [`Deserializer`]: ../de/struct.Deserializer.html
+//! [`Serializer`]: ../se/struct.Serializer.html
+//! [`Value`]: ../enum.Value.html
+//!
+//! ```
+//! use cbor_event::{
+//!     de::Deserializer,
+//!     se::Serializer,
+//!     transcode::{Action, Node, Transcoder},
+//!     Value,
+//! };
+//!
+//! // [_ "keep", h'00...00' (1MB), "password"]
+//! let mut bytes = vec![0x9f, 0x64, 0x6b, 0x65, 0x65, 0x70, 0x5a, 0x00, 0x10, 0x00, 0x00];
+//! bytes.resize(bytes.len() + 0x10_0000, 0);
+//! bytes.extend_from_slice(&[0x68, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, 0x64, 0xff]);
+//!
+//! let mut transcoder = Transcoder::new().hook(|_path, node| match node {
+//!     Node::Bytes(bytes) if bytes.len() > 1024 => Ok(Action::Drop),
+//!     Node::Text("password") => Ok(Action::Replace(Value::Text("***".to_owned()))),
+//!     _ => Ok(Action::Keep),
+//! });
+//! let mut raw = Deserializer::from(&bytes[..]);
+//! let mut se = Serializer::new_vec();
+//! transcoder.transcode(&mut raw, &mut se).unwrap();
+//!
+//! assert_eq!(
+//!     se.finalize(),
+//!     vec![0x9f, 0x64, 0x6b, 0x65, 0x65, 0x70, 0x63, 0x2a, 0x2a, 0x2a, 0xff]
+//! );
+//! ```
+
+use de::Deserializer;
+use diff::{Path, PathElement};
+use error::Error;
+use len::{Len, LenSz};
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use result::Result;
+use se::Serializer;
+use std::io::{BufRead, Write};
+use types::{Special, Type};
+use value::Value;
+
+/// an object read by the [`Transcoder`](./struct.Transcoder.html). Arrays,
+/// maps and tags are given by their header, before their content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node<'a> {
+    UnsignedInteger(u64),
+    NegativeInteger(i128),
+    Bytes(&'a [u8]),
+    Text(&'a str),
+    Array(Len),
+    Map(Len),
+    Tag(u64),
+    Special(Special),
+}
+
+/// what to do with a [`Node`](./enum.Node.html)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// copy the object, the hook is then called on the content of arrays,
+    /// maps and tags.
+    Keep,
+    /// skip the object and its content. Dropping a map key drops the whole
+    /// entry. Only elements of arrays, map keys and the top level object
+    /// can be dropped.
+    Drop,
+    /// skip the object and its content and write the given value instead
+    Replace(Value),
+}
+
+type Hook<'a> = Box<dyn FnMut(&Path, &Node<'_>) -> Result<Action> + 'a>;
+
+/// configurable streaming copy of CBOR objects, see the
+/// [module documentation](./index.html).
+pub struct Transcoder<'a> {
+    hook: Hook<'a>,
+    indefinite: bool,
+}
+impl<'a> Default for Transcoder<'a> {
+    fn default() -> Self {
+        Transcoder {
+            hook: Box::new(|_, _| Ok(Action::Keep)),
+            indefinite: false,
+        }
+    }
+}
+impl<'a> Transcoder<'a> {
+    /// a transcoder copying everything as is
+    pub fn new() -> Self {
+        Transcoder::default()
+    }
+
+    /// call `hook` on every object, with its path from the top level
+    /// object, to decide what to do with it.
+    pub fn hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&Path, &Node<'_>) -> Result<Action> + 'a,
+    {
+        self.hook = Box::new(hook);
+        self
+    }
+
+    /// write all the arrays and maps with an indefinite length.
+    ///
+    /// The length of an array or a map is written before its content, so
+    /// an element can only be [dropped](./enum.Action.html#variant.Drop)
+    /// from a container written with an indefinite length.
+    pub fn indefinite_lengths(mut self, indefinite: bool) -> Self {
+        self.indefinite = indefinite;
+        self
+    }
+
+    /// copy the next object from `raw` to `se`
+    pub fn transcode<R: BufRead, W: Write>(
+        &mut self,
+        raw: &mut Deserializer<R>,
+        se: &mut Serializer<W>,
+    ) -> Result<()> {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut path = Path::default();
+        let depth = raw.depth();
+        loop {
+            // leave the containers closed so far
+            raw.set_depth(depth + stack.len());
+            if let Some(frame) = stack.last_mut() {
+                let done = match frame.len {
+                    Len::Len(len) => frame.read >= len,
+                    Len::Indefinite => raw.special_break()?,
+                };
+                if done {
+                    if frame.indefinite_out {
+                        se.write_special(Special::Break)?;
+                    }
+                    stack.pop();
+                    if !complete(&mut stack, &mut path, 1) {
+                        raw.set_depth(depth);
+                        return Ok(());
+                    }
+                    continue;
+                }
+                path.push(frame.child());
+            }
+
+            let (node_type, header) = read_node(raw)?;
+            let node = header.node();
+            let action = (self.hook)(&path, &node)?;
+            let mut consumed = 1;
+            match action {
+                Action::Keep => match header {
+                    Header::Container(cbor_type, len) => {
+                        let indefinite_out = self.indefinite || len == LenSz::Indefinite;
+                        match (cbor_type, indefinite_out) {
+                            (Type::Array, true) => se.write_array(Len::Indefinite)?,
+                            (Type::Array, false) => se.write_array_sz(len)?,
+                            (_, true) => se.write_map(Len::Indefinite)?,
+                            (_, false) => se.write_map_sz(len)?,
+                        };
+                        stack.push(Frame::new(cbor_type, Len::from(len), indefinite_out));
+                        continue;
+                    }
+                    Header::Tag(tag, sz) => {
+                        se.write_tag_sz(tag, sz)?;
+                        stack.push(Frame::new(Type::Tag, Len::Len(1), false));
+                        continue;
+                    }
+                    header => header.write(se)?,
+                },
+                Action::Drop => {
+                    skip_content(raw, node_type, &header)?;
+                    match (stack.last(), path.elements().last()) {
+                        (None, _) | (Some(_), Some(PathElement::Index(_))) => {}
+                        (Some(_), Some(PathElement::Key(_))) => {
+                            raw.skip_value()?;
+                            consumed = 2;
+                        }
+                        _ => {
+                            return Err(Error::CustomError(format!(
+                                "cannot drop {}, only array elements and map keys can be dropped",
+                                path
+                            )))
+                        }
+                    }
+                    if let Some(frame) = stack.last() {
+                        if !frame.indefinite_out {
+                            return Err(Error::CustomError(format!(
+                                "cannot drop {} from a container of definite length",
+                                path
+                            )));
+                        }
+                    }
+                }
+                Action::Replace(value) => {
+                    skip_content(raw, node_type, &header)?;
+                    se.serialize(&value)?;
+                }
+            }
+            if !complete(&mut stack, &mut path, consumed) {
+                raw.set_depth(depth);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// copy the next object from `raw` to `se`, keeping its encoding.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, se::Serializer, transcode::transcode};
+///
+/// let bytes = vec![0x82, 0x18, 0x01, 0x5f, 0x41, 0x02, 0xff];
+/// let mut raw = Deserializer::from(&bytes[..]);
+/// let mut se = Serializer::new_vec();
+///
+/// transcode(&mut raw, &mut se).unwrap();
+/// assert_eq!(se.finalize(), bytes);
+/// ```
+pub fn transcode<R: BufRead, W: Write>(
+    raw: &mut Deserializer<R>,
+    se: &mut Serializer<W>,
+) -> Result<()> {
+    Transcoder::new().transcode(raw, se)
+}
+
+// a container being copied
+struct Frame {
+    cbor_type: Type,
+    // number of items (keys and values for maps)
+    len: Len,
+    read: u64,
+    indefinite_out: bool,
+}
+impl Frame {
+    fn new(cbor_type: Type, len: Len, indefinite_out: bool) -> Self {
+        let len = match (cbor_type, len) {
+            (Type::Map, Len::Len(len)) => Len::Len(len.saturating_mul(2)),
+            (_, len) => len,
+        };
+        Frame {
+            cbor_type,
+            len,
+            read: 0,
+            indefinite_out,
+        }
+    }
+
+    // path element of the next item
+    fn child(&self) -> PathElement {
+        match self.cbor_type {
+            Type::Array => PathElement::Index(self.read),
+            Type::Map if self.read % 2 == 0 => PathElement::Key(self.read / 2),
+            Type::Map => PathElement::Value(self.read / 2),
+            _ => PathElement::Tagged,
+        }
+    }
+}
+
+// account for `items` items of the current container being done, returns
+// `false` once the top level object is complete
+fn complete(stack: &mut [Frame], path: &mut Path, items: u64) -> bool {
+    match stack.last_mut() {
+        None => false,
+        Some(frame) => {
+            frame.read += items;
+            path.pop();
+            true
+        }
+    }
+}
+
+// an object read from the input, with everything needed to write it back
+// the same way
+enum Header {
+    UnsignedInteger(u64, ::Sz),
+    NegativeInteger(i128, ::Sz),
+    Bytes(Vec<u8>, ::StringLenSz),
+    Text(String, ::StringLenSz),
+    Container(Type, LenSz),
+    Tag(u64, ::Sz),
+    Special(Special, Vec<u8>),
+}
+impl Header {
+    fn node(&self) -> Node<'_> {
+        match self {
+            Header::UnsignedInteger(v, _) => Node::UnsignedInteger(*v),
+            Header::NegativeInteger(v, _) => Node::NegativeInteger(*v),
+            Header::Bytes(bytes, _) => Node::Bytes(bytes),
+            Header::Text(text, _) => Node::Text(text),
+            Header::Container(Type::Array, len) => Node::Array(Len::from(*len)),
+            Header::Container(_, len) => Node::Map(Len::from(*len)),
+            Header::Tag(tag, _) => Node::Tag(*tag),
+            Header::Special(special, _) => Node::Special(*special),
+        }
+    }
+
+    // write the header, followed by the content of the strings
+    fn write<W: Write>(self, se: &mut Serializer<W>) -> Result<()> {
+        match self {
+            Header::UnsignedInteger(v, sz) => se.write_unsigned_integer_sz(v, sz)?,
+            Header::NegativeInteger(v, sz) => se.write_negative_integer_sz(v, sz)?,
+            Header::Bytes(bytes, len_sz) => se.write_bytes_sz(bytes, len_sz)?,
+            Header::Text(text, len_sz) => se.write_text_sz(text, len_sz)?,
+            Header::Special(_, raw) => se.write_raw_bytes(&raw)?,
+            Header::Container(Type::Array, len) => se.write_array_sz(len)?,
+            Header::Container(_, len) => se.write_map_sz(len)?,
+            Header::Tag(tag, sz) => se.write_tag_sz(tag, sz)?,
+        };
+        Ok(())
+    }
+}
+
+fn read_node<R: BufRead>(raw: &mut Deserializer<R>) -> Result<(Type, Header)> {
+    let cbor_type = raw.cbor_type()?;
+    let header = match cbor_type {
+        Type::UnsignedInteger => {
+            let (v, sz) = raw.unsigned_integer_sz()?;
+            Header::UnsignedInteger(v, sz)
+        }
+        Type::NegativeInteger => {
+            let (v, sz) = raw.negative_integer_sz()?;
+            Header::NegativeInteger(v, sz)
+        }
+        Type::Bytes => {
+            let (bytes, len_sz) = raw.bytes_sz()?;
+            Header::Bytes(bytes, len_sz)
+        }
+        Type::Text => {
+            let (text, len_sz) = raw.text_sz()?;
+            Header::Text(text, len_sz)
+        }
+        Type::Array => Header::Container(Type::Array, raw.array_sz()?),
+        Type::Map => Header::Container(Type::Map, raw.map_sz()?),
+        Type::Tag => {
+            let (tag, sz) = raw.tag_sz()?;
+            Header::Tag(tag, sz)
+        }
+        Type::Special => {
+            let (len, sz) = raw.cbor_len()?;
+            let bytes = raw.peek_remaining(1 + sz)?.to_vec();
+            if len == Len::Indefinite {
+                return Err(Error::UnexpectedBreak);
+            }
+            Header::Special(raw.special()?, bytes)
+        }
+    };
+    Ok((cbor_type, header))
+}
+
+// skip the content of a container whose header was read
+fn skip_content<R: BufRead>(
+    raw: &mut Deserializer<R>,
+    cbor_type: Type,
+    header: &Header,
+) -> Result<()> {
+    match header {
+        Header::Container(_, len) => {
+            let items = match cbor_type {
+                Type::Map => 2,
+                _ => 1,
+            };
+            match len {
+                LenSz::Indefinite => {
+                    while !raw.special_break()? {
+                        raw.skip_value()?;
+                    }
+                }
+                LenSz::Len(len, _) => {
+                    for _ in 0..len.saturating_mul(items) {
+                        raw.skip_value()?;
+                    }
+                }
+            }
+        }
+        Header::Tag(..) => raw.skip_value()?,
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        // {_ "a": [1, -300, 24(h'01')], "b": (_ "x", "y"), 3: [_ true, null, 1.5]}
+        vec![
+            0xbf, 0x61, 0x61, 0x83, 0x01, 0x39, 0x01, 0x2b, 0xd8, 0x18, 0x41, 0x01, 0x61, 0x62,
+            0x7f, 0x61, 0x78, 0x61, 0x79, 0xff, 0x03, 0x9f, 0xf5, 0xf6, 0xf9, 0x3e, 0x00, 0xff,
+            0xff,
+        ]
+    }
+
+    #[test]
+    fn identity() {
+        let bytes = sample();
+        let mut raw = Deserializer::from(&bytes[..]);
+        let mut se = Serializer::new_vec();
+        transcode(&mut raw, &mut se).unwrap();
+        assert_eq!(se.finalize(), bytes);
+    }
+
+    #[test]
+    fn paths_and_nodes() {
+        let bytes = sample();
+        let mut seen = Vec::new();
+        {
+            let mut transcoder = Transcoder::new().hook(|path, node| {
+                seen.push(format!("{} {:?}", path, node));
+                Ok(Action::Keep)
+            });
+            let mut raw = Deserializer::from(&bytes[..]);
+            transcoder
+                .transcode(&mut raw, &mut Serializer::new_vec())
+                .unwrap();
+        }
+        assert_eq!(seen[0], "$ Map(Indefinite)");
+        assert_eq!(seen[4], "${0}[1] NegativeInteger(-300)");
+        assert_eq!(seen[6], "${0}[2].tagged Bytes([1])");
+        assert_eq!(seen[8], "${1} Text(\"xy\")");
+        assert_eq!(seen[13], "${2}[2] Special(Float(1.5))");
+        assert_eq!(seen.len(), 14);
+    }
+
+    #[test]
+    fn drop_and_replace() {
+        let bytes = sample();
+        let mut transcoder = Transcoder::new()
+            .indefinite_lengths(true)
+            .hook(|path, node| match (path.elements(), node) {
+                (_, Node::Text("b")) => Ok(Action::Drop),
+                (_, Node::Tag(_)) => Ok(Action::Replace(Value::U64(0))),
+                ([_, PathElement::Index(0)], _) => Ok(Action::Drop),
+                _ => Ok(Action::Keep),
+            });
+        let mut raw = Deserializer::from(&bytes[..]);
+        let mut se = Serializer::new_vec();
+        transcoder.transcode(&mut raw, &mut se).unwrap();
+        // {_ "a": [_ -300, 0], 3: [_ null, 1.5]}
+        assert_eq!(
+            se.finalize(),
+            vec![
+                0xbf, 0x61, 0x61, 0x9f, 0x39, 0x01, 0x2b, 0x00, 0xff, 0x03, 0x9f, 0xf6, 0xf9, 0x3e,
+                0x00, 0xff, 0xff
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_drops() {
+        // drop from a definite length array, a map value and a tagged object
+        let cases: [(&[u8], PathElement); 3] = [
+            (&[0x81, 0x01], PathElement::Index(0)),
+            (&[0xa1, 0x01, 0x02], PathElement::Value(0)),
+            (&[0xc1, 0x01], PathElement::Tagged),
+        ];
+        for (bytes, element) in cases.iter() {
+            let mut transcoder = Transcoder::new().hook(|path, _| {
+                if path.elements().last() == Some(element) {
+                    Ok(Action::Drop)
+                } else {
+                    Ok(Action::Keep)
+                }
+            });
+            let mut raw = Deserializer::from(*bytes);
+            let mut se = Serializer::new_vec();
+            assert!(transcoder.transcode(&mut raw, &mut se).is_err());
+        }
+    }
+}
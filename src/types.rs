@@ -1,4 +1,6 @@
 use error::Error;
+#[cfg(not(feature = "std"))]
+use prelude::*;
 #[cfg(test)]
 use quickcheck::{Arbitrary, Gen};
 use result::Result;
@@ -6,6 +8,7 @@ use result::Result;
 /// CBOR Major Types
 ///
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Type {
     UnsignedInteger,
     NegativeInteger,
@@ -53,6 +56,7 @@ impl From<u8> for Type {
 
 /// CBOR special (as in Special Primary Type).
 #[derive(Debug, PartialEq, PartialOrd, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Special {
     Bool(bool),
     Null,
@@ -62,7 +66,6 @@ pub enum Special {
 
     /// Float is not fully supported in this library and it is advised
     /// to avoid using it for now.
-    #[warn()]
     Float(f64),
     /// mark the stop of a given indefinite-length item
     Break,
@@ -135,6 +138,56 @@ impl Special {
     }
 }
 
+/// any CBOR encoded number, as returned by
+/// [`Deserializer::number`](./de/struct.Deserializer.html#method.number).
+#[derive(Debug, PartialEq, Clone)]
+pub enum Number {
+    /// unsigned integer (major type 0)
+    U64(u64),
+    /// negative integer (major type 1) of value `-1 - n`. This covers the
+    /// full range of the major type, down to `-2^64`.
+    NegU64(u64),
+    /// half, single or double precision floating point number
+    F64(f64),
+    /// bignum (tags 2 and 3): whether it is negative and the big endian
+    /// bytes of `n`. The value is `n` if positive, `-1 - n` otherwise.
+    BigInt(bool, Vec<u8>),
+}
+
+/// wrapper serializing bytes as a CBOR byte string.
+///
+/// Arrays like `[u8; 32]` are serialized as arrays of integers, as all the
+/// other arrays. Hashes and keys are byte strings on the wire: wrap them in
+/// a `ByteString` to serialize them as such.
+///
+/// ```
+/// use cbor_event::{de::Deserializer, se::Serializer, ByteString};
+///
+/// let hash = ByteString([0xab; 4]);
+/// let mut se = Serializer::new_vec();
+/// se.serialize(&hash).unwrap();
+/// let bytes = se.finalize();
+/// assert_eq!(bytes, vec![0x44, 0xab, 0xab, 0xab, 0xab]);
+///
+/// let mut raw = Deserializer::from(&bytes[..]);
+/// assert_eq!(raw.deserialize::<ByteString<[u8; 4]>>().unwrap(), hash);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ByteString<T>(pub T);
+
+/// what to do with an unsigned integer too large for the requested type,
+/// see [`Deserializer::unsigned_as`](./de/struct.Deserializer.html#method.unsigned_as).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// fail with the same error as the `Deserialize` impls (`ExpectedU8`,
+    /// `ExpectedU16` or `ExpectedU32`)
+    Error,
+    /// use the largest value of the type instead
+    Saturate,
+    /// keep the low order bits, like an `as` conversion
+    Truncate,
+}
+
 #[cfg(test)]
 impl Arbitrary for Special {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -9,16 +9,24 @@
 //!
 //! This is why all the objects here are marked as deprecated
 
+use canonical::{write_float, write_header};
 use de::*;
+use diag;
 use error::Error;
 use len::Len;
+#[cfg(not(feature = "std"))]
+use prelude::*;
 use result::Result;
 use se::*;
 use types::{Special, Type};
 
 use std::{
-    collections::BTreeMap,
+    cmp::Ordering,
+    collections::{btree_map, BTreeMap},
+    fmt,
+    hash::{Hash, Hasher},
     io::{BufRead, Write},
+    mem, slice,
 };
 
 #[cfg(test)]
@@ -74,9 +82,33 @@ impl Deserialize for ObjectKey {
 /// retrieving custom types as it is a slow and not memory efficient way to do
 /// so. However it is handy for debugging or reverse a given protocol.
 ///
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// # Equality, order and hash
+///
+/// Values are compared by their deterministic encoding (RFC 8949 section
+/// 4.2.1, see [`canonicalize`](../canonical/fn.canonicalize.html)): the
+/// definite and indefinite length variants of the arrays and maps are
+/// equal, and the values are ordered as the bytes of their encodings. It
+/// is a total order, floating points included: a float equals the other
+/// floats with the same shortest encoding, all the NaNs are equal and
+/// `-0.0` differs from `0.0`. Values can be sorted or used as keys of a
+/// `HashMap`.
+///
+/// ```
+/// use cbor_event::{Special, Value};
+///
+/// let a = Value::Array(vec![Value::U64(1)]);
+/// let b = Value::IArray(vec![Value::U64(1)]);
+/// assert_eq!(a, b);
+/// assert!(Value::U64(10) < Value::U64(1000));
+///
+/// let nan = Value::Special(Special::Float(f64::NAN));
+/// assert_eq!(nan, nan.clone());
+/// ```
+#[derive(Debug, Clone)]
 pub enum Value {
     U64(u64),
+    /// decoded from a negative integer, written as an unsigned integer if
+    /// not negative
     I64(i64),
     Bytes(Vec<u8>),
     Text(String),
@@ -88,6 +120,226 @@ pub enum Value {
     Special(Special),
 }
 
+impl Value {
+    /// the value of a `Value::U64`
+    ///
+    /// ```
+    /// use cbor_event::Value;
+    ///
+    /// assert_eq!(Value::U64(42).as_u64().unwrap(), 42);
+    /// assert_eq!(
+    ///     Value::Text("42".to_owned()).as_u64().unwrap_err().to_string(),
+    ///     "Invalid cbor: Expected Value::U64, received Value::Text"
+    /// );
+    /// ```
+    pub fn as_u64(&self) -> Result<u64> {
+        match self {
+            Value::U64(v) => Ok(*v),
+            _ => Err(self.unexpected("Value::U64")),
+        }
+    }
+
+    /// the value of a `Value::U64` or a `Value::I64`
+    pub fn as_i128(&self) -> Result<i128> {
+        match self {
+            Value::U64(v) => Ok(*v as i128),
+            Value::I64(v) => Ok(*v as i128),
+            _ => Err(self.unexpected("Value::U64 or Value::I64")),
+        }
+    }
+
+    /// the content of a `Value::Text`
+    pub fn as_str(&self) -> Result<&str> {
+        match self {
+            Value::Text(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Text")),
+        }
+    }
+
+    /// the content of a `Value::Bytes`
+    pub fn as_bytes(&self) -> Result<&[u8]> {
+        match self {
+            Value::Bytes(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Bytes")),
+        }
+    }
+
+    /// the elements of a `Value::Array` or a `Value::IArray`
+    pub fn as_array(&self) -> Result<&[Value]> {
+        match self {
+            Value::Array(v) | Value::IArray(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Array or Value::IArray")),
+        }
+    }
+
+    /// the entries of a `Value::Object` or a `Value::IObject`
+    pub fn as_map(&self) -> Result<&BTreeMap<ObjectKey, Value>> {
+        match self {
+            Value::Object(v) | Value::IObject(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Object or Value::IObject")),
+        }
+    }
+
+    /// the elements of a `Value::Array` or a `Value::IArray`, to add or
+    /// edit elements in place
+    ///
+    /// ```
+    /// use cbor_event::{de::Deserializer, se::Serializer, Value};
+    ///
+    /// let mut value: Value = Deserializer::from(&[0x81, 0x01][..]).deserialize_complete().unwrap();
+    /// value.as_array_mut().unwrap().push(Value::Text("a".to_owned()));
+    ///
+    /// let mut se = Serializer::new_vec();
+    /// se.serialize(&value).unwrap();
+    /// assert_eq!(se.finalize(), [0x82, 0x01, 0x61, 0x61]);
+    /// ```
+    pub fn as_array_mut(&mut self) -> Result<&mut Vec<Value>> {
+        match self {
+            Value::Array(v) | Value::IArray(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Array or Value::IArray")),
+        }
+    }
+
+    /// the entries of a `Value::Object` or a `Value::IObject`, to add,
+    /// edit or remove entries in place
+    pub fn as_map_mut(&mut self) -> Result<&mut BTreeMap<ObjectKey, Value>> {
+        match self {
+            Value::Object(v) | Value::IObject(v) => Ok(v),
+            _ => Err(self.unexpected("Value::Object or Value::IObject")),
+        }
+    }
+
+    /// the tag and the tagged value of a `Value::Tag`
+    pub fn as_tagged(&self) -> Result<(u64, &Value)> {
+        match self {
+            Value::Tag(tag, v) => Ok((*tag, v)),
+            _ => Err(self.unexpected("Value::Tag")),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        let found = match self {
+            Value::U64(_) => "U64",
+            Value::I64(_) => "I64",
+            Value::Bytes(_) => "Bytes",
+            Value::Text(_) => "Text",
+            Value::Array(_) => "Array",
+            Value::IArray(_) => "IArray",
+            Value::Object(_) => "Object",
+            Value::IObject(_) => "IObject",
+            Value::Tag(..) => "Tag",
+            Value::Special(_) => "Special",
+        };
+        Error::CustomError(format!("Expected {}, received Value::{}", expected, found))
+    }
+
+    // the deterministic encoding of the value, defining its equality, order
+    // and hash
+    fn canonical_encoding(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        canonical_value(self, &mut out);
+        out
+    }
+}
+
+// with an explicit stack of what is left to write, whatever the depth
+fn canonical_value(value: &Value, out: &mut Vec<u8>) {
+    // the values and encoded keys left to write, the next one last
+    let mut stack = vec![Encoded::Value(value)];
+    while let Some(next) = stack.pop() {
+        let value = match next {
+            Encoded::Value(value) => value,
+            Encoded::Key(key) => {
+                out.extend_from_slice(&key);
+                continue;
+            }
+        };
+        match value {
+            Value::U64(v) => write_header(Type::UnsignedInteger, *v, out),
+            // as written by `write_negative_integer`
+            Value::I64(v) if *v >= 0 => write_header(Type::UnsignedInteger, *v as u64, out),
+            Value::I64(v) => write_header(Type::NegativeInteger, (-1 - *v) as u64, out),
+            Value::Bytes(v) => canonical_string(Type::Bytes, v, out),
+            Value::Text(v) => canonical_string(Type::Text, v.as_bytes(), out),
+            Value::Array(v) | Value::IArray(v) => {
+                write_header(Type::Array, v.len() as u64, out);
+                stack.extend(v.iter().rev().map(Encoded::Value));
+            }
+            Value::Object(v) | Value::IObject(v) => {
+                let mut entries = v
+                    .iter()
+                    .map(|(key, value)| {
+                        let mut encoded = Vec::new();
+                        match key {
+                            ObjectKey::Integer(v) => {
+                                write_header(Type::UnsignedInteger, *v, &mut encoded)
+                            }
+                            ObjectKey::Bytes(v) => canonical_string(Type::Bytes, v, &mut encoded),
+                            ObjectKey::Text(v) => {
+                                canonical_string(Type::Text, v.as_bytes(), &mut encoded)
+                            }
+                        }
+                        (encoded, value)
+                    })
+                    .collect::<Vec<_>>();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                write_header(Type::Map, v.len() as u64, out);
+                for (key, value) in entries.into_iter().rev() {
+                    stack.push(Encoded::Value(value));
+                    stack.push(Encoded::Key(key));
+                }
+            }
+            Value::Tag(tag, v) => {
+                write_header(Type::Tag, *tag, out);
+                stack.push(Encoded::Value(v));
+            }
+            Value::Special(special) => match special {
+                Special::Bool(false) => out.push(0xf4),
+                Special::Bool(true) => out.push(0xf5),
+                Special::Null => out.push(0xf6),
+                Special::Undefined => out.push(0xf7),
+                Special::Unassigned(v) if *v < 24 => out.push(Type::Special.to_byte(*v)),
+                Special::Unassigned(v) => out.extend_from_slice(&[0xf8, *v]),
+                Special::Float(f) => write_float(*f, out),
+                Special::Break => out.push(0xff),
+            },
+        }
+    }
+}
+
+// what is left to write of a canonical encoding, see `canonical_value`
+enum Encoded<'a> {
+    Value(&'a Value),
+    Key(Vec<u8>),
+}
+
+fn canonical_string(cbor_type: Type, bytes: &[u8], out: &mut Vec<u8>) {
+    write_header(cbor_type, bytes.len() as u64, out);
+    out.extend_from_slice(bytes);
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Value {}
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.canonical_encoding().cmp(&other.canonical_encoding())
+    }
+}
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_encoding().hash(state)
+    }
+}
+
 impl Serialize for Value {
     fn serialize<'se, W: Write + Sized>(
         &self,
@@ -95,7 +347,7 @@ impl Serialize for Value {
     ) -> Result<&'se mut Serializer<W>> {
         match self {
             Value::U64(ref v) => serializer.write_unsigned_integer(*v),
-            Value::I64(ref v) => serializer.write_negative_integer(*v),
+            Value::I64(ref v) => serializer.write_integer(*v),
             Value::Bytes(ref v) => serializer.write_bytes(v),
             Value::Text(ref v) => serializer.write_text(v),
             Value::Array(ref v) => {
@@ -114,9 +366,11 @@ impl Serialize for Value {
             }
             Value::Object(ref v) => {
                 serializer.write_map(Len::Len(v.len() as u64))?;
-                for element in v {
-                    serializer.serialize(element.0)?.serialize(element.1)?;
+                let mut entries = MapEntries::new(serializer);
+                for (key, value) in v {
+                    entries.write(serializer, key, value)?;
                 }
+                entries.finish(serializer)?;
                 Ok(serializer)
             }
             Value::IObject(ref v) => {
@@ -131,74 +385,347 @@ impl Serialize for Value {
         }
     }
 }
+// a container being decoded by `Value::deserialize`
+enum Partial {
+    Array(Vec<Value>, Len),
+    // entries, length, number of entries read, key of the entry being read
+    // and the previous key, see `Deserializer::map_key`
+    Map(
+        BTreeMap<ObjectKey, Value>,
+        Len,
+        u64,
+        Option<ObjectKey>,
+        Option<Vec<u8>>,
+    ),
+    Tag(u64),
+}
+impl Partial {
+    // the container if all its elements have been read, consuming the
+    // break of the indefinite lengths
+    fn close<R: BufRead>(&mut self, raw: &mut Deserializer<R>) -> Result<Option<Value>> {
+        Ok(match self {
+            Partial::Array(vec, len) if is_end(raw, *len, vec.len() as u64)? => {
+                let vec = mem::take(vec);
+                Some(match len {
+                    Len::Len(_) => Value::Array(vec),
+                    Len::Indefinite => Value::IArray(vec),
+                })
+            }
+            Partial::Map(map, len, read, None, _) if is_end(raw, *len, *read)? => {
+                let map = mem::take(map);
+                Some(match len {
+                    Len::Len(_) => Value::Object(map),
+                    Len::Indefinite => Value::IObject(map),
+                })
+            }
+            _ => None,
+        })
+    }
+}
+
+fn is_end<R: BufRead>(raw: &mut Deserializer<R>, len: Len, read: u64) -> Result<bool> {
+    match len {
+        Len::Len(len) => Ok(read == len),
+        Len::Indefinite => raw.special_break(),
+    }
+}
+
+/// Nested values are decoded with an explicit stack, whatever the depth
+/// of the input, within the limit of
+/// [`set_max_depth`](../de/struct.Deserializer.html#method.set_max_depth).
+/// Dropping, cloning, formatting with `Debug` or encoding the value recurse
+/// once per level though: set a limit when decoding untrusted input.
 impl Deserialize for Value {
     fn deserialize<R: BufRead>(raw: &mut Deserializer<R>) -> Result<Self> {
-        match raw.cbor_type()? {
-            Type::UnsignedInteger => Ok(Value::U64(raw.unsigned_integer()?)),
-            Type::NegativeInteger => Ok(Value::I64(raw.negative_integer()?)),
-            Type::Bytes => Ok(Value::Bytes(raw.bytes()?)),
-            Type::Text => Ok(Value::Text(raw.text()?)),
-            Type::Array => {
-                let len = raw.array()?;
-                let mut vec = Vec::new();
-                match len {
-                    Len::Indefinite => {
-                        while {
-                            let t = raw.cbor_type()?;
-                            if t == Type::Special {
-                                let special = raw.special()?;
-                                assert_eq!(special, Special::Break);
-                                false
-                            } else {
-                                vec.push(Deserialize::deserialize(raw)?);
-                                true
-                            }
-                        } {}
-                        Ok(Value::IArray(vec))
+        // the containers being decoded, innermost last
+        let mut stack: Vec<Partial> = Vec::new();
+        let depth = raw.depth();
+        loop {
+            let closed = match stack.last_mut() {
+                Some(partial) => partial.close(raw)?,
+                None => None,
+            };
+            let mut value = match closed {
+                Some(value) => {
+                    stack.pop();
+                    raw.set_depth(depth + stack.len());
+                    value
+                }
+                None => {
+                    if let Some(Partial::Map(_, _, _, key @ None, previous)) = stack.last_mut() {
+                        *key = Some(raw.map_key(previous)?);
+                        continue;
                     }
-                    Len::Len(len) => {
-                        for _ in 0..len {
-                            vec.push(Deserialize::deserialize(raw)?);
+                    raw.set_depth(depth + stack.len());
+                    match raw.cbor_type()? {
+                        Type::UnsignedInteger => Value::U64(raw.unsigned_integer()?),
+                        Type::NegativeInteger => Value::I64(raw.negative_integer()?),
+                        Type::Bytes => Value::Bytes(raw.bytes()?),
+                        Type::Text => Value::Text(raw.text()?),
+                        Type::Array => {
+                            let len = raw.array()?;
+                            stack.push(Partial::Array(Vec::new(), len));
+                            continue;
+                        }
+                        Type::Map => {
+                            let len = raw.map()?;
+                            stack.push(Partial::Map(BTreeMap::new(), len, 0, None, None));
+                            continue;
+                        }
+                        Type::Tag => {
+                            stack.push(Partial::Tag(raw.tag()?));
+                            continue;
                         }
-                        Ok(Value::Array(vec))
+                        Type::Special => Value::Special(raw.special()?),
+                    }
+                }
+            };
+            // hand the value to its container, closing the tags
+            loop {
+                match stack.last_mut() {
+                    None => return Ok(value),
+                    Some(Partial::Array(vec, _)) => vec.push(value),
+                    Some(Partial::Map(map, _, read, key, _)) => {
+                        map.insert(key.take().unwrap(), value);
+                        *read += 1;
+                    }
+                    Some(Partial::Tag(tag)) => {
+                        value = Value::Tag(*tag, Box::new(value));
+                        stack.pop();
+                        raw.set_depth(depth + stack.len());
+                        continue;
                     }
                 }
+                break;
             }
-            Type::Map => {
-                let len = raw.map()?;
-                let mut vec = BTreeMap::new();
-                match len {
-                    Len::Indefinite => {
-                        while {
-                            let t = raw.cbor_type()?;
-                            if t == Type::Special {
-                                let special = raw.special()?;
-                                assert_eq!(special, Special::Break);
-                                false
-                            } else {
-                                let k = Deserialize::deserialize(raw)?;
-                                let v = Deserialize::deserialize(raw)?;
-                                vec.insert(k, v);
-                                true
-                            }
-                        } {}
-                        Ok(Value::IObject(vec))
+        }
+    }
+}
+
+// the values decoded so far are dropped without recursion on error
+impl Drop for Partial {
+    fn drop(&mut self) {
+        match self {
+            Partial::Array(vec, _) => drop_values(vec.drain(..)),
+            Partial::Map(map, ..) => drop_values(mem::take(map).into_values()),
+            Partial::Tag(_) => {}
+        }
+    }
+}
+
+// drop `values` with an explicit stack, whatever their depth
+fn drop_values<I: IntoIterator<Item = Value>>(values: I) {
+    let mut stack: Vec<Value> = values.into_iter().collect();
+    while let Some(mut value) = stack.pop() {
+        take_nested(&mut value, &mut stack);
+    }
+}
+
+// move out the children of `value` holding values themselves
+fn take_nested(value: &mut Value, stack: &mut Vec<Value>) {
+    let has_nested = |value: &Value| match value {
+        Value::Array(vec) | Value::IArray(vec) => !vec.is_empty(),
+        Value::Object(map) | Value::IObject(map) => !map.is_empty(),
+        Value::Tag(..) => true,
+        _ => false,
+    };
+    match value {
+        Value::Array(vec) | Value::IArray(vec) => {
+            stack.extend(vec.drain(..).filter(has_nested));
+        }
+        Value::Object(map) | Value::IObject(map) => {
+            stack.extend(mem::take(map).into_values().filter(has_nested));
+        }
+        Value::Tag(_, tagged) if has_nested(tagged) => {
+            stack.push(mem::replace(&mut **tagged, Value::Special(Special::Null)));
+        }
+        _ => {}
+    }
+}
+
+/// diagnostic notation of the key, as in
+/// [`Value`'s `Display`](./enum.Value.html#impl-Display-for-Value)
+impl fmt::Display for ObjectKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjectKey::Integer(v) => write!(f, "{}", v),
+            ObjectKey::Bytes(v) => write_bytes(f, v),
+            ObjectKey::Text(v) => write_text(f, v),
+        }
+    }
+}
+
+/// diagnostic notation (RFC 8949 section 8) of the value, on one line.
+///
+/// ```
+/// use cbor_event::{ObjectKey, Value};
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::new();
+/// map.insert(ObjectKey::Integer(1), Value::Text("text".to_owned()));
+/// map.insert(ObjectKey::Integer(2), Value::Bytes(vec![0xde, 0xad]));
+/// let value = Value::IArray(vec![Value::Object(map), Value::I64(-1)]);
+///
+/// assert_eq!(value.to_string(), r#"[_ {1: "text", 2: h'dead'}, -1]"#);
+/// ```
+///
+/// Nested values are written with an explicit stack, whatever their depth.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the containers being written, innermost last
+        let mut stack: Vec<Written<'_>> = Vec::new();
+        let mut next = Some(self);
+        loop {
+            if let Some(value) = next.take() {
+                match value {
+                    Value::U64(v) => write!(f, "{}", v)?,
+                    Value::I64(v) => write!(f, "{}", v)?,
+                    Value::Bytes(v) => write_bytes(f, v)?,
+                    Value::Text(v) => write_text(f, v)?,
+                    Value::Array(v) => {
+                        f.write_str("[")?;
+                        stack.push(Written::Array(v.iter(), true));
+                    }
+                    Value::IArray(v) => {
+                        f.write_str("[_ ")?;
+                        stack.push(Written::Array(v.iter(), true));
+                    }
+                    Value::Object(v) => {
+                        f.write_str("{")?;
+                        stack.push(Written::Map(v.iter(), true));
                     }
-                    Len::Len(len) => {
-                        for _ in 0..len {
-                            let k = Deserialize::deserialize(raw)?;
-                            let v = Deserialize::deserialize(raw)?;
-                            vec.insert(k, v);
+                    Value::IObject(v) => {
+                        f.write_str("{_ ")?;
+                        stack.push(Written::Map(v.iter(), true));
+                    }
+                    Value::Tag(tag, v) => {
+                        write!(f, "{}(", tag)?;
+                        stack.push(Written::Tag);
+                        next = Some(v);
+                        continue;
+                    }
+                    Value::Special(Special::Bool(v)) => write!(f, "{}", v)?,
+                    Value::Special(Special::Null) => f.write_str("null")?,
+                    Value::Special(Special::Undefined) => f.write_str("undefined")?,
+                    Value::Special(Special::Unassigned(v)) => write!(f, "simple({})", v)?,
+                    Value::Special(Special::Float(v)) => diag::write_float(f, *v)?,
+                    Value::Special(Special::Break) => f.write_str("break")?,
+                }
+            }
+            // move to the next element, closing the containers written
+            match stack.last_mut() {
+                None => return Ok(()),
+                Some(Written::Array(elements, first)) => match elements.next() {
+                    Some(element) => {
+                        if !mem::replace(first, false) {
+                            f.write_str(", ")?;
+                        }
+                        next = Some(element);
+                    }
+                    None => {
+                        f.write_str("]")?;
+                        stack.pop();
+                    }
+                },
+                Some(Written::Map(entries, first)) => match entries.next() {
+                    Some((key, value)) => {
+                        if !mem::replace(first, false) {
+                            f.write_str(", ")?;
                         }
-                        Ok(Value::Object(vec))
+                        write!(f, "{}: ", key)?;
+                        next = Some(value);
+                    }
+                    None => {
+                        f.write_str("}")?;
+                        stack.pop();
+                    }
+                },
+                Some(Written::Tag) => {
+                    f.write_str(")")?;
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+// a container being written by `Value`'s `Display`, with whether no
+// element was written yet
+enum Written<'a> {
+    Array(slice::Iter<'a, Value>, bool),
+    Map(btree_map::Iter<'a, ObjectKey, Value>, bool),
+    Tag,
+}
+
+fn write_bytes(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_str("h'")?;
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    f.write_str("'")
+}
+
+fn write_text(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    diag::escape(f, text)?;
+    f.write_str("\"")
+}
+
+/// same notation as `Display`, the bytes are shown as a list of hexadecimal
+/// numbers and the texts are not escaped
+#[cfg(feature = "defmt")]
+impl defmt::Format for ObjectKey {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            ObjectKey::Integer(v) => defmt::write!(f, "{=u64}", v),
+            ObjectKey::Bytes(v) => defmt::write!(f, "h{=[u8]:02x}", v),
+            ObjectKey::Text(v) => defmt::write!(f, "\"{=str}\"", v),
+        }
+    }
+}
+#[cfg(feature = "defmt")]
+impl defmt::Format for Value {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match self {
+            Value::U64(v) => defmt::write!(f, "{=u64}", v),
+            Value::I64(v) => defmt::write!(f, "{=i64}", v),
+            Value::Bytes(v) => defmt::write!(f, "h{=[u8]:02x}", v),
+            Value::Text(v) => defmt::write!(f, "\"{=str}\"", v),
+            Value::Array(v) | Value::IArray(v) => {
+                if let Value::IArray(_) = self {
+                    defmt::write!(f, "[_ ")
+                } else {
+                    defmt::write!(f, "[")
+                }
+                for (i, element) in v.iter().enumerate() {
+                    if i > 0 {
+                        defmt::write!(f, ", ");
                     }
+                    defmt::write!(f, "{}", element);
                 }
+                defmt::write!(f, "]")
             }
-            Type::Tag => {
-                let tag = raw.tag()?;
-                Ok(Value::Tag(tag, Box::new(Deserialize::deserialize(raw)?)))
+            Value::Object(v) | Value::IObject(v) => {
+                if let Value::IObject(_) = self {
+                    defmt::write!(f, "{{_ ")
+                } else {
+                    defmt::write!(f, "{{")
+                }
+                for (i, (key, value)) in v.iter().enumerate() {
+                    if i > 0 {
+                        defmt::write!(f, ", ");
+                    }
+                    defmt::write!(f, "{}: {}", key, value);
+                }
+                defmt::write!(f, "}}")
             }
-            Type::Special => Ok(Value::Special(raw.special()?)),
+            Value::Tag(tag, v) => defmt::write!(f, "{=u64}({})", tag, v.as_ref()),
+            Value::Special(Special::Bool(v)) => defmt::write!(f, "{=bool}", v),
+            Value::Special(Special::Null) => defmt::write!(f, "null"),
+            Value::Special(Special::Undefined) => defmt::write!(f, "undefined"),
+            Value::Special(Special::Unassigned(v)) => defmt::write!(f, "simple({=u8})", v),
+            Value::Special(Special::Float(v)) => defmt::write!(f, "{=f64}", v),
+            Value::Special(Special::Break) => defmt::write!(f, "break"),
         }
     }
 }
@@ -325,6 +852,18 @@ mod test {
         assert!(test_encode_decode(&Value::I64(-9999999)).unwrap());
         assert!(test_encode_decode(&Value::I64(-283749237289)).unwrap());
         assert!(test_encode_decode(&Value::I64(93892929229)).unwrap());
+        assert!(test_encode_decode(&Value::I64(i64::MIN)).unwrap());
+
+        // -2^64 does not fit, nor in a container
+        let bytes = [0x81, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert!(matches!(
+            Deserializer::from(&bytes[1..]).deserialize::<Value>(),
+            Err(Error::ExpectedI64)
+        ));
+        assert!(matches!(
+            Deserializer::from(&bytes[..]).deserialize::<Value>(),
+            Err(Error::ExpectedI64)
+        ));
     }
 
     #[test]
@@ -364,6 +903,171 @@ mod test {
         .unwrap());
     }
 
+    #[test]
+    fn accessors() {
+        let mut map = BTreeMap::new();
+        map.insert(ObjectKey::Integer(1), Value::I64(-2));
+        let value = Value::IArray(vec![
+            Value::Object(map),
+            Value::Tag(24, Box::new(Value::Bytes(vec![0x00]))),
+            Value::Text("a".to_owned()),
+        ]);
+
+        let elements = value.as_array().unwrap();
+        let entry = &elements[0].as_map().unwrap()[&ObjectKey::Integer(1)];
+        assert_eq!(entry.as_i128().unwrap(), -2);
+        let (tag, tagged) = elements[1].as_tagged().unwrap();
+        assert_eq!((tag, tagged.as_bytes().unwrap()), (24, &[0x00][..]));
+        assert_eq!(elements[2].as_str().unwrap(), "a");
+
+        match value.as_map() {
+            Err(Error::CustomError(msg)) => assert_eq!(
+                msg,
+                "Expected Value::Object or Value::IObject, received Value::IArray"
+            ),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert!(entry.as_u64().is_err());
+        assert!(elements[2].as_bytes().is_err());
+
+        let mut value = value;
+        assert!(value.as_map_mut().is_err());
+        let elements = value.as_array_mut().unwrap();
+        elements.pop();
+        let map = elements[0].as_map_mut().unwrap();
+        map.remove(&ObjectKey::Integer(1));
+        map.insert(ObjectKey::Text("b".to_owned()), Value::U64(3));
+        assert_eq!(value.to_string(), "[_ {\"b\": 3}, 24(h'00')]");
+    }
+
+    #[test]
+    fn canonical_comparisons() {
+        use std::collections::HashSet;
+
+        let mut map = BTreeMap::new();
+        map.insert(ObjectKey::Text("b".to_owned()), Value::U64(1));
+        map.insert(ObjectKey::Integer(1000), Value::IArray(vec![]));
+        let object = Value::Object(map.clone());
+        let iobject = Value::IObject(map);
+        assert_eq!(object, iobject);
+        assert_eq!(
+            object.canonical_encoding(),
+            vec![0xa2, 0x19, 0x03, 0xe8, 0x80, 0x61, 0x62, 0x01]
+        );
+
+        let float = |f: f64| Value::Special(Special::Float(f));
+        assert_eq!(float(f64::NAN), float(-f64::NAN));
+        assert_ne!(float(0.0), float(-0.0));
+        assert!(float(1.5) < float(1.0e300));
+
+        let mut values = vec![
+            Value::Text("a".to_owned()),
+            Value::I64(-1),
+            Value::U64(1000),
+            Value::U64(1),
+            float(f64::NAN),
+        ];
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                Value::U64(1),
+                Value::U64(1000),
+                Value::I64(-1),
+                Value::Text("a".to_owned()),
+                float(f64::NAN),
+            ]
+        );
+
+        let set: HashSet<Value> = vec![object, iobject, float(f64::NAN), float(f64::NAN)]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn malformed() {
+        let inputs: &[&[u8]] = &[
+            // [_ null, true] without break
+            &[0x9f, 0xf6, 0xf5],
+            // {_ 1: 2, false}
+            &[0xbf, 0x01, 0x02, 0xf4, 0xff],
+        ];
+        for bytes in inputs {
+            let result: Result<Value> = Deserializer::from(*bytes).deserialize_complete();
+            assert!(result.is_err(), "{:?}", bytes);
+        }
+        // the break is the only special ending an indefinite array
+        let value: Value = Deserializer::from(&[0x9f, 0xf6, 0xff][..])
+            .deserialize_complete()
+            .unwrap();
+        assert_eq!(value, Value::IArray(vec![Value::Special(Special::Null)]));
+    }
+
+    #[test]
+    fn no_default_depth_limit() {
+        let mut bytes = vec![0x81; 300];
+        bytes.push(0x00);
+        let mut value: Value = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+        for _ in 0..300 {
+            value = match value {
+                Value::Array(mut vec) => vec.pop().unwrap(),
+                value => panic!("unexpected {:?}", value),
+            };
+        }
+        assert_eq!(value, Value::U64(0));
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // [{0: 1([_ ...])}] nested far deeper than the stack would allow
+        let depth = 100_000;
+        let mut bytes = Vec::new();
+        for _ in 0..depth {
+            bytes.extend_from_slice(&[0x81, 0xa1, 0x00, 0xc1, 0x9f]);
+        }
+        bytes.push(0x01);
+        bytes.extend(vec![0xff; depth]);
+
+        // no limit by default
+        let value: Value = Deserializer::from(&bytes[..])
+            .deserialize_complete()
+            .unwrap();
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(4 * depth - 1);
+        assert!(matches!(
+            raw.deserialize_complete::<Value>(),
+            Err(Error::MaxDepthExceeded)
+        ));
+        let mut raw = Deserializer::from(&bytes[..]);
+        raw.set_max_depth(4 * depth);
+        let other: Value = raw.deserialize_complete().unwrap();
+        // compared and hashed without recursion
+        assert_eq!(value, other);
+        let set: std::collections::HashSet<&Value> = vec![&value, &other].into_iter().collect();
+        assert_eq!(set.len(), 1);
+        let mut nested = &value;
+        for _ in 0..depth {
+            let array = nested.as_array().unwrap();
+            let map = array[0].as_map().unwrap();
+            let (tag, tagged) = map[&ObjectKey::Integer(0)].as_tagged().unwrap();
+            assert_eq!(tag, 1);
+            nested = &tagged.as_array().unwrap()[0];
+        }
+        assert_eq!(nested.as_u64().unwrap(), 1);
+        drop(set);
+        drop_values(vec![value, other]);
+
+        // missing break, the values decoded so far are dropped without
+        // recursion
+        bytes.pop();
+        assert!(Deserializer::from(&bytes[..])
+            .deserialize::<Value>()
+            .is_err());
+    }
+
     #[test]
     fn tag() {
         assert!(test_encode_decode(&Value::Tag(23, Box::new(Value::U64(0)))).unwrap());
@@ -373,6 +1077,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn display() {
+        let mut map = BTreeMap::new();
+        map.insert(ObjectKey::Text("a\"".to_owned()), Value::Array(vec![]));
+        map.insert(ObjectKey::Bytes(vec![0x01]), Value::Special(Special::Null));
+        let value = Value::Tag(
+            24,
+            Box::new(Value::IArray(vec![
+                Value::IObject(map),
+                Value::Special(Special::Float(1.5)),
+                Value::Special(Special::Unassigned(32)),
+            ])),
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"24([_ {_ h'01': null, "a\"": []}, 1.5, simple(32)])"#
+        );
+
+        let mut value = Value::Array(vec![]);
+        for _ in 0..100_000 {
+            value = Value::Tag(1, Box::new(Value::Array(vec![Value::U64(0), value])));
+        }
+        assert_eq!(
+            value.to_string(),
+            format!("{}[]{}", "1([0, ".repeat(100_000), "])".repeat(100_000))
+        );
+        drop_values(vec![value]);
+    }
+
     quickcheck! {
         fn property_encode_decode(value: Value) -> bool {
             test_encode_decode(&value).unwrap()